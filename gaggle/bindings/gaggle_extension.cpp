@@ -87,6 +87,15 @@ static std::string GetGaggleError() {
   return err ? std::string(err) : std::string("unknown error");
 }
 
+/**
+ * @brief Whether a file in a dataset directory is Gaggle cache state (such as
+ * `.downloaded` or `.schema.json`) rather than a dataset file. The Rust core
+ * owns the list, so directory listings here cannot drift from it.
+ */
+static bool IsCacheStateFile(const std::string &name) {
+  return gaggle_is_cache_state_file(name.c_str()) == 1;
+}
+
 /**
  * @brief Implements the `gaggle_set_credentials(username, key)` SQL function.
  */
@@ -699,23 +708,20 @@ static unique_ptr<FunctionData> GaggleLsBind(ClientContext &context,
     }
   }
 
-  // Canonicalize dataset path: ensure we call downloader with owner/dataset
-  // even if caller provided owner/dataset/... nested path
-  string ds = result->dataset_path;
-  auto first_slash = ds.find('/');
-  if (first_slash == string::npos) {
-    throw InvalidInputException("Invalid dataset path: must be owner/dataset");
+  // Split the dataset path on the Rust side, which parses the version and
+  // rejects nested paths that leave the dataset (such as "owner/dataset/../x")
+  char *ref_c = gaggle_dataset_ref(result->dataset_path.c_str());
+  if (!ref_c) {
+    throw InvalidInputException("Invalid dataset path: " + GetGaggleError());
   }
-  auto second_slash = ds.find('/', first_slash + 1);
-  string canonical_ds;
-  string nested_path;
-  if (second_slash == string::npos) {
-    canonical_ds = ds;
-    nested_path = string();
-  } else {
-    canonical_ds = ds.substr(0, second_slash);
-    nested_path = ds.substr(second_slash + 1);
+  string canonical_ds(ref_c);
+  gaggle_free(ref_c);
+  char *sub_c = gaggle_dataset_subpath(result->dataset_path.c_str());
+  if (!sub_c) {
+    throw InvalidInputException("Invalid dataset path: " + GetGaggleError());
   }
+  string nested_path(sub_c);
+  gaggle_free(sub_c);
 
   // Verify that the dataset is downloaded and get directory for canonical
   // dataset
@@ -738,7 +744,7 @@ static unique_ptr<FunctionData> GaggleLsBind(ClientContext &context,
             continue;
           }
           auto name = entry.path().filename().string();
-          if (name == ".downloaded") {
+          if (IsCacheStateFile(name)) {
             continue;
           }
           auto full_path = entry.path().string();
@@ -766,7 +772,7 @@ static unique_ptr<FunctionData> GaggleLsBind(ClientContext &context,
             continue;
           }
           auto name = entry.path().filename().string();
-          if (name == ".downloaded") {
+          if (IsCacheStateFile(name)) {
             continue;
           }
           auto full_path = entry.path().string();
@@ -804,7 +810,7 @@ static unique_ptr<FunctionData> GaggleLsBind(ClientContext &context,
               continue;
             }
             auto name = entry.path().filename().string();
-            if (name == ".downloaded") {
+            if (IsCacheStateFile(name)) {
               continue;
             }
             auto full_path = entry.path().string();
@@ -830,7 +836,7 @@ static unique_ptr<FunctionData> GaggleLsBind(ClientContext &context,
               continue;
             }
             auto name = entry.path().filename().string();
-            if (name == ".downloaded") {
+            if (IsCacheStateFile(name)) {
               continue;
             }
             auto full_path = entry.path().string();
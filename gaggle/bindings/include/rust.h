@@ -18,6 +18,23 @@
 namespace gaggle {
 #endif  // __cplusplus
 
+/**
+ * A callback that receives Gaggle log messages.
+ *
+ * `level` is 0 for ERROR, 1 for WARN, 2 for INFO, 3 for DEBUG, and 4 for TRACE.
+ * `message` is a NUL-terminated string that is only valid for the duration of the call.
+ * `user_data` is the pointer passed to `gaggle_set_log_callback`.
+ */
+typedef void (*GaggleLogFn)(int32_t level, const char *message, void *user_data);
+
+/**
+ * A callback that receives one item of a streamed result.
+ *
+ * `item` is a NUL-terminated JSON string that is only valid for the duration of the call.
+ * `user_data` is the pointer passed to the streaming function.
+ */
+typedef void (*GaggleItemFn)(const char *item, void *user_data);
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
@@ -36,6 +53,25 @@ extern "C" {
  */
  const char *gaggle_last_error(void);
 
+/**
+ * Retrieves the numeric code of the last error set in the current thread.
+ *
+ * The value is the number in the error code, for example 4 for E004.
+ * Returns 0 if no error has occurred since the last call.
+ */
+ int32_t gaggle_last_error_code(void);
+
+/**
+ * Retrieves the last error set in the current thread as a JSON object.
+ *
+ * The object has the keys `code`, `category`, `message`, `dataset`, `filename`,
+ * `http_status`, and `retryable`. Context keys are `null` when unknown.
+ *
+ * Returns a null pointer if no error has occurred since the last call.
+ * The caller **must not** free this pointer, as it is managed by a thread-local static variable.
+ */
+ const char *gaggle_last_error_json(void);
+
 /**
  * Clears the last error for the current thread.
  *
@@ -49,6 +85,35 @@ extern "C" {
  */
  void gaggle_init_logging(void);
 
+/**
+ * Re-read the GAGGLE_LOG_* environment variables and apply them to the logger
+ */
+ int32_t gaggle_reinit_logging(void);
+
+/**
+ * Change the log level at runtime (same values as GAGGLE_LOG_LEVEL)
+ *
+ * Returns 0 on success, -1 on error.
+ */
+ int32_t gaggle_set_log_level(const char *level);
+
+/**
+ * Write out any log lines buffered for the log file
+ */
+ int32_t gaggle_flush_logs(void);
+
+/**
+ * Register a callback that receives all log messages (replaces the default logger)
+ *
+ * Returns 0 on success, -1 if `cb` is NULL.
+ */
+ int32_t gaggle_set_log_callback(GaggleLogFn cb, void *user_data);
+
+/**
+ * Deregister the log callback and restore the default logger
+ */
+ int32_t gaggle_clear_log_callback(void);
+
 /**
  * Set Kaggle API credentials
  *
@@ -64,11 +129,66 @@ extern "C" {
  */
  int32_t gaggle_set_credentials(const char *username, const char *key);
 
+/**
+ * Set Kaggle API credentials, failing if the username or key is malformed
+ *
+ * Safety:
+ * - The pointers must be valid and remain alive for the duration of this call.
+ * - Strings must be valid UTF-8; interior NULs are not allowed.
+ */
+ int32_t gaggle_set_credentials_strict(const char *username, const char *key);
+
+/**
+ * Set the base URL of the Kaggle API for this process (an empty string clears it)
+ *
+ * Takes precedence over GAGGLE_API_BASE and GAGGLE_KAGGLE_HOSTNAME.
+ * Returns 0 on success, or -1 on failure.
+ *
+ * Safety:
+ * - The pointer must be valid and remain alive for the duration of this call.
+ * - The string must be valid UTF-8; interior NULs are not allowed.
+ */
+ int32_t gaggle_set_api_base(const char *base_url);
+
+/**
+ * Re-create the shared HTTP client from the current configuration, closing pooled connections
+ *
+ * Returns 0 on success, or -1 on failure (such as an invalid proxy or certificate).
+ */
+ int32_t gaggle_reset_http_client(void);
+
+/**
+ * Set the download bandwidth limit for this process, in megabits per second
+ *
+ * The limit is shared by all downloads and takes precedence over GAGGLE_MAX_BANDWIDTH_MBPS.
+ * Pass 0 to remove the limit, or a negative value to revert to GAGGLE_MAX_BANDWIDTH_MBPS.
+ * Returns 0.
+ */
+ int32_t gaggle_set_bandwidth_limit(double mbps);
+
+/**
+ * Clear stored Kaggle API credentials so they are reloaded on next use
+ *
+ * Returns 0 on success.
+ */
+ int32_t gaggle_clear_credentials(void);
+
+/**
+ * Check the current Kaggle credentials against the API
+ */
+ char *gaggle_validate_credentials(void);
+
+/**
+ * Check credentials, API connectivity, and cache writability (always returns a JSON report)
+ */
+ char *gaggle_health_check(void);
+
 /**
  * Download a Kaggle dataset and return its local cache path
  *
  * Arguments:
  * - `dataset_path`: non-null pointer to a NUL-terminated C string "owner/dataset[[@vN|@latest]]".
+ *   A Kaggle dataset URL (https://www.kaggle.com/datasets/owner/dataset) is also accepted.
  *
  * Returns pointer to a heap-allocated C string. Free with gaggle_free(). On error, returns NULL and sets gaggle_last_error.
  *
@@ -78,6 +198,32 @@ extern "C" {
 
 char *gaggle_download_dataset(const char *dataset_path);
 
+/**
+ * Download a Kaggle dataset and return its local cache path, treating a cached copy by `mode`:
+ * 0 serves it (like gaggle_download_dataset), 1 downloads again only if it is not the latest
+ * version, and 2 always downloads again (like gaggle_update_dataset)
+ *
+ * Returns pointer to a heap-allocated C string. Free with gaggle_free(). On error, returns NULL and sets gaggle_last_error.
+ */
+ char *gaggle_download_dataset_ex(const char *dataset_path, int32_t mode);
+
+/**
+ * Download a Kaggle dataset directly into `target_dir`, bypassing the cache
+ * (no marker files are written; a non-empty directory is refused unless overwrite != 0)
+ *
+ * Returns the absolute path of the target directory. Free with gaggle_free(). On error,
+ * returns NULL and sets gaggle_last_error.
+ */
+ char *gaggle_download_dataset_to(const char *dataset_path, const char *target_dir, int32_t overwrite);
+
+/**
+ * Download a Kaggle dataset but extract only the files matching a JSON array of names or glob patterns
+ *
+ * Returns a JSON object {"dataset": ..., "files": [...]} with the local paths of the matching files.
+ * Free with gaggle_free(). On error, returns NULL and sets gaggle_last_error.
+ */
+ char *gaggle_download_dataset_filtered(const char *dataset_path, const char *patterns_json);
+
 /**
  * Get the local path to a specific file in a downloaded dataset
  *
@@ -87,16 +233,167 @@ char *gaggle_download_dataset(const char *dataset_path);
  */
  char *gaggle_get_file_path(const char *dataset_path, const char *filename);
 
+/**
+ * Resolve a glob pattern such as "*.csv" to the matching files in a dataset
+ *
+ * A "**" in the pattern matches across directories. Matching files are downloaded on demand. Returns a JSON array of absolute paths, which is
+ * empty if no file matches. Free with gaggle_free(). On error, returns NULL and sets
+ * gaggle_last_error.
+ */
+ char *gaggle_glob(const char *dataset_path, const char *pattern);
+
 /**
  * List files in a Kaggle dataset
  */
  char *gaggle_list_files(const char *dataset_path);
 
+/**
+ * Return the dataset reference ("owner/dataset" or "owner/dataset@N") of a dataset path
+ *
+ * Free with gaggle_free(). On error, returns NULL and sets gaggle_last_error.
+ */
+ char *gaggle_dataset_ref(const char *dataset_path);
+
+/**
+ * Return the validated path inside the dataset named by a dataset path, or "" if there is none
+ *
+ * Paths that leave the dataset are rejected. Free with gaggle_free(). On error, returns NULL
+ * and sets gaggle_last_error.
+ */
+ char *gaggle_dataset_subpath(const char *dataset_path);
+
+/**
+ * List files in a Kaggle dataset with the given extension, ignoring case
+ * (format = NULL, "", or "*" lists every file)
+ *
+ * Returns a JSON array. Free with gaggle_free(). On error, returns NULL and sets gaggle_last_error.
+ */
+ char *gaggle_list_files_by_format(const char *dataset_path, const char *format);
+
 /**
  * Search for Kaggle datasets
  */
  char *gaggle_search(const char *query, int32_t page, int32_t page_size);
 
+/**
+ * Search for Kaggle datasets, paging with a cursor instead of a page number
+ *
+ * `cursor_json` is NULL or a JSON string with the cursor returned for the previous page;
+ * NULL or a JSON null fetches the first page.
+ * Returns a JSON object {"results": [...], "next_cursor": "..." | null}, where next_cursor
+ * is null on the last page. Free with gaggle_free(). On error, returns NULL and sets
+ * gaggle_last_error.
+ */
+ char *gaggle_search_cursor(const char *query, const char *cursor_json, int32_t page_size);
+
+/**
+ * Search for Kaggle datasets and pass each result to `on_item` as a JSON string
+ *
+ * Returns the number of items emitted, or -1 on error. `on_item` must not keep the
+ * `item` pointer after it returns.
+ */
+ int32_t gaggle_search_streaming(const char *query,
+                                 int32_t page,
+                                 int32_t page_size,
+                                 GaggleItemFn on_item,
+                                 void *user_data);
+
+/**
+ * Search for Kaggle datasets and aggregate results across all pages
+ *
+ * Returns a JSON object {"results": [...], "complete": bool, "error": string|null}.
+ * On a mid-pagination failure, `complete` is false and `results` holds what was fetched.
+ */
+ char *gaggle_search_all(const char *query, int32_t max_results);
+
+/**
+ * Search the locally cached datasets and persisted metadata by path or title, without network access
+ */
+ char *gaggle_search_cached(const char *query);
+
+/**
+ * List trending Kaggle datasets (sortBy=hottest)
+ */
+ char *gaggle_list_trending_datasets(int32_t page, int32_t page_size);
+
+/**
+ * List newly published Kaggle datasets (sortBy=published)
+ */
+ char *gaggle_list_new_datasets(int32_t page, int32_t page_size);
+
+/**
+ * List recently updated Kaggle datasets (sortBy=updated)
+ */
+ char *gaggle_list_updated_datasets(int32_t page, int32_t page_size);
+
+/**
+ * List datasets owned by a Kaggle user or organization
+ */
+ char *gaggle_list_datasets_by_owner(const char *owner, int32_t page, int32_t page_size);
+
+/**
+ * List datasets owned by the current user
+ */
+ char *gaggle_list_my_datasets(int32_t page, int32_t page_size);
+
+/**
+ * Search for datasets with the given tag
+ */
+ char *gaggle_search_by_tag(const char *tag, int32_t page, int32_t page_size);
+
+/**
+ * Detect the DuckDB reader function for a local file from its first bytes
+ */
+ char *gaggle_detect_file_type(const char *path);
+
+/**
+ * Select the DuckDB reader function for a file name from its extension
+ *
+ * Returns a JSON object {"reader": ..., "options": {...}}. Free with gaggle_free().
+ */
+ char *gaggle_guess_reader(const char *filename);
+
+/**
+ * Sniff the delimiter, header, column names, and column types of a CSV file in a dataset
+ * (sample_rows = 0 samples the first 1000 rows; malformed rows are reported, not fatal)
+ *
+ * Returns a JSON object. Free with gaggle_free(). On error, returns NULL and sets gaggle_last_error.
+ */
+ char *gaggle_sniff_csv(const char *dataset_path, const char *filename, int32_t sample_rows);
+
+/**
+ * Get a path DuckDB can read directly for a dataset file, decompressing .gz, .bz2, .xz, .zst,
+ * and single-file .zip files next to the original (done once per file)
+ *
+ * Returns the local path. Free with gaggle_free(). On error, returns NULL and sets gaggle_last_error.
+ */
+ char *gaggle_materialize(const char *dataset_path, const char *filename);
+
+/**
+ * List datasets containing files of the given type (csv, parquet, ...)
+ */
+ char *gaggle_search_by_format(const char *file_type, int32_t page, int32_t page_size);
+
+/**
+ * List tag frequencies across the dataset metadata fetched in this session
+ */
+ char *gaggle_list_popular_tags(void);
+
+/**
+ * Clear the in-memory search result cache
+ */
+ int32_t gaggle_clear_search_cache(void);
+
+/**
+ * Remove all recorded HTTP responses
+ */
+ int32_t gaggle_clear_http_record(void);
+
+/**
+ * List recorded HTTP responses as JSON
+ */
+ char *gaggle_list_http_records(void);
+
 /**
  * Get metadata for a specific Kaggle dataset
  */
@@ -107,6 +404,26 @@ char *gaggle_download_dataset(const char *dataset_path);
  */
  char *gaggle_get_version(void);
 
+/**
+ * Get build metadata (version, Git commit, build date, compiler, target, and features) as JSON
+ */
+ char *gaggle_get_build_info(void);
+
+/**
+ * Check whether dry-run mode (GAGGLE_DRY_RUN) is enabled
+ *
+ * Returns 1 if downloads only report the paths they would use, or 0 otherwise.
+ */
+ int32_t gaggle_is_dry_run(void);
+
+/**
+ * Check whether a file name in a dataset directory is Gaggle cache state rather than dataset data
+ *
+ * Returns 1 for a cache state file (such as .downloaded or .schema.json), 0 for a dataset file,
+ * or -1 on error.
+ */
+ int32_t gaggle_is_cache_state_file(const char *name);
+
 /**
  * Frees a heap-allocated C string
  *
@@ -128,8 +445,48 @@ void gaggle_free(char *ptr);
  */
  int32_t gaggle_enforce_cache_limit(void);
 
+/**
+ * Remove one dataset from the cache, unless it is pinned
+ *
+ * Returns 0 on success (also when the dataset is not cached), or -1 on failure.
+ */
+ int32_t gaggle_clear_dataset_cache(const char *dataset_path);
+
+/**
+ * Remove one dataset from the cache, removing its pin first if it is pinned
+ *
+ * Returns 0 on success (also when the dataset is not cached), or -1 on failure.
+ */
+ int32_t gaggle_clear_dataset_cache_force(const char *dataset_path);
+
+/**
+ * Remove the cached datasets downloaded more than max_age_secs seconds ago, except pinned ones
+ *
+ * Returns the number of datasets removed, or -1 on failure.
+ */
+ int32_t gaggle_evict_datasets_older_than(uint64_t max_age_secs);
+
+/**
+ * Remove the staging directories last modified more than an hour ago, left when a process
+ * is killed while extracting a dataset
+ *
+ * Returns the number of directories removed, or -1 on failure.
+ */
+ int32_t gaggle_cleanup_staging(void);
+
+/**
+ * Moves the cache directory to `new_path` without downloading the cached datasets again,
+ * and records the new location so it is used ahead of GAGGLE_CACHE_DIR
+ *
+ * Returns 0 on success, or -1 on failure.
+ */
+ int32_t gaggle_move_cache_dir(const char *new_path);
+
 /**
  * Check if cached dataset is the current version
+ *
+ * Returns 1 if current, 0 if outdated or not cached, or -1 on error (including an unknown
+ * version in offline mode).
  */
  int32_t gaggle_is_dataset_current(const char *dataset_path);
 
@@ -138,21 +495,187 @@ void gaggle_free(char *ptr);
  */
  char *gaggle_update_dataset(const char *dataset_path);
 
+/**
+ * Download a dataset only if it is not cached or the cached copy is not the latest version
+ *
+ * Returns a JSON object {"path": ..., "was_updated": bool, "previous_version": ... | null,
+ * "current_version": ... | null}. Free with gaggle_free(). On error, returns NULL and sets
+ * gaggle_last_error.
+ */
+ char *gaggle_download_if_stale(const char *dataset_path);
+
+/**
+ * Create a new dataset on Kaggle from a JSON array of local file paths
+ */
+ char *gaggle_create_dataset(const char *owner,
+                             const char *title,
+                             const char *files_json,
+                             const char *license,
+                             int32_t is_private);
+
+/**
+ * Upload local files as a new version of an existing dataset
+ */
+ char *gaggle_update_dataset_files(const char *dataset_path, const char *files_json);
+
+/**
+ * Get the tag names of a dataset as a JSON array
+ */
+ char *gaggle_get_dataset_tags(const char *dataset_path);
+
+/**
+ * Get the documentation of a dataset as Markdown (empty if there is none)
+ */
+ char *gaggle_dataset_readme(const char *dataset_path);
+
+/**
+ * Get the license information of a dataset as a JSON object
+ */
+ char *gaggle_get_dataset_license(const char *dataset_path);
+
+/**
+ * Infer column names and types of the CSV and Parquet files in a cached dataset
+ */
+ char *gaggle_infer_schema(const char *dataset_path);
+
 /**
  * Get version information for a dataset
  */
  char *gaggle_dataset_version_info(const char *dataset_path);
 
 /**
- * Get cache information
+ * Report what downloading a dataset would do (cache state, versions, size, headroom, and evictions) as JSON
+ */
+ char *gaggle_plan_download(const char *dataset_path);
+
+/**
+ * Check a downloaded dataset's files against the checksums recorded by the last check
+ */
+ char *gaggle_verify_dataset_integrity(const char *dataset_path);
+
+/**
+ * Compare a dataset's cached files with the files Kaggle lists for it
+ * (in offline mode, with the metadata saved by the last fetch)
+ *
+ * Returns a JSON object {"complete": ..., "remote_files": ..., "local_files": ...,
+ * "missing": [...], "size_mismatches": [...], "extra": [...]}. Free with gaggle_free().
+ * On error, returns NULL and sets gaggle_last_error.
+ */
+ char *gaggle_check_dataset(const char *dataset_path);
+
+/**
+ * Get cache information, including `total_size_mb` and a per-dataset `datasets` array
  */
  char *gaggle_get_cache_info(void);
 
+/**
+ * Get the total size of the cache in megabytes
+ *
+ * Returns 0 on failure.
+ */
+ uint64_t gaggle_get_total_cache_size_mb(void);
+
+/**
+ * Measure the size of one cached dataset in megabytes
+ *
+ * Returns 0 for a dataset that is not cached, or on failure.
+ */
+ uint64_t gaggle_get_dataset_cache_size_mb(const char *dataset_path);
+
+/**
+ * Recalculate dataset sizes, store them in the cache markers, and return the cache information
+ */
+ char *gaggle_refresh_cache_info(void);
+
+/**
+ * Move cached datasets with uppercase letters in their directories to lowercase directories,
+ * and return a JSON report of the moved datasets and the ones left in place because of a conflict
+ */
+ char *gaggle_migrate_cache_case(void);
+
+/**
+ * Get the counters of API calls, retries, cache hits and misses, downloads, and bytes downloaded as JSON
+ */
+ char *gaggle_get_stats(void);
+
+/**
+ * Reset the counters returned by gaggle_get_stats to zero
+ */
+ int32_t gaggle_reset_stats(void);
+
+/**
+ * Export all cached datasets to a ZIP archive at `dest_path`
+ */
+ char *gaggle_export_cache(const char *dest_path);
+
+/**
+ * Import datasets from a ZIP archive created by gaggle_export_cache
+ * (merge != 0 keeps datasets that are already cached)
+ */
+ char *gaggle_import_cache(const char *archive_path, int32_t merge);
+
+/**
+ * Copy a dataset to `dest_dir` outside the cache, downloading it first if needed
+ * (subdirectories are preserved; marker files are not copied)
+ *
+ * Returns the number of bytes copied, or -1 on failure.
+ */
+ int64_t gaggle_copy_dataset_to(const char *dataset_path, const char *dest_dir);
+
+/**
+ * Export a dataset to `dest_dir` outside the cache, downloading it first if needed
+ * (subdirectories are preserved; files are hard-linked on the same file system and copied
+ * otherwise; overwrite != 0 replaces existing files)
+ *
+ * Returns a JSON object {"files_copied": ..., "files_linked": ..., "bytes": ...,
+ * "errors": [{"file": ..., "error": ...}]}. Free with gaggle_free(). On error, returns NULL
+ * and sets gaggle_last_error.
+ */
+ char *gaggle_export_dataset(const char *dataset_path, const char *dest_dir, int32_t overwrite);
+
+/**
+ * Import a local directory into the cache as a dataset, replacing any cached copy
+ * (version may be NULL; the dataset is then served like a downloaded one, including offline)
+ *
+ * Returns the cache directory of the dataset. Free with gaggle_free(). On error, returns
+ * NULL and sets gaggle_last_error.
+ */
+ char *gaggle_import_dataset(const char *dataset_path, const char *source_dir, const char *version);
+
+/**
+ * Copy a single file of a dataset to `dest_file`, downloading it first if needed
+ *
+ * Returns the number of bytes copied, or -1 on failure.
+ */
+ int64_t gaggle_copy_file_to(const char *dataset_path, const char *filename, const char *dest_file);
+
 /**
  * Parse JSON and expand objects/arrays similar to json_each
  */
  char *gaggle_json_each(const char *json_str);
 
+/**
+ * Parse JSON and expand nested objects/arrays recursively, adding a `depth` field to each row
+ * (max_depth <= 0 means unlimited, capped at 32)
+ */
+ char *gaggle_json_each_recursive(const char *json_str, int32_t max_depth);
+
+/**
+ * Parse JSON, navigate to `path` (e.g. "$.files[2].columns"), and expand that node like json_each
+ * (empty if the path does not resolve)
+ */
+ char *gaggle_json_each_path(const char *json_str, const char *path);
+
+/**
+ * Parse JSON and emit one row per node below `path` (the root if NULL), similar to json_tree
+ */
+ char *gaggle_json_tree(const char *json_str, const char *path);
+
+/**
+ * Flatten nested JSON into a single object whose keys are joined with `separator` (default ".")
+ */
+ char *gaggle_json_flatten(const char *json_str, const char *separator);
+
 /**
  * Prefetch multiple files in a dataset without downloading the entire archive
  */
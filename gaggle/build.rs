@@ -0,0 +1,94 @@
+// build.rs
+//
+// This build script records metadata about the build (the Git commit, the build date, the
+// compiler version, the target triple, and the enabled Cargo features) in compile-time
+// environment variables, which `gaggle_get_build_info` reports at runtime. Values that
+// cannot be determined (for example, when building outside a Git checkout) are left empty.
+// `SOURCE_DATE_EPOCH` is honored for reproducible builds.
+
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let git_sha = command_output("git", &["rev-parse", "--short", "HEAD"]).unwrap_or_default();
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    // The output looks like "rustc 1.80.0 (051478957 2024-07-21)"
+    let rust_version = command_output(&rustc, &["--version"])
+        .and_then(|v| v.split_whitespace().nth(1).map(str::to_string))
+        .unwrap_or_default();
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|f| f.to_ascii_lowercase())
+        })
+        .filter(|f| f != "default")
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=GAGGLE_BUILD_GIT_SHA={}", git_sha);
+    println!("cargo:rustc-env=GAGGLE_BUILD_DATE={}", build_date());
+    println!(
+        "cargo:rustc-env=GAGGLE_BUILD_RUSTC_VERSION={}",
+        rust_version
+    );
+    println!(
+        "cargo:rustc-env=GAGGLE_BUILD_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=GAGGLE_BUILD_FEATURES={}",
+        features.join(",")
+    );
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    // Rerun when another branch is checked out or a commit is made on the current one
+    let mut git_paths = vec!["HEAD".to_string()];
+    git_paths.extend(command_output("git", &["symbolic-ref", "-q", "HEAD"]));
+    for path in git_paths {
+        if let Some(p) = command_output("git", &["rev-parse", "--git-path", &path]) {
+            println!("cargo:rerun-if-changed={}", p);
+        }
+    }
+}
+
+/// Runs a command and returns its trimmed standard output, if it succeeded.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Returns the build date as `YYYY-MM-DD` in UTC.
+fn build_date() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs())
+        })
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date in the Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
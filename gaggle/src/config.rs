@@ -1,4 +1,5 @@
 use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 
 #[cfg(test)]
 use std::cell::RefCell;
@@ -27,9 +28,48 @@ pub struct GaggleConfig {
     /// `download_wait_timeout_ms` sets the timeout for waiting on a download lock.
     #[allow(dead_code)]
     pub download_wait_timeout_ms: u64,
-    /// `download_wait_poll_ms` sets the polling interval for the download lock.
+    /// `tls` holds the custom CA, client certificate, and verification settings.
     #[allow(dead_code)]
-    pub download_wait_poll_ms: u64,
+    pub tls: TlsConfig,
+}
+
+/// `TlsConfig` contains the TLS settings used when building the HTTP client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// `ca_cert` is a PEM file with extra root certificates (`GAGGLE_CA_CERT`, or its
+    /// alias `GAGGLE_CA_BUNDLE`).
+    pub ca_cert: Option<PathBuf>,
+    /// `client_cert` is a PEM client certificate for mutual TLS (`GAGGLE_CLIENT_CERT`).
+    pub client_cert: Option<PathBuf>,
+    /// `client_key` is the PEM private key for `client_cert` (`GAGGLE_CLIENT_KEY`).
+    pub client_key: Option<PathBuf>,
+    /// `verify` enables certificate verification (`GAGGLE_TLS_VERIFY`, default `true`). It is
+    /// also turned off by `GAGGLE_ACCEPT_INVALID_CERTS`.
+    pub verify: bool,
+}
+
+impl TlsConfig {
+    /// Loads the TLS settings from environment variables.
+    pub fn from_env() -> Self {
+        let path_var = |name: &str| {
+            env::var(name)
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from)
+        };
+        let accept_invalid_certs = env::var("GAGGLE_ACCEPT_INVALID_CERTS")
+            .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+            .unwrap_or(false);
+        Self {
+            ca_cert: path_var("GAGGLE_CA_CERT").or_else(|| path_var("GAGGLE_CA_BUNDLE")),
+            client_cert: path_var("GAGGLE_CLIENT_CERT"),
+            client_key: path_var("GAGGLE_CLIENT_KEY"),
+            verify: !accept_invalid_certs
+                && env::var("GAGGLE_TLS_VERIFY")
+                    .map(|v| !matches!(v.to_lowercase().as_str(), "false" | "no" | "off" | "0"))
+                    .unwrap_or(true),
+        }
+    }
 }
 
 impl GaggleConfig {
@@ -40,7 +80,7 @@ impl GaggleConfig {
             verbose_logging: Self::get_verbose(),
             http_timeout_secs: Self::get_http_timeout(),
             download_wait_timeout_ms: Self::get_download_wait_timeout_ms(),
-            download_wait_poll_ms: Self::get_download_wait_poll_ms(),
+            tls: TlsConfig::from_env(),
         }
     }
 
@@ -90,16 +130,6 @@ impl GaggleConfig {
             .map(|secs| (secs * 1000.0).round() as u64)
             .unwrap_or(30_000)
     }
-
-    /// Retrieves the download wait poll interval from `GAGGLE_DOWNLOAD_WAIT_POLL`,
-    /// in milliseconds. Defaults to 100 ms.
-    fn get_download_wait_poll_ms() -> u64 {
-        env::var("GAGGLE_DOWNLOAD_WAIT_POLL")
-            .ok()
-            .and_then(|v| v.parse::<f64>().ok())
-            .map(|secs| (secs * 1000.0).round() as u64)
-            .unwrap_or(100)
-    }
 }
 
 impl Default for GaggleConfig {
@@ -124,16 +154,57 @@ pub fn cache_dir_runtime() -> PathBuf {
             return p;
         }
     }
-    // 2) Environment variable
+    // 2) Directory recorded by `gaggle_move_cache_dir`
+    if let Some(dir) = cache_dir_override() {
+        return dir;
+    }
+    // 3) Environment variable
     if let Ok(val) = env::var("GAGGLE_CACHE_DIR") {
         if !val.is_empty() {
             return PathBuf::from(val);
         }
     }
-    // 3) Fallback to static config
+    // 4) Fallback to static config
     CONFIG.cache_dir.clone()
 }
 
+/// File recording the cache directory set with `gaggle_move_cache_dir`.
+/// Controlled by GAGGLE_CACHE_OVERRIDE_FILE (default: `gaggle/cache_override` in the user's
+/// config directory, normally `~/.config/gaggle/cache_override`). Tests have no default, so
+/// they never pick up a cache moved on the machine running them.
+pub fn cache_override_file() -> Option<PathBuf> {
+    match env::var("GAGGLE_CACHE_OVERRIDE_FILE") {
+        Ok(val) if !val.trim().is_empty() => Some(PathBuf::from(val)),
+        #[cfg(test)]
+        _ => None,
+        #[cfg(not(test))]
+        _ => dirs::config_dir().map(|d| d.join(DEFAULT_CACHE_DIR_NAME).join("cache_override")),
+    }
+}
+
+/// The cache directory recorded in the override file, read once per process and updated by
+/// `gaggle_move_cache_dir`
+static CACHE_DIR_OVERRIDE: Lazy<RwLock<Option<PathBuf>>> =
+    Lazy::new(|| RwLock::new(read_cache_dir_override()));
+
+/// Reads the override file, returning the directory if the file exists and is not empty
+fn read_cache_dir_override() -> Option<PathBuf> {
+    let contents = std::fs::read_to_string(cache_override_file()?).ok()?;
+    let dir = contents.trim();
+    (!dir.is_empty()).then(|| PathBuf::from(dir))
+}
+
+/// The cache directory set with `gaggle_move_cache_dir`, if any
+fn cache_dir_override() -> Option<PathBuf> {
+    CACHE_DIR_OVERRIDE.read().clone()
+}
+
+/// Sets the cache directory that takes precedence over GAGGLE_CACHE_DIR in this process,
+/// after it has been recorded in the override file
+pub(crate) fn set_cache_dir_override(dir: Option<PathBuf>) {
+    *CACHE_DIR_OVERRIDE.write() = dir;
+}
+
 /// Runtime-resolved HTTP timeout in seconds
 pub fn http_timeout_runtime_secs() -> u64 {
     env::var("GAGGLE_HTTP_TIMEOUT")
@@ -142,6 +213,78 @@ pub fn http_timeout_runtime_secs() -> u64 {
         .unwrap_or(CONFIG.http_timeout_secs)
 }
 
+/// Runtime-resolved timeout for dataset and file downloads in seconds
+/// (GAGGLE_DOWNLOAD_TIMEOUT_SECS, default 3600)
+pub fn download_timeout_runtime_secs() -> u64 {
+    env::var("GAGGLE_DOWNLOAD_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(3600)
+}
+
+/// Maximum number of idle HTTP connections kept open per host
+/// (GAGGLE_CONNECTION_POOL_SIZE, default 10)
+pub fn connection_pool_size() -> usize {
+    env::var("GAGGLE_CONNECTION_POOL_SIZE")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(10)
+}
+
+/// TCP keepalive interval for HTTP connections in seconds (GAGGLE_KEEPALIVE_SECS, default 90).
+/// `0` disables keepalive.
+pub fn keepalive_secs() -> u64 {
+    env::var("GAGGLE_KEEPALIVE_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(90)
+}
+
+/// Runtime-resolved verbose logging setting (GAGGLE_VERBOSE)
+pub fn verbose_runtime() -> bool {
+    GaggleConfig::get_verbose()
+}
+
+/// Runtime-resolved TLS settings (checks env each call)
+pub fn tls_config() -> TlsConfig {
+    TlsConfig::from_env()
+}
+
+/// Proxy URL used for all HTTP requests, from GAGGLE_HTTP_PROXY.
+/// When unset, reqwest falls back to the standard proxy environment variables.
+pub fn http_proxy() -> Option<String> {
+    env::var("GAGGLE_HTTP_PROXY")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Hostname of the Kaggle API, from GAGGLE_KAGGLE_HOSTNAME (default "www.kaggle.com").
+/// Used to build the API base URL when GAGGLE_API_BASE is not set.
+pub fn kaggle_hostname() -> String {
+    env::var("GAGGLE_KAGGLE_HOSTNAME")
+        .ok()
+        .map(|v| v.trim().trim_end_matches('/').to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "www.kaggle.com".to_string())
+}
+
+/// Protocol of the Kaggle API, from GAGGLE_KAGGLE_PROTOCOL: "http" or "https" (default).
+pub fn kaggle_protocol() -> &'static str {
+    match env::var("GAGGLE_KAGGLE_PROTOCOL") {
+        Ok(v) if v.trim().eq_ignore_ascii_case("http") => "http",
+        _ => "https",
+    }
+}
+
+/// Text appended to the default User-Agent header, from GAGGLE_USER_AGENT_SUFFIX
+pub fn user_agent_suffix() -> Option<String> {
+    env::var("GAGGLE_USER_AGENT_SUFFIX")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
 /// HTTP retry attempts (default 3)
 pub fn http_retry_attempts() -> u32 {
     env::var("GAGGLE_HTTP_RETRY_ATTEMPTS")
@@ -168,6 +311,15 @@ pub fn http_retry_max_delay_ms() -> u64 {
         .unwrap_or(30000)
 }
 
+/// Maximum size of a JSON API response body in bytes (GAGGLE_MAX_RESPONSE_BYTES, default 10MB)
+pub fn max_response_bytes() -> usize {
+    env::var("GAGGLE_MAX_RESPONSE_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&n: &usize| n > 0)
+        .unwrap_or(10_485_760)
+}
+
 /// Cache size limit in megabytes (default 100GB = 102400 MB)
 /// Returns None if unlimited
 pub fn cache_size_limit_mb() -> Option<u64> {
@@ -178,6 +330,44 @@ pub fn cache_size_limit_mb() -> Option<u64> {
     }
 }
 
+/// Maximum size of a single dataset download in megabytes (GAGGLE_MAX_DATASET_SIZE_MB)
+/// Returns None if unset or invalid, meaning no per-dataset limit
+pub fn max_dataset_size_mb() -> Option<u64> {
+    env::var("GAGGLE_MAX_DATASET_SIZE_MB")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Maximum total uncompressed size of an extracted archive in megabytes
+/// (GAGGLE_MAX_EXTRACT_SIZE_MB, default 10GB = 10240 MB). Returns None if `unlimited`
+pub fn max_extract_size_mb() -> Option<u64> {
+    match env::var("GAGGLE_MAX_EXTRACT_SIZE_MB").ok() {
+        Some(val) if val.trim().eq_ignore_ascii_case("unlimited") => None,
+        Some(val) => Some(val.trim().parse().unwrap_or(10240)),
+        None => Some(10240),
+    }
+}
+
+/// Maximum compression ratio of an archive entry (GAGGLE_MAX_COMPRESSION_RATIO, default 100)
+/// Returns None if `unlimited`
+pub fn max_compression_ratio() -> Option<u64> {
+    match env::var("GAGGLE_MAX_COMPRESSION_RATIO").ok() {
+        Some(val) if val.trim().eq_ignore_ascii_case("unlimited") => None,
+        Some(val) => Some(val.trim().parse().unwrap_or(100)),
+        None => Some(100),
+    }
+}
+
+/// Maximum number of entries in a ZIP archive that is extracted (GAGGLE_MAX_ZIP_FILES,
+/// default 100000)
+pub fn max_zip_files() -> usize {
+    env::var("GAGGLE_MAX_ZIP_FILES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(100_000)
+}
+
 /// Whether cache limit is a soft limit (default true)
 /// Soft limit allows download to complete even if it exceeds limit,
 /// then triggers cleanup afterwards
@@ -188,6 +378,34 @@ pub fn cache_limit_is_soft() -> bool {
         .unwrap_or(true)
 }
 
+/// Space in megabytes that must remain under the cache size limit after a download in hard
+/// limit mode (GAGGLE_MIN_FREE_CACHE_MB, default 0)
+pub fn min_free_cache_mb() -> u64 {
+    env::var("GAGGLE_MIN_FREE_CACHE_MB")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Maximum age of a cached dataset in seconds before it is revalidated (GAGGLE_CACHE_TTL_SECS)
+/// Returns None if unset, `unlimited`, zero, or invalid, meaning cached datasets never expire
+pub fn cache_ttl_secs() -> Option<u64> {
+    env::var("GAGGLE_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+}
+
+/// Cache eviction policy (GAGGLE_CACHE_EVICTION_POLICY)
+/// One of `lru` (default), `lfu`, `fifo`, or `largest_first`; returned lowercased
+pub fn cache_eviction_policy() -> String {
+    env::var("GAGGLE_CACHE_EVICTION_POLICY")
+        .ok()
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "lru".to_string())
+}
+
 /// Runtime-resolved download wait timeout in milliseconds
 pub fn download_wait_timeout_ms() -> u64 {
     env::var("GAGGLE_DOWNLOAD_WAIT_TIMEOUT")
@@ -197,13 +415,75 @@ pub fn download_wait_timeout_ms() -> u64 {
         .unwrap_or(CONFIG.download_wait_timeout_ms)
 }
 
-/// Runtime-resolved download wait poll interval in milliseconds
-pub fn download_wait_poll_interval_ms() -> u64 {
-    env::var("GAGGLE_DOWNLOAD_WAIT_POLL")
+/// Number of parallel connections used to download a dataset archive
+/// (GAGGLE_DOWNLOAD_CONNECTIONS, default 1, between 1 and 16)
+pub fn download_connections() -> usize {
+    env::var("GAGGLE_DOWNLOAD_CONNECTIONS")
         .ok()
-        .and_then(|v| v.parse::<f64>().ok())
-        .map(|secs| (secs * 1000.0).round() as u64)
-        .unwrap_or(CONFIG.download_wait_poll_ms)
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(1)
+        .clamp(1, 16)
+}
+
+/// Download bandwidth limit in megabits per second (GAGGLE_MAX_BANDWIDTH_MBPS, unset by
+/// default for no limit)
+pub fn max_bandwidth_mbps() -> Option<f64> {
+    env::var("GAGGLE_MAX_BANDWIDTH_MBPS")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v > 0.0)
+}
+
+/// API call rate limit in calls per second (GAGGLE_RATE_LIMIT_RPS, unset or `0` for no limit)
+pub fn rate_limit_rps() -> Option<f64> {
+    env::var("GAGGLE_RATE_LIMIT_RPS")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v > 0.0)
+}
+
+/// Number of API calls that can be made at once before the rate limit applies
+/// (GAGGLE_RATE_LIMIT_BURST, default 10, at least 1)
+pub fn rate_limit_burst() -> f64 {
+    env::var("GAGGLE_RATE_LIMIT_BURST")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 1.0)
+        .unwrap_or(10.0)
+}
+
+/// Number of worker threads used to prefetch files (GAGGLE_PREFETCH_CONCURRENCY, default 4, at least 1)
+pub fn prefetch_concurrency() -> usize {
+    env::var("GAGGLE_PREFETCH_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(4)
+        .max(1)
+}
+
+/// File that logs are written to instead of the console (GAGGLE_LOG_FILE, unset by default)
+pub fn log_file() -> Option<PathBuf> {
+    env::var_os("GAGGLE_LOG_FILE")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Size in MB at which the log file is rotated (GAGGLE_LOG_MAX_SIZE_MB, unset by default,
+/// in which case the file is rotated daily)
+pub fn log_max_size_mb() -> Option<u64> {
+    env::var("GAGGLE_LOG_MAX_SIZE_MB")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|mb| *mb > 0)
+}
+
+/// Whether search results repeated within a page are dropped. Controlled by
+/// GAGGLE_SEARCH_DEDUPLICATE
+pub fn search_deduplicate() -> bool {
+    std::env::var("GAGGLE_SEARCH_DEDUPLICATE")
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
 }
 
 /// Whether offline mode is enabled (disables network operations). Controlled by GAGGLE_OFFLINE
@@ -214,6 +494,33 @@ pub fn offline_mode() -> bool {
         .unwrap_or(false)
 }
 
+/// Whether every download of a cached dataset checks for a newer version first, like
+/// `download_dataset_if_stale`. Controlled by GAGGLE_ALWAYS_CHECK_UPDATES
+pub fn always_check_updates() -> bool {
+    std::env::var("GAGGLE_ALWAYS_CHECK_UPDATES")
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+/// Whether dry-run mode is enabled, in which downloads only report the paths they would
+/// use and make no HTTP requests. Controlled by GAGGLE_DRY_RUN
+pub fn dry_run() -> bool {
+    std::env::var("GAGGLE_DRY_RUN")
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+/// Whether credentials are checked against the Kaggle API when they are set.
+/// Controlled by GAGGLE_VALIDATE_CREDENTIALS_ON_SET
+pub fn validate_credentials_on_set() -> bool {
+    std::env::var("GAGGLE_VALIDATE_CREDENTIALS_ON_SET")
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
 /// Whether strict on-demand mode is enabled. When true, gaggle_get_file_path will NOT fall back to
 /// full dataset download if single-file fetch fails.
 pub fn strict_on_demand() -> bool {
@@ -223,6 +530,52 @@ pub fn strict_on_demand() -> bool {
         .unwrap_or(false)
 }
 
+/// Whether the archive downloaded by a filtered download is kept in the dataset's cache
+/// directory, so later filtered downloads can extract more entries without downloading it
+/// again. Controlled by GAGGLE_KEEP_ARCHIVE
+pub fn keep_archive() -> bool {
+    std::env::var("GAGGLE_KEEP_ARCHIVE")
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+/// Directory where dataset archives are extracted before they are moved into the cache.
+/// Controlled by GAGGLE_STAGING_DIR (default: `.staging` in the cache directory)
+pub fn staging_dir() -> PathBuf {
+    match env::var("GAGGLE_STAGING_DIR") {
+        Ok(val) if !val.trim().is_empty() => PathBuf::from(val),
+        _ => cache_dir_runtime().join(".staging"),
+    }
+}
+
+/// Whether `gaggle_get_file_path` decompresses `.gz`, `.bz2`, `.xz`, `.zst`, and single-file
+/// `.zip` files and returns the decompressed path. Controlled by GAGGLE_AUTO_DECOMPRESS
+pub fn auto_decompress() -> bool {
+    std::env::var("GAGGLE_AUTO_DECOMPRESS")
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+/// Whether `KAGGLE_USERNAME` and `KAGGLE_KEY` are also read from a `.env` file in the
+/// current directory. Controlled by GAGGLE_DOTENV
+pub fn dotenv_enabled() -> bool {
+    std::env::var("GAGGLE_DOTENV")
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+/// Whether the metadata lookup that catches missing datasets before a download is skipped.
+/// Controlled by GAGGLE_SKIP_EXISTENCE_CHECK
+pub fn skip_existence_check() -> bool {
+    std::env::var("GAGGLE_SKIP_EXISTENCE_CHECK")
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -236,7 +589,6 @@ mod tests {
         assert!(!config.verbose_logging);
         assert_eq!(config.http_timeout_secs, 30);
         assert!(config.download_wait_timeout_ms >= 1000);
-        assert!(config.download_wait_poll_ms > 0);
     }
 
     #[test]
@@ -511,6 +863,29 @@ mod tests {
         env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
     }
 
+    #[test]
+    #[serial]
+    fn test_extract_limits() {
+        env::remove_var("GAGGLE_MAX_EXTRACT_SIZE_MB");
+        env::remove_var("GAGGLE_MAX_COMPRESSION_RATIO");
+        assert_eq!(max_extract_size_mb(), Some(10240));
+        assert_eq!(max_compression_ratio(), Some(100));
+
+        env::set_var("GAGGLE_MAX_EXTRACT_SIZE_MB", "51200");
+        env::set_var("GAGGLE_MAX_COMPRESSION_RATIO", "Unlimited");
+        assert_eq!(max_extract_size_mb(), Some(51200));
+        assert_eq!(max_compression_ratio(), None);
+
+        // Invalid values keep the defaults
+        env::set_var("GAGGLE_MAX_EXTRACT_SIZE_MB", "lots");
+        env::set_var("GAGGLE_MAX_COMPRESSION_RATIO", "-1");
+        assert_eq!(max_extract_size_mb(), Some(10240));
+        assert_eq!(max_compression_ratio(), Some(100));
+
+        env::remove_var("GAGGLE_MAX_EXTRACT_SIZE_MB");
+        env::remove_var("GAGGLE_MAX_COMPRESSION_RATIO");
+    }
+
     #[test]
     #[serial]
     fn test_cache_limit_soft_by_default() {
@@ -526,15 +901,155 @@ mod tests {
         env::remove_var("GAGGLE_CACHE_HARD_LIMIT");
     }
 
+    #[test]
+    #[serial]
+    fn test_max_response_bytes() {
+        env::remove_var("GAGGLE_MAX_RESPONSE_BYTES");
+        assert_eq!(max_response_bytes(), 10_485_760);
+        env::set_var("GAGGLE_MAX_RESPONSE_BYTES", "1024");
+        assert_eq!(max_response_bytes(), 1024);
+        env::set_var("GAGGLE_MAX_RESPONSE_BYTES", "0");
+        assert_eq!(max_response_bytes(), 10_485_760);
+        env::remove_var("GAGGLE_MAX_RESPONSE_BYTES");
+    }
+
+    #[test]
+    #[serial]
+    fn test_min_free_cache_mb() {
+        env::remove_var("GAGGLE_MIN_FREE_CACHE_MB");
+        assert_eq!(min_free_cache_mb(), 0);
+        env::set_var("GAGGLE_MIN_FREE_CACHE_MB", " 512 ");
+        assert_eq!(min_free_cache_mb(), 512);
+        env::set_var("GAGGLE_MIN_FREE_CACHE_MB", "lots");
+        assert_eq!(min_free_cache_mb(), 0);
+        env::remove_var("GAGGLE_MIN_FREE_CACHE_MB");
+    }
+
+    #[test]
+    #[serial]
+    fn test_prefetch_concurrency() {
+        env::remove_var("GAGGLE_PREFETCH_CONCURRENCY");
+        assert_eq!(prefetch_concurrency(), 4);
+        env::set_var("GAGGLE_PREFETCH_CONCURRENCY", "16");
+        assert_eq!(prefetch_concurrency(), 16);
+        env::set_var("GAGGLE_PREFETCH_CONCURRENCY", "0");
+        assert_eq!(prefetch_concurrency(), 1);
+        env::remove_var("GAGGLE_PREFETCH_CONCURRENCY");
+    }
+
+    #[test]
+    #[serial]
+    fn test_cache_ttl_secs() {
+        env::remove_var("GAGGLE_CACHE_TTL_SECS");
+        assert_eq!(cache_ttl_secs(), None);
+        env::set_var("GAGGLE_CACHE_TTL_SECS", "86400");
+        assert_eq!(cache_ttl_secs(), Some(86400));
+        env::set_var("GAGGLE_CACHE_TTL_SECS", "unlimited");
+        assert_eq!(cache_ttl_secs(), None);
+        env::set_var("GAGGLE_CACHE_TTL_SECS", "0");
+        assert_eq!(cache_ttl_secs(), None);
+        env::remove_var("GAGGLE_CACHE_TTL_SECS");
+    }
+
+    #[test]
+    #[serial]
+    fn test_cache_eviction_policy() {
+        env::remove_var("GAGGLE_CACHE_EVICTION_POLICY");
+        assert_eq!(cache_eviction_policy(), "lru");
+        env::set_var("GAGGLE_CACHE_EVICTION_POLICY", " LFU ");
+        assert_eq!(cache_eviction_policy(), "lfu");
+        env::remove_var("GAGGLE_CACHE_EVICTION_POLICY");
+    }
+
     #[test]
     #[serial]
     fn test_download_wait_runtime_overrides() {
         env::set_var("GAGGLE_DOWNLOAD_WAIT_TIMEOUT", "1.234");
-        env::set_var("GAGGLE_DOWNLOAD_WAIT_POLL", "0.017");
         assert_eq!(download_wait_timeout_ms(), 1234);
-        assert_eq!(download_wait_poll_interval_ms(), 17);
         env::remove_var("GAGGLE_DOWNLOAD_WAIT_TIMEOUT");
-        env::remove_var("GAGGLE_DOWNLOAD_WAIT_POLL");
+    }
+
+    #[test]
+    #[serial]
+    fn test_download_timeout_runtime_secs() {
+        env::remove_var("GAGGLE_DOWNLOAD_TIMEOUT_SECS");
+        assert_eq!(download_timeout_runtime_secs(), 3600);
+        env::set_var("GAGGLE_DOWNLOAD_TIMEOUT_SECS", "7200");
+        assert_eq!(download_timeout_runtime_secs(), 7200);
+        env::set_var("GAGGLE_DOWNLOAD_TIMEOUT_SECS", "soon");
+        assert_eq!(download_timeout_runtime_secs(), 3600);
+        env::remove_var("GAGGLE_DOWNLOAD_TIMEOUT_SECS");
+    }
+
+    #[test]
+    #[serial]
+    fn test_connection_pool_size_and_keepalive() {
+        env::remove_var("GAGGLE_CONNECTION_POOL_SIZE");
+        env::remove_var("GAGGLE_KEEPALIVE_SECS");
+        assert_eq!(connection_pool_size(), 10);
+        assert_eq!(keepalive_secs(), 90);
+        env::set_var("GAGGLE_CONNECTION_POOL_SIZE", "32");
+        env::set_var("GAGGLE_KEEPALIVE_SECS", "0");
+        assert_eq!(connection_pool_size(), 32);
+        assert_eq!(keepalive_secs(), 0);
+        env::set_var("GAGGLE_CONNECTION_POOL_SIZE", "many");
+        env::set_var("GAGGLE_KEEPALIVE_SECS", "-1");
+        assert_eq!(connection_pool_size(), 10);
+        assert_eq!(keepalive_secs(), 90);
+        env::remove_var("GAGGLE_CONNECTION_POOL_SIZE");
+        env::remove_var("GAGGLE_KEEPALIVE_SECS");
+    }
+
+    #[test]
+    #[serial]
+    fn test_download_connections_is_clamped() {
+        env::remove_var("GAGGLE_DOWNLOAD_CONNECTIONS");
+        assert_eq!(download_connections(), 1);
+        env::set_var("GAGGLE_DOWNLOAD_CONNECTIONS", "4");
+        assert_eq!(download_connections(), 4);
+        env::set_var("GAGGLE_DOWNLOAD_CONNECTIONS", "0");
+        assert_eq!(download_connections(), 1);
+        env::set_var("GAGGLE_DOWNLOAD_CONNECTIONS", "100");
+        assert_eq!(download_connections(), 16);
+        env::remove_var("GAGGLE_DOWNLOAD_CONNECTIONS");
+    }
+
+    #[test]
+    #[serial]
+    fn test_max_bandwidth_mbps_parsing() {
+        env::remove_var("GAGGLE_MAX_BANDWIDTH_MBPS");
+        assert_eq!(max_bandwidth_mbps(), None);
+        env::set_var("GAGGLE_MAX_BANDWIDTH_MBPS", "2.5");
+        assert_eq!(max_bandwidth_mbps(), Some(2.5));
+        env::set_var("GAGGLE_MAX_BANDWIDTH_MBPS", "0");
+        assert_eq!(max_bandwidth_mbps(), None);
+        env::set_var("GAGGLE_MAX_BANDWIDTH_MBPS", "fast");
+        assert_eq!(max_bandwidth_mbps(), None);
+        env::remove_var("GAGGLE_MAX_BANDWIDTH_MBPS");
+    }
+
+    #[test]
+    #[serial]
+    fn test_always_check_updates_env_parsing() {
+        env::remove_var("GAGGLE_ALWAYS_CHECK_UPDATES");
+        assert!(!always_check_updates());
+        env::set_var("GAGGLE_ALWAYS_CHECK_UPDATES", "1");
+        assert!(always_check_updates());
+        env::set_var("GAGGLE_ALWAYS_CHECK_UPDATES", "off");
+        assert!(!always_check_updates());
+        env::remove_var("GAGGLE_ALWAYS_CHECK_UPDATES");
+    }
+
+    #[test]
+    #[serial]
+    fn test_dry_run_env_parsing() {
+        std::env::remove_var("GAGGLE_DRY_RUN");
+        assert!(!dry_run());
+        std::env::set_var("GAGGLE_DRY_RUN", "1");
+        assert!(dry_run());
+        std::env::set_var("GAGGLE_DRY_RUN", "off");
+        assert!(!dry_run());
+        std::env::remove_var("GAGGLE_DRY_RUN");
     }
 
     #[test]
@@ -551,6 +1066,56 @@ mod tests {
         std::env::remove_var("GAGGLE_OFFLINE");
     }
 
+    #[test]
+    #[serial]
+    fn test_validate_credentials_on_set_env_parsing() {
+        std::env::remove_var("GAGGLE_VALIDATE_CREDENTIALS_ON_SET");
+        assert!(!validate_credentials_on_set());
+        std::env::set_var("GAGGLE_VALIDATE_CREDENTIALS_ON_SET", "yes");
+        let enabled = validate_credentials_on_set();
+        std::env::set_var("GAGGLE_VALIDATE_CREDENTIALS_ON_SET", "0");
+        let disabled = validate_credentials_on_set();
+        std::env::remove_var("GAGGLE_VALIDATE_CREDENTIALS_ON_SET");
+        assert!(enabled);
+        assert!(!disabled);
+    }
+
+    #[test]
+    #[serial]
+    fn test_keep_archive_env_parsing() {
+        std::env::remove_var("GAGGLE_KEEP_ARCHIVE");
+        assert!(!keep_archive());
+        std::env::set_var("GAGGLE_KEEP_ARCHIVE", "1");
+        assert!(keep_archive());
+        std::env::set_var("GAGGLE_KEEP_ARCHIVE", "no");
+        assert!(!keep_archive());
+        std::env::remove_var("GAGGLE_KEEP_ARCHIVE");
+    }
+
+    #[test]
+    #[serial]
+    fn test_dotenv_enabled_env_parsing() {
+        std::env::remove_var("GAGGLE_DOTENV");
+        assert!(!dotenv_enabled());
+        std::env::set_var("GAGGLE_DOTENV", "1");
+        assert!(dotenv_enabled());
+        std::env::set_var("GAGGLE_DOTENV", "0");
+        assert!(!dotenv_enabled());
+        std::env::remove_var("GAGGLE_DOTENV");
+    }
+
+    #[test]
+    #[serial]
+    fn test_skip_existence_check_env_parsing() {
+        std::env::remove_var("GAGGLE_SKIP_EXISTENCE_CHECK");
+        assert!(!skip_existence_check());
+        std::env::set_var("GAGGLE_SKIP_EXISTENCE_CHECK", "1");
+        assert!(skip_existence_check());
+        std::env::set_var("GAGGLE_SKIP_EXISTENCE_CHECK", "off");
+        assert!(!skip_existence_check());
+        std::env::remove_var("GAGGLE_SKIP_EXISTENCE_CHECK");
+    }
+
     #[test]
     #[serial]
     fn test_strict_on_demand_env_parsing() {
@@ -564,4 +1129,60 @@ mod tests {
         assert!(!strict_on_demand());
         std::env::remove_var("GAGGLE_STRICT_ONDEMAND");
     }
+
+    #[test]
+    #[serial]
+    fn test_tls_config_from_env() {
+        env::remove_var("GAGGLE_CA_CERT");
+        env::remove_var("GAGGLE_CLIENT_CERT");
+        env::remove_var("GAGGLE_CLIENT_KEY");
+        env::remove_var("GAGGLE_TLS_VERIFY");
+        let tls = tls_config();
+        assert_eq!(tls.ca_cert, None);
+        assert!(tls.verify);
+
+        env::set_var("GAGGLE_CA_CERT", "/etc/ssl/corp.pem");
+        env::set_var("GAGGLE_TLS_VERIFY", "false");
+        let tls = GaggleConfig::from_env().tls;
+        assert_eq!(tls.ca_cert, Some(PathBuf::from("/etc/ssl/corp.pem")));
+        assert!(!tls.verify);
+
+        // GAGGLE_CA_BUNDLE is an alias, and GAGGLE_ACCEPT_INVALID_CERTS also disables verification
+        env::remove_var("GAGGLE_CA_CERT");
+        env::remove_var("GAGGLE_TLS_VERIFY");
+        env::set_var("GAGGLE_CA_BUNDLE", "/etc/ssl/bundle.pem");
+        env::set_var("GAGGLE_ACCEPT_INVALID_CERTS", "1");
+        let tls = tls_config();
+        assert_eq!(tls.ca_cert, Some(PathBuf::from("/etc/ssl/bundle.pem")));
+        assert!(!tls.verify);
+        env::set_var("GAGGLE_CA_CERT", "/etc/ssl/corp.pem");
+        env::set_var("GAGGLE_ACCEPT_INVALID_CERTS", "no");
+        let tls = tls_config();
+        assert_eq!(tls.ca_cert, Some(PathBuf::from("/etc/ssl/corp.pem")));
+        assert!(tls.verify);
+
+        env::remove_var("GAGGLE_CA_CERT");
+        env::remove_var("GAGGLE_CA_BUNDLE");
+        env::remove_var("GAGGLE_ACCEPT_INVALID_CERTS");
+    }
+
+    #[test]
+    #[serial]
+    fn test_http_proxy_and_user_agent_suffix() {
+        env::remove_var("GAGGLE_HTTP_PROXY");
+        env::remove_var("GAGGLE_USER_AGENT_SUFFIX");
+        assert_eq!(http_proxy(), None);
+        assert_eq!(user_agent_suffix(), None);
+
+        env::set_var("GAGGLE_HTTP_PROXY", " http://proxy.corp:3128 ");
+        env::set_var("GAGGLE_USER_AGENT_SUFFIX", "acme-etl/2.1");
+        assert_eq!(http_proxy().as_deref(), Some("http://proxy.corp:3128"));
+        assert_eq!(user_agent_suffix().as_deref(), Some("acme-etl/2.1"));
+
+        env::set_var("GAGGLE_HTTP_PROXY", "");
+        assert_eq!(http_proxy(), None);
+
+        env::remove_var("GAGGLE_HTTP_PROXY");
+        env::remove_var("GAGGLE_USER_AGENT_SUFFIX");
+    }
 }
@@ -1,5 +1,5 @@
 use serde_json::json;
-use std::ffi::{c_char, CStr, CString};
+use std::ffi::{c_char, c_void, CStr, CString};
 use std::fs;
 
 use crate::error;
@@ -15,6 +15,133 @@ pub extern "C" fn gaggle_init_logging() {
     crate::init_logging();
 }
 
+/// Re-reads the `GAGGLE_LOG_LEVEL`, `GAGGLE_LOG_FILE`, and `GAGGLE_LOG_MAX_SIZE_MB`
+/// environment variables and applies them to the logger.
+///
+/// This is useful when the variables are set after the library was loaded. Logging is
+/// initialized if it was not already.
+///
+/// # Returns
+///
+/// Returns `0` on success, or `-1` if the log file cannot be opened. If the operation
+/// fails, a detailed error message can be retrieved using `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_reinit_logging() -> i32 {
+    error::clear_last_error_internal();
+
+    match crate::reinit_logging() {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Changes the log level at runtime, without touching `GAGGLE_LOG_LEVEL`.
+///
+/// `level` accepts the same values as `GAGGLE_LOG_LEVEL`, such as `DEBUG`. The new level
+/// lasts until it is changed again or `gaggle_reinit_logging` is called.
+///
+/// # Returns
+///
+/// Returns `0` on success, or `-1` if the level is invalid. If the operation fails, a
+/// detailed error message can be retrieved using `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The `level` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_set_log_level(level: *const c_char) -> i32 {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<(), error::GaggleError> {
+        if level.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        crate::set_log_level(CStr::from_ptr(level).to_str()?)
+    })();
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Writes out any log lines buffered for the log file.
+///
+/// # Returns
+///
+/// Returns `0` on success (including when no log file is configured), or `-1` on failure.
+/// If the operation fails, a detailed error message can be retrieved using `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_flush_logs() -> i32 {
+    error::clear_last_error_internal();
+
+    match crate::log_file::flush() {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// A callback that receives Gaggle log messages.
+///
+/// `level` is `0` for ERROR, `1` for WARN, `2` for INFO, `3` for DEBUG, and `4` for TRACE.
+/// `message` is a NUL-terminated string that is only valid for the duration of the call.
+/// `user_data` is the pointer passed to `gaggle_set_log_callback`.
+pub type GaggleLogFn =
+    unsafe extern "C" fn(level: i32, message: *const c_char, user_data: *mut c_void);
+
+/// Registers a callback that receives all log messages, replacing any previous one.
+///
+/// While a callback is registered, the default formatted logger is silenced. Messages are
+/// still filtered by `GAGGLE_LOG_LEVEL`. Logging is initialized if it was not already.
+///
+/// # Returns
+///
+/// Returns `0` on success, or `-1` if `cb` is `NULL`.
+///
+/// # Safety
+///
+/// - `cb` must remain callable until it is deregistered with `gaggle_clear_log_callback`.
+/// - `cb` may be called from any thread, and it receives `user_data` unchanged.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_set_log_callback(
+    cb: Option<GaggleLogFn>,
+    user_data: *mut c_void,
+) -> i32 {
+    error::clear_last_error_internal();
+
+    match cb {
+        Some(cb) => {
+            crate::ffi_log::set_callback(cb, user_data);
+            crate::init_logging();
+            0
+        }
+        None => {
+            error::set_last_error(&error::GaggleError::NullPointer);
+            -1
+        }
+    }
+}
+
+/// Deregisters the log callback, so the default formatted logger is used again.
+///
+/// Returns `0` on success.
+#[no_mangle]
+pub extern "C" fn gaggle_clear_log_callback() -> i32 {
+    error::clear_last_error_internal();
+    crate::ffi_log::clear_callback();
+    0
+}
+
 /// Sets the Kaggle API credentials.
 ///
 /// # Arguments
@@ -67,12 +194,189 @@ pub unsafe extern "C" fn gaggle_set_credentials(
     }
 }
 
+/// Sets the Kaggle API credentials, rejecting a malformed username or key.
+///
+/// Unlike `gaggle_set_credentials`, this fails if the username or key is empty, or if
+/// the key is not a 32-character lowercase hex string.
+///
+/// # Returns
+///
+/// Returns `0` on success, or `-1` on failure. If the operation fails,
+/// a detailed error message can be retrieved using `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointers must be valid and remain accessible for the duration of this call.
+/// - The provided strings must be valid UTF-8, and interior NUL characters are not permitted.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_set_credentials_strict(
+    username: *const c_char,
+    key: *const c_char,
+) -> i32 {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<(), error::GaggleError> {
+        if username.is_null() || key.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let username_str = CStr::from_ptr(username).to_str()?;
+        let key_str = CStr::from_ptr(key).to_str()?;
+        kaggle::credentials::set_credentials_strict(username_str, key_str)
+    })();
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Sets the base URL of the Kaggle API for this process.
+///
+/// The URL takes precedence over `GAGGLE_API_BASE` and `GAGGLE_KAGGLE_HOSTNAME`. It must
+/// be an `http` or `https` URL, such as `https://kaggle.example.com/api/v1`. An empty
+/// string clears it.
+///
+/// # Returns
+///
+/// Returns `0` on success, or `-1` on failure. If the operation fails,
+/// a detailed error message can be retrieved using `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and remain accessible for the duration of this call.
+/// - The provided string must be valid UTF-8, and interior NUL characters are not permitted.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_set_api_base(base_url: *const c_char) -> i32 {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<(), error::GaggleError> {
+        if base_url.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let base_url_str = CStr::from_ptr(base_url).to_str()?;
+        kaggle::api::set_api_base(base_url_str)
+    })();
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Re-creates the shared HTTP client, closing its pooled connections.
+///
+/// The client is rebuilt from the current configuration, so this also reports settings that
+/// cannot be used, such as an invalid proxy URL or an unreadable certificate.
+///
+/// # Returns
+///
+/// Returns `0` on success, or `-1` on failure. If the operation fails,
+/// a detailed error message can be retrieved using `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_reset_http_client() -> i32 {
+    error::clear_last_error_internal();
+
+    kaggle::api::reset_http_clients();
+    match kaggle::api::get_client() {
+        Ok(_) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Sets the download bandwidth limit for this process, in megabits per second.
+///
+/// The limit is shared by all downloads in the process and takes precedence over
+/// `GAGGLE_MAX_BANDWIDTH_MBPS`.
+///
+/// # Arguments
+///
+/// * `mbps` - The limit in megabits per second. `0` (or a value that is not finite) removes
+///   the limit, and a negative value reverts to `GAGGLE_MAX_BANDWIDTH_MBPS`.
+///
+/// # Returns
+///
+/// Returns `0` on success.
+#[no_mangle]
+pub extern "C" fn gaggle_set_bandwidth_limit(mbps: f64) -> i32 {
+    error::clear_last_error_internal();
+
+    if mbps < 0.0 {
+        kaggle::throttle::set_bandwidth_limit(None);
+    } else {
+        kaggle::throttle::set_bandwidth_limit(Some(mbps));
+    }
+    0
+}
+
+/// Clears the stored Kaggle API credentials.
+///
+/// The next operation that needs credentials reloads them from the environment
+/// or `kaggle.json`, bypassing any cached "no credentials found" result.
+///
+/// # Returns
+///
+/// Returns `0` on success.
+#[no_mangle]
+pub extern "C" fn gaggle_clear_credentials() -> i32 {
+    error::clear_last_error_internal();
+    kaggle::credentials::clear_credentials();
+    0
+}
+
+/// Checks the current Kaggle credentials against the API.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON object of the form
+/// `{"valid": bool, "username": string|null, "error": string|null}` that must be
+/// freed with `gaggle_free()`. When the credentials are not valid,
+/// `gaggle_last_error` is set as well.
+#[no_mangle]
+pub extern "C" fn gaggle_validate_credentials() -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let info = match kaggle::credentials::validate_credentials() {
+        Ok(username) => json!({"valid": true, "username": username, "error": null}),
+        Err(e) => {
+            error::set_last_error(&e);
+            json!({"valid": false, "username": null, "error": e.to_string()})
+        }
+    };
+    string_to_c_string(info.to_string())
+}
+
+/// Checks that credentials are present, the Kaggle API is reachable, and the cache is writable.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON object with the keys `credentials_present`,
+/// `credentials_file`, `api_reachable`, `api_latency_ms`, `api_status`, `api_error`,
+/// `cache_writable`, and `cache_error` that must be freed with `gaggle_free()`. Failed checks are reported in
+/// the object, so this function does not return `NULL` for them.
+#[no_mangle]
+pub extern "C" fn gaggle_health_check() -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let report = kaggle::health_check().unwrap_or_else(|e| json!({"error": e.to_string()}));
+    string_to_c_string(report.to_string())
+}
+
 /// Downloads a Kaggle dataset and returns its local cache path.
 ///
 /// # Arguments
 ///
 /// * `dataset_path` - A non-null pointer to a NUL-terminated C string in the format
-///   "owner/dataset[[@vN|@latest]]".
+///   "owner/dataset[[@vN|@latest]]". A Kaggle dataset URL such as
+///   "https://www.kaggle.com/datasets/owner/dataset" can be used in place of "owner/dataset".
 ///
 /// # Returns
 ///
@@ -89,11 +393,14 @@ pub unsafe extern "C" fn gaggle_download_dataset(dataset_path: *const c_char) ->
     // Clear any previous error
     error::clear_last_error_internal();
 
+    let mut ctx = error::ErrorContext::default();
     let result = (|| -> Result<String, error::GaggleError> {
         if dataset_path.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
         let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
         if path_str.len() > 4096 {
             return Err(error::GaggleError::InvalidDatasetPath(
                 "dataset path too long".to_string(),
@@ -107,449 +414,572 @@ pub unsafe extern "C" fn gaggle_download_dataset(dataset_path: *const c_char) ->
     match result {
         Ok(path) => string_to_c_string(path),
         Err(e) => {
-            error::set_last_error(&e);
+            error::set_last_error_with_context(&e, &ctx);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Retrieves the local path to a specific file within a downloaded dataset.
+/// Downloads a Kaggle dataset and returns its local cache path, treating a cached copy as
+/// `mode` says.
 ///
 /// # Arguments
 ///
-/// * `dataset_path` - A non-null pointer to a NUL-terminated C string representing the owner and dataset.
-/// * `filename` - A non-null pointer to a NUL-terminated C string for the relative filename inside the dataset.
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string, as for
+///   `gaggle_download_dataset`.
+/// * `mode` - `0` serves a cached copy like `gaggle_download_dataset`, `1` downloads again
+///   only if the cached copy is not the latest version, and `2` always downloads again like
+///   `gaggle_update_dataset`. With `1`, a cached copy is kept if the latest version cannot be
+///   determined.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated C string containing the local path.
+/// This string must be freed with `gaggle_free()`. On error, returns `NULL`
+/// and sets a detailed error message retrievable with `gaggle_last_error`.
 ///
 /// # Safety
 ///
-/// - Both pointers must be valid and point to valid NUL-terminated C strings.
-/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+/// - The pointer must be valid and the string must be valid UTF-8.
+/// - Interior NUL characters are not allowed in the string.
 #[no_mangle]
-pub unsafe extern "C" fn gaggle_get_file_path(
+pub unsafe extern "C" fn gaggle_download_dataset_ex(
     dataset_path: *const c_char,
-    filename: *const c_char,
+    mode: i32,
 ) -> *mut c_char {
-    // Clear any previous error
     error::clear_last_error_internal();
 
+    let mut ctx = error::ErrorContext::default();
     let result = (|| -> Result<String, error::GaggleError> {
-        if dataset_path.is_null() || filename.is_null() {
+        if dataset_path.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
         let path_str = CStr::from_ptr(dataset_path).to_str()?;
-        let filename_str = CStr::from_ptr(filename).to_str()?;
-        if path_str.len() > 4096 || filename_str.len() > 4096 {
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
             return Err(error::GaggleError::InvalidDatasetPath(
-                "input too long".to_string(),
+                "dataset path too long".to_string(),
             ));
         }
-
-        let file_path = kaggle::get_dataset_file_path(path_str, filename_str)?;
-        Ok(file_path.to_string_lossy().to_string())
+        let mode = kaggle::download::DownloadMode::from_i32(mode).ok_or_else(|| {
+            error::GaggleError::InvalidDatasetPath(format!(
+                "Invalid download mode {}: expected 0 (cache first), 1 (refresh if outdated), \
+                 or 2 (force)",
+                mode
+            ))
+        })?;
+
+        let local_path = kaggle::download_dataset_with_mode(path_str, mode)?;
+        Ok(local_path.to_string_lossy().to_string())
     })();
 
     match result {
         Ok(path) => string_to_c_string(path),
         Err(e) => {
-            error::set_last_error(&e);
+            error::set_last_error_with_context(&e, &ctx);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Lists the files available in a Kaggle dataset.
+/// Downloads a Kaggle dataset directly into a directory, bypassing the cache.
+///
+/// No marker files are written and the cache is left untouched. A directory that already
+/// has files is refused unless `overwrite` is non-zero.
+///
+/// # Arguments
+///
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string in the same formats as
+///   for `gaggle_download_dataset`.
+/// * `target_dir` - A non-null pointer to a NUL-terminated C string with the directory to
+///   extract the dataset into. It is created if it does not exist.
+/// * `overwrite` - Non-zero to download into a directory that is not empty, replacing files
+///   with the same names.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated C string with the absolute path of the target
+/// directory, which must be freed with `gaggle_free()`. On error, returns `NULL` and sets
+/// `gaggle_last_error`.
 ///
 /// # Safety
 ///
-/// - The pointer must be valid and point to a valid NUL-terminated C string.
-/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+/// - The pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
 #[no_mangle]
-pub unsafe extern "C" fn gaggle_list_files(dataset_path: *const c_char) -> *mut c_char {
-    // Clear any previous error
+pub unsafe extern "C" fn gaggle_download_dataset_to(
+    dataset_path: *const c_char,
+    target_dir: *const c_char,
+    overwrite: i32,
+) -> *mut c_char {
     error::clear_last_error_internal();
 
+    let mut ctx = error::ErrorContext::default();
     let result = (|| -> Result<String, error::GaggleError> {
-        if dataset_path.is_null() {
+        if dataset_path.is_null() || target_dir.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
         let path_str = CStr::from_ptr(dataset_path).to_str()?;
-        if path_str.len() > 4096 {
-            return Err(error::GaggleError::InvalidDatasetPath(
-                "dataset path too long".to_string(),
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let target_str = CStr::from_ptr(target_dir).to_str()?;
+        if target_str.is_empty() || target_str.len() > 4096 {
+            return Err(error::GaggleError::IoError(
+                "invalid download target path".to_string(),
             ));
         }
-
-        let files = kaggle::list_dataset_files(path_str)?;
-        let json = serde_json::to_string(&files)?;
-        Ok(json)
+        let local_path = kaggle::download_dataset_to(
+            path_str,
+            std::path::Path::new(target_str),
+            overwrite != 0,
+        )?;
+        Ok(local_path.to_string_lossy().to_string())
     })();
 
     match result {
-        Ok(json) => string_to_c_string(json),
+        Ok(path) => string_to_c_string(path),
         Err(e) => {
-            error::set_last_error(&e);
+            error::set_last_error_with_context(&e, &ctx);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Searches for Kaggle datasets.
+/// Downloads a Kaggle dataset but extracts only the files matching the given patterns.
 ///
-/// # Safety
+/// # Arguments
 ///
-/// - The `query` pointer must be valid and point to a valid NUL-terminated C string.
-/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string in the same formats as
+///   for `gaggle_download_dataset`.
+/// * `patterns_json` - A non-null pointer to a NUL-terminated C string holding a JSON array of
+///   file names or glob patterns, such as `["train.csv", "*.parquet"]`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string `{"dataset": ..., "files": [...]}` with
+/// the local paths of the matching files. This string must be freed with `gaggle_free()`.
+/// On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - Both pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
 #[no_mangle]
-pub unsafe extern "C" fn gaggle_search(
-    query: *const c_char,
-    page: i32,
-    page_size: i32,
+pub unsafe extern "C" fn gaggle_download_dataset_filtered(
+    dataset_path: *const c_char,
+    patterns_json: *const c_char,
 ) -> *mut c_char {
-    // Clear any previous error
     error::clear_last_error_internal();
 
+    let mut ctx = error::ErrorContext::default();
     let result = (|| -> Result<String, error::GaggleError> {
-        if query.is_null() {
+        if dataset_path.is_null() || patterns_json.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
-        let query_str = CStr::from_ptr(query).to_str()?;
-        if query_str.len() > 8192 {
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let patterns_str = CStr::from_ptr(patterns_json).to_str()?;
+        if path_str.len() > 4096 || patterns_str.len() > 1_000_000 {
             return Err(error::GaggleError::InvalidDatasetPath(
-                "query too long".to_string(),
+                "input too long".to_string(),
             ));
         }
-
-        let results = kaggle::search_datasets(query_str, page, page_size)?;
-        let json = serde_json::to_string(&results)?;
-        Ok(json)
+        let patterns: Vec<String> = serde_json::from_str(patterns_str).map_err(|e| {
+            error::GaggleError::JsonError(format!(
+                "patterns must be a JSON array of strings: {}",
+                e
+            ))
+        })?;
+        let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+
+        let files = kaggle::download_dataset_files(path_str, &patterns)?;
+        let files: Vec<String> = files
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        Ok(json!({"dataset": path_str, "files": files}).to_string())
     })();
 
     match result {
         Ok(json) => string_to_c_string(json),
         Err(e) => {
-            error::set_last_error(&e);
+            error::set_last_error_with_context(&e, &ctx);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Retrieves metadata for a specific Kaggle dataset.
+/// Retrieves the local path to a specific file within a downloaded dataset.
+///
+/// # Arguments
+///
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string representing the owner and dataset.
+///   It may also name a version and a path inside the dataset, as in `"owner/dataset@v2/Twitter"`.
+/// * `filename` - A non-null pointer to a NUL-terminated C string for the relative filename inside the dataset.
+///   It is joined to the path inside the dataset, and may be empty when that path names the file.
 ///
 /// # Safety
 ///
-/// - The pointer must be valid and point to a valid NUL-terminated C string.
-/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+/// - Both pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
 #[no_mangle]
-pub unsafe extern "C" fn gaggle_get_dataset_info(dataset_path: *const c_char) -> *mut c_char {
+pub unsafe extern "C" fn gaggle_get_file_path(
+    dataset_path: *const c_char,
+    filename: *const c_char,
+) -> *mut c_char {
     // Clear any previous error
     error::clear_last_error_internal();
 
+    let mut ctx = error::ErrorContext::default();
     let result = (|| -> Result<String, error::GaggleError> {
-        if dataset_path.is_null() {
+        if dataset_path.is_null() || filename.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
         let path_str = CStr::from_ptr(dataset_path).to_str()?;
-        if path_str.len() > 4096 {
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let filename_str = CStr::from_ptr(filename).to_str()?;
+        ctx.filename = Some(filename_str.to_string());
+        if path_str.len() > 4096 || filename_str.len() > 4096 {
             return Err(error::GaggleError::InvalidDatasetPath(
-                "dataset path too long".to_string(),
+                "input too long".to_string(),
             ));
         }
 
-        let metadata = kaggle::get_dataset_metadata(path_str)?;
-        let json = serde_json::to_string(&metadata)?;
-        Ok(json)
+        // A third '/' component names a path inside the dataset, possibly at a version
+        let file_path = if path_str.split('/').nth(2).is_some() {
+            kaggle::get_dataset_file_path_full(path_str, filename_str)?
+        } else {
+            kaggle::get_dataset_file_path(path_str, filename_str)?
+        };
+        Ok(file_path.to_string_lossy().to_string())
     })();
 
     match result {
-        Ok(json) => string_to_c_string(json),
+        Ok(path) => string_to_c_string(path),
         Err(e) => {
-            error::set_last_error(&e);
+            error::set_last_error_with_context(&e, &ctx);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Retrieves the version of the Gaggle library.
-#[no_mangle]
-pub extern "C" fn gaggle_get_version() -> *mut c_char {
-    // Return only the version string (no JSON wrapper)
-    string_to_c_string(env!("CARGO_PKG_VERSION").to_string())
-}
-
-/// Frees a heap-allocated C string.
+/// Resolves a glob pattern to the local paths of the matching files in a dataset.
 ///
-/// # Safety
+/// Matching files are downloaded on demand. The result can be passed to DuckDB readers that
+/// take a list of files, such as `read_csv_auto([...])`.
 ///
-/// `ptr` must be a pointer previously returned by a Gaggle FFI function that transfers ownership,
-/// such as `gaggle_get_version` or `gaggle_list_files`. Passing the same pointer more than once,
-/// or providing a pointer not allocated by Gaggle, will result in undefined behavior.
-#[no_mangle]
-pub unsafe extern "C" fn gaggle_free(ptr: *mut c_char) {
-    if !ptr.is_null() {
-        let _ = CString::from_raw(ptr);
-    }
-}
-
-/// Clears the dataset cache.
-#[no_mangle]
-pub extern "C" fn gaggle_clear_cache() -> i32 {
-    let result = (|| -> Result<(), error::GaggleError> {
-        // Use runtime-resolved cache dir to honor env overrides
-        let cache_dir = crate::config::cache_dir_runtime();
-        if cache_dir.exists() {
-            fs::remove_dir_all(&cache_dir)?;
-            fs::create_dir_all(&cache_dir)?;
-        } else {
-            fs::create_dir_all(&cache_dir)?;
-        }
-        Ok(())
-    })();
-
-    match result {
-        Ok(()) => 0,
-        Err(e) => {
-            error::set_last_error(&e);
-            -1
-        }
-    }
-}
-
-/// Enforces the cache size limit by evicting the oldest datasets.
-#[no_mangle]
-pub extern "C" fn gaggle_enforce_cache_limit() -> i32 {
-    let result = kaggle::download::enforce_cache_limit_now();
-
-    match result {
-        Ok(()) => 0,
-        Err(e) => {
-            error::set_last_error(&e);
-            -1
-        }
-    }
-}
-
-/// Checks if the cached dataset is the current version.
+/// # Arguments
+///
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string representing the owner and dataset.
+/// * `pattern` - A non-null pointer to a NUL-terminated C string holding a glob pattern, such as
+///   `"*.csv"`, `"data/*.csv"`, or `"**/*.parquet"`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON array of absolute paths, which is empty if no
+/// file matches. This string must be freed with `gaggle_free()`. On error, returns `NULL`
+/// and sets `gaggle_last_error`.
 ///
 /// # Safety
 ///
-/// - The pointer must be valid and point to a valid NUL-terminated C string.
-/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+/// - Both pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
 #[no_mangle]
-pub unsafe extern "C" fn gaggle_is_dataset_current(dataset_path: *const c_char) -> i32 {
+pub unsafe extern "C" fn gaggle_glob(
+    dataset_path: *const c_char,
+    pattern: *const c_char,
+) -> *mut c_char {
     error::clear_last_error_internal();
 
-    let result = (|| -> Result<bool, error::GaggleError> {
-        if dataset_path.is_null() {
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() || pattern.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
         let path_str = CStr::from_ptr(dataset_path).to_str()?;
-        if path_str.len() > 4096 {
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let pattern_str = CStr::from_ptr(pattern).to_str()?;
+        if path_str.len() > 4096 || pattern_str.len() > 4096 {
             return Err(error::GaggleError::InvalidDatasetPath(
-                "dataset path too long".to_string(),
+                "input too long".to_string(),
             ));
         }
-        kaggle::is_dataset_current(path_str)
+
+        let files: Vec<String> = kaggle::resolve_glob(path_str, pattern_str)?
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        Ok(serde_json::to_string(&files)?)
     })();
 
     match result {
-        Ok(true) => 1,
-        Ok(false) => 0,
+        Ok(json) => string_to_c_string(json),
         Err(e) => {
-            error::set_last_error(&e);
-            -1
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
         }
     }
 }
 
-/// Forces an update of the dataset to the latest version, ignoring the cache.
+/// Lists the files in a Kaggle dataset with the given extension, such as `csv`.
+///
+/// # Arguments
+///
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string representing the owner and dataset.
+/// * `format` - A pointer to a NUL-terminated C string with the extension, matched without
+///   regard to case. `NULL`, an empty string, or `*` lists every file.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string like the one from `gaggle_list_files`,
+/// which must be freed with `gaggle_free()`. On error, returns `NULL` and sets
+/// `gaggle_last_error`.
 ///
 /// # Safety
 ///
-/// - The pointer must be valid and point to a valid NUL-terminated C string.
-/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+/// - The pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
 #[no_mangle]
-pub unsafe extern "C" fn gaggle_update_dataset(dataset_path: *const c_char) -> *mut c_char {
+pub unsafe extern "C" fn gaggle_list_files_by_format(
+    dataset_path: *const c_char,
+    format: *const c_char,
+) -> *mut c_char {
     error::clear_last_error_internal();
 
+    let mut ctx = error::ErrorContext::default();
     let result = (|| -> Result<String, error::GaggleError> {
         if dataset_path.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
         let path_str = CStr::from_ptr(dataset_path).to_str()?;
-        if path_str.len() > 4096 {
-            return Err(error::GaggleError::InvalidDatasetPath(
-                "dataset path too long".to_string(),
-            ));
-        }
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let format_str = if format.is_null() {
+            "*"
+        } else {
+            CStr::from_ptr(format).to_str()?
+        };
 
-        let local_path = kaggle::update_dataset(path_str)?;
-        Ok(local_path.to_string_lossy().to_string())
+        let files = kaggle::list_dataset_files_by_format(path_str, format_str)?;
+        Ok(serde_json::to_string(&files)?)
     })();
 
     match result {
-        Ok(path) => string_to_c_string(path),
+        Ok(json) => string_to_c_string(json),
         Err(e) => {
-            error::set_last_error(&e);
+            error::set_last_error_with_context(&e, &ctx);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Retrieves version information for a dataset.
+/// Lists the files available in a Kaggle dataset.
+///
+/// The dataset path may name a version and a directory inside the dataset, as in
+/// `"owner/dataset@v2/Twitter"`, to list the files of that directory.
 ///
 /// # Safety
 ///
 /// - The pointer must be valid and point to a valid NUL-terminated C string.
 /// - The string must be valid UTF-8, and interior NUL characters are not allowed.
 #[no_mangle]
-pub unsafe extern "C" fn gaggle_dataset_version_info(dataset_path: *const c_char) -> *mut c_char {
+pub unsafe extern "C" fn gaggle_list_files(dataset_path: *const c_char) -> *mut c_char {
+    // Clear any previous error
     error::clear_last_error_internal();
 
+    let mut ctx = error::ErrorContext::default();
     let result = (|| -> Result<String, error::GaggleError> {
         if dataset_path.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
         let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
         if path_str.len() > 4096 {
             return Err(error::GaggleError::InvalidDatasetPath(
                 "dataset path too long".to_string(),
             ));
         }
 
-        let info = kaggle::get_dataset_version_info(path_str)?;
-        Ok(info.to_string())
+        let files = if path_str.split('/').nth(2).is_some() {
+            kaggle::list_dataset_files_full(path_str)?
+        } else {
+            kaggle::list_dataset_files(path_str)?
+        };
+        let json = serde_json::to_string(&files)?;
+        Ok(json)
     })();
 
     match result {
         Ok(json) => string_to_c_string(json),
         Err(e) => {
-            error::set_last_error(&e);
+            error::set_last_error_with_context(&e, &ctx);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Retrieves information about the cache.
+/// Returns the dataset reference of a dataset path that may name a version and a path inside
+/// the dataset, such as `"owner/dataset@2"` for `"owner/dataset@v2/Twitter"`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated C string that must be freed with `gaggle_free()`.
+/// If the path is invalid, including a path inside the dataset that leaves it, returns `NULL`
+/// and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
 #[no_mangle]
-pub extern "C" fn gaggle_get_cache_info() -> *mut c_char {
-    let cache_dir = crate::config::cache_dir_runtime();
-
-    // Prefer fast metadata-based computation when possible
-    let size_mb = crate::kaggle::download::get_total_cache_size_mb().unwrap_or(0);
-
-    // If metadata yields zero, fallback to scanning
-    let size_mb = if size_mb == 0 {
-        match crate::utils::calculate_dir_size(&cache_dir) {
-            Ok(bytes) => bytes / (1024 * 1024),
-            Err(_) => 0,
-        }
-    } else {
-        size_mb
-    };
-
-    let limit_mb = crate::config::cache_size_limit_mb();
-    let is_soft_limit = crate::config::cache_limit_is_soft();
-
-    let usage_percent = if let Some(limit) = limit_mb {
-        if limit > 0 {
-            ((size_mb as f64 / limit as f64) * 100.0) as u64
-        } else {
-            0
-        }
-    } else {
-        0
-    };
-
-    let info = json!({
-        "path": cache_dir.to_string_lossy(),
-        "size_mb": size_mb, // MB (1024*1024)
-        "limit_mb": limit_mb,
-        "usage_percent": usage_percent,
-        "is_soft_limit": is_soft_limit,
-        "type": "local",
-    });
-    string_to_c_string(info.to_string())
+pub unsafe extern "C" fn gaggle_dataset_ref(dataset_path: *const c_char) -> *mut c_char {
+    split_dataset_path_ffi(dataset_path, |(dataset_ref, _)| dataset_ref)
 }
 
-/// Parses JSON and expands objects/arrays, similar to `json_each`.
+/// Returns the path inside the dataset named by a dataset path, such as `"Twitter"` for
+/// `"owner/dataset@v2/Twitter"`, or an empty string when there is none. The path is relative
+/// and never contains `..`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated C string that must be freed with `gaggle_free()`.
+/// If the path is invalid, returns `NULL` and sets `gaggle_last_error`.
 ///
 /// # Safety
 ///
 /// - The pointer must be valid and point to a valid NUL-terminated C string.
 /// - The string must be valid UTF-8, and interior NUL characters are not allowed.
 #[no_mangle]
-pub unsafe extern "C" fn gaggle_json_each(json_str: *const c_char) -> *mut c_char {
-    // Clear any previous error
+pub unsafe extern "C" fn gaggle_dataset_subpath(dataset_path: *const c_char) -> *mut c_char {
+    split_dataset_path_ffi(dataset_path, |(_, subpath)| subpath.unwrap_or_default())
+}
+
+/// Splits a dataset path with [`kaggle::split_dataset_path`] and returns the part picked by
+/// `pick`, for `gaggle_dataset_ref` and `gaggle_dataset_subpath`.
+unsafe fn split_dataset_path_ffi(
+    dataset_path: *const c_char,
+    pick: impl FnOnce((String, Option<String>)) -> String,
+) -> *mut c_char {
     error::clear_last_error_internal();
 
+    let mut ctx = error::ErrorContext::default();
     let result = (|| -> Result<String, error::GaggleError> {
-        if json_str.is_null() {
+        if dataset_path.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
-        let json_cstr = CStr::from_ptr(json_str).to_str()?;
-
-        // Parse the JSON
-        let value: serde_json::Value = serde_json::from_str(json_cstr)?;
-
-        // Expand into rows
-        let mut rows = Vec::new();
-        expand_json_value(&value, "$", &mut rows);
-
-        // Convert rows to newline-delimited JSON
-        let result_str = rows
-            .into_iter()
-            .map(|row| row.to_string())
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        Ok(result_str)
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        Ok(pick(kaggle::split_dataset_path(path_str)?))
     })();
 
     match result {
         Ok(s) => string_to_c_string(s),
         Err(e) => {
-            error::set_last_error(&e);
+            error::set_last_error_with_context(&e, &ctx);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Prefetches multiple files in a dataset without downloading the entire archive.
+/// Searches for Kaggle datasets.
 ///
 /// # Safety
 ///
-/// - Both pointers must be valid and point to valid NUL-terminated C strings.
-/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
-#[no_mangle]
-pub unsafe extern "C" fn gaggle_prefetch_files(
-    dataset_path: *const c_char,
-    file_list: *const c_char,
+/// - The `query` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_search(
+    query: *const c_char,
+    page: i32,
+    page_size: i32,
 ) -> *mut c_char {
+    // Clear any previous error
     error::clear_last_error_internal();
 
     let result = (|| -> Result<String, error::GaggleError> {
-        if dataset_path.is_null() || file_list.is_null() {
+        if query.is_null() {
             return Err(error::GaggleError::NullPointer);
         }
-        let ds = CStr::from_ptr(dataset_path).to_str()?;
-        let files_str = CStr::from_ptr(file_list).to_str()?;
-        if ds.len() > 4096 || files_str.len() > 1_000_000 {
+        let query_str = CStr::from_ptr(query).to_str()?;
+        if query_str.len() > 8192 {
             return Err(error::GaggleError::InvalidDatasetPath(
-                "input too long".to_string(),
+                "query too long".to_string(),
             ));
         }
-        let files: Vec<&str> = files_str
-            .lines()
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .collect();
-        if files.is_empty() {
-            return Err(error::GaggleError::IoError(
-                "no valid files provided".to_string(),
+
+        let results = kaggle::search_datasets(query_str, page, page_size)?;
+        let json = serde_json::to_string(&results)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Searches for Kaggle datasets, paging with a cursor instead of a page number.
+///
+/// # Arguments
+///
+/// * `query` - A non-null pointer to a NUL-terminated C string holding the search query.
+/// * `cursor_json` - `NULL` or a pointer to a NUL-terminated C string holding a JSON string
+///   with the cursor of the page to fetch, such as `"\"abc\""`. `NULL` or a JSON `null`
+///   fetches the first page.
+/// * `page_size` - The number of results per page (1 to 100).
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string `{"results": [...], "next_cursor": ...}`,
+/// where `next_cursor` is `null` on the last page. This string must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointers must be valid and point to valid NUL-terminated C strings, except that
+///   `cursor_json` may be `NULL`.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_search_cursor(
+    query: *const c_char,
+    cursor_json: *const c_char,
+    page_size: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if query.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let query_str = CStr::from_ptr(query).to_str()?;
+        if query_str.len() > 8192 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "query too long".to_string(),
             ));
         }
-        let json_val = crate::kaggle::prefetch_files(ds, &files)?;
-        Ok(json_val.to_string())
+        let cursor = if cursor_json.is_null() {
+            None
+        } else {
+            let cursor_str = CStr::from_ptr(cursor_json).to_str()?;
+            serde_json::from_str::<Option<String>>(cursor_str).map_err(|e| {
+                error::GaggleError::JsonError(format!(
+                    "cursor must be a JSON string or null: {}",
+                    e
+                ))
+            })?
+        };
+
+        let (results, next_cursor) =
+            kaggle::search_datasets_cursor(query_str, cursor.as_deref(), page_size)?;
+        Ok(json!({"results": results, "next_cursor": next_cursor}).to_string())
     })();
 
     match result {
@@ -561,245 +991,3561 @@ pub unsafe extern "C" fn gaggle_prefetch_files(
     }
 }
 
-pub(crate) fn string_to_c_string(s: String) -> *mut c_char {
-    match CString::new(s) {
-        Ok(cstring) => cstring.into_raw(),
+/// A callback that receives one item of a streamed result.
+///
+/// `item` is a NUL-terminated JSON string that is only valid for the duration of the call.
+/// `user_data` is the pointer passed to the streaming function.
+pub type GaggleItemFn = unsafe extern "C" fn(item: *const c_char, user_data: *mut c_void);
+
+/// Searches for Kaggle datasets and passes each result to a callback, one at a time.
+///
+/// Unlike `gaggle_search`, the results are never joined into one string, so only a single
+/// item has to be held as a C string at a time.
+///
+/// # Returns
+///
+/// Returns the number of items passed to `on_item`, or `-1` on error. On error, details are
+/// available via `gaggle_last_error`, and some items may already have been emitted.
+///
+/// # Safety
+///
+/// - The `query` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+/// - `on_item` must not keep the `item` pointer after it returns.
+/// - `user_data` is passed to `on_item` unchanged. Treat it as if it had to be `Send + Sync`:
+///   the callback is currently called on the calling thread before this function returns,
+///   but no thread is guaranteed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_search_streaming(
+    query: *const c_char,
+    page: i32,
+    page_size: i32,
+    on_item: Option<GaggleItemFn>,
+    user_data: *mut c_void,
+) -> i32 {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<i32, error::GaggleError> {
+        if query.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let Some(on_item) = on_item else {
+            return Err(error::GaggleError::NullPointer);
+        };
+        let query_str = CStr::from_ptr(query).to_str()?;
+        if query_str.len() > 8192 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "query too long".to_string(),
+            ));
+        }
+
+        let results = kaggle::search_datasets(query_str, page, page_size)?;
+        let items = results.as_array().ok_or_else(|| {
+            error::GaggleError::JsonError("Expected a JSON array of search results".to_string())
+        })?;
+        let mut emitted = 0;
+        for item in items {
+            // serde_json escapes NUL characters, so this only fails on a bug
+            let item = CString::new(serde_json::to_string(item)?)
+                .map_err(|e| error::GaggleError::JsonError(e.to_string()))?;
+            on_item(item.as_ptr(), user_data);
+            emitted += 1;
+        }
+        Ok(emitted)
+    })();
+
+    match result {
+        Ok(count) => count,
         Err(e) => {
-            let err = error::GaggleError::IoError(format!(
-                "String contains null byte at position {}",
-                e.nul_position()
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Searches the locally cached datasets and persisted metadata, without network access.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON array of `{"ref", "title", "cached"}` objects
+/// for the datasets whose path or title contains `query` (case-insensitive). The string must
+/// be freed with `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The `query` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_search_cached(query: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if query.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let query_str = CStr::from_ptr(query).to_str()?;
+        if query_str.len() > 8192 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "query too long".to_string(),
             ));
-            error::set_last_error(&err);
+        }
+
+        let results = kaggle::search_cached(query_str)?;
+        Ok(serde_json::to_string(&results)?)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
             std::ptr::null_mut()
         }
     }
 }
 
-/// Helper function to recursively expand JSON values
-fn expand_json_value(value: &serde_json::Value, path: &str, rows: &mut Vec<serde_json::Value>) {
-    match value {
-        serde_json::Value::Object(map) => {
-            for (key, val) in map.iter() {
-                let new_path = if path == "$" {
-                    format!("$.{}", key)
-                } else {
-                    format!("{}.{}", path, key)
-                };
+/// Searches for Kaggle datasets and aggregates results across all pages.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON object with the fields `results`
+/// (an array), `complete` (a boolean), and `error` (a string or null). If a page
+/// request fails after some results were fetched, `complete` is `false` and
+/// `error` describes the failure. If nothing could be fetched, returns `NULL` and
+/// sets `gaggle_last_error`. The string must be freed with `gaggle_free()`.
+///
+/// # Safety
+///
+/// - The `query` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_search_all(query: *const c_char, max_results: i32) -> *mut c_char {
+    error::clear_last_error_internal();
 
-                let row = json!({
-                    "key": key,
-                    "value": val,
-                    "type": get_json_type(val),
-                    "path": new_path
-                });
-                rows.push(row);
-            }
+    let result = (|| -> Result<String, error::GaggleError> {
+        if query.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let query_str = CStr::from_ptr(query).to_str()?;
+        if query_str.len() > 8192 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "query too long".to_string(),
+            ));
+        }
+        if max_results < 1 {
+            return Err(error::GaggleError::InvalidDatasetPath(format!(
+                "Maximum number of results must be >= 1, got: {}",
+                max_results
+            )));
         }
-        serde_json::Value::Array(arr) => {
-            for (idx, val) in arr.iter().enumerate() {
-                let new_path = format!("{}[{}]", path, idx);
 
-                let row = json!({
-                    "key": idx,
-                    "value": val,
-                    "type": get_json_type(val),
-                    "path": new_path
-                });
-                rows.push(row);
+        let info = match kaggle::search_all(query_str, max_results as usize) {
+            Ok(results) => json!({"results": results, "complete": true, "error": null}),
+            Err(e) if !e.partial.is_empty() => {
+                json!({"results": e.partial, "complete": false, "error": e.source.to_string()})
             }
+            Err(e) => return Err(e.source),
+        };
+        Ok(info.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
         }
-        _ => {
-            // For scalar values, return as is
-            let row = json!({
-                "key": null,
-                "value": value,
-                "type": get_json_type(value),
-                "path": path
-            });
-            rows.push(row);
+    }
+}
+
+/// Lists trending datasets on Kaggle.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_list_trending_datasets(page: i32, page_size: i32) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        let results = kaggle::list_trending_datasets(page, page_size)?;
+        let json = serde_json::to_string(&results)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Lists newly published datasets on Kaggle.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_list_new_datasets(page: i32, page_size: i32) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        let results = kaggle::list_new_datasets(page, page_size)?;
+        let json = serde_json::to_string(&results)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Lists recently updated datasets on Kaggle.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_list_updated_datasets(page: i32, page_size: i32) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        let results = kaggle::list_updated_datasets(page, page_size)?;
+        let json = serde_json::to_string(&results)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Lists the datasets owned by a Kaggle user or organization.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The `owner` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_list_datasets_by_owner(
+    owner: *const c_char,
+    page: i32,
+    page_size: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if owner.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let owner_str = CStr::from_ptr(owner).to_str()?;
+        if owner_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "owner too long".to_string(),
+            ));
+        }
+
+        let results = kaggle::list_datasets_by_owner(owner_str, page, page_size)?;
+        let json = serde_json::to_string(&results)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Lists the datasets owned by the user of the current credentials.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_list_my_datasets(page: i32, page_size: i32) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        let results = kaggle::list_my_datasets(page, page_size)?;
+        let json = serde_json::to_string(&results)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Searches for datasets with the given tag.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The `tag` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_search_by_tag(
+    tag: *const c_char,
+    page: i32,
+    page_size: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if tag.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let tag_str = CStr::from_ptr(tag).to_str()?;
+        if tag_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "tag too long".to_string(),
+            ));
+        }
+
+        let results = kaggle::search_datasets_by_tag(tag_str, page, page_size)?;
+        let json = serde_json::to_string(&results)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Detects the DuckDB reader function for a local file from its first bytes.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated string with the reader name (such as
+/// `read_parquet`) that must be freed with `gaggle_free()`. If the file cannot be read
+/// or its type is not recognized, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The `path` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_detect_file_type(path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(path).to_str()?;
+        let reader = crate::utils::detect_file_type_from_bytes(std::path::Path::new(path_str))?;
+        Ok(reader.to_string())
+    })();
+
+    match result {
+        Ok(reader) => string_to_c_string(reader),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Selects the DuckDB reader function for a file name from its extension.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string `{"reader": ..., "options": {...}}`,
+/// where the options are named parameters for the reader (such as `{"delim": "\t"}` for TSV
+/// files). This string must be freed with `gaggle_free()`. On error, returns `NULL` and
+/// sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The `filename` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_guess_reader(filename: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if filename.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let filename_str = CStr::from_ptr(filename).to_str()?;
+        Ok(serde_json::to_string(&crate::utils::guess_reader(
+            filename_str,
+        ))?)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Sniffs the schema of a CSV file in a dataset, downloading the file first if needed.
+///
+/// # Arguments
+///
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string representing the owner and dataset.
+/// * `filename` - A non-null pointer to a NUL-terminated C string with the file's path in the dataset.
+/// * `sample_rows` - The number of data rows to sample, or `0` for the default of 1000.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string `{"delimiter": ..., "has_header": ...,
+/// "columns": [{"name": ..., "type": ...}], "sampled_rows": ..., "estimated_rows": ...,
+/// "exact_row_count": ..., "malformed_rows": ..., "errors": [{"line": ..., "error": ...}]}`,
+/// where the type is one of `int`, `float`, `bool`, `date`, and `string`. This string must
+/// be freed with `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_sniff_csv(
+    dataset_path: *const c_char,
+    filename: *const c_char,
+    sample_rows: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() || filename.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let filename_str = CStr::from_ptr(filename).to_str()?;
+        let sample_rows = match usize::try_from(sample_rows) {
+            Ok(0) => crate::utils::DEFAULT_SNIFF_ROWS,
+            Ok(n) => n,
+            Err(_) => {
+                return Err(error::GaggleError::CsvError(
+                    "sample_rows cannot be negative".to_string(),
+                ))
+            }
+        };
+        let file = kaggle::get_dataset_file_path(path_str, filename_str)?;
+        let schema = crate::utils::sniff_csv(&file, sample_rows)?;
+        Ok(serde_json::to_string(&schema)?)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Returns the path of a dataset file that DuckDB can read directly, decompressing the file
+/// first if it is a `.gz`, `.bz2`, `.xz`, or `.zst` file, or a `.zip` file containing a
+/// single data file. The file is downloaded first if needed. Other files are returned as is.
+///
+/// # Arguments
+///
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string representing the owner and dataset.
+/// * `filename` - A non-null pointer to a NUL-terminated C string with the file's path in the dataset.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated C string with the local path, which must be freed
+/// with `gaggle_free()`. On error (including a file that exceeds the extraction limits),
+/// returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_materialize(
+    dataset_path: *const c_char,
+    filename: *const c_char,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() || filename.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let filename_str = CStr::from_ptr(filename).to_str()?;
+        let file = kaggle::get_dataset_file_path(path_str, filename_str)?;
+        let readable = crate::utils::materialize_readable(&file)?;
+        Ok(readable.to_string_lossy().to_string())
+    })();
+
+    match result {
+        Ok(path) => string_to_c_string(path),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Lists the datasets containing files of the given type, such as `csv` or `parquet`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error (including an unknown file type), returns `NULL` and
+/// sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The `file_type` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_search_by_format(
+    file_type: *const c_char,
+    page: i32,
+    page_size: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if file_type.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let file_type_str = CStr::from_ptr(file_type).to_str()?;
+
+        let results = kaggle::search_datasets_by_format(file_type_str, page, page_size)?;
+        let json = serde_json::to_string(&results)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Lists how often each tag appears across the dataset metadata fetched in this session.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON array of `{"name", "count"}` objects that
+/// must be freed with `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_list_popular_tags() -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        let tags = kaggle::list_popular_tags()?;
+        let json = serde_json::to_string(&tags)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Clears the in-memory search result cache.
+///
+/// # Returns
+///
+/// Returns `0` on success.
+#[no_mangle]
+pub extern "C" fn gaggle_clear_search_cache() -> i32 {
+    error::clear_last_error_internal();
+    kaggle::search::clear_search_cache();
+    0
+}
+
+/// Removes all HTTP responses recorded with `GAGGLE_HTTP_RECORD`.
+///
+/// # Returns
+///
+/// `0` on success, `-1` on failure.
+#[no_mangle]
+pub extern "C" fn gaggle_clear_http_record() -> i32 {
+    error::clear_last_error_internal();
+    match kaggle::api::clear_http_records() {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Lists the HTTP responses recorded with `GAGGLE_HTTP_RECORD`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON array of objects with the fields
+/// `method`, `url`, `status`, and `recorded_at_secs` that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_list_http_records() -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        let records = kaggle::api::list_http_records()?;
+        Ok(serde_json::to_string(&records)?)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Retrieves metadata for a specific Kaggle dataset.
+///
+/// The JSON object has stable keys regardless of the Kaggle API payload shape: `ref`,
+/// `title`, `subtitle`, `url`, `current_version`, `total_bytes`, `file_count`,
+/// `license`, `last_updated`, `download_count`, and `files` (an array of `name` and
+/// `size` objects). Missing values are `null`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_get_dataset_info(dataset_path: *const c_char) -> *mut c_char {
+    // Clear any previous error
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let metadata = kaggle::get_dataset_metadata_typed(path_str)?;
+        let json = serde_json::to_string(&metadata)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Retrieves the tag names of a Kaggle dataset as a JSON array of strings.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_get_dataset_tags(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let tags = kaggle::get_dataset_tags(path_str)?;
+        let json = serde_json::to_string(&tags)?;
+        Ok(json)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Retrieves the documentation of a Kaggle dataset as Markdown.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated string that must be freed with `gaggle_free()`.
+/// The string is empty if the dataset has no documentation. Any NUL characters in the
+/// text are replaced with U+FFFD. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_dataset_readme(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let readme = kaggle::get_dataset_readme(path_str)?;
+        Ok(readme.replace('\0', "\u{FFFD}"))
+    })();
+
+    match result {
+        Ok(readme) => string_to_c_string(readme),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Gets the license information of a dataset as a JSON object.
+///
+/// The object holds `licenseName`, `licenseShortName`, `licenseUrl`, `ownerName`,
+/// `ownerRef`, and `usabilityRating`, with `null` for fields that are not reported.
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_get_dataset_license(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let license = kaggle::get_dataset_license(path_str)?;
+        Ok(serde_json::to_string(&license)?)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Infers the column names and types of the CSV and Parquet files in a cached dataset.
+///
+/// A file that cannot be read is listed with an `error` instead of its `columns`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. If the schema cannot be inferred (for example, because the
+/// dataset is not cached), the JSON is an object of the form
+/// `{"error": "...", "code": "E002"}` and `gaggle_last_error` is set as well.
+/// Returns `NULL` only for a `NULL` or invalid input pointer.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_infer_schema(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let path_str = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+        kaggle::normalize_dataset_ref(path_str)
+    })();
+    let path_str = match path_str {
+        Ok(p) => p,
+        Err(e) => {
+            error::set_last_error(&e);
+            return std::ptr::null_mut();
+        }
+    };
+
+    match kaggle::infer_dataset_schema(&path_str) {
+        Ok(schema) => string_to_c_string(schema.to_string()),
+        Err(e) => {
+            let ctx = error::ErrorContext {
+                dataset: Some(path_str.to_string()),
+                ..Default::default()
+            };
+            error::set_last_error_with_context(&e, &ctx);
+            string_to_c_string(json!({"error": e.to_string(), "code": e.code_str()}).to_string())
+        }
+    }
+}
+
+/// Retrieves the version of the Gaggle library.
+#[no_mangle]
+pub extern "C" fn gaggle_get_version() -> *mut c_char {
+    // Return only the version string (no JSON wrapper)
+    string_to_c_string(env!("CARGO_PKG_VERSION").to_string())
+}
+
+/// Retrieves metadata about how the Gaggle library was built.
+///
+/// # Returns
+///
+/// A pointer to a JSON object with the keys `version`, `git_commit`, `build_date`,
+/// `rust_version`, `target`, and `features` that must be freed with `gaggle_free()`. Values
+/// that were not known at build time are `null`.
+#[no_mangle]
+pub extern "C" fn gaggle_get_build_info() -> *mut c_char {
+    let known = |v: &'static str| (!v.is_empty()).then_some(v);
+    let features: Vec<&str> = env!("GAGGLE_BUILD_FEATURES")
+        .split(',')
+        .filter(|f| !f.is_empty())
+        .collect();
+    let info = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_commit": known(env!("GAGGLE_BUILD_GIT_SHA")),
+        "build_date": known(env!("GAGGLE_BUILD_DATE")),
+        "rust_version": known(env!("GAGGLE_BUILD_RUSTC_VERSION")),
+        "target": known(env!("GAGGLE_BUILD_TARGET")),
+        "features": features,
+    });
+    string_to_c_string(info.to_string())
+}
+
+/// Reports whether dry-run mode (`GAGGLE_DRY_RUN`) is enabled.
+///
+/// # Returns
+///
+/// Returns `1` if downloads only report the paths they would use, or `0` otherwise.
+#[no_mangle]
+pub extern "C" fn gaggle_is_dry_run() -> i32 {
+    error::clear_last_error_internal();
+    i32::from(crate::config::dry_run())
+}
+
+/// Reports whether a file name in a dataset directory belongs to Gaggle's own cache state,
+/// such as the `.downloaded` marker or a saved schema, rather than to the dataset. Callers
+/// that walk a dataset directory use this to skip those files.
+///
+/// # Returns
+///
+/// Returns `1` for a cache state file, `0` for a dataset file, or `-1` on error (such as a
+/// null pointer), in which case `gaggle_last_error` is set.
+///
+/// # Safety
+///
+/// - The `name` pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_is_cache_state_file(name: *const c_char) -> i32 {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<bool, error::GaggleError> {
+        if name.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let name_str = CStr::from_ptr(name).to_str()?;
+        Ok(kaggle::download::is_cache_state_file(std::ffi::OsStr::new(
+            name_str,
+        )))
+    })();
+
+    match result {
+        Ok(is_state) => i32::from(is_state),
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Frees a heap-allocated C string.
+///
+/// # Safety
+///
+/// `ptr` must be a pointer previously returned by a Gaggle FFI function that transfers ownership,
+/// such as `gaggle_get_version` or `gaggle_list_files`. Passing the same pointer more than once,
+/// or providing a pointer not allocated by Gaggle, will result in undefined behavior.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_free(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        let _ = CString::from_raw(ptr);
+    }
+}
+
+/// Clears the dataset cache.
+#[no_mangle]
+pub extern "C" fn gaggle_clear_cache() -> i32 {
+    let result = (|| -> Result<(), error::GaggleError> {
+        // Use runtime-resolved cache dir to honor env overrides
+        let cache_dir = crate::config::cache_dir_runtime();
+        if cache_dir.exists() {
+            fs::remove_dir_all(&cache_dir)?;
+            fs::create_dir_all(&cache_dir)?;
+        } else {
+            fs::create_dir_all(&cache_dir)?;
+        }
+        kaggle::metadata::clear_metadata_cache();
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Enforces the cache size limit by evicting the oldest datasets.
+#[no_mangle]
+pub extern "C" fn gaggle_enforce_cache_limit() -> i32 {
+    let result = kaggle::download::enforce_cache_limit_now();
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+unsafe fn clear_dataset_cache_impl(dataset_path: *const c_char, force: bool) -> i32 {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<(), error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+        kaggle::clear_dataset_cache(path_str, force)
+    })();
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            -1
+        }
+    }
+}
+
+/// Removes one dataset from the cache, unless it is pinned.
+///
+/// # Returns
+///
+/// Returns `0` on success, including when the dataset is not cached, or `-1` on failure,
+/// such as when the dataset is pinned. The error can be retrieved with `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_clear_dataset_cache(dataset_path: *const c_char) -> i32 {
+    clear_dataset_cache_impl(dataset_path, false)
+}
+
+/// Removes one dataset from the cache, removing its pin first if it is pinned.
+///
+/// # Returns
+///
+/// Returns `0` on success, including when the dataset is not cached, or `-1` on failure.
+/// The error can be retrieved with `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_clear_dataset_cache_force(dataset_path: *const c_char) -> i32 {
+    clear_dataset_cache_impl(dataset_path, true)
+}
+
+/// Removes the cached datasets downloaded more than `max_age_secs` seconds ago, except
+/// pinned ones.
+///
+/// # Returns
+///
+/// Returns the number of datasets removed, or `-1` on failure. The error can be retrieved
+/// with `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_evict_datasets_older_than(max_age_secs: u64) -> i32 {
+    error::clear_last_error_internal();
+
+    match kaggle::download::evict_datasets_older_than(max_age_secs) {
+        Ok(removed) => i32::try_from(removed).unwrap_or(i32::MAX),
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Removes the staging directories (see `GAGGLE_STAGING_DIR`) last modified more than an
+/// hour ago, which are left when a process is killed while extracting a dataset.
+///
+/// # Returns
+///
+/// Returns the number of directories removed, or `-1` on failure. The error can be
+/// retrieved with `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_cleanup_staging() -> i32 {
+    error::clear_last_error_internal();
+
+    match kaggle::download::cleanup_staging_dir() {
+        Ok(removed) => i32::try_from(removed).unwrap_or(i32::MAX),
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Moves the cache directory to `new_path` without downloading the cached datasets again.
+///
+/// The new location is recorded in the cache override file (see
+/// `GAGGLE_CACHE_OVERRIDE_FILE`), so it is used by later calls and processes ahead of
+/// `GAGGLE_CACHE_DIR`. `new_path` must not be inside the current cache, and must either not
+/// exist or be an empty directory.
+///
+/// # Returns
+///
+/// Returns `0` on success, or `-1` on failure. If the operation fails,
+/// a detailed error message can be retrieved using `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and remain accessible for the duration of this call.
+/// - The provided string must be valid UTF-8, and interior NUL characters are not permitted.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_move_cache_dir(new_path: *const c_char) -> i32 {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<(), error::GaggleError> {
+        if new_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let new_path_str = CStr::from_ptr(new_path).to_str()?;
+        if new_path_str.trim().is_empty() {
+            return Err(error::GaggleError::IoError(
+                "Cache directory path cannot be empty".to_string(),
+            ));
+        }
+        kaggle::download::move_cache_dir(std::path::Path::new(new_path_str))
+    })();
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Checks if the cached dataset is the current version.
+///
+/// # Returns
+///
+/// Returns `1` if it is current, `0` if it is outdated or not cached, or `-1` on error. In
+/// offline mode, a cache marker written by an older version cannot be upgraded, so whether
+/// the dataset is current is unknown, and `-1` is returned with an error that says so.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_is_dataset_current(dataset_path: *const c_char) -> i32 {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<bool, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+        kaggle::is_dataset_current(path_str)
+    })();
+
+    match result {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            -1
+        }
+    }
+}
+
+/// Forces an update of the dataset to the latest version, ignoring the cache.
+///
+/// The cached copy is replaced only after the new copy is downloaded and extracted, so it is
+/// kept if the update fails. A path with a version updates the cached copy of that version.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_update_dataset(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let local_path = kaggle::update_dataset(path_str)?;
+        Ok(local_path.to_string_lossy().to_string())
+    })();
+
+    match result {
+        Ok(path) => string_to_c_string(path),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Downloads a dataset only if it is not cached or the cached copy is not the latest version.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string `{"path": ..., "was_updated": bool,
+/// "previous_version": ... | null, "current_version": ... | null}`, where `previous_version` is
+/// the cached version before the call. This string must be freed with `gaggle_free()`. On
+/// error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_download_if_stale(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let check = kaggle::download::check_for_update(path_str)?;
+        Ok(json!({
+            "path": check.path.to_string_lossy(),
+            "was_updated": check.was_updated,
+            "previous_version": check.previous_version,
+            "current_version": check.current_version,
+        })
+        .to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Parses a JSON array of local file paths passed over FFI.
+fn parse_files_json(files_json: &str) -> Result<Vec<std::path::PathBuf>, error::GaggleError> {
+    let files: Vec<String> = serde_json::from_str(files_json).map_err(|e| {
+        error::GaggleError::JsonError(format!("files must be a JSON array of paths: {}", e))
+    })?;
+    Ok(files.into_iter().map(std::path::PathBuf::from).collect())
+}
+
+/// Creates a new dataset on Kaggle from local files.
+///
+/// `files_json` is a JSON array of local file paths. `is_private` is non-zero for
+/// a private dataset.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string with the API response that
+/// must be freed with `gaggle_free()`. On error, returns `NULL` and sets
+/// `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - All pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_create_dataset(
+    owner: *const c_char,
+    title: *const c_char,
+    files_json: *const c_char,
+    license: *const c_char,
+    is_private: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if owner.is_null() || title.is_null() || files_json.is_null() || license.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let owner_str = CStr::from_ptr(owner).to_str()?;
+        let title_str = CStr::from_ptr(title).to_str()?;
+        let files_str = CStr::from_ptr(files_json).to_str()?;
+        let license_str = CStr::from_ptr(license).to_str()?;
+
+        let files = parse_files_json(files_str)?;
+        let file_refs: Vec<&std::path::Path> = files.iter().map(|p| p.as_path()).collect();
+        let response = kaggle::create_dataset(
+            owner_str,
+            title_str,
+            &file_refs,
+            license_str,
+            is_private != 0,
+        )?;
+        Ok(response.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Uploads local files as a new version of an existing Kaggle dataset.
+///
+/// `files_json` is a JSON array of local file paths.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string with the API response that
+/// must be freed with `gaggle_free()`. On error, returns `NULL` and sets
+/// `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - All pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_update_dataset_files(
+    dataset_path: *const c_char,
+    files_json: *const c_char,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() || files_json.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+        let files_str = CStr::from_ptr(files_json).to_str()?;
+
+        let files = parse_files_json(files_str)?;
+        let file_refs: Vec<&std::path::Path> = files.iter().map(|p| p.as_path()).collect();
+        let response = kaggle::update_dataset_files(path_str, &file_refs)?;
+        Ok(response.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Retrieves version information for a dataset.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_dataset_version_info(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let info = kaggle::get_dataset_version_info(path_str)?;
+        Ok(info.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Reports what downloading a dataset would do, without downloading or removing anything.
+///
+/// The plan covers whether the dataset is cached, its cached and latest versions, its size,
+/// the cache headroom, and the datasets that eviction would remove to make room. In offline
+/// mode, it is built from local information only, and the fields that could not be
+/// determined are listed in `undetermined`.
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_plan_download(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let plan = kaggle::plan_download(path_str)?;
+        Ok(plan.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Checks the files of a downloaded dataset against the checksums recorded by the last check.
+///
+/// Returns a JSON object with `valid`, the `modified` and `missing` files, and the
+/// `checksums` of all files. Files whose modification time has not changed are not hashed
+/// again.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_verify_dataset_integrity(
+    dataset_path: *const c_char,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let report = kaggle::verify_dataset_integrity(path_str)?;
+        Ok(report.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Compares the cached files of a dataset with the files Kaggle lists for it.
+///
+/// In offline mode, the metadata saved by the last fetch is used instead, and the check fails
+/// if there is none.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string `{"complete": ..., "remote_files": ...,
+/// "local_files": ..., "missing": [...], "size_mismatches": [{"file": ..., "expected_bytes":
+/// ..., "actual_bytes": ...}], "extra": [...]}` that must be freed with `gaggle_free()`.
+/// On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_check_dataset(dataset_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+
+        let report = kaggle::check_dataset_integrity(path_str)?;
+        Ok(serde_json::to_string(&report)?)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Builds the cache information object shared by the cache info functions.
+fn cache_info_json() -> serde_json::Value {
+    let cache_dir = crate::config::cache_dir_runtime();
+
+    // Sizes come from the dataset markers, so this is O(datasets) rather than O(files)
+    let datasets = kaggle::list_cached_datasets().unwrap_or_default();
+    let size_mb: u64 = datasets.iter().map(|d| d.size_mb).sum();
+
+    let limit_mb = crate::config::cache_size_limit_mb();
+    let is_soft_limit = crate::config::cache_limit_is_soft();
+
+    let usage_percent = if let Some(limit) = limit_mb {
+        if limit > 0 {
+            ((size_mb as f64 / limit as f64) * 100.0) as u64
+        } else {
+            0
+        }
+    } else {
+        0
+    };
+
+    json!({
+        "path": cache_dir.to_string_lossy(),
+        "size_mb": size_mb, // MB (1024*1024)
+        "total_size_mb": size_mb,
+        "limit_mb": limit_mb,
+        "usage_percent": usage_percent,
+        "is_soft_limit": is_soft_limit,
+        "type": "local",
+        "datasets": datasets,
+    })
+}
+
+/// Retrieves information about the cache.
+///
+/// The returned JSON object includes the `total_size_mb` of the cache, and a `datasets`
+/// array with the `path`, `size_mb`, `version`, `age_seconds`, and `partial` flag of each
+/// cached dataset.
+#[no_mangle]
+pub extern "C" fn gaggle_get_cache_info() -> *mut c_char {
+    string_to_c_string(cache_info_json().to_string())
+}
+
+/// Retrieves the total size of the cache in megabytes, from the sizes recorded in the
+/// dataset markers.
+///
+/// # Returns
+///
+/// Returns the size in megabytes, or `0` on failure. The error can be retrieved with
+/// `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_get_total_cache_size_mb() -> u64 {
+    error::clear_last_error_internal();
+
+    match kaggle::download::get_total_cache_size_mb() {
+        Ok(size_mb) => size_mb,
+        Err(e) => {
+            error::set_last_error(&e);
+            0
+        }
+    }
+}
+
+/// Measures the size of one cached dataset in megabytes by walking its cache directory.
+///
+/// # Returns
+///
+/// Returns the size in megabytes, which is `0` for a dataset that is not cached, or `0` on
+/// failure. The error can be retrieved with `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_get_dataset_cache_size_mb(dataset_path: *const c_char) -> u64 {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<u64, error::GaggleError> {
+        if dataset_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        if path_str.len() > 4096 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "dataset path too long".to_string(),
+            ));
+        }
+        kaggle::download::get_dataset_cache_size_mb(path_str)
+    })();
+
+    match result {
+        Ok(size_mb) => size_mb,
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            0
+        }
+    }
+}
+
+/// Recalculates the size of every cached dataset, stores it in the dataset markers,
+/// and returns the updated cache information.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string in the same format as
+/// `gaggle_get_cache_info` that must be freed with `gaggle_free()`.
+/// On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_refresh_cache_info() -> *mut c_char {
+    error::clear_last_error_internal();
+
+    match kaggle::refresh_cache_sizes() {
+        Ok(_) => string_to_c_string(cache_info_json().to_string()),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Moves cached datasets whose directories have uppercase letters to their lowercase
+/// directories, which every casing of a dataset path now shares.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string of the form
+/// `{"moved": ["Owner/Data"], "conflicts": ["other/Dup"]}` that must be freed with
+/// `gaggle_free()`. Datasets listed in `conflicts` already have a lowercase copy and are left
+/// in place. On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_migrate_cache_case() -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = kaggle::download::migrate_cache_case()
+        .and_then(|report| serde_json::to_string(&report).map_err(error::GaggleError::from));
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Retrieves the counters of the work done in this process as a JSON object.
+///
+/// The object has the `api_calls`, `retries`, `cache_hits`, `cache_misses`,
+/// `downloads_started`, `downloads_completed`, `downloads_failed`, `single_file_fetches`, and
+/// `bytes_downloaded` counters, counted since the process started or since the last call to
+/// `gaggle_reset_stats`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string that must be freed with `gaggle_free()`.
+/// On error, returns `NULL` and sets `gaggle_last_error`.
+#[no_mangle]
+pub extern "C" fn gaggle_get_stats() -> *mut c_char {
+    error::clear_last_error_internal();
+
+    match serde_json::to_string(&kaggle::metrics::stats()) {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e.into());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Sets the counters returned by `gaggle_get_stats` back to zero.
+///
+/// # Returns
+///
+/// Returns `0` on success.
+#[no_mangle]
+pub extern "C" fn gaggle_reset_stats() -> i32 {
+    error::clear_last_error_internal();
+    kaggle::metrics::reset_stats();
+    0
+}
+
+/// Exports all cached datasets to a ZIP archive.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string of the form
+/// `{"datasets": N, "total_mb": N, "path": "..."}` that must be freed with
+/// `gaggle_free()`. On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_export_cache(dest_path: *const c_char) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dest_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let dest_str = CStr::from_ptr(dest_path).to_str()?;
+        if dest_str.is_empty() || dest_str.len() > 4096 {
+            return Err(error::GaggleError::IoError(
+                "invalid export destination path".to_string(),
+            ));
+        }
+
+        let summary = kaggle::export_cache(std::path::Path::new(dest_str))?;
+        Ok(summary.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Imports datasets from a ZIP archive created by `gaggle_export_cache`.
+///
+/// If `merge` is non-zero, datasets that are already cached are kept and skipped;
+/// otherwise they are replaced by the archived copy.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string of the form
+/// `{"imported": N, "skipped": N}` that must be freed with `gaggle_free()`.
+/// On error, returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_import_cache(
+    archive_path: *const c_char,
+    merge: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if archive_path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let archive_str = CStr::from_ptr(archive_path).to_str()?;
+        if archive_str.is_empty() || archive_str.len() > 4096 {
+            return Err(error::GaggleError::IoError(
+                "invalid import archive path".to_string(),
+            ));
+        }
+
+        let summary = kaggle::import_cache(std::path::Path::new(archive_str), merge != 0)?;
+        Ok(summary.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Copies a dataset to a directory outside the cache, downloading it first if needed.
+///
+/// Subdirectories are preserved, and Gaggle's marker files are not copied.
+///
+/// # Returns
+///
+/// Returns the number of bytes copied, or `-1` on failure. If the operation fails,
+/// a detailed error message can be retrieved using `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_copy_dataset_to(
+    dataset_path: *const c_char,
+    dest_dir: *const c_char,
+) -> i64 {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<u64, error::GaggleError> {
+        if dataset_path.is_null() || dest_dir.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let dest_str = CStr::from_ptr(dest_dir).to_str()?;
+        if dest_str.is_empty() || dest_str.len() > 4096 {
+            return Err(error::GaggleError::IoError(
+                "invalid copy destination path".to_string(),
+            ));
+        }
+        kaggle::copy_dataset_to(path_str, std::path::Path::new(dest_str))
+    })();
+    bytes_or_error(result)
+}
+
+/// Exports the files of a dataset to a directory outside the cache, downloading it first if
+/// needed.
+///
+/// Subdirectories are preserved, and Gaggle's marker files are not exported. Files are
+/// hard-linked when the destination is on the same file system as the cache, and copied
+/// otherwise.
+///
+/// # Arguments
+///
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string representing the owner and dataset.
+/// * `dest_dir` - A non-null pointer to a NUL-terminated C string with the destination directory.
+/// * `overwrite` - Non-zero to replace files that already exist in `dest_dir`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated JSON string `{"files_copied": ..., "files_linked":
+/// ..., "bytes": ..., "errors": [{"file": ..., "error": ...}]}`, where `errors` lists the files
+/// that could not be exported. This string must be freed with `gaggle_free()`. On error,
+/// returns `NULL` and sets `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_export_dataset(
+    dataset_path: *const c_char,
+    dest_dir: *const c_char,
+    overwrite: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() || dest_dir.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let dest_str = CStr::from_ptr(dest_dir).to_str()?;
+        if dest_str.is_empty() || dest_str.len() > 4096 {
+            return Err(error::GaggleError::IoError(
+                "invalid export destination path".to_string(),
+            ));
+        }
+        let summary =
+            kaggle::export_dataset(path_str, std::path::Path::new(dest_str), overwrite != 0)?;
+        Ok(serde_json::to_string(&summary)?)
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Imports a local directory into the cache as a dataset.
+///
+/// The files of `source_dir` are copied into the cache and marked as downloaded, so
+/// listing files, resolving file paths, and offline mode treat the dataset like one
+/// downloaded from Kaggle. A cached copy of the dataset is replaced.
+///
+/// # Arguments
+///
+/// * `dataset_path` - A non-null pointer to a NUL-terminated C string representing the owner and dataset.
+/// * `source_dir` - A non-null pointer to a NUL-terminated C string with the directory to import.
+/// * `version` - A pointer to a NUL-terminated C string with the version to record, or `NULL`.
+///
+/// # Returns
+///
+/// Returns a pointer to a heap-allocated C string with the cache directory of the dataset,
+/// which must be freed with `gaggle_free()`. On error, returns `NULL` and sets
+/// `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The non-null pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_import_dataset(
+    dataset_path: *const c_char,
+    source_dir: *const c_char,
+    version: *const c_char,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() || source_dir.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(path_str.to_string());
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let source_str = CStr::from_ptr(source_dir).to_str()?;
+        if source_str.is_empty() || source_str.len() > 4096 {
+            return Err(error::GaggleError::IoError(
+                "invalid import source path".to_string(),
+            ));
+        }
+        let version = if version.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(version).to_str()?)
+        };
+        let dir = kaggle::import_dataset(path_str, std::path::Path::new(source_str), version)?;
+        Ok(dir.to_string_lossy().to_string())
+    })();
+
+    match result {
+        Ok(path) => string_to_c_string(path),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Copies a single file of a dataset to `dest_file`, downloading it first if needed.
+///
+/// # Returns
+///
+/// Returns the number of bytes copied, or `-1` on failure. If the operation fails,
+/// a detailed error message can be retrieved using `gaggle_last_error`.
+///
+/// # Safety
+///
+/// - The pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_copy_file_to(
+    dataset_path: *const c_char,
+    filename: *const c_char,
+    dest_file: *const c_char,
+) -> i64 {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<u64, error::GaggleError> {
+        if dataset_path.is_null() || filename.is_null() || dest_file.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let path_str = CStr::from_ptr(dataset_path).to_str()?;
+        let path_str = &kaggle::normalize_dataset_ref(path_str)?;
+        let filename_str = CStr::from_ptr(filename).to_str()?;
+        let dest_str = CStr::from_ptr(dest_file).to_str()?;
+        if dest_str.is_empty() || dest_str.len() > 4096 {
+            return Err(error::GaggleError::IoError(
+                "invalid copy destination path".to_string(),
+            ));
+        }
+        kaggle::copy_file_to(path_str, filename_str, std::path::Path::new(dest_str))
+    })();
+    bytes_or_error(result)
+}
+
+/// Converts a byte count into an FFI return value, setting the last error on failure.
+fn bytes_or_error(result: Result<u64, error::GaggleError>) -> i64 {
+    match result {
+        Ok(bytes) => i64::try_from(bytes).unwrap_or(i64::MAX),
+        Err(e) => {
+            error::set_last_error(&e);
+            -1
+        }
+    }
+}
+
+/// Parses JSON and expands objects/arrays, similar to `json_each`.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_json_each(json_str: *const c_char) -> *mut c_char {
+    // Clear any previous error
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if json_str.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let json_cstr = CStr::from_ptr(json_str).to_str()?;
+
+        // Parse the JSON
+        let value: serde_json::Value = serde_json::from_str(json_cstr)?;
+
+        // Expand into rows
+        let mut rows = Vec::new();
+        expand_json_value(&value, "$", &mut rows);
+
+        // Convert rows to newline-delimited JSON
+        let result_str = rows
+            .into_iter()
+            .map(|row| row.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(result_str)
+    })();
+
+    match result {
+        Ok(s) => string_to_c_string(s),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Hard cap on the recursion depth of `gaggle_json_each_recursive`.
+const JSON_EACH_MAX_DEPTH: i32 = 32;
+
+/// Parses JSON and expands nested objects/arrays recursively, up to `max_depth` levels.
+///
+/// Each row has the same fields as `gaggle_json_each` plus `depth`, which is `1` for the
+/// children of the root value. A `max_depth` of `0` (or less) means unlimited, and the
+/// depth is always capped at 32.
+///
+/// # Safety
+///
+/// - The pointer must be valid and point to a valid NUL-terminated C string.
+/// - The string must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_json_each_recursive(
+    json_str: *const c_char,
+    max_depth: i32,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if json_str.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let json_cstr = CStr::from_ptr(json_str).to_str()?;
+        let value: serde_json::Value = serde_json::from_str(json_cstr)?;
+
+        let max_depth = if max_depth <= 0 {
+            JSON_EACH_MAX_DEPTH
+        } else {
+            max_depth.min(JSON_EACH_MAX_DEPTH)
+        };
+        let mut rows = Vec::new();
+        expand_json_value_recursive(&value, "$", 1, max_depth, &mut rows);
+
+        Ok(rows
+            .into_iter()
+            .map(|row| row.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    })();
+
+    match result {
+        Ok(s) => string_to_c_string(s),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Parses JSON, navigates to the node at `path`, and expands that node like `gaggle_json_each`.
+///
+/// `path` uses dotted and bracket syntax, such as `$.files[2].columns` or `$["odd key"]`.
+/// A path that does not resolve produces an empty result. Invalid path syntax sets a
+/// `JsonError` that names the offset of the problem.
+///
+/// # Safety
+///
+/// - Both pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_json_each_path(
+    json_str: *const c_char,
+    path: *const c_char,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if json_str.is_null() || path.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let json_cstr = CStr::from_ptr(json_str).to_str()?;
+        let segments = parse_json_path(CStr::from_ptr(path).to_str()?)?;
+        let value: serde_json::Value = serde_json::from_str(json_cstr)?;
+
+        let mut rows = Vec::new();
+        if let Some(node) = resolve_json_path(&value, &segments) {
+            expand_json_value(node, &json_path_to_string(&segments), &mut rows);
+        }
+        Ok(rows
+            .into_iter()
+            .map(|row| row.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    })();
+
+    match result {
+        Ok(s) => string_to_c_string(s),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Parses JSON and walks the whole tree below `path`, similar to SQLite's `json_tree`.
+///
+/// Emits one row per node, starting with the node at `path` itself and visiting children in
+/// document order. Each row has `key`, `value`, `type`, and `path` like `gaggle_json_each`,
+/// plus `parent` (the path of the parent node, `null` for the starting node) and `depth`
+/// (`0` for the starting node). A `NULL` path starts at the root. A path that does not
+/// resolve produces an empty result, and invalid path syntax sets a `JsonError`.
+///
+/// # Safety
+///
+/// - `json_str` must be a valid pointer to a NUL-terminated C string.
+/// - `path` must be `NULL` or a valid pointer to a NUL-terminated C string.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_json_tree(
+    json_str: *const c_char,
+    path: *const c_char,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if json_str.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let json_cstr = CStr::from_ptr(json_str).to_str()?;
+        let segments = if path.is_null() {
+            Vec::new()
+        } else {
+            parse_json_path(CStr::from_ptr(path).to_str()?)?
+        };
+        let value: serde_json::Value = serde_json::from_str(json_cstr)?;
+
+        let mut rows = Vec::new();
+        if let Some(node) = resolve_json_path(&value, &segments) {
+            let key = match segments.last() {
+                Some(JsonPathSegment::Key(k)) => json!(k),
+                Some(JsonPathSegment::Index(i)) => json!(i),
+                None => serde_json::Value::Null,
+            };
+            let node_path = json_path_to_string(&segments);
+            rows.push(json!({
+                "key": key,
+                "value": node,
+                "type": get_json_type(node),
+                "path": node_path,
+                "parent": null,
+                "depth": 0
+            }));
+            walk_json_tree(node, &node_path, 1, &mut rows);
+        }
+        Ok(rows
+            .into_iter()
+            .map(|row| row.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    })();
+
+    match result {
+        Ok(s) => string_to_c_string(s),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Flattens nested JSON objects/arrays into a single object of path-to-value pairs.
+///
+/// Keys are joined with `separator` (`.` when it is `NULL` or empty), and array elements
+/// use their index as the key, so `{"a": {"b": [1]}}` becomes `{"a.b.0": 1}`.
+/// Empty objects and arrays are kept as values.
+///
+/// # Safety
+///
+/// - `json_str` must be a valid pointer to a NUL-terminated C string.
+/// - `separator` must be `NULL` or a valid pointer to a NUL-terminated C string.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_json_flatten(
+    json_str: *const c_char,
+    separator: *const c_char,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let result = (|| -> Result<String, error::GaggleError> {
+        if json_str.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let json_cstr = CStr::from_ptr(json_str).to_str()?;
+        let separator = if separator.is_null() {
+            "."
+        } else {
+            match CStr::from_ptr(separator).to_str()? {
+                "" => ".",
+                sep => sep,
+            }
+        };
+        let value: serde_json::Value = serde_json::from_str(json_cstr)?;
+        if !value.is_object() && !value.is_array() {
+            return Err(error::GaggleError::JsonError(
+                "expected a JSON object or array to flatten".to_string(),
+            ));
+        }
+
+        let mut flat = serde_json::Map::new();
+        flatten_json_value(&value, None, separator, &mut flat);
+        Ok(serde_json::Value::Object(flat).to_string())
+    })();
+
+    match result {
+        Ok(s) => string_to_c_string(s),
+        Err(e) => {
+            error::set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Prefetches multiple files in a dataset without downloading the entire archive.
+///
+/// # Safety
+///
+/// - Both pointers must be valid and point to valid NUL-terminated C strings.
+/// - The strings must be valid UTF-8, and interior NUL characters are not allowed.
+#[no_mangle]
+pub unsafe extern "C" fn gaggle_prefetch_files(
+    dataset_path: *const c_char,
+    file_list: *const c_char,
+) -> *mut c_char {
+    error::clear_last_error_internal();
+
+    let mut ctx = error::ErrorContext::default();
+    let result = (|| -> Result<String, error::GaggleError> {
+        if dataset_path.is_null() || file_list.is_null() {
+            return Err(error::GaggleError::NullPointer);
+        }
+        let ds = CStr::from_ptr(dataset_path).to_str()?;
+        ctx.dataset = Some(ds.to_string());
+        let ds = &kaggle::normalize_dataset_ref(ds)?;
+        let files_str = CStr::from_ptr(file_list).to_str()?;
+        if ds.len() > 4096 || files_str.len() > 1_000_000 {
+            return Err(error::GaggleError::InvalidDatasetPath(
+                "input too long".to_string(),
+            ));
+        }
+        let files: Vec<&str> = files_str
+            .lines()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if files.is_empty() {
+            return Err(error::GaggleError::IoError(
+                "no valid files provided".to_string(),
+            ));
+        }
+        let json_val = crate::kaggle::prefetch_files(ds, &files)?;
+        Ok(json_val.to_string())
+    })();
+
+    match result {
+        Ok(json) => string_to_c_string(json),
+        Err(e) => {
+            error::set_last_error_with_context(&e, &ctx);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+pub(crate) fn string_to_c_string(s: String) -> *mut c_char {
+    match CString::new(s) {
+        Ok(cstring) => cstring.into_raw(),
+        Err(e) => {
+            let err = error::GaggleError::IoError(format!(
+                "String contains null byte at position {}",
+                e.nul_position()
+            ));
+            error::set_last_error(&err);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Helper function to recursively expand JSON values
+fn expand_json_value(value: &serde_json::Value, path: &str, rows: &mut Vec<serde_json::Value>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, val) in map.iter() {
+                let new_path = if path == "$" {
+                    format!("$.{}", key)
+                } else {
+                    format!("{}.{}", path, key)
+                };
+
+                let row = json!({
+                    "key": key,
+                    "value": val,
+                    "type": get_json_type(val),
+                    "path": new_path
+                });
+                rows.push(row);
+            }
+        }
+        serde_json::Value::Array(arr) => {
+            for (idx, val) in arr.iter().enumerate() {
+                let new_path = format!("{}[{}]", path, idx);
+
+                let row = json!({
+                    "key": idx,
+                    "value": val,
+                    "type": get_json_type(val),
+                    "path": new_path
+                });
+                rows.push(row);
+            }
+        }
+        _ => {
+            // For scalar values, return as is
+            let row = json!({
+                "key": null,
+                "value": value,
+                "type": get_json_type(value),
+                "path": path
+            });
+            rows.push(row);
+        }
+    }
+}
+
+/// Helper function to expand JSON values recursively, adding a `depth` field to each row
+fn expand_json_value_recursive(
+    value: &serde_json::Value,
+    path: &str,
+    depth: i32,
+    max_depth: i32,
+    rows: &mut Vec<serde_json::Value>,
+) {
+    let children: Vec<(serde_json::Value, String, &serde_json::Value)> = match value {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(key, val)| {
+                let new_path = if path == "$" {
+                    format!("$.{}", key)
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                (json!(key), new_path, val)
+            })
+            .collect(),
+        serde_json::Value::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(idx, val)| (json!(idx), format!("{}[{}]", path, idx), val))
+            .collect(),
+        _ => {
+            // Only a scalar root value reaches this branch
+            rows.push(json!({
+                "key": null,
+                "value": value,
+                "type": get_json_type(value),
+                "path": path,
+                "depth": depth - 1
+            }));
+            return;
+        }
+    };
+
+    for (key, new_path, val) in children {
+        rows.push(json!({
+            "key": key,
+            "value": val,
+            "type": get_json_type(val),
+            "path": new_path,
+            "depth": depth
+        }));
+        if depth < max_depth && (val.is_object() || val.is_array()) {
+            expand_json_value_recursive(val, &new_path, depth + 1, max_depth, rows);
+        }
+    }
+}
+
+/// One step of a JSON path: an object key or an array index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JsonPathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parses a JSON path like `$.files[2].columns` or `$["odd key"]` into its segments.
+fn parse_json_path(path: &str) -> Result<Vec<JsonPathSegment>, error::GaggleError> {
+    let invalid = |offset: usize, reason: &str| {
+        error::GaggleError::JsonError(format!(
+            "Invalid JSON path '{}' at offset {}: {}",
+            path, offset, reason
+        ))
+    };
+
+    let bytes = path.as_bytes();
+    if bytes.first() != Some(&b'$') {
+        return Err(invalid(0, "path must start with '$'"));
+    }
+    let mut segments = Vec::new();
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'.' => {
+                let start = i + 1;
+                let end = path[start..]
+                    .find(['.', '['])
+                    .map_or(path.len(), |n| start + n);
+                if end == start {
+                    return Err(invalid(start, "expected a key after '.'"));
+                }
+                segments.push(JsonPathSegment::Key(path[start..end].to_string()));
+                i = end;
+            }
+            b'[' if bytes.get(i + 1) == Some(&b'"') => {
+                // Quoted key; the closing quote must be followed by ']'
+                let start = i + 2;
+                let Some(len) = path[start..].find("\"]") else {
+                    return Err(invalid(i, "unterminated quoted key"));
+                };
+                segments.push(JsonPathSegment::Key(path[start..start + len].to_string()));
+                i = start + len + 2;
+            }
+            b'[' => {
+                let start = i + 1;
+                let Some(len) = path[start..].find(']') else {
+                    return Err(invalid(i, "missing ']'"));
+                };
+                let index = &path[start..start + len];
+                if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid(start, "array index must be a non-negative integer"));
+                }
+                let index = index
+                    .parse()
+                    .map_err(|_| invalid(start, "array index is too large"))?;
+                segments.push(JsonPathSegment::Index(index));
+                i = start + len + 1;
+            }
+            _ => return Err(invalid(i, "expected '.' or '['")),
+        }
+    }
+    Ok(segments)
+}
+
+/// Follows `segments` from `value`, returning `None` if the path does not exist.
+fn resolve_json_path<'a>(
+    value: &'a serde_json::Value,
+    segments: &[JsonPathSegment],
+) -> Option<&'a serde_json::Value> {
+    segments
+        .iter()
+        .try_fold(value, |node, segment| match segment {
+            JsonPathSegment::Key(key) => node.as_object()?.get(key),
+            JsonPathSegment::Index(idx) => node.as_array()?.get(*idx),
+        })
+}
+
+/// Formats path segments the same way the expansion functions write the `path` field.
+fn json_path_to_string(segments: &[JsonPathSegment]) -> String {
+    let mut path = String::from("$");
+    for segment in segments {
+        match segment {
+            JsonPathSegment::Key(key) => {
+                path.push('.');
+                path.push_str(key);
+            }
+            JsonPathSegment::Index(idx) => path.push_str(&format!("[{}]", idx)),
+        }
+    }
+    path
+}
+
+/// Helper function to emit a row for every descendant of `value`, in document order
+fn walk_json_tree(
+    value: &serde_json::Value,
+    path: &str,
+    depth: u32,
+    rows: &mut Vec<serde_json::Value>,
+) {
+    let children: Vec<(serde_json::Value, String, &serde_json::Value)> = match value {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(key, val)| (json!(key), format!("{}.{}", path, key), val))
+            .collect(),
+        serde_json::Value::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(idx, val)| (json!(idx), format!("{}[{}]", path, idx), val))
+            .collect(),
+        _ => return,
+    };
+
+    for (key, new_path, val) in children {
+        rows.push(json!({
+            "key": key,
+            "value": val,
+            "type": get_json_type(val),
+            "path": new_path,
+            "parent": path,
+            "depth": depth
+        }));
+        walk_json_tree(val, &new_path, depth + 1, rows);
+    }
+}
+
+/// Helper function to flatten JSON values into `out`, joining keys with `separator`
+fn flatten_json_value(
+    value: &serde_json::Value,
+    prefix: Option<&str>,
+    separator: &str,
+    out: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    let join = |key: &str| match prefix {
+        Some(p) => format!("{}{}{}", p, separator, key),
+        None => key.to_string(),
+    };
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (key, val) in map {
+                flatten_json_value(val, Some(&join(key)), separator, out);
+            }
+        }
+        serde_json::Value::Array(arr) if !arr.is_empty() => {
+            for (idx, val) in arr.iter().enumerate() {
+                flatten_json_value(val, Some(&join(&idx.to_string())), separator, out);
+            }
+        }
+        _ => {
+            // An empty root object or array flattens to an empty object
+            if let Some(p) = prefix {
+                out.insert(p.to_string(), value.clone());
+            }
+        }
+    }
+}
+
+/// Helper function to get JSON type as string
+fn get_json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaggle_set_log_callback_null() {
+        let result = unsafe { gaggle_set_log_callback(None, std::ptr::null_mut()) };
+        assert_eq!(result, -1);
+        assert_eq!(crate::error::gaggle_last_error_code(), 10);
+        assert_eq!(gaggle_clear_log_callback(), 0);
+    }
+
+    #[test]
+    fn test_gaggle_get_version_not_null() {
+        let version_ptr = gaggle_get_version();
+        assert!(!version_ptr.is_null());
+
+        unsafe {
+            let version_cstr = CStr::from_ptr(version_ptr);
+            let version_str = version_cstr.to_str().unwrap();
+            assert!(!version_str.is_empty());
+            // Should be a plain version string like "0.1.0"
+            assert!(!version_str.contains("{"));
+            assert!(!version_str.contains("}"));
+
+            gaggle_free(version_ptr);
+        }
+    }
+
+    #[test]
+    fn test_gaggle_get_build_info() {
+        let ptr = gaggle_get_build_info();
+        assert!(!ptr.is_null());
+        let info: serde_json::Value = unsafe {
+            let value = serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            gaggle_free(ptr);
+            value
+        };
+
+        assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!info["version"].as_str().unwrap().is_empty());
+        for key in ["git_commit", "build_date", "rust_version", "target"] {
+            assert!(
+                info[key].is_null() || info[key].as_str().is_some_and(|v| !v.is_empty()),
+                "{}: {}",
+                key,
+                info[key]
+            );
+        }
+        assert!(info["features"].is_array());
+        let date = info["build_date"].as_str().unwrap();
+        assert_eq!(date.len(), 10);
+        assert_eq!(date.as_bytes()[4], b'-');
+        assert!(info["target"].as_str().is_some());
+    }
+
+    #[test]
+    fn test_gaggle_get_version_format() {
+        let version_ptr = gaggle_get_version();
+        unsafe {
+            let version_cstr = CStr::from_ptr(version_ptr);
+            let version_str = version_cstr.to_str().unwrap();
+            // Should not be JSON; just a semantic version-like string
+            assert!(!version_str.starts_with('{'));
+            assert!(!version_str.ends_with('}'));
+
+            gaggle_free(version_ptr);
+        }
+    }
+
+    #[test]
+    fn test_gaggle_free_null_pointer() {
+        // Should not panic when freeing null pointer
+        unsafe {
+            gaggle_free(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_gaggle_free_valid_pointer() {
+        let ptr = gaggle_get_version();
+        unsafe {
+            gaggle_free(ptr);
+        }
+        // If we got here without crashing, the test passes
+    }
+
+    #[test]
+    fn test_gaggle_clear_cache_success() {
+        let result = gaggle_clear_cache();
+        // Should return 0 (success) or -1 (error), but most likely 0
+        assert!(result == 0 || result == -1);
+    }
+
+    #[test]
+    fn test_gaggle_get_cache_info_not_null() {
+        let info_ptr = gaggle_get_cache_info();
+        assert!(!info_ptr.is_null());
+
+        unsafe {
+            let info_cstr = CStr::from_ptr(info_ptr);
+            let info_str = info_cstr.to_str().unwrap();
+            assert!(!info_str.is_empty());
+            assert!(info_str.contains("\"path\""));
+
+            gaggle_free(info_ptr);
+        }
+    }
+
+    #[test]
+    fn test_gaggle_get_cache_info_format() {
+        let info_ptr = gaggle_get_cache_info();
+        unsafe {
+            let info_cstr = CStr::from_ptr(info_ptr);
+            let info_str = info_cstr.to_str().unwrap();
+            // Should be valid JSON
+            assert!(info_str.starts_with('{'));
+            assert!(info_str.ends_with('}'));
+            // Should contain the documented keys
+            assert!(info_str.contains("\"path\""));
+            assert!(info_str.contains("\"size_mb\""));
+            assert!(info_str.contains("\"limit_mb\""));
+            assert!(info_str.contains("\"usage_percent\""));
+            assert!(info_str.contains("\"is_soft_limit\""));
+            assert!(info_str.contains("\"type\""));
+
+            gaggle_free(info_ptr);
+        }
+    }
+
+    #[test]
+    fn test_gaggle_set_credentials_valid() {
+        let username = CString::new("testuser").unwrap();
+        let key = CString::new("testkey").unwrap();
+
+        unsafe {
+            let result = gaggle_set_credentials(username.as_ptr(), key.as_ptr());
+            assert_eq!(result, 0); // Should succeed
+        }
+    }
+
+    #[test]
+    fn test_gaggle_set_credentials_null_username() {
+        let key = CString::new("testkey").unwrap();
+
+        unsafe {
+            let result = gaggle_set_credentials(std::ptr::null(), key.as_ptr());
+            assert_eq!(result, -1); // Should fail
+        }
+    }
+
+    #[test]
+    fn test_gaggle_set_credentials_null_key() {
+        let username = CString::new("testuser").unwrap();
+
+        unsafe {
+            let result = gaggle_set_credentials(username.as_ptr(), std::ptr::null());
+            assert_eq!(result, -1); // Should fail
+        }
+    }
+
+    #[test]
+    fn test_gaggle_set_credentials_both_null() {
+        unsafe {
+            let result = gaggle_set_credentials(std::ptr::null(), std::ptr::null());
+            assert_eq!(result, -1); // Should fail
+        }
+    }
+
+    #[test]
+    fn test_gaggle_set_credentials_empty_strings() {
+        let username = CString::new("").unwrap();
+        let key = CString::new("").unwrap();
+
+        unsafe {
+            let result = gaggle_set_credentials(username.as_ptr(), key.as_ptr());
+            assert_eq!(result, 0); // Should succeed even with empty strings
+        }
+    }
+
+    #[test]
+    fn test_gaggle_set_credentials_long_strings() {
+        let long_username = "user".repeat(100);
+        let long_key = "key".repeat(100);
+        let username = CString::new(long_username).unwrap();
+        let key = CString::new(long_key).unwrap();
+
+        unsafe {
+            let result = gaggle_set_credentials(username.as_ptr(), key.as_ptr());
+            assert_eq!(result, 0); // Should succeed
+        }
+    }
+
+    #[test]
+    fn test_gaggle_set_credentials_special_chars() {
+        let username = CString::new("user@example.com").unwrap();
+        let key = CString::new("key!@#$%^&*()").unwrap();
+
+        unsafe {
+            let result = gaggle_set_credentials(username.as_ptr(), key.as_ptr());
+            assert_eq!(result, 0); // Should succeed
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_reinit_logging_writes_to_log_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("logs/gaggle.log");
+        std::env::set_var("GAGGLE_LOG_FILE", &log_path);
+        std::env::set_var("GAGGLE_LOG_MAX_SIZE_MB", "1");
+        std::env::set_var("GAGGLE_LOG_LEVEL", "INFO");
+
+        assert_eq!(gaggle_reinit_logging(), 0);
+        tracing::info!("sized log file marker");
+        assert_eq!(gaggle_flush_logs(), 0);
+        let content = fs::read_to_string(&log_path).unwrap();
+        assert!(content.contains("sized log file marker"), "{}", content);
+
+        // Without a size limit, the file rotates daily and gets a date suffix
+        std::env::remove_var("GAGGLE_LOG_MAX_SIZE_MB");
+        assert_eq!(gaggle_reinit_logging(), 0);
+        tracing::info!("daily log file marker");
+        assert_eq!(gaggle_flush_logs(), 0);
+        let daily: Vec<_> = fs::read_dir(log_path.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p != &log_path)
+            .collect();
+        assert_eq!(daily.len(), 1);
+        assert!(fs::read_to_string(&daily[0])
+            .unwrap()
+            .contains("daily log file marker"));
+
+        // A log file that cannot be created is reported
+        let not_a_dir = temp_dir.path().join("file");
+        fs::write(&not_a_dir, b"x").unwrap();
+        std::env::set_var("GAGGLE_LOG_FILE", not_a_dir.join("gaggle.log"));
+        assert_eq!(gaggle_reinit_logging(), -1);
+
+        std::env::remove_var("GAGGLE_LOG_FILE");
+        std::env::remove_var("GAGGLE_LOG_LEVEL");
+        assert_eq!(gaggle_reinit_logging(), 0);
+        assert_eq!(gaggle_flush_logs(), 0);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_set_log_level_logs_requests() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("requests.log");
+        std::env::set_var("GAGGLE_LOG_FILE", &log_path);
+        std::env::set_var("GAGGLE_LOG_MAX_SIZE_MB", "1");
+        std::env::set_var("GAGGLE_LOG_LEVEL", "WARN");
+        assert_eq!(gaggle_reinit_logging(), 0);
+
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        std::env::set_var("KAGGLE_USERNAME", "user");
+        std::env::set_var("KAGGLE_KEY", "key");
+        let _meta = server
+            .mock("GET", "/datasets/view/owner/logged-meta")
+            .with_status(200)
+            .with_body("{\"ref\":\"owner/logged-meta\"}")
+            .create();
+
+        let level = CString::new("debug").unwrap();
+        let invalid = CString::new("gaggle=loud").unwrap();
+        unsafe {
+            assert_eq!(gaggle_set_log_level(invalid.as_ptr()), -1);
+            assert!(!error::gaggle_last_error().is_null());
+            assert_eq!(gaggle_set_log_level(std::ptr::null()), -1);
+            assert_eq!(gaggle_set_log_level(level.as_ptr()), 0);
+        }
+        crate::kaggle::metadata::get_dataset_metadata("owner/logged-meta").unwrap();
+        assert_eq!(gaggle_flush_logs(), 0);
+
+        let content = fs::read_to_string(&log_path).unwrap();
+        let request = content
+            .lines()
+            .find(|l| l.contains("HTTP request"))
+            .unwrap_or_else(|| panic!("no request logged: {}", content));
+        assert!(request.contains("get_dataset_metadata"), "{}", request);
+        assert!(request.contains("owner/logged-meta"), "{}", request);
+        assert!(request.contains("status=200"), "{}", request);
+        assert!(request.contains("duration_ms="), "{}", request);
+        assert!(content.contains("operation finished"), "{}", content);
+
+        for var in [
+            "GAGGLE_LOG_FILE",
+            "GAGGLE_LOG_MAX_SIZE_MB",
+            "GAGGLE_LOG_LEVEL",
+            "GAGGLE_API_BASE",
+            "KAGGLE_USERNAME",
+            "KAGGLE_KEY",
+        ] {
+            std::env::remove_var(var);
+        }
+        assert_eq!(gaggle_reinit_logging(), 0);
+        assert_eq!(gaggle_flush_logs(), 0);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_verify_dataset_integrity() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dir = temp_dir.path().join("datasets/owner/verified");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("data.csv"), "a\n1\n").unwrap();
+        fs::write(dir.join(".downloaded"), "").unwrap();
+
+        let path = CString::new("owner/verified").unwrap();
+        unsafe {
+            let ptr = gaggle_verify_dataset_integrity(path.as_ptr());
+            assert!(!ptr.is_null());
+            let report: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            gaggle_free(ptr);
+            assert_eq!(report["valid"], true);
+            assert_eq!(report["files"], 1);
+
+            let missing = CString::new("owner/absent").unwrap();
+            assert!(gaggle_verify_dataset_integrity(missing.as_ptr()).is_null());
+            assert!(gaggle_verify_dataset_integrity(std::ptr::null()).is_null());
+        }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_dataset_readme_replaces_nul() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        let dir = temp_dir.path().join("datasets/owner/nul");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("README.md"), "a\0b").unwrap();
+
+        let path = CString::new("owner/nul").unwrap();
+        unsafe {
+            let ptr = gaggle_dataset_readme(path.as_ptr());
+            assert!(!ptr.is_null());
+            assert_eq!(CStr::from_ptr(ptr).to_str().unwrap(), "a\u{FFFD}b");
+            gaggle_free(ptr);
+            assert!(gaggle_dataset_readme(std::ptr::null()).is_null());
+        }
+
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_get_dataset_license_offline() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        let dir = temp_dir.path().join("datasets/owner/licensed");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".license.json"), r#"{"licenseName":"MIT"}"#).unwrap();
+
+        let path = CString::new("owner/licensed").unwrap();
+        let missing = CString::new("owner/unlicensed").unwrap();
+        unsafe {
+            let ptr = gaggle_get_dataset_license(path.as_ptr());
+            assert!(!ptr.is_null());
+            let json: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            assert_eq!(json["licenseName"], "MIT");
+            gaggle_free(ptr);
+            assert!(gaggle_get_dataset_license(missing.as_ptr()).is_null());
+            assert!(gaggle_get_dataset_license(std::ptr::null()).is_null());
+        }
+
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_plan_download_offline() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+
+        let path = CString::new("owner/planned").unwrap();
+        unsafe {
+            let ptr = gaggle_plan_download(path.as_ptr());
+            assert!(!ptr.is_null());
+            let plan: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            assert_eq!(plan["offline"], true);
+            assert_eq!(plan["would_download"], true);
+            assert!(plan["latest_version"].is_null());
+            gaggle_free(ptr);
+            assert!(gaggle_plan_download(std::ptr::null()).is_null());
         }
+
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
-}
 
-/// Helper function to get JSON type as string
-fn get_json_type(value: &serde_json::Value) -> &'static str {
-    match value {
-        serde_json::Value::Null => "null",
-        serde_json::Value::Bool(_) => "boolean",
-        serde_json::Value::Number(_) => "number",
-        serde_json::Value::String(_) => "string",
-        serde_json::Value::Array(_) => "array",
-        serde_json::Value::Object(_) => "object",
+    #[test]
+    fn test_gaggle_detect_file_type() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("data.bin");
+        fs::write(&path, b"PAR1\x00\x00PAR1").unwrap();
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+        unsafe {
+            let ptr = gaggle_detect_file_type(path.as_ptr());
+            assert_eq!(CStr::from_ptr(ptr).to_str().unwrap(), "read_parquet");
+            gaggle_free(ptr);
+
+            let missing = CString::new("/nonexistent/data.bin").unwrap();
+            assert!(gaggle_detect_file_type(missing.as_ptr()).is_null());
+            assert!(gaggle_detect_file_type(std::ptr::null()).is_null());
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_sniff_csv() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path().join("cache"));
+        std::env::set_var("KAGGLE_USERNAME", "user");
+        std::env::set_var("KAGGLE_KEY", "key");
+        let source = temp_dir.path().join("local");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("data.csv"), b"x\ty\n1\ta\n2\tb\n").unwrap();
+        crate::kaggle::import_dataset("owner/ffi-sniff", &source, None).unwrap();
+
+        let ds = CString::new("owner/ffi-sniff").unwrap();
+        let file = CString::new("data.csv").unwrap();
+        unsafe {
+            let ptr = gaggle_sniff_csv(ds.as_ptr(), file.as_ptr(), 0);
+            assert!(!ptr.is_null());
+            let schema: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            gaggle_free(ptr);
+            assert_eq!(schema["delimiter"], "\t");
+            assert_eq!(schema["has_header"], true);
+            assert_eq!(schema["columns"][0]["name"], "x");
+            assert_eq!(schema["columns"][0]["type"], "int");
+            assert_eq!(schema["estimated_rows"], 2);
+
+            assert!(gaggle_sniff_csv(ds.as_ptr(), file.as_ptr(), -1).is_null());
+            assert!(gaggle_sniff_csv(ds.as_ptr(), std::ptr::null(), 0).is_null());
+        }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+    }
 
     #[test]
-    fn test_gaggle_get_version_not_null() {
-        let version_ptr = gaggle_get_version();
-        assert!(!version_ptr.is_null());
+    #[serial_test::serial]
+    fn test_gaggle_materialize_and_auto_decompress() {
+        use std::io::Write;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path().join("cache"));
+        std::env::set_var("KAGGLE_USERNAME", "user");
+        std::env::set_var("KAGGLE_KEY", "key");
+        let source = temp_dir.path().join("local");
+        std::fs::create_dir_all(&source).unwrap();
+        for name in ["a.csv.gz", "b.csv.gz"] {
+            let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            enc.write_all(b"x\n1\n").unwrap();
+            std::fs::write(source.join(name), enc.finish().unwrap()).unwrap();
+        }
+        let dir = crate::kaggle::import_dataset("owner/ffi-materialize", &source, None).unwrap();
 
+        let ds = CString::new("owner/ffi-materialize").unwrap();
+        let a = CString::new("a.csv.gz").unwrap();
+        let b = CString::new("b.csv.gz").unwrap();
         unsafe {
-            let version_cstr = CStr::from_ptr(version_ptr);
-            let version_str = version_cstr.to_str().unwrap();
-            assert!(!version_str.is_empty());
-            // Should be a plain version string like "0.1.0"
-            assert!(!version_str.contains("{"));
-            assert!(!version_str.contains("}"));
+            let ptr = gaggle_materialize(ds.as_ptr(), a.as_ptr());
+            assert!(!ptr.is_null());
+            assert_eq!(
+                CStr::from_ptr(ptr).to_str().unwrap(),
+                dir.join("a.csv").to_str().unwrap()
+            );
+            gaggle_free(ptr);
+            assert!(gaggle_materialize(ds.as_ptr(), std::ptr::null()).is_null());
 
-            gaggle_free(version_ptr);
+            // Without GAGGLE_AUTO_DECOMPRESS the compressed file is returned
+            let ptr = gaggle_get_file_path(ds.as_ptr(), b.as_ptr());
+            assert!(CStr::from_ptr(ptr).to_str().unwrap().ends_with("b.csv.gz"));
+            gaggle_free(ptr);
+
+            std::env::set_var("GAGGLE_AUTO_DECOMPRESS", "1");
+            let ptr = gaggle_get_file_path(ds.as_ptr(), b.as_ptr());
+            assert_eq!(
+                CStr::from_ptr(ptr).to_str().unwrap(),
+                dir.join("b.csv").to_str().unwrap()
+            );
+            gaggle_free(ptr);
+            std::env::remove_var("GAGGLE_AUTO_DECOMPRESS");
         }
+        assert_eq!(std::fs::read(dir.join("b.csv")).unwrap(), b"x\n1\n");
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
     }
 
     #[test]
-    fn test_gaggle_get_version_format() {
-        let version_ptr = gaggle_get_version();
+    #[serial_test::serial]
+    fn test_gaggle_list_files_by_format() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dir = temp_dir.path().join("datasets/owner/ffi-formats");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.csv"), "x").unwrap();
+        std::fs::write(dir.join("b.json"), "{}").unwrap();
+        std::fs::write(dir.join(".downloaded"), "{}").unwrap();
+
+        let ds = CString::new("owner/ffi-formats").unwrap();
+        let csv = CString::new("csv").unwrap();
         unsafe {
-            let version_cstr = CStr::from_ptr(version_ptr);
-            let version_str = version_cstr.to_str().unwrap();
-            // Should not be JSON; just a semantic version-like string
-            assert!(!version_str.starts_with('{'));
-            assert!(!version_str.ends_with('}'));
+            let ptr = gaggle_list_files_by_format(ds.as_ptr(), csv.as_ptr());
+            let files: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            gaggle_free(ptr);
+            assert_eq!(files.as_array().unwrap().len(), 1);
+            assert_eq!(files[0]["name"], "a.csv");
 
-            gaggle_free(version_ptr);
+            // A null format lists every file
+            let ptr = gaggle_list_files_by_format(ds.as_ptr(), std::ptr::null());
+            let files: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            gaggle_free(ptr);
+            assert_eq!(files.as_array().unwrap().len(), 2);
+
+            assert!(gaggle_list_files_by_format(std::ptr::null(), csv.as_ptr()).is_null());
         }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_gaggle_free_null_pointer() {
-        // Should not panic when freeing null pointer
+    fn test_gaggle_is_cache_state_file() {
         unsafe {
-            gaggle_free(std::ptr::null_mut());
+            for name in [
+                ".downloaded",
+                ".schema.json",
+                ".pinned",
+                "data.csv.decompressed",
+            ] {
+                let c = CString::new(name).unwrap();
+                assert_eq!(gaggle_is_cache_state_file(c.as_ptr()), 1, "{}", name);
+            }
+            let c = CString::new("data.csv").unwrap();
+            assert_eq!(gaggle_is_cache_state_file(c.as_ptr()), 0);
+            assert_eq!(gaggle_is_cache_state_file(std::ptr::null()), -1);
         }
     }
 
     #[test]
-    fn test_gaggle_free_valid_pointer() {
-        let ptr = gaggle_get_version();
+    fn test_gaggle_dataset_ref_and_subpath() {
+        let path = CString::new("owner/dataset@v2/sub").unwrap();
+        let traversal = CString::new("owner/dataset/../../x").unwrap();
         unsafe {
+            let ptr = gaggle_dataset_ref(path.as_ptr());
+            assert_eq!(CStr::from_ptr(ptr).to_str().unwrap(), "owner/dataset@2");
+            gaggle_free(ptr);
+            let ptr = gaggle_dataset_subpath(path.as_ptr());
+            assert_eq!(CStr::from_ptr(ptr).to_str().unwrap(), "sub");
             gaggle_free(ptr);
+
+            assert!(gaggle_dataset_ref(traversal.as_ptr()).is_null());
+            assert!(gaggle_dataset_subpath(traversal.as_ptr()).is_null());
+            assert!(gaggle_dataset_subpath(std::ptr::null()).is_null());
         }
-        // If we got here without crashing, the test passes
     }
 
     #[test]
-    fn test_gaggle_clear_cache_success() {
-        let result = gaggle_clear_cache();
-        // Should return 0 (success) or -1 (error), but most likely 0
-        assert!(result == 0 || result == -1);
+    #[serial_test::serial]
+    fn test_gaggle_functions_accept_kaggle_urls() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dir = temp_dir.path().join("datasets/owner/ffi-url");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.csv"), "x").unwrap();
+        std::fs::write(dir.join(".downloaded"), "{}").unwrap();
+
+        let url =
+            CString::new("https://www.kaggle.com/datasets/owner/ffi-url?select=a.csv").unwrap();
+        let file = CString::new("a.csv").unwrap();
+        unsafe {
+            let ptr = gaggle_get_file_path(url.as_ptr(), file.as_ptr());
+            assert!(!ptr.is_null());
+            let path = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+            gaggle_free(ptr);
+            assert_eq!(std::path::PathBuf::from(path), dir.join("a.csv"));
+
+            let ptr = gaggle_list_files(url.as_ptr());
+            let files: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            gaggle_free(ptr);
+            assert_eq!(files[0]["name"], "a.csv");
+
+            let other = CString::new("https://www.kaggle.com/code/owner/notebook").unwrap();
+            assert!(gaggle_list_files(other.as_ptr()).is_null());
+            let err = CStr::from_ptr(crate::error::gaggle_last_error())
+                .to_str()
+                .unwrap();
+            assert!(err.contains("Not a Kaggle dataset URL"), "{}", err);
+        }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_gaggle_get_cache_info_not_null() {
-        let info_ptr = gaggle_get_cache_info();
-        assert!(!info_ptr.is_null());
-
+    #[serial_test::serial]
+    fn test_gaggle_file_path_and_list_files_with_subpath() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dir = temp_dir.path().join("datasets/owner/ffi-subpath");
+        std::fs::create_dir_all(dir.join("Twitter")).unwrap();
+        std::fs::write(dir.join("top.csv"), "x").unwrap();
+        std::fs::write(dir.join("Twitter/tweets.csv"), "y").unwrap();
+        std::fs::write(dir.join(".downloaded"), "{}").unwrap();
+
+        let ds = CString::new("owner/ffi-subpath/Twitter").unwrap();
+        let file = CString::new("tweets.csv").unwrap();
+        let empty = CString::new("").unwrap();
         unsafe {
-            let info_cstr = CStr::from_ptr(info_ptr);
-            let info_str = info_cstr.to_str().unwrap();
-            assert!(!info_str.is_empty());
-            assert!(info_str.contains("\"path\""));
+            let ptr = gaggle_get_file_path(ds.as_ptr(), file.as_ptr());
+            assert!(!ptr.is_null());
+            let path = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+            gaggle_free(ptr);
+            assert_eq!(
+                std::path::PathBuf::from(path),
+                dir.join("Twitter/tweets.csv")
+            );
+
+            // The subpath can name the file itself
+            let full = CString::new("owner/ffi-subpath/Twitter/tweets.csv").unwrap();
+            let ptr = gaggle_get_file_path(full.as_ptr(), empty.as_ptr());
+            assert!(!ptr.is_null());
+            gaggle_free(ptr);
 
-            gaggle_free(info_ptr);
+            let ptr = gaggle_list_files(ds.as_ptr());
+            let files: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            gaggle_free(ptr);
+            assert_eq!(files.as_array().unwrap().len(), 1);
+            assert_eq!(files[0]["name"], "Twitter/tweets.csv");
+
+            let escape = CString::new("owner/ffi-subpath/../other").unwrap();
+            assert!(gaggle_get_file_path(escape.as_ptr(), file.as_ptr()).is_null());
+            assert!(gaggle_list_files(escape.as_ptr()).is_null());
         }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_gaggle_get_cache_info_format() {
-        let info_ptr = gaggle_get_cache_info();
+    fn test_gaggle_guess_reader() {
+        let name = CString::new("train.TSV.gz").unwrap();
         unsafe {
-            let info_cstr = CStr::from_ptr(info_ptr);
-            let info_str = info_cstr.to_str().unwrap();
-            // Should be valid JSON
-            assert!(info_str.starts_with('{'));
-            assert!(info_str.ends_with('}'));
-            // Should contain the documented keys
-            assert!(info_str.contains("\"path\""));
-            assert!(info_str.contains("\"size_mb\""));
-            assert!(info_str.contains("\"limit_mb\""));
-            assert!(info_str.contains("\"usage_percent\""));
-            assert!(info_str.contains("\"is_soft_limit\""));
-            assert!(info_str.contains("\"type\""));
+            let ptr = gaggle_guess_reader(name.as_ptr());
+            let guess: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap();
+            gaggle_free(ptr);
+            assert_eq!(guess["reader"], "read_csv_auto");
+            assert_eq!(guess["options"]["delim"], "\t");
 
-            gaggle_free(info_ptr);
+            assert!(gaggle_guess_reader(std::ptr::null()).is_null());
         }
     }
 
     #[test]
-    fn test_gaggle_set_credentials_valid() {
+    #[serial_test::serial]
+    fn test_gaggle_set_credentials_strict() {
         let username = CString::new("testuser").unwrap();
-        let key = CString::new("testkey").unwrap();
+        let bad_key = CString::new("testkey").unwrap();
+        let good_key = CString::new("0123456789abcdef0123456789abcdef").unwrap();
 
         unsafe {
-            let result = gaggle_set_credentials(username.as_ptr(), key.as_ptr());
-            assert_eq!(result, 0); // Should succeed
+            assert_eq!(
+                gaggle_set_credentials_strict(username.as_ptr(), bad_key.as_ptr()),
+                -1
+            );
+            assert_eq!(
+                gaggle_set_credentials_strict(std::ptr::null(), good_key.as_ptr()),
+                -1
+            );
+            assert_eq!(
+                gaggle_set_credentials_strict(username.as_ptr(), good_key.as_ptr()),
+                0
+            );
         }
     }
 
     #[test]
-    fn test_gaggle_set_credentials_null_username() {
-        let key = CString::new("testkey").unwrap();
+    #[serial_test::serial]
+    fn test_gaggle_copy_dataset_to() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+        std::env::set_var("KAGGLE_USERNAME", "user");
+        std::env::set_var("KAGGLE_KEY", "key");
+        let dir = cache.join("datasets/owner/ffi-copy");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/data.csv"), b"a\n1\n").unwrap();
+        std::fs::write(dir.join(".downloaded"), b"{}").unwrap();
+
+        let ds = CString::new("owner/ffi-copy").unwrap();
+        let dest = temp_dir.path().join("out");
+        let dest_c = CString::new(dest.to_str().unwrap()).unwrap();
+        let file = CString::new("sub/data.csv").unwrap();
+        let dest_file = temp_dir.path().join("single.csv");
+        let dest_file_c = CString::new(dest_file.to_str().unwrap()).unwrap();
+        let inside = CString::new(cache.join("x").to_str().unwrap()).unwrap();
 
         unsafe {
-            let result = gaggle_set_credentials(std::ptr::null(), key.as_ptr());
-            assert_eq!(result, -1); // Should fail
+            assert_eq!(gaggle_copy_dataset_to(ds.as_ptr(), dest_c.as_ptr()), 4);
+            assert!(dest.join("sub/data.csv").exists());
+            assert!(!dest.join(".downloaded").exists());
+            assert_eq!(
+                gaggle_copy_file_to(ds.as_ptr(), file.as_ptr(), dest_file_c.as_ptr()),
+                4
+            );
+            assert_eq!(std::fs::read(&dest_file).unwrap(), b"a\n1\n");
+
+            assert_eq!(gaggle_copy_dataset_to(ds.as_ptr(), inside.as_ptr()), -1);
+            assert!(!error::gaggle_last_error().is_null());
+            assert_eq!(
+                gaggle_copy_dataset_to(std::ptr::null(), dest_c.as_ptr()),
+                -1
+            );
+            assert_eq!(
+                gaggle_copy_file_to(ds.as_ptr(), std::ptr::null(), dest_file_c.as_ptr()),
+                -1
+            );
         }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
     }
 
     #[test]
-    fn test_gaggle_set_credentials_null_key() {
-        let username = CString::new("testuser").unwrap();
+    #[serial_test::serial]
+    fn test_gaggle_export_dataset() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+        std::env::set_var("KAGGLE_USERNAME", "user");
+        std::env::set_var("KAGGLE_KEY", "key");
+        let dir = cache.join("datasets/owner/ffi-export");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/data.csv"), b"a\n1\n").unwrap();
+        std::fs::write(dir.join(".downloaded"), b"{}").unwrap();
+
+        let ds = CString::new("owner/ffi-export").unwrap();
+        let dest = temp_dir.path().join("out");
+        let dest_c = CString::new(dest.to_str().unwrap()).unwrap();
+        let summary = unsafe { gaggle_export_dataset(ds.as_ptr(), dest_c.as_ptr(), 0) };
+        assert!(!summary.is_null());
+        let json: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(summary) }.to_str().unwrap()).unwrap();
+        unsafe { gaggle_free(summary) };
+        assert_eq!(json["files_copied"], 1);
+        assert_eq!(json["bytes"], 4);
+        assert_eq!(json["errors"], serde_json::json!([]));
+        assert!(dest.join("sub/data.csv").exists());
 
         unsafe {
-            let result = gaggle_set_credentials(username.as_ptr(), std::ptr::null());
-            assert_eq!(result, -1); // Should fail
+            assert!(gaggle_export_dataset(std::ptr::null(), dest_c.as_ptr(), 0).is_null());
         }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
     }
 
     #[test]
-    fn test_gaggle_set_credentials_both_null() {
+    #[serial_test::serial]
+    fn test_gaggle_reset_http_client() {
+        std::env::remove_var("GAGGLE_HTTP_PROXY");
+        assert_eq!(gaggle_reset_http_client(), 0);
+
+        std::env::set_var("GAGGLE_HTTP_PROXY", "not a proxy url");
+        assert_eq!(gaggle_reset_http_client(), -1);
+        assert!(!error::gaggle_last_error().is_null());
+        std::env::remove_var("GAGGLE_HTTP_PROXY");
+        assert_eq!(gaggle_reset_http_client(), 0);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_clear_dataset_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dir = temp_dir.path().join("datasets/owner/ffi-clear");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".downloaded"), b"{}").unwrap();
+        std::fs::write(dir.join(".pinned"), b"").unwrap();
+
+        let ds = CString::new("owner/ffi-clear").unwrap();
         unsafe {
-            let result = gaggle_set_credentials(std::ptr::null(), std::ptr::null());
-            assert_eq!(result, -1); // Should fail
+            assert_eq!(gaggle_clear_dataset_cache(ds.as_ptr()), -1);
+            assert!(!error::gaggle_last_error().is_null());
+            assert!(dir.exists());
+            assert_eq!(gaggle_clear_dataset_cache_force(ds.as_ptr()), 0);
+            assert!(!dir.exists());
+            assert_eq!(gaggle_clear_dataset_cache(std::ptr::null()), -1);
         }
+        assert_eq!(gaggle_evict_datasets_older_than(0), 0);
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_gaggle_set_credentials_empty_strings() {
-        let username = CString::new("").unwrap();
-        let key = CString::new("").unwrap();
+    #[serial_test::serial]
+    fn test_gaggle_import_dataset() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path().join("cache"));
+        let source = temp_dir.path().join("local");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("data.csv"), b"a\n1\n").unwrap();
+
+        let ds = CString::new("owner/ffi-import").unwrap();
+        let source_c = CString::new(source.to_str().unwrap()).unwrap();
+        let version = CString::new("2").unwrap();
+        let dir =
+            unsafe { gaggle_import_dataset(ds.as_ptr(), source_c.as_ptr(), version.as_ptr()) };
+        assert!(!dir.is_null());
+        let dir_str = unsafe { CStr::from_ptr(dir) }.to_str().unwrap().to_string();
+        unsafe { gaggle_free(dir) };
+        assert!(std::path::Path::new(&dir_str).join("data.csv").exists());
 
         unsafe {
-            let result = gaggle_set_credentials(username.as_ptr(), key.as_ptr());
-            assert_eq!(result, 0); // Should succeed even with empty strings
+            assert!(
+                gaggle_import_dataset(ds.as_ptr(), std::ptr::null(), std::ptr::null()).is_null()
+            );
+            let bad = CString::new("not-a-path").unwrap();
+            assert!(
+                gaggle_import_dataset(bad.as_ptr(), source_c.as_ptr(), std::ptr::null()).is_null()
+            );
         }
+        assert!(!error::gaggle_last_error().is_null());
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_gaggle_set_credentials_long_strings() {
-        let long_username = "user".repeat(100);
-        let long_key = "key".repeat(100);
-        let username = CString::new(long_username).unwrap();
-        let key = CString::new(long_key).unwrap();
+    fn test_gaggle_search_cursor_rejects_invalid_cursor() {
+        let query = CString::new("titanic").unwrap();
+        let cursor = CString::new("not json").unwrap();
+        unsafe {
+            assert!(gaggle_search_cursor(std::ptr::null(), std::ptr::null(), 10).is_null());
+            assert!(gaggle_search_cursor(query.as_ptr(), cursor.as_ptr(), 10).is_null());
+        }
+        let err = unsafe { CStr::from_ptr(error::gaggle_last_error()) };
+        assert!(err
+            .to_str()
+            .unwrap()
+            .contains("cursor must be a JSON string"));
+    }
 
+    #[test]
+    fn test_gaggle_glob_null_pointers() {
+        let pattern = CString::new("*.csv").unwrap();
         unsafe {
-            let result = gaggle_set_credentials(username.as_ptr(), key.as_ptr());
-            assert_eq!(result, 0); // Should succeed
+            assert!(gaggle_glob(std::ptr::null(), pattern.as_ptr()).is_null());
+            assert!(gaggle_glob(pattern.as_ptr(), std::ptr::null()).is_null());
         }
     }
 
     #[test]
-    fn test_gaggle_set_credentials_special_chars() {
-        let username = CString::new("user@example.com").unwrap();
-        let key = CString::new("key!@#$%^&*()").unwrap();
+    #[serial_test::serial]
+    fn test_gaggle_set_bandwidth_limit() {
+        assert_eq!(gaggle_set_bandwidth_limit(4.0), 0);
+        assert_eq!(kaggle::throttle::limit_bytes_per_sec(), Some(500_000.0));
+        assert_eq!(gaggle_set_bandwidth_limit(0.0), 0);
+        assert_eq!(kaggle::throttle::limit_bytes_per_sec(), None);
+        assert_eq!(gaggle_set_bandwidth_limit(-1.0), 0);
+        assert_eq!(
+            kaggle::throttle::limit_bytes_per_sec(),
+            crate::config::max_bandwidth_mbps().map(|v| v * 125_000.0)
+        );
+    }
 
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_move_cache_dir_rejects_invalid_input() {
+        let empty = CString::new(" ").unwrap();
         unsafe {
-            let result = gaggle_set_credentials(username.as_ptr(), key.as_ptr());
-            assert_eq!(result, 0); // Should succeed
+            assert_eq!(gaggle_move_cache_dir(std::ptr::null()), -1);
+            assert_eq!(gaggle_move_cache_dir(empty.as_ptr()), -1);
+        }
+        assert!(!crate::error::gaggle_last_error().is_null());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_set_api_base() {
+        let base = CString::new("http://localhost:9999/api/v1").unwrap();
+        let invalid = CString::new("localhost:9999").unwrap();
+        let empty = CString::new("").unwrap();
+
+        unsafe {
+            assert_eq!(gaggle_set_api_base(std::ptr::null()), -1);
+            assert_eq!(gaggle_set_api_base(invalid.as_ptr()), -1);
+            assert_eq!(gaggle_set_api_base(base.as_ptr()), 0);
+            assert_eq!(
+                crate::kaggle::api::get_api_base(),
+                "http://localhost:9999/api/v1"
+            );
+            assert_eq!(gaggle_set_api_base(empty.as_ptr()), 0);
         }
+        assert_ne!(
+            crate::kaggle::api::get_api_base(),
+            "http://localhost:9999/api/v1"
+        );
     }
 
     #[test]
@@ -886,6 +4632,42 @@ mod tests {
         }
     }
 
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_refresh_cache_info_lists_datasets() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dataset_dir = temp_dir.path().join("datasets/owner/ds");
+        fs::create_dir_all(&dataset_dir).unwrap();
+        fs::write(dataset_dir.join("a.bin"), vec![0u8; 3 * 1024 * 1024]).unwrap();
+        fs::write(
+            dataset_dir.join(".downloaded"),
+            r#"{"downloaded_at_secs":1,"dataset_path":"owner/ds","size_mb":0,"version":"2"}"#,
+        )
+        .unwrap();
+
+        unsafe {
+            let info_ptr = gaggle_get_cache_info();
+            let info: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(info_ptr).to_str().unwrap()).unwrap();
+            gaggle_free(info_ptr);
+            assert_eq!(info["size_mb"], 0);
+            assert_eq!(info["datasets"][0]["path"], "owner/ds");
+            assert_eq!(info["datasets"][0]["version"], "2");
+            assert_eq!(info["datasets"][0]["partial"], false);
+
+            let info_ptr = gaggle_refresh_cache_info();
+            assert!(!info_ptr.is_null());
+            let info: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(info_ptr).to_str().unwrap()).unwrap();
+            gaggle_free(info_ptr);
+            assert_eq!(info["size_mb"], 3);
+            assert_eq!(info["datasets"][0]["size_mb"], 3);
+        }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
     #[test]
     fn test_gaggle_version_contains_package_version() {
         let version_ptr = gaggle_get_version();
@@ -978,6 +4760,107 @@ mod tests {
         std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
+    #[test]
+    fn test_gaggle_json_each_recursive_depths() {
+        let input = CString::new(r#"{"a":{"b":[1,{"c":2}]},"d":3}"#).unwrap();
+        let rows = |max_depth: i32| -> Vec<serde_json::Value> {
+            let out_ptr = unsafe { gaggle_json_each_recursive(input.as_ptr(), max_depth) };
+            assert!(!out_ptr.is_null());
+            let out = unsafe { CStr::from_ptr(out_ptr) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            unsafe { gaggle_free(out_ptr) };
+            out.lines()
+                .map(|l| serde_json::from_str(l).unwrap())
+                .collect()
+        };
+
+        let all = rows(0);
+        let paths: Vec<(&str, i64)> = all
+            .iter()
+            .map(|r| (r["path"].as_str().unwrap(), r["depth"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("$.a", 1),
+                ("$.a.b", 2),
+                ("$.a.b[0]", 3),
+                ("$.a.b[1]", 3),
+                ("$.a.b[1].c", 4),
+                ("$.d", 1),
+            ]
+        );
+        assert_eq!(all[4]["key"], "c");
+        assert_eq!(all[4]["value"], 2);
+
+        assert_eq!(rows(2).len(), 3);
+        assert_eq!(rows(1).len(), 2);
+    }
+
+    #[test]
+    fn test_gaggle_json_each_recursive_caps_depth() {
+        let mut input = "1".to_string();
+        for _ in 0..40 {
+            input = format!("[{}]", input);
+        }
+        let c = CString::new(input).unwrap();
+        let out_ptr = unsafe { gaggle_json_each_recursive(c.as_ptr(), 0) };
+        assert!(!out_ptr.is_null());
+        let out = unsafe { CStr::from_ptr(out_ptr) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { gaggle_free(out_ptr) };
+        assert_eq!(out.lines().count(), JSON_EACH_MAX_DEPTH as usize);
+    }
+
+    #[test]
+    fn test_gaggle_json_flatten() {
+        let input = CString::new(r#"{"a":{"b":[1,{"c":2}],"e":{}},"d":null}"#).unwrap();
+        let flatten = |sep: Option<&str>| -> serde_json::Value {
+            let sep = sep.map(|s| CString::new(s).unwrap());
+            let sep_ptr = sep.as_ref().map_or(std::ptr::null(), |s| s.as_ptr());
+            let out_ptr = unsafe { gaggle_json_flatten(input.as_ptr(), sep_ptr) };
+            assert!(!out_ptr.is_null());
+            let out = unsafe { CStr::from_ptr(out_ptr) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            unsafe { gaggle_free(out_ptr) };
+            serde_json::from_str(&out).unwrap()
+        };
+
+        assert_eq!(
+            flatten(None),
+            json!({"a.b.0": 1, "a.b.1.c": 2, "a.e": {}, "d": null})
+        );
+        assert_eq!(flatten(Some("")), flatten(None));
+        let empty = CString::new("[]").unwrap();
+        let out_ptr = unsafe { gaggle_json_flatten(empty.as_ptr(), std::ptr::null()) };
+        assert_eq!(unsafe { CStr::from_ptr(out_ptr) }.to_str().unwrap(), "{}");
+        unsafe { gaggle_free(out_ptr) };
+        assert_eq!(
+            flatten(Some("__")),
+            json!({"a__b__0": 1, "a__b__1__c": 2, "a__e": {}, "d": null})
+        );
+    }
+
+    #[test]
+    fn test_gaggle_json_recursive_and_flatten_errors() {
+        unsafe {
+            assert!(gaggle_json_each_recursive(std::ptr::null(), 0).is_null());
+            assert_eq!(error::gaggle_last_error_code(), 10);
+            assert!(gaggle_json_flatten(std::ptr::null(), std::ptr::null()).is_null());
+            assert_eq!(error::gaggle_last_error_code(), 10);
+
+            let scalar = CString::new("42").unwrap();
+            assert!(gaggle_json_flatten(scalar.as_ptr(), std::ptr::null()).is_null());
+            assert_eq!(error::gaggle_last_error_code(), 6);
+        }
+    }
+
     #[test]
     fn test_gaggle_json_each_object_and_array() {
         let input = json!({
@@ -999,6 +4882,106 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_json_path() {
+        use JsonPathSegment::{Index, Key};
+        assert_eq!(parse_json_path("$").unwrap(), vec![]);
+        assert_eq!(
+            parse_json_path("$.files[2].columns").unwrap(),
+            vec![Key("files".into()), Index(2), Key("columns".into())]
+        );
+        assert_eq!(
+            parse_json_path(r#"$["a.b"][0]"#).unwrap(),
+            vec![Key("a.b".into()), Index(0)]
+        );
+
+        for (path, offset) in [
+            ("files", 0),
+            ("$.", 2),
+            ("$.a..b", 4),
+            ("$[x]", 2),
+            ("$[-1]", 2),
+            ("$[1", 1),
+            (r#"$["a"#, 1),
+            ("$a", 1),
+        ] {
+            match parse_json_path(path) {
+                Err(error::GaggleError::JsonError(msg)) => {
+                    assert!(msg.contains(&format!("at offset {}", offset)), "{}", msg)
+                }
+                other => panic!("{} parsed as {:?}", path, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_gaggle_json_each_path() {
+        let input = CString::new(
+            json!({"files": [{"name": "a.csv"}, {"name": "b.csv", "columns": ["x", "y"]}]})
+                .to_string(),
+        )
+        .unwrap();
+        let rows = |path: &str| -> Vec<serde_json::Value> {
+            let path = CString::new(path).unwrap();
+            let ptr = unsafe { gaggle_json_each_path(input.as_ptr(), path.as_ptr()) };
+            assert!(!ptr.is_null());
+            let out = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { gaggle_free(ptr) };
+            out.lines()
+                .map(|l| serde_json::from_str(l).unwrap())
+                .collect()
+        };
+
+        let columns = rows("$.files[1].columns");
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[1]["key"], 1);
+        assert_eq!(columns[1]["value"], "y");
+        assert_eq!(columns[1]["path"], "$.files[1].columns[1]");
+        assert_eq!(rows("$").len(), 1);
+        assert!(rows("$.files[5]").is_empty());
+        assert!(rows("$.files.name").is_empty());
+
+        let bad = CString::new("$.files[").unwrap();
+        assert!(unsafe { gaggle_json_each_path(input.as_ptr(), bad.as_ptr()) }.is_null());
+        assert_eq!(error::gaggle_last_error_code(), 6);
+    }
+
+    #[test]
+    fn test_gaggle_json_tree() {
+        let input = CString::new(json!({"a": {"b": [1, 2]}, "c": null}).to_string()).unwrap();
+        let ptr = unsafe { gaggle_json_tree(input.as_ptr(), std::ptr::null()) };
+        assert!(!ptr.is_null());
+        let out = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { gaggle_free(ptr) };
+        let rows: Vec<serde_json::Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let paths: Vec<&str> = rows.iter().map(|r| r["path"].as_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            vec!["$", "$.a", "$.a.b", "$.a.b[0]", "$.a.b[1]", "$.c"]
+        );
+        assert_eq!(rows[0]["parent"], serde_json::Value::Null);
+        assert_eq!(rows[3]["parent"], "$.a.b");
+        assert_eq!(rows[3]["depth"], 3);
+        assert_eq!(rows[5]["type"], "null");
+
+        let path = CString::new("$.a.b").unwrap();
+        let ptr = unsafe { gaggle_json_tree(input.as_ptr(), path.as_ptr()) };
+        let out = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { gaggle_free(ptr) };
+        let first: serde_json::Value = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+        assert_eq!(first["key"], "b");
+        assert_eq!(first["depth"], 0);
+        assert_eq!(out.lines().count(), 3);
+
+        let missing = CString::new("$.nope").unwrap();
+        let ptr = unsafe { gaggle_json_tree(input.as_ptr(), missing.as_ptr()) };
+        assert_eq!(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap(), "");
+        unsafe { gaggle_free(ptr) };
+    }
+
     #[test]
     fn test_gaggle_json_each_invalid_json_sets_error() {
         let invalid = CString::new("{not json}").unwrap();
@@ -1082,4 +5065,26 @@ mod tests {
             assert!(err_str.contains("no valid files"));
         }
     }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_gaggle_infer_schema_uncached_returns_json_error() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+        let dataset_path = CString::new("owner/not-cached").unwrap();
+
+        unsafe {
+            let result_ptr = super::gaggle_infer_schema(dataset_path.as_ptr());
+            assert!(!result_ptr.is_null());
+            let s = CStr::from_ptr(result_ptr).to_str().unwrap().to_string();
+            super::gaggle_free(result_ptr);
+            let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+            assert_eq!(v["code"], "E002");
+            assert!(v["error"].as_str().unwrap().contains("not cached"));
+
+            assert!(super::gaggle_infer_schema(std::ptr::null()).is_null());
+        }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 }
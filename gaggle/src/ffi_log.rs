@@ -0,0 +1,183 @@
+//! Forwards `tracing` events to a log callback registered over FFI.
+//!
+//! Host applications (like the DuckDB extension) register a callback with
+//! `gaggle_set_log_callback` to route Gaggle's logs into their own diagnostics.
+//! While a callback is registered, the default formatted logger is silenced.
+
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use std::ffi::{c_void, CString};
+use std::fmt::Write as _;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::ffi::GaggleLogFn;
+
+/// A registered callback together with the opaque pointer passed back to it.
+#[derive(Clone, Copy)]
+struct LogCallback {
+    func: GaggleLogFn,
+    user_data: *mut c_void,
+}
+
+// SAFETY: `user_data` is never dereferenced by Gaggle. The caller of
+// `gaggle_set_log_callback` guarantees the callback may be invoked from any thread.
+unsafe impl Send for LogCallback {}
+unsafe impl Sync for LogCallback {}
+
+static LOG_CALLBACK: OnceCell<RwLock<Option<LogCallback>>> = OnceCell::new();
+
+fn registry() -> &'static RwLock<Option<LogCallback>> {
+    LOG_CALLBACK.get_or_init(|| RwLock::new(None))
+}
+
+/// Registers `func` as the log callback, replacing any previous one.
+pub(crate) fn set_callback(func: GaggleLogFn, user_data: *mut c_void) {
+    *registry().write() = Some(LogCallback { func, user_data });
+}
+
+/// Removes the registered log callback, if any.
+pub(crate) fn clear_callback() {
+    *registry().write() = None;
+}
+
+/// Returns whether a log callback is currently registered.
+pub(crate) fn has_callback() -> bool {
+    registry().read().is_some()
+}
+
+/// Maps a `tracing` level to the numeric level passed to the callback.
+fn level_to_i32(level: &Level) -> i32 {
+    match *level {
+        Level::ERROR => 0,
+        Level::WARN => 1,
+        Level::INFO => 2,
+        Level::DEBUG => 3,
+        Level::TRACE => 4,
+    }
+}
+
+/// Collects the message and the other fields of an event into one line.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        let mut line = self.message;
+        line.push_str(&self.fields);
+        // Interior NULs would truncate the message on the C side
+        line.retain(|c| c != '\0');
+        line
+    }
+}
+
+/// A `tracing` layer that sends each event to the registered log callback.
+pub struct FfiLogLayer;
+
+impl<S: Subscriber> Layer<S> for FfiLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // Copy the callback out so it can (de)register callbacks without deadlocking
+        let Some(callback) = *registry().read() else {
+            return;
+        };
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let Ok(message) = CString::new(visitor.finish()) else {
+            return;
+        };
+
+        // SAFETY: the registrant guarantees `func` stays valid until it is cleared
+        unsafe {
+            (callback.func)(
+                level_to_i32(event.metadata().level()),
+                message.as_ptr(),
+                callback.user_data,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::{c_char, CStr};
+    use std::sync::Mutex;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    type Captured = Mutex<Vec<(i32, String)>>;
+
+    unsafe extern "C" fn capture(level: i32, message: *const c_char, user_data: *mut c_void) {
+        let captured = &*(user_data as *const Captured);
+        let message = CStr::from_ptr(message).to_string_lossy().into_owned();
+        captured.lock().unwrap().push((level, message));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_callback_receives_levels_and_messages() {
+        let captured: Captured = Mutex::new(Vec::new());
+        set_callback(capture, &captured as *const Captured as *mut c_void);
+        assert!(has_callback());
+
+        let subscriber = tracing_subscriber::registry().with(FfiLogLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::error!("e");
+            tracing::warn!(dataset = "owner/ds", "w");
+            tracing::info!("i");
+            tracing::debug!("d");
+            tracing::trace!("t");
+        });
+        clear_callback();
+
+        let captured = captured.into_inner().unwrap();
+        assert_eq!(
+            captured,
+            vec![
+                (0, "e".to_string()),
+                (1, "w dataset=owner/ds".to_string()),
+                (2, "i".to_string()),
+                (3, "d".to_string()),
+                (4, "t".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_cleared_callback_is_not_invoked() {
+        let captured: Captured = Mutex::new(Vec::new());
+        set_callback(capture, &captured as *const Captured as *mut c_void);
+        clear_callback();
+        assert!(!has_callback());
+
+        let subscriber = tracing_subscriber::registry().with(FfiLogLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::error!("dropped");
+        });
+
+        assert!(captured.lock().unwrap().is_empty());
+    }
+}
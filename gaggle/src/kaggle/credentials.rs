@@ -4,22 +4,243 @@
 // functions for setting and retrieving credentials, with support for loading them
 // from environment variables or a `kaggle.json` file. The credentials are stored
 // in a thread-safe, lazily-initialized global variable to check that they are
-// loaded only once and can be safely accessed from multiple threads.
+// loaded only once and can be safely accessed from multiple threads. A failed lookup
+// is remembered for a short time so that credential-less processes do not probe the
+// filesystem on every call.
 
 use crate::error::GaggleError;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+#[cfg(test)]
+use std::cell::RefCell;
 use std::fs;
+use std::path::PathBuf;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 static CREDENTIALS: once_cell::sync::Lazy<RwLock<Option<KaggleCredentials>>> =
     once_cell::sync::Lazy::new(|| RwLock::new(None));
 
+/// The time of the last lookup that found no credentials at all, and the error it returned.
+/// The error is built once and returned again while the lookup is fresh.
+static NEGATIVE_LOOKUP: once_cell::sync::Lazy<Mutex<Option<(Instant, GaggleError)>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// Number of times the kaggle.json location has been probed (test-only)
+#[cfg(test)]
+static KAGGLE_JSON_PROBES: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(test)]
+thread_local! {
+    static TEST_HOME_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Negative lookup TTL (seconds), configurable via GAGGLE_CREDENTIALS_NEGATIVE_TTL (default 5s)
+fn negative_lookup_ttl() -> Duration {
+    let ms = std::env::var("GAGGLE_CREDENTIALS_NEGATIVE_TTL")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|secs| *secs >= 0.0)
+        .map(|secs| (secs * 1000.0).round() as u64)
+        .unwrap_or(5000);
+    Duration::from_millis(ms)
+}
+
+/// Returns the error reported when no credentials are available, naming the locations tried.
+fn no_credentials_error(tried: &[PathBuf]) -> GaggleError {
+    let mut locations = vec!["gaggle_set_credentials()".to_string()];
+    locations.push("KAGGLE_USERNAME/KAGGLE_KEY environment variables".to_string());
+    locations.extend(tried.iter().map(|p| p.display().to_string()));
+    GaggleError::CredentialsError(format!(
+        "No Kaggle credentials found. Tried: {}",
+        locations.join(", ")
+    ))
+}
+
+/// Returns the credential files to try, in order of precedence: `KAGGLE_JSON_PATH`, its alias
+/// `GAGGLE_CREDENTIALS_PATH`, `GAGGLE_CREDENTIALS_FILE`, `$KAGGLE_CONFIG_DIR/kaggle.json`, and
+/// `~/.kaggle/kaggle.json`. A file that does not exist is skipped during the lookup.
+fn credential_file_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    for var in [
+        "KAGGLE_JSON_PATH",
+        "GAGGLE_CREDENTIALS_PATH",
+        "GAGGLE_CREDENTIALS_FILE",
+    ] {
+        if let Some(file) = std::env::var_os(var).filter(|v| !v.is_empty()) {
+            candidates.push(PathBuf::from(file));
+        }
+    }
+    if let Some(dir) = std::env::var_os("KAGGLE_CONFIG_DIR").filter(|v| !v.is_empty()) {
+        candidates.push(PathBuf::from(dir).join("kaggle.json"));
+    }
+    if let Some(home) = home_dir() {
+        candidates.push(home.join(".kaggle").join("kaggle.json"));
+    }
+    candidates
+}
+
+/// Returns the `kaggle.json` file that credentials are loaded from.
+///
+/// This is the first existing file among the locations listed by the lookup chain (see
+/// [`get_credentials`]). If none exists, the location with the highest precedence is
+/// returned, which is `~/.kaggle/kaggle.json` unless another location is configured.
+pub fn kaggle_json_path() -> PathBuf {
+    let candidates = credential_file_candidates();
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .or_else(|| candidates.first())
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from(".kaggle").join("kaggle.json"))
+}
+
+/// Reads `KAGGLE_USERNAME` and `KAGGLE_KEY` from a `.env` file, if it defines both.
+///
+/// The process environment is not modified.
+fn read_dotenv_credentials(path: &std::path::Path) -> Option<KaggleCredentials> {
+    let mut username = None;
+    let mut key = None;
+    for item in dotenvy::from_path_iter(path).ok()? {
+        match item {
+            Ok((name, value)) if name == "KAGGLE_USERNAME" => username = Some(value),
+            Ok((name, value)) if name == "KAGGLE_KEY" => key = Some(value),
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "could not parse .env file");
+                return None;
+            }
+        }
+    }
+    Some(KaggleCredentials {
+        username: username?,
+        key: MaskedKey::new(key?),
+    })
+}
+
+/// Reads credentials from a `kaggle.json`-style file.
+fn read_credentials_file(path: &std::path::Path) -> Result<KaggleCredentials, GaggleError> {
+    // Verify file permissions for security (should not be world-readable)
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata = fs::metadata(path).map_err(|e| {
+            GaggleError::CredentialsError(format!(
+                "Cannot read metadata of {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let mode = metadata.permissions().mode();
+        if mode & 0o077 != 0 {
+            tracing::warn!(
+                path = %path.display(),
+                "credentials file has overly permissive permissions; it should be owner-readable only (chmod 600)"
+            );
+        }
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| {
+        GaggleError::CredentialsError(format!("Cannot read {}: {}", path.display(), e))
+    })?;
+
+    let json: serde_json::Value = serde_json::from_str(&content).map_err(|e| {
+        GaggleError::CredentialsError(format!("Invalid JSON in {}: {}", path.display(), e))
+    })?;
+
+    let username = json["username"]
+        .as_str()
+        .ok_or_else(|| {
+            GaggleError::CredentialsError(format!("Missing username in {}", path.display()))
+        })?
+        .to_string();
+    let key = json["key"]
+        .as_str()
+        .ok_or_else(|| GaggleError::CredentialsError(format!("Missing key in {}", path.display())))?
+        .to_string();
+
+    Ok(KaggleCredentials {
+        username,
+        key: MaskedKey::new(key),
+    })
+}
+
+/// Returns the error of a recent lookup that found no credentials, if it is still fresh.
+fn fresh_negative_lookup() -> Option<GaggleError> {
+    match *NEGATIVE_LOOKUP.lock() {
+        Some((ts, ref err)) if ts.elapsed() < negative_lookup_ttl() => Some(err.clone()),
+        _ => None,
+    }
+}
+
+/// Forgets any cached "no credentials found" outcome.
+fn invalidate_negative_lookup() {
+    *NEGATIVE_LOOKUP.lock() = None;
+}
+
+/// Resolves the home directory used to locate `.kaggle/kaggle.json`.
+fn home_dir() -> Option<PathBuf> {
+    #[cfg(test)]
+    {
+        let mut tls: Option<PathBuf> = None;
+        TEST_HOME_DIR.with(|c| tls = c.borrow().clone());
+        if tls.is_some() {
+            return tls;
+        }
+    }
+    dirs::home_dir()
+}
+
+/// A Kaggle API key that shows only its first 4 characters when formatted.
+///
+/// Formatting with `{}` or `{:?}` prints the prefix followed by `****`, so a key that ends up
+/// in a log message or an error is not leaked. Keys of 4 characters or less print as `****`.
+/// Use [`MaskedKey::as_str`] where the real value is needed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MaskedKey(String);
+
+impl MaskedKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// The unmasked key
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for MaskedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let shown: String = if self.0.chars().count() > 4 {
+            self.0.chars().take(4).collect()
+        } else {
+            String::new()
+        };
+        write!(f, "{}****", shown)
+    }
+}
+
+impl std::fmt::Debug for MaskedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
 /// A struct that represents Kaggle API credentials.
 #[derive(Clone)]
 pub struct KaggleCredentials {
     /// The Kaggle username.
     pub username: String,
-    /// The Kaggle API key.
-    pub key: String,
+    /// The Kaggle API key, masked when formatted.
+    pub key: MaskedKey,
+}
+
+impl KaggleCredentials {
+    /// The unmasked API key, for authenticating requests
+    pub fn key_str(&self) -> &str {
+        self.key.as_str()
+    }
 }
 
 impl std::fmt::Debug for KaggleCredentials {
@@ -31,17 +252,120 @@ impl std::fmt::Debug for KaggleCredentials {
     }
 }
 
+/// Returns the API keys Gaggle currently knows about, so they can be scrubbed from messages.
+///
+/// This never loads credentials and never blocks on the credentials lock, so it is safe to
+/// call while building an error.
+pub(crate) fn known_secrets() -> Vec<String> {
+    let mut secrets = Vec::new();
+    if let Some(guard) = CREDENTIALS.try_read() {
+        if let Some(creds) = guard.as_ref() {
+            secrets.push(creds.key_str().to_string());
+        }
+    }
+    if let Ok(key) = std::env::var("KAGGLE_KEY") {
+        if !secrets.contains(&key) {
+            secrets.push(key);
+        }
+    }
+    secrets
+}
+
+/// Whether `key` looks like a Kaggle API key, which is 32 lowercase hex characters.
+fn is_well_formed_key(key: &str) -> bool {
+    key.len() == 32 && key.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Checks that a username and API key look usable before they are stored.
+///
+/// An empty (or whitespace-only) username or key is an error. A key that is not a
+/// 32-character lowercase hex string only produces a warning, since the format is
+/// not guaranteed by the API.
+#[allow(dead_code)]
+pub fn validate_credential_format(username: &str, key: &str) -> Result<(), GaggleError> {
+    check_not_empty(username, key)?;
+    warn_if_malformed_key(username, key);
+    Ok(())
+}
+
+/// Rejects an empty (or whitespace-only) username or key.
+fn check_not_empty(username: &str, key: &str) -> Result<(), GaggleError> {
+    if username.trim().is_empty() {
+        return Err(GaggleError::CredentialsError(
+            "Username cannot be empty".to_string(),
+        ));
+    }
+    if key.trim().is_empty() {
+        return Err(GaggleError::CredentialsError(
+            "API key cannot be empty".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Logs a warning if `key` does not look like a Kaggle API key.
+fn warn_if_malformed_key(username: &str, key: &str) {
+    if !is_well_formed_key(key) {
+        tracing::warn!(
+            username,
+            "Kaggle API key does not look like a 32-character lowercase hex string"
+        );
+    }
+}
+
 /// Sets the Kaggle API credentials.
+///
+/// Any strings are accepted, but a warning is logged if the key does not look like a Kaggle
+/// API key. With `GAGGLE_VALIDATE_CREDENTIALS_ON_SET` enabled, the credentials are first
+/// checked against the API and are not stored if the API rejects them.
 pub fn set_credentials(username: &str, key: &str) -> Result<(), GaggleError> {
-    let mut creds = CREDENTIALS.write();
-    *creds = Some(KaggleCredentials {
+    warn_if_malformed_key(username, key);
+    store_credentials(username, key, crate::config::validate_credentials_on_set())
+}
+
+/// Sets the Kaggle API credentials, rejecting an empty username or key, or a key that is not
+/// a 32-character lowercase hex string.
+pub fn set_credentials_strict(username: &str, key: &str) -> Result<(), GaggleError> {
+    // A malformed key is an error here, so it is not also logged as a warning
+    check_not_empty(username, key)?;
+    if !is_well_formed_key(key) {
+        return Err(GaggleError::CredentialsError(
+            "API key must be a 32-character lowercase hex string".to_string(),
+        ));
+    }
+    store_credentials(username, key, crate::config::validate_credentials_on_set())
+}
+
+/// Stores the credentials, optionally checking them against the API first.
+///
+/// The online check is skipped in offline mode.
+fn store_credentials(username: &str, key: &str, check_online: bool) -> Result<(), GaggleError> {
+    let new_creds = KaggleCredentials {
         username: username.to_string(),
-        key: key.to_string(),
-    });
+        key: MaskedKey::new(key),
+    };
+    if check_online && !crate::config::offline_mode() {
+        check_credentials_online(&new_creds)?;
+    }
+    *CREDENTIALS.write() = Some(new_creds);
+    invalidate_negative_lookup();
     Ok(())
 }
 
+/// Clears the stored credentials so that the next lookup reloads them from the
+/// environment or `kaggle.json`.
+pub fn clear_credentials() {
+    *CREDENTIALS.write() = None;
+    invalidate_negative_lookup();
+}
+
 /// Retrieves the stored credentials, or attempts to load them from the environment or a file.
+///
+/// Sources are checked in this order: credentials set in memory, the `KAGGLE_USERNAME` and
+/// `KAGGLE_KEY` environment variables, the same variables in `./.env` (with `GAGGLE_DOTENV`
+/// enabled), the file named by `KAGGLE_JSON_PATH`, `GAGGLE_CREDENTIALS_PATH`, or
+/// `GAGGLE_CREDENTIALS_FILE`, `$KAGGLE_CONFIG_DIR/kaggle.json`, and finally
+/// `~/.kaggle/kaggle.json`.
 pub fn get_credentials() -> Result<KaggleCredentials, GaggleError> {
     // Check if credentials are already set in memory (fast path with read lock)
     if let Some(creds) = CREDENTIALS.read().as_ref() {
@@ -62,63 +386,91 @@ pub fn get_credentials() -> Result<KaggleCredentials, GaggleError> {
         std::env::var("KAGGLE_USERNAME"),
         std::env::var("KAGGLE_KEY"),
     ) {
-        let creds = KaggleCredentials { username, key };
+        let creds = KaggleCredentials {
+            username,
+            key: MaskedKey::new(key),
+        };
         *creds_guard = Some(creds.clone());
         return Ok(creds);
     }
 
-    // Try kaggle.json file
-    let kaggle_json_path = dirs::home_dir()
-        .ok_or_else(|| GaggleError::CredentialsError("Cannot find home directory".to_string()))?
-        .join(".kaggle")
-        .join("kaggle.json");
-
-    if kaggle_json_path.exists() {
-        // Verify file permissions for security (should not be world-readable)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let metadata = fs::metadata(&kaggle_json_path).map_err(|e| {
-                GaggleError::CredentialsError(format!("Cannot read kaggle.json metadata: {}", e))
-            })?;
-            let mode = metadata.permissions().mode();
-            if mode & 0o077 != 0 {
-                tracing::warn!(
-                    path = %kaggle_json_path.display(),
-                    "kaggle.json has overly permissive permissions; it should be owner-readable only (chmod 600)"
-                );
-            }
+    // Try a .env file in the current directory, if enabled
+    if crate::config::dotenv_enabled() {
+        if let Some(creds) = read_dotenv_credentials(std::path::Path::new(".env")) {
+            tracing::debug!("loading Kaggle credentials from .env");
+            *creds_guard = Some(creds.clone());
+            return Ok(creds);
         }
+    }
 
-        let content = fs::read_to_string(&kaggle_json_path).map_err(|e| {
-            GaggleError::CredentialsError(format!("Cannot read kaggle.json: {}", e))
-        })?;
+    // Skip the filesystem probe if a recent lookup already came up empty
+    if let Some(err) = fresh_negative_lookup() {
+        return Err(err);
+    }
+    let candidates = credential_file_candidates();
 
-        let json: serde_json::Value = serde_json::from_str(&content).map_err(|e| {
-            GaggleError::CredentialsError(format!("Invalid JSON in kaggle.json: {}", e))
-        })?;
+    // Try credential files in order of precedence
+    #[cfg(test)]
+    KAGGLE_JSON_PROBES.fetch_add(1, Ordering::SeqCst);
+    for path in &candidates {
+        if path.is_file() {
+            tracing::debug!(path = %path.display(), "loading Kaggle credentials from file");
+            let creds = read_credentials_file(path)?;
+            *creds_guard = Some(creds.clone());
+            return Ok(creds);
+        }
+    }
 
-        let username = json["username"]
-            .as_str()
-            .ok_or_else(|| {
-                GaggleError::CredentialsError("Missing username in kaggle.json".to_string())
-            })?
-            .to_string();
-        let key = json["key"]
-            .as_str()
-            .ok_or_else(|| GaggleError::CredentialsError("Missing key in kaggle.json".to_string()))?
-            .to_string();
-
-        let creds = KaggleCredentials { username, key };
-        *creds_guard = Some(creds.clone());
-        return Ok(creds);
+    let err = no_credentials_error(&candidates);
+    *NEGATIVE_LOOKUP.lock() = Some((Instant::now(), err.clone()));
+    Err(err)
+}
+
+/// Checks the current credentials against the Kaggle API with a cheap authenticated call.
+///
+/// # Returns
+///
+/// The username on success. If the API rejects the request, a `CredentialsError` naming the
+/// HTTP status is returned. The API key never appears in logs or error messages.
+pub fn validate_credentials() -> Result<String, GaggleError> {
+    if crate::config::offline_mode() {
+        return Err(GaggleError::HttpRequestError(
+            "Offline mode enabled; cannot validate credentials offline. Unset GAGGLE_OFFLINE to enable network."
+                .to_string(),
+        ));
     }
 
-    Err(GaggleError::CredentialsError(
-        "No Kaggle credentials found. Set KAGGLE_USERNAME and KAGGLE_KEY environment variables, \
-         create ~/.kaggle/kaggle.json, or call gaggle_set_credentials()"
-            .to_string(),
-    ))
+    let creds = get_credentials()?;
+    check_credentials_online(&creds)?;
+    Ok(creds.username)
+}
+
+/// Makes a cheap authenticated API call and fails with a `CredentialsError` if it is rejected.
+fn check_credentials_online(creds: &KaggleCredentials) -> Result<(), GaggleError> {
+    let url = format!(
+        "{}/datasets/list?page=1&pageSize=1",
+        super::api::get_api_base()
+    );
+    tracing::debug!(username = %creds.username, "validating Kaggle credentials");
+
+    let client = super::api::get_client()?;
+    let response = super::api::with_retries(|| {
+        super::api::send(
+            client
+                .get(&url)
+                .basic_auth(&creds.username, Some(creds.key_str())),
+        )
+    })?;
+
+    if !response.status().is_success() {
+        return Err(GaggleError::CredentialsError(format!(
+            "Kaggle rejected the credentials for user '{}': HTTP {}",
+            creds.username,
+            response.status()
+        )));
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -132,10 +484,10 @@ mod tests {
     fn test_kaggle_credentials_struct() {
         let creds = KaggleCredentials {
             username: "testuser".to_string(),
-            key: "testkey".to_string(),
+            key: MaskedKey::new("testkey".to_string()),
         };
         assert_eq!(creds.username, "testuser");
-        assert_eq!(creds.key, "testkey");
+        assert_eq!(creds.key_str(), "testkey");
     }
 
     #[test]
@@ -148,7 +500,7 @@ mod tests {
         assert!(creds.is_some());
         let c = creds.as_ref().unwrap();
         assert_eq!(c.username, "user");
-        assert_eq!(c.key, "key");
+        assert_eq!(c.key_str(), "key");
     }
 
     #[test]
@@ -162,7 +514,7 @@ mod tests {
         assert!(result.is_ok());
         let creds = result.unwrap();
         assert_eq!(creds.username, "test_user");
-        assert_eq!(creds.key, "test_key");
+        assert_eq!(creds.key_str(), "test_key");
     }
 
     #[test]
@@ -178,7 +530,7 @@ mod tests {
         assert!(result.is_ok());
         let creds = result.unwrap();
         assert_eq!(creds.username, "env_user");
-        assert_eq!(creds.key, "env_key");
+        assert_eq!(creds.key_str(), "env_key");
 
         std::env::remove_var("KAGGLE_USERNAME");
         std::env::remove_var("KAGGLE_KEY");
@@ -212,11 +564,11 @@ mod tests {
     fn test_credentials_clone() {
         let creds1 = KaggleCredentials {
             username: "user".to_string(),
-            key: "key".to_string(),
+            key: MaskedKey::new("key".to_string()),
         };
         let creds2 = creds1.clone();
         assert_eq!(creds1.username, creds2.username);
-        assert_eq!(creds1.key, creds2.key);
+        assert_eq!(creds1.key_str(), creds2.key_str());
     }
 
     #[test]
@@ -224,7 +576,7 @@ mod tests {
     fn test_credentials_debug() {
         let creds = KaggleCredentials {
             username: "user".to_string(),
-            key: "supersecret".to_string(),
+            key: MaskedKey::new("supersecret".to_string()),
         };
         let debug_str = format!("{:?}", creds);
         assert!(debug_str.contains("KaggleCredentials"));
@@ -233,6 +585,18 @@ mod tests {
         assert!(debug_str.contains("REDACTED"));
     }
 
+    #[test]
+    fn test_masked_key_hides_the_key() {
+        let key = MaskedKey::new("0123456789abcdef0123456789abcdef");
+        assert_eq!(format!("{:?}", key), "0123****");
+        assert_eq!(key.to_string(), "0123****");
+        assert!(!format!("{:?}", key).contains("0123456789abcdef"));
+        assert_eq!(key.as_str(), "0123456789abcdef0123456789abcdef");
+
+        // Short keys are masked entirely
+        assert_eq!(format!("{:?}", MaskedKey::new("abcd")), "****");
+    }
+
     #[test]
     #[serial]
     fn test_concurrent_credential_access() {
@@ -303,6 +667,105 @@ mod tests {
         std::env::remove_var("KAGGLE_USERNAME");
     }
 
+    fn set_test_home_dir(path: Option<PathBuf>) {
+        TEST_HOME_DIR.with(|c| *c.borrow_mut() = path);
+    }
+
+    #[test]
+    #[serial]
+    fn test_negative_lookup_probes_once_per_ttl_window() {
+        let home = tempfile::TempDir::new().unwrap();
+        set_test_home_dir(Some(home.path().to_path_buf()));
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::set_var("GAGGLE_CREDENTIALS_NEGATIVE_TTL", "60");
+        clear_credentials();
+
+        let before = KAGGLE_JSON_PROBES.load(Ordering::SeqCst);
+        let first = get_credentials().err().unwrap();
+        for _ in 0..4 {
+            match get_credentials() {
+                Err(err @ GaggleError::CredentialsError(_)) => {
+                    // The same error is returned, with its code from the enum
+                    assert_eq!(err.to_string(), first.to_string());
+                    assert_eq!(err.code(), crate::error::ErrorCode::E001_InvalidCredentials);
+                    assert!(err.to_string().contains("No Kaggle credentials found"))
+                }
+                other => panic!("Expected CredentialsError, got {:?}", other),
+            }
+        }
+        assert_eq!(KAGGLE_JSON_PROBES.load(Ordering::SeqCst) - before, 1);
+
+        // With a zero TTL every lookup probes the filesystem again
+        std::env::set_var("GAGGLE_CREDENTIALS_NEGATIVE_TTL", "0");
+        let before = KAGGLE_JSON_PROBES.load(Ordering::SeqCst);
+        for _ in 0..3 {
+            assert!(get_credentials().is_err());
+        }
+        assert_eq!(KAGGLE_JSON_PROBES.load(Ordering::SeqCst) - before, 3);
+
+        std::env::remove_var("GAGGLE_CREDENTIALS_NEGATIVE_TTL");
+        set_test_home_dir(None);
+        clear_credentials();
+    }
+
+    #[test]
+    #[serial]
+    fn test_negative_lookup_picks_up_new_credentials() {
+        let home = tempfile::TempDir::new().unwrap();
+        set_test_home_dir(Some(home.path().to_path_buf()));
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::set_var("GAGGLE_CREDENTIALS_NEGATIVE_TTL", "60");
+        clear_credentials();
+
+        assert!(get_credentials().is_err());
+
+        // Environment variables are still honored while the negative entry is fresh
+        std::env::set_var("KAGGLE_USERNAME", "env_user");
+        std::env::set_var("KAGGLE_KEY", "env_key");
+        assert_eq!(get_credentials().unwrap().username, "env_user");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+
+        // A kaggle.json added mid-session is found after explicit invalidation
+        clear_credentials();
+        assert!(get_credentials().is_err());
+        let kaggle_dir = home.path().join(".kaggle");
+        fs::create_dir_all(&kaggle_dir).unwrap();
+        fs::write(
+            kaggle_dir.join("kaggle.json"),
+            r#"{"username":"file_user","key":"file_key"}"#,
+        )
+        .unwrap();
+        assert!(get_credentials().is_err());
+        clear_credentials();
+        assert_eq!(get_credentials().unwrap().username, "file_user");
+
+        std::env::remove_var("GAGGLE_CREDENTIALS_NEGATIVE_TTL");
+        set_test_home_dir(None);
+        clear_credentials();
+    }
+
+    #[test]
+    #[serial]
+    fn test_set_credentials_invalidates_negative_lookup() {
+        let home = tempfile::TempDir::new().unwrap();
+        set_test_home_dir(Some(home.path().to_path_buf()));
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        clear_credentials();
+
+        assert!(get_credentials().is_err());
+        assert!(fresh_negative_lookup().is_some());
+        set_credentials("user", "key").unwrap();
+        assert!(fresh_negative_lookup().is_none());
+        assert_eq!(get_credentials().unwrap().username, "user");
+
+        set_test_home_dir(None);
+        clear_credentials();
+    }
+
     #[test]
     #[serial]
     fn test_set_empty_credentials() {
@@ -311,6 +774,367 @@ mod tests {
 
         let creds = get_credentials().unwrap();
         assert_eq!(creds.username, "");
-        assert_eq!(creds.key, "");
+        assert_eq!(creds.key_str(), "");
+    }
+
+    fn write_creds(path: &std::path::Path, user: &str) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, format!(r#"{{"username":"{}","key":"k"}}"#, user)).unwrap();
+    }
+
+    /// Isolates a lookup from the real environment, returning the temp home directory.
+    fn isolated_lookup() -> tempfile::TempDir {
+        let home = tempfile::TempDir::new().unwrap();
+        set_test_home_dir(Some(home.path().to_path_buf()));
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("KAGGLE_JSON_PATH");
+        std::env::remove_var("GAGGLE_CREDENTIALS_PATH");
+        std::env::remove_var("GAGGLE_CREDENTIALS_FILE");
+        std::env::remove_var("KAGGLE_CONFIG_DIR");
+        std::env::remove_var("GAGGLE_DOTENV");
+        std::env::set_var("GAGGLE_CREDENTIALS_NEGATIVE_TTL", "0");
+        clear_credentials();
+        home
+    }
+
+    fn cleanup_lookup() {
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("KAGGLE_JSON_PATH");
+        std::env::remove_var("GAGGLE_CREDENTIALS_PATH");
+        std::env::remove_var("GAGGLE_CREDENTIALS_FILE");
+        std::env::remove_var("KAGGLE_CONFIG_DIR");
+        std::env::remove_var("GAGGLE_DOTENV");
+        std::env::remove_var("GAGGLE_CREDENTIALS_NEGATIVE_TTL");
+        set_test_home_dir(None);
+        clear_credentials();
+    }
+
+    #[test]
+    #[serial]
+    fn test_credentials_precedence_order() {
+        let home = isolated_lookup();
+        let custom = home.path().join("custom/creds.json");
+        let config_dir = home.path().join("config");
+        write_creds(&home.path().join(".kaggle/kaggle.json"), "home_user");
+        write_creds(&config_dir.join("kaggle.json"), "config_dir_user");
+        write_creds(&custom, "custom_file_user");
+
+        // Home directory default
+        assert_eq!(get_credentials().unwrap().username, "home_user");
+
+        // KAGGLE_CONFIG_DIR beats the home directory
+        clear_credentials();
+        std::env::set_var("KAGGLE_CONFIG_DIR", &config_dir);
+        assert_eq!(get_credentials().unwrap().username, "config_dir_user");
+
+        // GAGGLE_CREDENTIALS_FILE beats KAGGLE_CONFIG_DIR
+        clear_credentials();
+        std::env::set_var("GAGGLE_CREDENTIALS_FILE", &custom);
+        assert_eq!(get_credentials().unwrap().username, "custom_file_user");
+
+        // GAGGLE_CREDENTIALS_PATH beats GAGGLE_CREDENTIALS_FILE
+        clear_credentials();
+        let alias = home.path().join("alias/creds.json");
+        write_creds(&alias, "alias_user");
+        std::env::set_var("GAGGLE_CREDENTIALS_PATH", &alias);
+        assert_eq!(get_credentials().unwrap().username, "alias_user");
+
+        // KAGGLE_JSON_PATH beats its alias
+        clear_credentials();
+        let official = home.path().join("official/creds.json");
+        write_creds(&official, "json_path_user");
+        std::env::set_var("KAGGLE_JSON_PATH", &official);
+        assert_eq!(get_credentials().unwrap().username, "json_path_user");
+
+        // The environment variable pair beats all files
+        clear_credentials();
+        std::env::set_var("KAGGLE_USERNAME", "env_user");
+        std::env::set_var("KAGGLE_KEY", "env_key");
+        assert_eq!(get_credentials().unwrap().username, "env_user");
+
+        // In-memory credentials beat everything
+        set_credentials("memory_user", "memory_key").unwrap();
+        assert_eq!(get_credentials().unwrap().username, "memory_user");
+
+        cleanup_lookup();
+    }
+
+    #[test]
+    #[serial]
+    fn test_kaggle_json_path_follows_lookup_chain() {
+        let home = isolated_lookup();
+        let default = home.path().join(".kaggle/kaggle.json");
+        let config_dir = home.path().join("config");
+        let alias = home.path().join("alias.json");
+        let official = home.path().join("official.json");
+
+        // Nothing exists yet, so the default location is reported
+        assert_eq!(kaggle_json_path(), default);
+
+        write_creds(&default, "home_user");
+        write_creds(&config_dir.join("kaggle.json"), "config_dir_user");
+        std::env::set_var("KAGGLE_CONFIG_DIR", &config_dir);
+        assert_eq!(kaggle_json_path(), config_dir.join("kaggle.json"));
+
+        // A configured file that does not exist is skipped
+        std::env::set_var("GAGGLE_CREDENTIALS_PATH", &alias);
+        assert_eq!(kaggle_json_path(), config_dir.join("kaggle.json"));
+        write_creds(&alias, "alias_user");
+        assert_eq!(kaggle_json_path(), alias);
+
+        std::env::set_var("KAGGLE_JSON_PATH", &official);
+        write_creds(&official, "json_path_user");
+        assert_eq!(kaggle_json_path(), official);
+
+        // With no existing file, the location with the highest precedence is reported
+        fs::remove_file(&official).unwrap();
+        fs::remove_file(&alias).unwrap();
+        fs::remove_file(config_dir.join("kaggle.json")).unwrap();
+        fs::remove_file(&default).unwrap();
+        assert_eq!(kaggle_json_path(), official);
+
+        cleanup_lookup();
+    }
+
+    #[test]
+    #[serial]
+    fn test_dotenv_credentials() {
+        let home = isolated_lookup();
+        let env_file = home.path().join(".env");
+        fs::write(
+            &env_file,
+            "# local secrets\nKAGGLE_USERNAME=dotenv_user\nKAGGLE_KEY=\"dotenv_key\"\nOTHER=1\n",
+        )
+        .unwrap();
+        let creds = read_dotenv_credentials(&env_file).unwrap();
+        assert_eq!(creds.username, "dotenv_user");
+        assert_eq!(creds.key_str(), "dotenv_key");
+        // The process environment is left alone
+        assert!(std::env::var("KAGGLE_USERNAME").is_err());
+
+        fs::write(&env_file, "KAGGLE_USERNAME=dotenv_user\n").unwrap();
+        assert!(read_dotenv_credentials(&env_file).is_none());
+        assert!(read_dotenv_credentials(&home.path().join("missing.env")).is_none());
+
+        cleanup_lookup();
+    }
+
+    #[test]
+    #[serial]
+    fn test_missing_credentials_file_falls_through() {
+        let home = isolated_lookup();
+        std::env::set_var("GAGGLE_CREDENTIALS_FILE", home.path().join("missing.json"));
+        std::env::set_var("KAGGLE_CONFIG_DIR", home.path().join("missing_dir"));
+        write_creds(&home.path().join(".kaggle/kaggle.json"), "home_user");
+
+        assert_eq!(get_credentials().unwrap().username, "home_user");
+
+        cleanup_lookup();
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_credentials_file_reports_path() {
+        let home = isolated_lookup();
+        let custom = home.path().join("bad.json");
+        fs::write(&custom, "not json").unwrap();
+        std::env::set_var("GAGGLE_CREDENTIALS_FILE", &custom);
+
+        match get_credentials() {
+            Err(GaggleError::CredentialsError(msg)) => {
+                assert!(msg.contains("Invalid JSON"));
+                assert!(msg.contains("bad.json"));
+            }
+            other => panic!("Expected CredentialsError, got {:?}", other),
+        }
+
+        cleanup_lookup();
+    }
+
+    #[test]
+    #[serial]
+    fn test_no_credentials_error_lists_locations() {
+        let home = isolated_lookup();
+        let custom = home.path().join("custom.json");
+        std::env::set_var("GAGGLE_CREDENTIALS_FILE", &custom);
+        std::env::set_var("KAGGLE_CONFIG_DIR", home.path().join("cfg"));
+
+        match get_credentials() {
+            Err(GaggleError::CredentialsError(msg)) => {
+                assert!(msg.contains("No Kaggle credentials found"));
+                assert!(msg.contains("KAGGLE_USERNAME/KAGGLE_KEY"));
+                assert!(msg.contains(&custom.display().to_string()));
+                assert!(msg.contains(
+                    &home
+                        .path()
+                        .join("cfg")
+                        .join("kaggle.json")
+                        .display()
+                        .to_string()
+                ));
+                assert!(msg.contains(".kaggle"));
+            }
+            other => panic!("Expected CredentialsError, got {:?}", other),
+        }
+
+        cleanup_lookup();
+    }
+
+    #[test]
+    #[serial]
+    fn test_validate_credentials_with_mock() {
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        std::env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+        set_credentials("valid_user", "secret-key-123").unwrap();
+
+        let ok = server
+            .mock("GET", "/datasets/list")
+            .match_query(mockito::Matcher::UrlEncoded("pageSize".into(), "1".into()))
+            .match_header("authorization", mockito::Matcher::Regex("^Basic ".into()))
+            .with_status(200)
+            .with_body("[]")
+            .create();
+        assert_eq!(validate_credentials().unwrap(), "valid_user");
+        ok.assert();
+        ok.remove();
+
+        let _denied = server
+            .mock("GET", "/datasets/list")
+            .match_query(mockito::Matcher::Any)
+            .with_status(401)
+            .create();
+        match validate_credentials() {
+            Err(GaggleError::CredentialsError(msg)) => {
+                assert!(msg.contains("401"));
+                assert!(!msg.contains("secret-key-123"));
+            }
+            other => panic!("Expected CredentialsError, got {:?}", other),
+        }
+
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+        clear_credentials();
+    }
+
+    #[test]
+    fn test_validate_credential_format() {
+        let key = "0123456789abcdef0123456789abcdef";
+        assert!(is_well_formed_key(key));
+        assert!(validate_credential_format("user", key).is_ok());
+        // A malformed key only warns
+        assert!(!is_well_formed_key("0123456789ABCDEF0123456789ABCDEF"));
+        assert!(!is_well_formed_key("abc"));
+        assert!(validate_credential_format("user", "not-a-hex-key").is_ok());
+
+        for (username, key, expected) in [
+            ("", key, "Username cannot be empty"),
+            ("  ", key, "Username cannot be empty"),
+            ("user", "", "API key cannot be empty"),
+            ("user", " ", "API key cannot be empty"),
+        ] {
+            match validate_credential_format(username, key) {
+                Err(GaggleError::CredentialsError(msg)) => assert_eq!(msg, expected),
+                other => panic!("Expected CredentialsError, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_set_credentials_strict_rejects_malformed_key() {
+        clear_credentials();
+        set_credentials("previous", "0123456789abcdef0123456789abcdef").unwrap();
+
+        for key in ["", "secret-key-123", "0123456789ABCDEF0123456789ABCDEF"] {
+            assert!(matches!(
+                set_credentials_strict("user", key),
+                Err(GaggleError::CredentialsError(_))
+            ));
+        }
+        assert_eq!(get_credentials().unwrap().username, "previous");
+
+        set_credentials_strict("user", "fedcba9876543210fedcba9876543210").unwrap();
+        assert_eq!(get_credentials().unwrap().username, "user");
+        clear_credentials();
+    }
+
+    #[test]
+    #[serial]
+    fn test_store_credentials_checks_online_with_mock() {
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        std::env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+        clear_credentials();
+        set_credentials("previous", "0123456789abcdef0123456789abcdef").unwrap();
+
+        let denied = server
+            .mock("GET", "/datasets/list")
+            .match_query(mockito::Matcher::UrlEncoded("pageSize".into(), "1".into()))
+            .match_header(
+                "authorization",
+                mockito::Matcher::Exact(format!(
+                    "Basic {}",
+                    base64::Engine::encode(
+                        &base64::engine::general_purpose::STANDARD,
+                        "rejected:bad-key"
+                    )
+                )),
+            )
+            .with_status(401)
+            .create();
+        let accepted = server
+            .mock("GET", "/datasets/list")
+            .match_query(mockito::Matcher::UrlEncoded("pageSize".into(), "1".into()))
+            .match_header(
+                "authorization",
+                mockito::Matcher::Exact(format!(
+                    "Basic {}",
+                    base64::Engine::encode(
+                        &base64::engine::general_purpose::STANDARD,
+                        "accepted:good-key"
+                    )
+                )),
+            )
+            .with_status(200)
+            .with_body("[]")
+            .create();
+
+        // Rejected credentials are not stored
+        match store_credentials("rejected", "bad-key", true) {
+            Err(GaggleError::CredentialsError(msg)) => assert!(msg.contains("401")),
+            other => panic!("Expected CredentialsError, got {:?}", other),
+        }
+        assert_eq!(get_credentials().unwrap().username, "previous");
+
+        store_credentials("accepted", "good-key", true).unwrap();
+        assert_eq!(get_credentials().unwrap().username, "accepted");
+        denied.assert();
+        accepted.assert();
+
+        // Offline mode skips the check
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        store_credentials("offline", "any-key", true).unwrap();
+        assert_eq!(get_credentials().unwrap().username, "offline");
+        std::env::remove_var("GAGGLE_OFFLINE");
+
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+        clear_credentials();
+    }
+
+    #[test]
+    #[serial]
+    fn test_validate_credentials_offline() {
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        let err = validate_credentials().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("cannot validate credentials offline"));
+        std::env::remove_var("GAGGLE_OFFLINE");
     }
 }
@@ -6,53 +6,92 @@
 // The module also provides functions for listing files in a dataset and for checking if a
 // cached dataset is the most recent version.
 
-use crate::error::GaggleError;
-use parking_lot::Mutex;
+use crate::error::{sanitize_message, GaggleError};
+use parking_lot::{Condvar, Mutex};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::io::{BufWriter, Write};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::thread::sleep;
-use std::time::{Duration, SystemTime};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use super::api::{build_client, get_api_base, with_retries};
+use super::api::{get_api_base, get_download_client, send, with_retries, HttpRecordMode};
 use super::credentials::get_credentials;
+use super::metrics::{self, Counter};
+use super::throttle::ThrottledWriter;
 use tracing::{debug, warn};
 
 /// Track ongoing dataset downloads to prevent concurrent downloads of the same dataset
-static DOWNLOAD_LOCKS: once_cell::sync::Lazy<Mutex<HashMap<String, ()>>> =
+static DOWNLOAD_LOCKS: once_cell::sync::Lazy<Mutex<HashMap<String, Arc<DownloadSlot>>>> =
     once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// Outcome of an in-flight download, as seen by the threads waiting on it.
+enum DownloadState {
+    InProgress,
+    Succeeded,
+    Failed(GaggleError),
+}
+
+/// Shared between the thread holding a download lock and the threads waiting for it.
+struct DownloadSlot {
+    state: Mutex<DownloadState>,
+    finished: Condvar,
+}
+
+impl DownloadSlot {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(DownloadState::InProgress),
+            finished: Condvar::new(),
+        }
+    }
+
+    /// Records the outcome and wakes up every waiter. Only the first outcome is kept.
+    fn finish(&self, outcome: DownloadState) {
+        let mut state = self.state.lock();
+        if matches!(*state, DownloadState::InProgress) {
+            *state = outcome;
+        }
+        self.finished.notify_all();
+    }
+}
+
 /// A struct that represents a file within a Kaggle dataset.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatasetFile {
     /// The name of the file.
     pub name: String,
     /// The size of the file in bytes.
     pub size: u64,
+    /// When the file was created on Kaggle, or last modified in the local cache, if known.
+    #[serde(default)]
+    pub modified: Option<String>,
+    /// The number of columns Kaggle reports for the file, if known.
+    #[serde(default)]
+    pub columns: Option<u32>,
 }
 
-fn list_dataset_files_from_metadata(dataset_path: &str) -> Result<Vec<DatasetFile>, GaggleError> {
-    let meta = super::metadata::get_dataset_metadata(dataset_path)?;
-    let mut out = Vec::new();
-    if let Some(files) = meta.get("files").and_then(|v| v.as_array()) {
-        for f in files {
-            if let Some(name) = f.get("name").and_then(|n| n.as_str()) {
-                // support size keys in different schemas
-                let size = f
-                    .get("totalBytes")
-                    .and_then(|x| x.as_u64())
-                    .or_else(|| f.get("size").and_then(|x| x.as_u64()))
-                    .unwrap_or(0);
-                out.push(DatasetFile {
-                    name: name.to_string(),
-                    size,
-                });
-            }
+impl DatasetFile {
+    /// Describes a file in the local cache, with its modification time from the file system.
+    fn from_local(name: String, metadata: &fs::Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| crate::utils::format_rfc3339(d.as_secs()));
+        Self {
+            name,
+            size: metadata.len(),
+            modified,
+            columns: None,
         }
     }
-    Ok(out)
+}
+
+fn list_dataset_files_from_metadata(dataset_path: &str) -> Result<Vec<DatasetFile>, GaggleError> {
+    Ok(super::metadata::get_dataset_metadata_typed(dataset_path)?.files)
 }
 
 /// A struct that represents the metadata stored in the `.downloaded` marker file.
@@ -60,24 +99,51 @@ fn list_dataset_files_from_metadata(dataset_path: &str) -> Result<Vec<DatasetFil
 struct CacheMetadata {
     /// The time the dataset was downloaded, in seconds since the Unix epoch.
     downloaded_at_secs: u64,
+    /// The time the dataset was last served from the cache, in seconds since the Unix epoch.
+    /// Markers written by older versions do not have it, which is stored as `0`.
+    #[serde(default)]
+    last_accessed_secs: u64,
+    /// The number of times the dataset was served from the cache.
+    #[serde(default)]
+    access_count: u64,
     /// The path to the dataset.
     dataset_path: String,
     /// The size of the dataset in megabytes.
     size_mb: u64,
     /// The version of the dataset.
     version: Option<String>,
+    /// The format of the downloaded archive (`zip`, `tar.gz`, `tar.bz2`, or `tar.xz`).
+    archive_format: Option<String>,
+    /// SHA-256 checksums (hex) of the dataset files, keyed by relative path, as recorded
+    /// by the last integrity check.
+    #[serde(default)]
+    file_checksums: HashMap<String, String>,
+    /// Modification times of the dataset files when their checksums were computed, in
+    /// seconds since the Unix epoch.
+    #[serde(default)]
+    file_mtimes: HashMap<String, u64>,
+    /// The time of the last integrity check, in seconds since the Unix epoch.
+    #[serde(default)]
+    checksum_computed_at_secs: u64,
 }
 
 impl CacheMetadata {
     fn new(dataset_path: String, size_mb: u64) -> Self {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
         Self {
-            downloaded_at_secs: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
+            downloaded_at_secs: now,
+            last_accessed_secs: now,
+            access_count: 0,
             dataset_path,
             size_mb,
             version: None,
+            archive_format: None,
+            file_checksums: HashMap::new(),
+            file_mtimes: HashMap::new(),
+            checksum_computed_at_secs: 0,
         }
     }
 
@@ -88,16 +154,318 @@ impl CacheMetadata {
             .as_secs();
         now.saturating_sub(self.downloaded_at_secs)
     }
+
+    /// Returns the last-accessed time, falling back to the download time for old markers.
+    fn last_accessed(&self) -> u64 {
+        if self.last_accessed_secs == 0 {
+            self.downloaded_at_secs
+        } else {
+            self.last_accessed_secs
+        }
+    }
+}
+
+/// Records that the cached dataset in `dataset_dir` was served, for the eviction policies.
+///
+/// Failures are logged and otherwise ignored.
+fn record_cache_access(dataset_dir: &Path) {
+    let marker_file = dataset_dir.join(".downloaded");
+    let Ok(content) = fs::read_to_string(&marker_file) else {
+        return; // Not a complete download
+    };
+    let Ok(mut metadata) = serde_json::from_str::<CacheMetadata>(&content) else {
+        return;
+    };
+
+    metadata.last_accessed_secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    metadata.access_count = metadata.access_count.saturating_add(1);
+    let written = serde_json::to_string(&metadata)
+        .map_err(GaggleError::from)
+        .and_then(|json| crate::utils::write_marker_atomic(&marker_file, &json));
+    if let Err(e) = written {
+        debug!(path = %marker_file.display(), error = %e, "failed to record cache access");
+    }
+}
+
+/// Checks the dataset size reported by the metadata against `limit_mb`.
+///
+/// If the size cannot be determined, a warning is logged and the download proceeds.
+fn check_dataset_size(dataset_path: &str, limit_mb: u64) -> Result<(), GaggleError> {
+    match super::metadata::get_dataset_metadata_typed(dataset_path) {
+        Ok(meta) => {
+            if let Some(total_bytes) = meta.total_bytes {
+                let size_mb = total_bytes / 1_048_576;
+                if size_mb > limit_mb {
+                    return Err(GaggleError::DatasetTooLarge {
+                        dataset_path: dataset_path.to_string(),
+                        size_mb,
+                        limit_mb,
+                    });
+                }
+            }
+        }
+        Err(e) => {
+            warn!(dataset = dataset_path, error = %e, "could not read dataset size; proceeding with download");
+        }
+    }
+    Ok(())
+}
+
+/// Checks a cached dataset against `GAGGLE_CACHE_TTL_SECS`.
+///
+/// If the marker is older than the TTL, or `GAGGLE_ALWAYS_CHECK_UPDATES` is set, the latest
+/// version is looked up. When the cached copy is still the latest version, the marker
+/// timestamp is refreshed. Returns `true` only if the cache expired and is outdated, so it
+/// should be downloaded again. This is skipped in offline mode, and a failed version lookup
+/// keeps serving the cached copy.
+fn revalidate_if_expired(dataset_path: &str, cache_dir: &Path) -> bool {
+    let always_check = crate::config::always_check_updates();
+    let ttl_secs = crate::config::cache_ttl_secs();
+    if (!always_check && ttl_secs.is_none()) || crate::config::offline_mode() {
+        return false;
+    }
+
+    let marker_file = cache_dir.join(".downloaded");
+    let Some(mut metadata) = fs::read_to_string(&marker_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CacheMetadata>(&content).ok())
+    else {
+        return false;
+    };
+    if !always_check && ttl_secs.is_some_and(|ttl| metadata.age_seconds() <= ttl) {
+        return false;
+    }
+
+    // Bypass the in-memory metadata cache so the version is really rechecked
+    super::metadata::invalidate_metadata(dataset_path);
+    let current_version = match super::metadata::get_current_version(dataset_path) {
+        Ok(v) if v != "unknown" => v,
+        Ok(_) => return false,
+        Err(e) => {
+            warn!(dataset = dataset_path, error = %e, "could not revalidate expired cache; serving cached copy");
+            return false;
+        }
+    };
+    if metadata.version.as_deref() != Some(current_version.as_str()) {
+        return true;
+    }
+
+    metadata.downloaded_at_secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if let Ok(json) = serde_json::to_string(&metadata) {
+        if let Err(e) = crate::utils::write_marker_atomic(&marker_file, &json) {
+            warn!(path = %marker_file.display(), error = %e, "failed to refresh cache marker");
+        }
+    }
+    false
+}
+
+/// Makes room for `dataset_path` under the hard cache limit of `limit_mb`, with at least
+/// `GAGGLE_MIN_FREE_CACHE_MB` to spare.
+///
+/// The dataset size is read from its metadata, and datasets are evicted in eviction policy
+/// order until it fits. `cache_dir` (the download target) is never evicted, and its current
+/// copy is not counted, since the download replaces it. If the size cannot be determined,
+/// a warning is logged and the download proceeds.
+fn reserve_cache_space(
+    dataset_path: &str,
+    cache_dir: &Path,
+    limit_mb: u64,
+) -> Result<(), GaggleError> {
+    check_cache_space(dataset_path, cache_dir, limit_mb, true)
+}
+
+/// Checks that downloading `dataset_path` keeps the cache within `GAGGLE_CACHE_SIZE_LIMIT`
+/// like [`reserve_cache_space`], without evicting anything.
+#[cfg(test)]
+fn pre_download_cache_check(dataset_path: &str) -> Result<(), GaggleError> {
+    let Some(limit_mb) = crate::config::cache_size_limit_mb() else {
+        return Ok(());
+    };
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let cache_dir = dataset_cache_dir(&owner, &dataset);
+    check_cache_space(dataset_path, &cache_dir, limit_mb, false)
+}
+
+/// The check behind [`reserve_cache_space`], which evicts datasets only with `evict`.
+fn check_cache_space(
+    dataset_path: &str,
+    cache_dir: &Path,
+    limit_mb: u64,
+    evict: bool,
+) -> Result<(), GaggleError> {
+    let total_bytes = match super::metadata::get_dataset_metadata_typed(dataset_path) {
+        Ok(meta) => meta.total_bytes,
+        Err(e) => {
+            warn!(dataset = dataset_path, error = %e, "could not read dataset size; skipping hard cache limit check");
+            return Ok(());
+        }
+    };
+    let Some(total_bytes) = total_bytes else {
+        warn!(
+            dataset = dataset_path,
+            "dataset metadata has no size; skipping hard cache limit check"
+        );
+        return Ok(());
+    };
+    // Round up so a hard limit never undercounts the download
+    let required_mb = total_bytes
+        .div_ceil(1_048_576)
+        .saturating_add(crate::config::min_free_cache_mb());
+
+    let datasets = get_cached_datasets()?;
+    let own_mb: u64 = datasets
+        .iter()
+        .filter(|(dir, _)| dir == cache_dir)
+        .map(|(_, meta)| meta.size_mb)
+        .sum();
+    let total_mb: u64 = datasets.iter().map(|(_, meta)| meta.size_mb).sum();
+    let mut usage_mb = total_mb.saturating_sub(own_mb);
+    if evict && usage_mb.saturating_add(required_mb) > limit_mb && required_mb <= limit_mb {
+        let target_mb = limit_mb.saturating_sub(required_mb).saturating_add(own_mb);
+        usage_mb = evict_lru(target_mb, Some(cache_dir))?.saturating_sub(own_mb);
+    }
+
+    let available_mb = limit_mb.saturating_sub(usage_mb);
+    if required_mb > available_mb {
+        return Err(GaggleError::CacheLimitExceeded {
+            dataset_path: dataset_path.to_string(),
+            required_mb,
+            available_mb,
+        });
+    }
+    Ok(())
+}
+
+/// Free space needed to download and extract an archive, as a multiple of its size: the
+/// archive itself, its extracted contents (usually larger than the archive), and headroom
+const ARCHIVE_SPACE_FACTOR: u64 = 3;
+
+/// Fails early with an `IoError` if `dir` has less than `required_bytes` of free space.
+///
+/// If the free space cannot be determined, the check is skipped.
+fn ensure_free_space(dir: &Path, required_bytes: u64, what: &str) -> Result<(), GaggleError> {
+    match fs4::available_space(dir) {
+        Ok(available_bytes) => check_free_space(dir, required_bytes, available_bytes, what),
+        Err(e) => {
+            debug!(error = %e, dir = %dir.display(), "cannot determine free disk space; skipping check");
+            Ok(())
+        }
+    }
+}
+
+fn check_free_space(
+    dir: &Path,
+    required_bytes: u64,
+    available_bytes: u64,
+    what: &str,
+) -> Result<(), GaggleError> {
+    if required_bytes > available_bytes {
+        return Err(GaggleError::IoError(format!(
+            "Not enough free disk space to download {}: about {} MB is needed in {}, but only {} MB is available",
+            what,
+            required_bytes.div_ceil(1024 * 1024),
+            dir.display(),
+            available_bytes / (1024 * 1024)
+        )));
+    }
+    Ok(())
+}
+
+/// Acquires the download lock for `key`, or waits for the thread holding it to finish.
+///
+/// If the lock is free, `is_done` is checked while holding the lock map; if it returns `true`,
+/// the work was completed by a previous holder and `None` is returned. Otherwise, the caller
+/// becomes the downloader and must report the outcome with [`LockGuard::finish`].
+///
+/// If another thread holds the lock, this blocks until that thread finishes and then returns
+/// `None` on success or the downloader's error on failure. Waiting is bounded by
+/// `GAGGLE_DOWNLOAD_WAIT_TIMEOUT` (`0` waits indefinitely).
+fn acquire_download_lock(
+    key: &str,
+    what: &str,
+    is_done: impl Fn() -> bool,
+) -> Result<Option<LockGuard>, GaggleError> {
+    let slot = {
+        let mut locks = DOWNLOAD_LOCKS.lock();
+        match locks.get(key) {
+            Some(slot) => Arc::clone(slot),
+            None => {
+                // While holding the lock map, check completion to avoid a race with the previous holder
+                if is_done() {
+                    return Ok(None);
+                }
+                let slot = Arc::new(DownloadSlot::new());
+                locks.insert(key.to_string(), Arc::clone(&slot));
+                return Ok(Some(LockGuard {
+                    key: key.to_string(),
+                    slot,
+                }));
+            }
+        }
+    };
+
+    let timeout_ms = crate::config::download_wait_timeout_ms();
+    let deadline = (timeout_ms > 0).then(|| Instant::now() + Duration::from_millis(timeout_ms));
+    let mut state = slot.state.lock();
+    loop {
+        match &*state {
+            DownloadState::Succeeded => return Ok(None),
+            DownloadState::Failed(err) => return Err(err.clone()),
+            DownloadState::InProgress => {}
+        }
+        match deadline {
+            Some(deadline) => {
+                if slot.finished.wait_until(&mut state, deadline).timed_out()
+                    && matches!(*state, DownloadState::InProgress)
+                {
+                    return Err(GaggleError::HttpRequestError(format!(
+                        "Timeout waiting for download of {} after {} ms",
+                        what, timeout_ms
+                    )));
+                }
+            }
+            None => slot.finished.wait(&mut state),
+        }
+    }
 }
 
-/// Guard to guarantee download lock is released
+/// Guard to guarantee download lock is released and waiters are woken up
 struct LockGuard {
     key: String,
+    slot: Arc<DownloadSlot>,
+}
+
+impl LockGuard {
+    /// Reports the outcome of the download to the threads waiting on it.
+    fn finish<T>(&self, result: &Result<T, GaggleError>) {
+        self.slot.finish(match result {
+            Ok(_) => DownloadState::Succeeded,
+            Err(err) => DownloadState::Failed(err.clone()),
+        });
+    }
 }
 
 impl Drop for LockGuard {
     fn drop(&mut self) {
-        DOWNLOAD_LOCKS.lock().remove(&self.key);
+        let mut locks = DOWNLOAD_LOCKS.lock();
+        if locks
+            .get(&self.key)
+            .is_some_and(|slot| Arc::ptr_eq(slot, &self.slot))
+        {
+            locks.remove(&self.key);
+        }
+        drop(locks);
+        // Waiters must never block forever, even if the holder panicked
+        self.slot
+            .finish(DownloadState::Failed(GaggleError::HttpRequestError(
+                format!("Download of {} was abandoned", self.key),
+            )));
     }
 }
 
@@ -115,15 +483,263 @@ pub fn download_dataset(dataset_path: &str) -> Result<PathBuf, GaggleError> {
     // Reconstruct base path without version for internal use
     let base_path = format!("{}/{}", owner, dataset);
 
-    download_dataset_version(&base_path, version)
+    super::api::logged_operation("download_dataset", dataset_path, || {
+        download_dataset_version(&base_path, version, false)
+    })
+}
+
+/// How [`download_dataset_with_mode`] treats a cached copy of a dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadMode {
+    /// Serve the cached copy if there is one, like [`download_dataset`].
+    CacheFirst,
+    /// Download again only if the cached copy is not the latest version.
+    RefreshIfOutdated,
+    /// Always download again, like [`update_dataset`].
+    Force,
+}
+
+impl DownloadMode {
+    /// The mode for a number passed over FFI: `0` is cache first, `1` is refresh if
+    /// outdated, and `2` is force.
+    pub fn from_i32(mode: i32) -> Option<Self> {
+        match mode {
+            0 => Some(DownloadMode::CacheFirst),
+            1 => Some(DownloadMode::RefreshIfOutdated),
+            2 => Some(DownloadMode::Force),
+            _ => None,
+        }
+    }
+}
+
+/// Downloads a Kaggle dataset, treating a cached copy as `mode` says.
+///
+/// With [`DownloadMode::RefreshIfOutdated`], the latest version is looked up and the dataset
+/// is downloaded again only if the cached copy is another version. A cached copy is kept when
+/// the latest version cannot be determined. Versioned paths never go stale, so they are
+/// served from the cache like with [`DownloadMode::CacheFirst`].
+pub fn download_dataset_with_mode(
+    dataset_path: &str,
+    mode: DownloadMode,
+) -> Result<PathBuf, GaggleError> {
+    match mode {
+        DownloadMode::CacheFirst => download_dataset(dataset_path),
+        DownloadMode::Force => update_dataset(dataset_path),
+        DownloadMode::RefreshIfOutdated => {
+            let (owner, dataset, version) = super::parse_dataset_path_with_version(dataset_path)?;
+            let cache_dir = dataset_cache_dir(&owner, &dataset);
+            if version.is_some() || !cache_dir.join(".downloaded").exists() {
+                return download_dataset(dataset_path);
+            }
+
+            let base_path = format!("{}/{}", owner, dataset);
+            // Bypass the in-memory metadata cache so the version is really rechecked
+            super::metadata::invalidate_metadata(&base_path);
+            if super::metadata::get_current_version(&base_path)? == "unknown" {
+                warn!(
+                    dataset = dataset_path,
+                    "latest version is unknown; keeping the cached copy"
+                );
+                return download_dataset(dataset_path);
+            }
+            if is_dataset_current(&base_path)? {
+                return download_dataset(dataset_path);
+            }
+            debug!(
+                dataset = dataset_path,
+                "cached copy is outdated; downloading again"
+            );
+            update_dataset(dataset_path)
+        }
+    }
+}
+
+/// Downloads a Kaggle dataset without blocking the calling thread.
+///
+/// This is the async counterpart of [`download_dataset`]. It shares the cache layout,
+/// download locks, and `.downloaded` marker with the blocking API, so datasets downloaded
+/// by either are reused by both. The archive is streamed with the async client, while
+/// lock waits and extraction run on Tokio's blocking thread pool.
+#[cfg(feature = "async")]
+pub async fn download_dataset_async(dataset_path: &str) -> Result<PathBuf, GaggleError> {
+    let (owner, dataset, version) = super::parse_dataset_path_with_version(dataset_path)?;
+    let base_path = format!("{}/{}", owner, dataset);
+
+    let path = base_path.clone();
+    let pending =
+        match crate::utils::run_blocking(move || begin_dataset_download(&path, version, false))
+            .await?
+        {
+            DownloadStart::Ready(dir) => return Ok(dir),
+            DownloadStart::Pending(pending) => pending,
+        };
+
+    let fetched = fetch_archive_async(
+        &base_path,
+        &pending.creds,
+        pending.version.as_deref(),
+        &pending.cache_dir,
+        "dataset",
+    )
+    .await;
+    crate::utils::run_blocking(move || pending.complete(fetched)).await
 }
 
-/// Download a specific version of a Kaggle dataset
+/// Download a specific version of a Kaggle dataset. With `force`, a cached copy is
+/// downloaded again, and kept if the new download fails.
 fn download_dataset_version(
     dataset_path: &str,
     version: Option<String>,
+    force: bool,
+) -> Result<PathBuf, GaggleError> {
+    let pending = match begin_dataset_download(dataset_path, version, force)? {
+        DownloadStart::Ready(dir) => return Ok(dir),
+        DownloadStart::Pending(pending) => pending,
+    };
+
+    let fetched = fetch_archive(
+        dataset_path,
+        &pending.creds,
+        pending.version.as_deref(),
+        &pending.cache_dir,
+        "dataset",
+    );
+    pending.complete(fetched)
+}
+
+/// Downloads a dataset directly into `target_dir`, bypassing the cache.
+///
+/// The dataset is fetched, validated, and extracted like [`download_dataset`], but no marker
+/// files are written and the cache size is neither accounted for nor enforced. A `target_dir`
+/// that already has files is refused unless `overwrite` is set, in which case files with the
+/// same names are replaced. Concurrent downloads of the same dataset into the same directory
+/// share one request.
+///
+/// Returns `target_dir`.
+pub fn download_dataset_to(
+    dataset_path: &str,
+    target_dir: &Path,
+    overwrite: bool,
 ) -> Result<PathBuf, GaggleError> {
+    let (owner, dataset, version) = super::parse_dataset_path_with_version(dataset_path)?;
+    let base_path = format!("{}/{}", owner, dataset);
+    check_copy_destination(target_dir)?;
+    let target = std::path::absolute(target_dir)?;
+
+    if crate::config::dry_run() {
+        let url = archive_url(&base_path, version.as_deref())?;
+        debug!(dataset = dataset_path, url = %sanitize_message(&url), target = %target.display(), "dry run; skipping download");
+        return Ok(target);
+    }
+    if crate::config::offline_mode() {
+        return Err(GaggleError::HttpRequestError(format!(
+            "Offline mode enabled; cannot download '{}'. Unset GAGGLE_OFFLINE to enable network.",
+            dataset_path
+        )));
+    }
+
     let creds = get_credentials()?;
+    super::metadata::ensure_dataset_exists(&base_path)?;
+    if let Some(limit_mb) = crate::config::max_dataset_size_mb() {
+        check_dataset_size(&base_path, limit_mb)?;
+    }
+
+    let lock_key = match version {
+        Some(ref v) => format!(
+            "{}-v{}@{}",
+            dataset_lock_key(&owner, &dataset),
+            v,
+            target.display()
+        ),
+        None => format!(
+            "{}@{}",
+            dataset_lock_key(&owner, &dataset),
+            target.display()
+        ),
+    };
+    let Some(guard) = acquire_download_lock(&lock_key, dataset_path, || false)? else {
+        return Ok(target);
+    };
+
+    let created = !target.exists();
+    let result = (|| -> Result<PathBuf, GaggleError> {
+        if !overwrite && !created && fs::read_dir(&target)?.next().is_some() {
+            return Err(GaggleError::IoError(format!(
+                "Download target {} is not empty",
+                target.display()
+            )));
+        }
+        fs::create_dir_all(&target)?;
+
+        let (archive_path, format) = fetch_archive(
+            &base_path,
+            &creds,
+            version.as_deref(),
+            &target,
+            ".gaggle-download",
+        )?;
+        let extracted = extract_archive(&archive_path, &target, format);
+        let _ = fs::remove_file(&archive_path);
+        if extracted? == 0 {
+            return Err(GaggleError::ZipError(format!(
+                "{} archive contained no files",
+                format.as_str()
+            )));
+        }
+        debug!(dataset = dataset_path, target = %target.display(), "downloaded dataset outside the cache");
+        Ok(target.clone())
+    })();
+    if result.is_err() && created {
+        // Only a directory created by this download is removed, never the caller's files
+        let _ = fs::remove_dir_all(&target);
+    }
+    guard.finish(&result);
+    result
+}
+
+/// The state of a download once the cache and the download lock have been checked.
+enum DownloadStart<P> {
+    /// The result is already cached, or another thread finished downloading it meanwhile.
+    Ready(PathBuf),
+    /// This caller holds the download lock and must fetch the data.
+    Pending(P),
+}
+
+/// A dataset download that holds its lock and is waiting for the archive to be fetched.
+struct PendingDownload {
+    dataset_path: String,
+    version: Option<String>,
+    creds: super::credentials::KaggleCredentials,
+    cache_dir: PathBuf,
+    guard: LockGuard,
+    /// Whether a cached copy is being replaced, which is kept if the download fails
+    replacing: bool,
+}
+
+/// Directory of a dataset in the cache, `datasets/{owner}/{subdir}`, where `subdir` is the
+/// dataset name with a `-v{n}` suffix for a pinned version. Kaggle slugs are
+/// case-insensitive, so both are lowercased and every casing of a path shares one copy.
+pub(crate) fn dataset_cache_dir(owner: &str, subdir: &str) -> PathBuf {
+    crate::config::cache_dir_runtime()
+        .join("datasets")
+        .join(owner.to_lowercase())
+        .join(subdir.to_lowercase())
+}
+
+/// Key of the download lock of a dataset, `{owner}/{subdir}` lowercased like
+/// [`dataset_cache_dir`].
+fn dataset_lock_key(owner: &str, subdir: &str) -> String {
+    format!("{}/{}", owner, subdir).to_lowercase()
+}
+
+/// Runs the checks before a dataset archive is fetched: the cache fast path, offline mode,
+/// the existence and size checks, the download lock, and the hard cache limit. With
+/// `force`, the cache fast path is skipped.
+fn begin_dataset_download(
+    dataset_path: &str,
+    version: Option<String>,
+    force: bool,
+) -> Result<DownloadStart<PendingDownload>, GaggleError> {
     let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
 
     // Cache directory includes version if specified
@@ -133,15 +749,37 @@ fn download_dataset_version(
         dataset.clone()
     };
 
-    let cache_dir = crate::config::cache_dir_runtime()
-        .join("datasets")
-        .join(&owner)
-        .join(&cache_subdir);
+    let cache_dir = dataset_cache_dir(&owner, &cache_subdir);
+
+    // Dry run: report the path and the URL that would be downloaded, without any request
+    if crate::config::dry_run() {
+        let url = archive_url(dataset_path, version.as_deref())?;
+        debug!(dataset = dataset_path, url = %sanitize_message(&url), "dry run; skipping download");
+        fs::create_dir_all(&cache_dir)?;
+        let marker = serde_json::json!({"dry_run": true, "would_download_url": url});
+        crate::utils::write_marker_atomic(
+            &cache_dir.join(DRY_RUN_MARKER),
+            &serde_json::to_string(&marker)?,
+        )?;
+        return Ok(DownloadStart::Ready(cache_dir));
+    }
+
+    let creds = get_credentials()?;
 
     // Check if already downloaded (fast path)
     let marker_file = cache_dir.join(".downloaded");
-    if marker_file.exists() {
-        return Ok(cache_dir);
+    if marker_file.exists() && !force {
+        // Pinned versions never change, so only the latest version can go stale
+        if version.is_some() || !revalidate_if_expired(dataset_path, &cache_dir) {
+            record_cache_access(&cache_dir);
+            metrics::incr(Counter::CacheHits);
+            return Ok(DownloadStart::Ready(cache_dir));
+        }
+        debug!(
+            dataset = dataset_path,
+            "cached dataset expired and is outdated; downloading again"
+        );
+        fs::remove_dir_all(&cache_dir)?;
     }
 
     // Offline mode: if not cached, fail fast
@@ -152,418 +790,1143 @@ fn download_dataset_version(
         )));
     }
 
+    // Catch mistyped paths before the download endpoint is retried
+    super::metadata::ensure_dataset_exists(dataset_path)?;
+
+    // Refuse datasets larger than the configured per-download limit
+    if let Some(limit_mb) = crate::config::max_dataset_size_mb() {
+        check_dataset_size(dataset_path, limit_mb)?;
+    }
+
     // Use a lock per dataset path (including version) to prevent concurrent downloads
-    let lock_key = if let Some(ref v) = version {
-        format!("{}/{}-v{}", owner, dataset, v)
-    } else {
-        format!("{}/{}", owner, dataset)
-    };
+    let lock_key = dataset_lock_key(&owner, &cache_subdir);
 
-    // Acquire a "lock" by inserting into the map
-    // If another thread is downloading, wait with timeout (configurable)
-    let poll_ms = crate::config::download_wait_poll_interval_ms();
-    let timeout_ms = crate::config::download_wait_timeout_ms();
-    let max_attempts: u64 = if poll_ms == 0 {
-        0
-    } else {
-        timeout_ms / poll_ms
+    // Acquire a "lock" by inserting into the map; another thread may finish the download meanwhile
+    let Some(guard) =
+        acquire_download_lock(&lock_key, dataset_path, || !force && marker_file.exists())?
+    else {
+        return Ok(DownloadStart::Ready(cache_dir));
     };
-    let mut wait_attempts: u64 = 0;
 
-    loop {
-        let mut locks = DOWNLOAD_LOCKS.lock();
-        // While holding the lock, check marker existence to avoid race
-        if marker_file.exists() {
-            return Ok(cache_dir.clone());
+    let prepared = (|| -> Result<bool, GaggleError> {
+        // Double-check after acquiring lock
+        if !force && marker_file.exists() {
+            return Ok(true);
         }
-        if !locks.contains_key(&lock_key) {
-            locks.insert(lock_key.clone(), ());
-            break;
-        }
-        // Release lock and sleep briefly before retrying
-        drop(locks);
 
-        // Check timeout to prevent indefinite waiting
-        if max_attempts > 0 {
-            if wait_attempts >= max_attempts {
-                return Err(GaggleError::HttpRequestError(format!(
-                    "Timeout waiting for download of {}. Another thread may have stalled.",
-                    dataset_path
-                )));
+        // With a hard limit, make room before downloading instead of cleaning up afterwards
+        if !crate::config::cache_limit_is_soft() {
+            if let Some(limit_mb) = crate::config::cache_size_limit_mb() {
+                reserve_cache_space(dataset_path, &cache_dir, limit_mb)?;
             }
-            wait_attempts = wait_attempts.saturating_add(1);
         }
 
-        sleep(Duration::from_millis(poll_ms.max(1)));
+        fs::create_dir_all(&cache_dir)?;
+        Ok(false)
+    })();
+
+    match prepared {
+        Ok(false) => {
+            metrics::incr(Counter::CacheMisses);
+            metrics::incr(Counter::DownloadsStarted);
+            Ok(DownloadStart::Pending(PendingDownload {
+                dataset_path: dataset_path.to_string(),
+                version,
+                creds,
+                replacing: marker_file.exists(),
+                cache_dir,
+                guard,
+            }))
+        }
+        Ok(true) => {
+            let result = Ok(DownloadStart::Ready(cache_dir));
+            guard.finish(&result);
+            result
+        }
+        Err(e) => {
+            let result = Err(e);
+            guard.finish(&result);
+            result
+        }
     }
+}
 
-    // Make sure we clean up the lock when done
-    let _guard = LockGuard {
-        key: lock_key.clone(),
-    };
-
-    // Double-check after acquiring lock
-    if marker_file.exists() {
-        return Ok(cache_dir.clone());
+impl PendingDownload {
+    /// Extracts the fetched archive, writes the `.downloaded` marker, and releases the
+    /// download lock with the outcome.
+    fn complete(
+        self,
+        fetched: Result<(PathBuf, ArchiveFormat), GaggleError>,
+    ) -> Result<PathBuf, GaggleError> {
+        let result = self.extract(fetched);
+        metrics::incr(if result.is_ok() {
+            Counter::DownloadsCompleted
+        } else {
+            Counter::DownloadsFailed
+        });
+        self.guard.finish(&result);
+        result
     }
 
-    fs::create_dir_all(&cache_dir)?;
+    fn extract(
+        &self,
+        fetched: Result<(PathBuf, ArchiveFormat), GaggleError>,
+    ) -> Result<PathBuf, GaggleError> {
+        let cache_dir = &self.cache_dir;
+        let (archive_path, format) = match fetched {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                // Only removes the directory if nothing else is cached in it
+                let _ = fs::remove_dir(cache_dir);
+                return Err(e);
+            }
+        };
 
-    // Build URL with version if specified
-    let url = if let Some(ref v) = version {
-        format!(
-            "{}/datasets/download/{}/{}/versions/{}",
-            get_api_base(),
-            owner,
-            dataset,
-            v
-        )
-    } else {
-        format!("{}/datasets/download/{}/{}", get_api_base(), owner, dataset)
-    };
+        // Extract into a staging directory, so a failure never leaves a half-extracted
+        // dataset in the cache, then move it into place. This also drops the state of
+        // earlier filtered downloads and dry runs.
+        let staged = new_staging_dir().and_then(|staging| {
+            let result = extract_archive(&archive_path, &staging, format).and_then(|extracted| {
+                if extracted == 0 {
+                    return Err(GaggleError::ZipError(format!(
+                        "{} archive contained no files",
+                        format.as_str()
+                    )));
+                }
+                let _ = fs::remove_file(&archive_path);
+                publish_staged(&staging, cache_dir)
+            });
+            if result.is_err() {
+                let _ = fs::remove_dir_all(&staging);
+            }
+            result
+        });
+        if let Err(err) = staged {
+            // Best-effort cleanup of corrupt archive and partial files
+            let _ = fs::remove_file(&archive_path);
+            if !self.replacing {
+                let _ = fs::remove_dir_all(cache_dir);
+            }
+            return Err(err);
+        }
 
-    debug!(%url, "downloading dataset");
+        // Calculate dataset size in MB
+        let dataset_size_mb = crate::utils::calculate_dir_size_no_follow(cache_dir)
+            .unwrap_or(0)
+            .saturating_div(1024 * 1024);
+
+        // Create marker file with metadata including version
+        let mut metadata = CacheMetadata::new(self.dataset_path.clone(), dataset_size_mb);
+        // Use specified version, or fetch current version from API
+        metadata.version = self
+            .version
+            .clone()
+            .or_else(|| super::metadata::get_current_version(&self.dataset_path).ok());
+        metadata.archive_format = Some(format.as_str().to_string());
+        crate::utils::write_marker_atomic(
+            &cache_dir.join(".downloaded"),
+            &serde_json::to_string(&metadata)?,
+        )?;
+
+        // Enforce cache limit after successful download (soft limit)
+        if crate::config::cache_limit_is_soft() {
+            let _ = enforce_cache_limit(); // Don't fail the download if cleanup fails
+        }
 
-    let client = build_client()?;
-    let mut response = with_retries(|| {
-        client
-            .get(&url)
-            .basic_auth(&creds.username, Some(&creds.key))
-            .send()
-            .map_err(|e| GaggleError::HttpRequestError(e.to_string()))
-    })?;
+        Ok(cache_dir.clone())
+    }
+}
 
-    if !response.status().is_success() {
-        return Err(GaggleError::HttpRequestError(format!(
-            "Failed to download dataset: HTTP {}",
-            response.status()
-        )));
+/// Cache state files of a dataset directory that are kept when the dataset is downloaded
+/// again
+const KEPT_STATE_FILES: &[&str] = &[
+    PINNED_MARKER,
+    super::metadata::SCHEMA_FILE,
+    super::metadata::README_FILE,
+    super::metadata::LICENSE_FILE,
+];
+
+/// Staging directories older than this are left by a killed process, and are removed by
+/// [`cleanup_staging_dir`]
+const STAGING_MAX_AGE: Duration = Duration::from_secs(3600);
+
+/// Creates an empty directory in the staging directory (see GAGGLE_STAGING_DIR) for one
+/// extraction. The process ID and a counter keep concurrent extractions apart.
+fn new_staging_dir() -> Result<PathBuf, GaggleError> {
+    static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let root = crate::config::staging_dir();
+    fs::create_dir_all(&root)?;
+    loop {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = root.join(format!("{}-{}", std::process::id(), n));
+        match fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            // Left by an earlier process with the same ID
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
     }
+}
 
-    // Stream response to a temporary file to avoid large memory usage
-    let zip_path = cache_dir.join("dataset.zip");
-    let zip_file = fs::File::create(&zip_path)?;
-    let mut writer = BufWriter::new(zip_file);
-    response
-        .copy_to(&mut writer)
-        .map_err(|e| GaggleError::HttpRequestError(e.to_string()))?;
-    writer.flush().ok();
-
-    // Extract ZIP - require at least one file extracted; cleanup on failure
-    let extracted = match extract_zip(&zip_path, &cache_dir) {
-        Ok(n) => n,
-        Err(err) => {
-            // Best-effort cleanup of corrupt zip and partial files
-            let _ = fs::remove_file(&zip_path);
-            let _ = fs::remove_dir_all(&cache_dir);
-            return Err(err);
+/// Moves a dataset extracted into `staging` to `cache_dir`, replacing what `cache_dir`
+/// held except [`KEPT_STATE_FILES`].
+fn publish_staged(staging: &Path, cache_dir: &Path) -> Result<(), GaggleError> {
+    for name in KEPT_STATE_FILES {
+        let kept = cache_dir.join(name);
+        if kept.is_file() {
+            fs::copy(&kept, staging.join(name))?;
         }
-    };
-    if extracted == 0 {
-        // Clean up if nothing extracted
-        let _ = fs::remove_file(&zip_path);
-        let _ = fs::remove_dir_all(&cache_dir);
-        return Err(GaggleError::ZipError("ZIP contained no files".to_string()));
     }
+    if cache_dir.exists() {
+        fs::remove_dir_all(cache_dir)?;
+    }
+    crate::utils::move_dir(staging, cache_dir)?;
+    Ok(())
+}
 
-    // Clean up ZIP file
-    let _ = fs::remove_file(&zip_path);
+/// Removes the staging directories that were last modified more than an hour ago, which
+/// are left when a process is killed while extracting a dataset.
+///
+/// Returns the number of directories removed.
+pub fn cleanup_staging_dir() -> Result<usize, GaggleError> {
+    let root = crate::config::staging_dir();
+    let entries = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let stale = entry
+            .metadata()?
+            .modified()?
+            .elapsed()
+            .is_ok_and(|age| age > STAGING_MAX_AGE);
+        if stale {
+            fs::remove_dir_all(entry.path())?;
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        debug!(removed, dir = %root.display(), "removed stale staging directories");
+    }
+    Ok(removed)
+}
 
-    // Calculate dataset size in MB
-    let dataset_size_mb = crate::utils::calculate_dir_size(&cache_dir)
-        .unwrap_or(0)
-        .saturating_div(1024 * 1024);
+/// The result of [`migrate_cache_case`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CaseMigrationReport {
+    /// Cached datasets moved to their lowercase directory, as `owner/dataset` before the move.
+    pub moved: Vec<String>,
+    /// Cached datasets left in place because their lowercase directory already holds a copy.
+    pub conflicts: Vec<String>,
+}
 
-    // Create marker file with metadata including version
-    let mut metadata = CacheMetadata::new(dataset_path.to_string(), dataset_size_mb);
-    // Use specified version, or fetch current version from API
-    metadata.version = version.or_else(|| super::metadata::get_current_version(dataset_path).ok());
-    fs::write(&marker_file, serde_json::to_string(&metadata)?)?;
+/// Moves cached datasets whose directories have uppercase letters, which were created
+/// before cache paths were lowercased, to their lowercase directories.
+///
+/// A dataset whose lowercase directory already exists is left in place and reported as a
+/// conflict, so no files are overwritten. The copy that should go can then be removed by
+/// hand. Downloads should not run during the migration.
+pub fn migrate_cache_case() -> Result<CaseMigrationReport, GaggleError> {
+    let root = crate::config::cache_dir_runtime().join("datasets");
+    let mut report = CaseMigrationReport::default();
+    let owners = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(e.into()),
+    };
 
-    // Enforce cache limit after successful download (soft limit)
-    if crate::config::cache_limit_is_soft() {
-        let _ = enforce_cache_limit(); // Don't fail the download if cleanup fails
+    let mut mixed = Vec::new();
+    for owner in owners {
+        let owner = owner?;
+        if !owner.file_type()?.is_dir() {
+            continue;
+        }
+        let owner_name = owner.file_name().to_string_lossy().into_owned();
+        for entry in fs::read_dir(owner.path())? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if owner_name != owner_name.to_lowercase() || name != name.to_lowercase() {
+                mixed.push((owner_name.clone(), name));
+            }
+        }
     }
-
-    Ok(cache_dir)
+    mixed.sort();
+
+    for (owner, name) in mixed {
+        let label = format!("{}/{}", owner, name);
+        let source = root.join(&owner).join(&name);
+        let target = dataset_cache_dir(&owner, &name);
+        // Moving through a temporary name also renames on case-insensitive file systems,
+        // where the source and the target are the same directory
+        let tmp = root.join(format!(".case-migration-{}", std::process::id()));
+        fs::rename(&source, &tmp)?;
+        if target.exists() {
+            fs::rename(&tmp, &source)?;
+            warn!(dataset = %label, "cache holds another casing of this dataset; leaving it in place");
+            report.conflicts.push(label);
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&tmp, &target)?;
+        debug!(dataset = %label, to = %target.display(), "moved cached dataset to lowercase path");
+        report.moved.push(label);
+        // Removes the old owner directory once it is empty
+        let _ = fs::remove_dir(root.join(&owner));
+    }
+    Ok(report)
 }
 
-/// Downloads a single file from a Kaggle dataset into the cache, without extracting the entire archive.
-pub fn download_single_file(dataset_path: &str, filename: &str) -> Result<PathBuf, GaggleError> {
-    // Validate dataset path and filename to prevent traversal
-    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
-    use std::path::Component;
-    let fname_path = Path::new(filename);
-    if fname_path.is_absolute() {
-        return Err(GaggleError::InvalidDatasetPath(
-            "Absolute filenames are not allowed".to_string(),
-        ));
+/// Moves the cache directory to `new_dir` without downloading the cached datasets again.
+///
+/// `new_dir` must not be inside the current cache, and must either not exist or be an empty
+/// directory. The cache is renamed where possible, and copied and then removed where
+/// renaming fails, as it does across file systems. The new location is written to the
+/// cache override file (see `GAGGLE_CACHE_OVERRIDE_FILE`), which takes precedence over
+/// GAGGLE_CACHE_DIR in this and later processes. Downloads should not run during the move.
+pub fn move_cache_dir(new_dir: &Path) -> Result<(), GaggleError> {
+    let current = crate::config::cache_dir_runtime();
+    let current = match fs::canonicalize(&current) {
+        Ok(path) => path,
+        Err(_) => std::path::absolute(&current)?,
+    };
+    let target = std::path::absolute(new_dir)?;
+    let target = match (target.parent(), target.file_name()) {
+        (Some(parent), Some(name)) if parent.exists() => fs::canonicalize(parent)?.join(name),
+        _ => target,
+    };
+    let cannot_move = |reason: &str| {
+        GaggleError::IoError(format!(
+            "Cannot move cache to {}: {}",
+            target.display(),
+            reason
+        ))
+    };
+
+    if target.starts_with(&current) {
+        return Err(cannot_move("the directory is inside the current cache"));
     }
-    for comp in fname_path.components() {
-        match comp {
-            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
-                return Err(GaggleError::InvalidDatasetPath(
-                    "Filename must not contain parent or root components".to_string(),
-                ));
-            }
-            _ => {}
+    let Some(target_str) = target.to_str() else {
+        return Err(cannot_move("the path is not valid UTF-8"));
+    };
+    let Some(override_file) = crate::config::cache_override_file() else {
+        return Err(cannot_move(
+            "no config directory to record the new location in",
+        ));
+    };
+    if target.is_dir() {
+        if fs::read_dir(&target)?.next().is_some() {
+            return Err(cannot_move("the directory is not empty"));
         }
+        fs::remove_dir(&target)?;
+    } else if target.exists() {
+        return Err(cannot_move("the path is a file"));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
     }
 
-    // Offline mode: fail if file isn't already present
-    let base_dir = crate::config::cache_dir_runtime()
-        .join("datasets")
-        .join(&owner)
-        .join(&dataset);
-    let target_path = base_dir.join(fname_path);
-    if crate::config::offline_mode() {
-        if target_path.exists() {
-            return Ok(target_path);
+    if current.exists() {
+        crate::utils::move_dir(&current, &target)?;
+    } else {
+        fs::create_dir_all(&target)?;
+    }
+    if let Some(parent) = override_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    crate::utils::write_atomic(&override_file, target_str.as_bytes())?;
+    crate::config::set_cache_dir_override(Some(target.clone()));
+    debug!(from = %current.display(), to = %target.display(), "moved cache directory");
+    Ok(())
+}
+
+/// Downloads the archive of a dataset into `dir`, named `{stem}.{format}`.
+///
+/// Returns the archive's path and its format, which is detected from the magic bytes.
+fn fetch_archive(
+    dataset_path: &str,
+    creds: &super::credentials::KaggleCredentials,
+    version: Option<&str>,
+    dir: &Path,
+    stem: &str,
+) -> Result<(PathBuf, ArchiveFormat), GaggleError> {
+    let url = archive_url(dataset_path, version)?;
+    debug!(url = %sanitize_message(&url), "downloading dataset");
+
+    let client = get_download_client()?;
+    let zip_path = dir.join(format!("{}.zip", stem));
+    let connections = crate::config::download_connections();
+    // Recorded responses are keyed by URL, so ranged requests are only made against the network
+    let ranged = connections > 1 && super::api::http_record_mode() == HttpRecordMode::Off;
+    let single_stream = if ranged {
+        download_ranges(&client, &url, creds, connections, &zip_path, dataset_path)?
+    } else {
+        None
+    };
+    let mut response = match single_stream {
+        Some(response) => response,
+        None if ranged => return identify_archive(&zip_path, dir, stem),
+        None => with_retries(|| {
+            send(
+                client
+                    .get(&url)
+                    .basic_auth(&creds.username, Some(creds.key_str())),
+            )
+        })?,
+    };
+    check_archive_response(
+        response.status(),
+        response.content_length(),
+        dir,
+        dataset_path,
+    )?;
+    // Peek at the body before anything is written, since Kaggle may answer with an error page
+    let content_type = FileResponse::from_headers(response.headers()).content_type;
+    let head = read_archive_head(&mut response, content_type.as_deref())?;
+
+    // Stream response to a temporary file to avoid large memory usage
+    let zip_file = fs::File::create(&zip_path)?;
+    let mut writer = BufWriter::new(zip_file);
+    let started = Instant::now();
+    let mut throttled = ThrottledWriter::new(&mut writer);
+    let copied = throttled
+        .write_all(&head)
+        .map_err(GaggleError::from)
+        .and_then(|()| Ok(response.copy_to(&mut throttled)?));
+    match copied {
+        Ok(bytes) => debug!(
+            bytes = bytes + head.len() as u64,
+            duration_ms = started.elapsed().as_millis() as u64,
+            "downloaded archive"
+        ),
+        Err(e) => {
+            drop(writer);
+            let _ = fs::remove_file(&zip_path);
+            return Err(e);
         }
-        return Err(GaggleError::HttpRequestError(format!(
-            "Offline mode enabled; cannot download '{}' from '{}'.",
-            filename, dataset_path
-        )));
     }
+    writer.flush().ok();
+    drop(writer);
+
+    identify_archive(&zip_path, dir, stem)
+}
+
+/// Smallest byte range fetched by one connection of a parallel download. Smaller archives
+/// use fewer connections.
+const MIN_RANGE_BYTES: u64 = 1024 * 1024;
+
+/// Downloads an archive over several connections, each fetching one byte range into its
+/// place in `path`.
+///
+/// The server is probed with a one-byte range request first. Returns `None` once the
+/// archive is written, or a response to stream from when ranges cannot be used: either the
+/// probe's own response, if the server ignored the range, or a plain request when the
+/// archive is too small to split.
+fn download_ranges(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    creds: &super::credentials::KaggleCredentials,
+    connections: usize,
+    path: &Path,
+    dataset_path: &str,
+) -> Result<Option<reqwest::blocking::Response>, GaggleError> {
+    let probe = with_retries(|| {
+        send(
+            client
+                .get(url)
+                .basic_auth(&creds.username, Some(creds.key_str()))
+                .header(reqwest::header::RANGE, "bytes=0-0"),
+        )
+    })?;
+    if probe.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        debug!(status = %probe.status(), "server does not support ranges; using a single stream");
+        return Ok(Some(probe));
+    }
+    let total = content_range_total(probe.headers());
+    let connections = total.map_or(1, |total| {
+        connections.min(usize::try_from(total.div_ceil(MIN_RANGE_BYTES)).unwrap_or(usize::MAX))
+    });
+    let Some(total) = total.filter(|_| connections > 1) else {
+        let response = with_retries(|| {
+            send(
+                client
+                    .get(url)
+                    .basic_auth(&creds.username, Some(creds.key_str())),
+            )
+        })?;
+        return Ok(Some(response));
+    };
 
-    // Make sure the parent directories exist
-    if let Some(parent) = target_path.parent() {
-        fs::create_dir_all(parent)?;
+    // Chunks are fetched from where the download endpoint redirected to. Credentials are only
+    // sent back to the API itself, not to other hosts such as a storage bucket
+    let chunk_url = probe.url().clone();
+    let same_origin = reqwest::Url::parse(url).is_ok_and(|u| u.origin() == chunk_url.origin());
+    let chunk_creds = same_origin.then_some(creds);
+    drop(probe);
+
+    if let Some(dir) = path.parent() {
+        ensure_free_space(
+            dir,
+            total.saturating_mul(ARCHIVE_SPACE_FACTOR),
+            dataset_path,
+        )?;
     }
+    fs::File::create(path)?.set_len(total)?;
 
-    // Build single-file download URL
-    // We use an endpoint shape that is easy to mock in tests and aligns with typical Kaggle CLI patterns
-    let url = format!(
-        "{}/datasets/download/{}/{}?fileName={}",
-        get_api_base(),
-        owner,
-        dataset,
-        urlencoding::encode(filename)
+    let ranges = split_ranges(total, connections);
+    debug!(
+        bytes = total,
+        connections = ranges.len(),
+        "downloading archive in parallel"
     );
+    let downloaded = std::sync::atomic::AtomicU64::new(0);
+    let results: Vec<Result<(), GaggleError>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = ranges
+            .iter()
+            .map(|&(start, end)| {
+                let (chunk_url, downloaded) = (&chunk_url, &downloaded);
+                scope.spawn(move || {
+                    fetch_range(client, chunk_url, chunk_creds, start, end, path)?;
+                    let done = downloaded.fetch_add(end - start + 1, Ordering::Relaxed);
+                    debug!(
+                        downloaded = done + end - start + 1,
+                        total, "downloaded archive chunk"
+                    );
+                    Ok(())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join().unwrap_or_else(|_| {
+                    Err(GaggleError::IoError("Download worker panicked".to_string()))
+                })
+            })
+            .collect()
+    });
 
-    let creds = get_credentials()?;
-    debug!(%url, "downloading single file");
-    let client = build_client()?;
+    if let Some(err) = results.into_iter().find_map(Result::err) {
+        let _ = fs::remove_file(path);
+        return Err(err);
+    }
+    Ok(None)
+}
+
+/// Fetches bytes `start..=end` of an archive and writes them at the same offset in `path`.
+fn fetch_range(
+    client: &reqwest::blocking::Client,
+    url: &reqwest::Url,
+    creds: Option<&super::credentials::KaggleCredentials>,
+    start: u64,
+    end: u64,
+    path: &Path,
+) -> Result<(), GaggleError> {
     let mut response = with_retries(|| {
-        client
-            .get(&url)
-            .basic_auth(&creds.username, Some(&creds.key))
-            .send()
-            .map_err(|e| GaggleError::HttpRequestError(e.to_string()))
+        let mut request = client
+            .get(url.clone())
+            .header(reqwest::header::RANGE, format!("bytes={}-{}", start, end));
+        if let Some(creds) = creds {
+            request = request.basic_auth(&creds.username, Some(creds.key_str()));
+        }
+        send(request)
     })?;
-
-    if !response.status().is_success() {
+    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
         return Err(GaggleError::HttpRequestError(format!(
-            "Failed to download file '{}': HTTP {}",
-            filename,
+            "Failed to download bytes {}-{} of archive: HTTP {}",
+            start,
+            end,
             response.status()
         )));
     }
 
-    // Stream to disk; avoid loading whole file into memory
-    let mut outfile = fs::File::create(&target_path)?;
-    response
-        .copy_to(&mut outfile)
-        .map_err(|e| GaggleError::HttpRequestError(e.to_string()))?;
-
-    Ok(target_path)
+    // Each worker opens its own handle, so the file offsets do not interfere
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let expected = end - start + 1;
+    let mut writer = BufWriter::new(file);
+    let written = std::io::copy(
+        &mut (&mut response).take(expected.saturating_add(1)),
+        &mut ThrottledWriter::new(&mut writer),
+    )?;
+    writer.flush()?;
+    if written != expected {
+        return Err(GaggleError::HttpRequestError(format!(
+            "Range request for bytes {}-{} returned {} bytes instead of {}",
+            start, end, written, expected
+        )));
+    }
+    Ok(())
 }
 
-/// Extracts the contents of a ZIP file.
-pub(crate) fn extract_zip(zip_path: &Path, dest_dir: &Path) -> Result<usize, GaggleError> {
-    let file = fs::File::open(zip_path)?;
-    let mut archive =
-        zip::ZipArchive::new(file).map_err(|e| GaggleError::ZipError(e.to_string()))?;
-
-    // ZIP bomb protection: limit total uncompressed size to 10GB and compression ratio
-    const MAX_TOTAL_SIZE: u64 = 10 * 1024 * 1024 * 1024;
-    const MAX_COMPRESSION_RATIO: u64 = 100; // reject entries with >100:1 ratio
-    let mut total_size: u64 = 0;
-    let mut files_extracted: usize = 0;
-
-    // Make sure the destination directory exists and canonicalize it once
-    fs::create_dir_all(dest_dir)?;
-    let canonical_dest = dest_dir.canonicalize().map_err(|e| {
-        GaggleError::IoError(format!(
-            "Failed to canonicalize destination directory: {}",
-            e
-        ))
-    })?;
+/// Splits `total` bytes into at most `parts` contiguous, inclusive byte ranges.
+fn split_ranges(total: u64, parts: usize) -> Vec<(u64, u64)> {
+    let parts = u64::try_from(parts.max(1)).unwrap_or(1);
+    let chunk = total.div_ceil(parts).max(1);
+    (0..total)
+        .step_by(usize::try_from(chunk).unwrap_or(usize::MAX))
+        .map(|start| (start, (start + chunk).min(total) - 1))
+        .collect()
+}
 
-    for i in 0..archive.len() {
-        let mut entry = archive
-            .by_index(i)
-            .map_err(|e| GaggleError::ZipError(e.to_string()))?;
+/// Reads the total size from a `Content-Range: bytes 0-0/{total}` header.
+fn content_range_total(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    headers
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .trim()
+        .parse()
+        .ok()
+}
 
-        // Reject symlink entries based on UNIX mode bits if present
-        if let Some(mode) = entry.unix_mode() {
-            let file_type = mode & 0o170000;
-            if file_type == 0o120000 {
-                return Err(GaggleError::ZipError(format!(
-                    "Symlink entry not allowed in archive: {}",
-                    entry.name()
-                )));
-            }
+/// The async counterpart of [`fetch_archive`], streaming the archive with `tokio::fs`.
+#[cfg(feature = "async")]
+async fn fetch_archive_async(
+    dataset_path: &str,
+    creds: &super::credentials::KaggleCredentials,
+    version: Option<&str>,
+    dir: &Path,
+    stem: &str,
+) -> Result<(PathBuf, ArchiveFormat), GaggleError> {
+    use tokio::io::AsyncWriteExt;
+
+    let url = archive_url(dataset_path, version)?;
+    debug!(url = %sanitize_message(&url), "downloading dataset");
+
+    let client = super::api::get_async_download_client()?;
+    let mut response = super::api::with_retries_async(|| {
+        super::api::send_async(
+            client
+                .get(&url)
+                .basic_auth(&creds.username, Some(creds.key_str())),
+        )
+    })
+    .await?;
+    check_archive_response(
+        response.status(),
+        response.content_length(),
+        dir,
+        dataset_path,
+    )?;
+    // Peek at the body before anything is written, since Kaggle may answer with an error page
+    let content_type = FileResponse::from_headers(response.headers()).content_type;
+    let mut head = Vec::new();
+    while head.len() < ARCHIVE_MAGIC_LEN {
+        match response.chunk().await? {
+            Some(chunk) => head.extend_from_slice(&chunk),
+            None => break,
         }
-
-        // Verify the path is safe (prevents path traversal like ../)
-        let rel_path = match entry.enclosed_name() {
-            Some(path) => path.to_owned(),
-            None => {
-                // Skip entries with invalid names
-                continue;
+    }
+    if !is_archive_head(&head) {
+        while head.len() < ERROR_BODY_LIMIT {
+            match response.chunk().await? {
+                Some(chunk) => head.extend_from_slice(&chunk),
+                None => break,
             }
-        };
-
-        // Compute output path and validate parent within dest (no dir creation before validation)
-        let outpath = dest_dir.join(&rel_path);
-        let parent = outpath.parent().unwrap_or(dest_dir);
-        // Validate the output path is still within dest_dir using canonical parent
-        // Create parent only after validation
-        let canonical_parent_candidate = if parent.exists() {
-            parent.canonicalize()
-        } else {
-            // If parent doesn't exist yet, use canonical dest and join the relative path's parent
-            Ok(canonical_dest.clone())
-        };
-        let canonical_parent = canonical_parent_candidate.map_err(|e| {
-            GaggleError::ZipError(format!(
-                "Failed to canonicalize parent directory for {}: {}",
-                rel_path.display(),
-                e
-            ))
-        })?;
-        if !canonical_parent.starts_with(&canonical_dest) {
-            return Err(GaggleError::ZipError(format!(
-                "Path traversal attempt detected: {:?}",
-                entry.name()
-            )));
         }
+        return Err(non_archive_error(&head, content_type.as_deref()));
+    }
 
-        // Directory entries
-        if entry.is_dir() || entry.name().ends_with('/') {
-            fs::create_dir_all(&outpath)?;
-            continue;
+    let zip_path = dir.join(format!("{}.zip", stem));
+    let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(&zip_path).await?);
+    let copied: Result<(), GaggleError> = async {
+        let limit = super::throttle::limit_bytes_per_sec();
+        writer.write_all(&head).await?;
+        super::metrics::add(Counter::BytesDownloaded, head.len() as u64);
+        tokio::time::sleep(super::throttle::reserve(limit, head.len())).await;
+        while let Some(chunk) = response.chunk().await? {
+            writer.write_all(&chunk).await?;
+            super::metrics::add(Counter::BytesDownloaded, chunk.len() as u64);
+            tokio::time::sleep(super::throttle::reserve(limit, chunk.len())).await;
         }
+        writer.flush().await?;
+        Ok(())
+    }
+    .await;
+    drop(writer);
+    if let Err(e) = copied {
+        let _ = tokio::fs::remove_file(&zip_path).await;
+        return Err(e);
+    }
 
-        // Check total uncompressed size and per-entry compression ratio if possible
-        let uncompressed = entry.size();
-        total_size = total_size.saturating_add(uncompressed);
-        if total_size > MAX_TOTAL_SIZE {
-            return Err(GaggleError::ZipError(format!(
-                "ZIP file too large: uncompressed size exceeds {} GB",
-                MAX_TOTAL_SIZE / (1024 * 1024 * 1024)
-            )));
-        }
-        let comp_size = entry.compressed_size();
-        if comp_size > 0 {
-            let ratio = uncompressed.saturating_div(comp_size.max(1));
-            if ratio > MAX_COMPRESSION_RATIO {
-                return Err(GaggleError::ZipError(format!(
-                    "Excessive compression ratio ({}:1) for entry {}",
-                    ratio,
-                    rel_path.display()
-                )));
-            }
-        }
+    identify_archive(&zip_path, dir, stem)
+}
 
-        // Finally, write the file
-        if let Some(p) = outpath.parent() {
-            fs::create_dir_all(p)?;
-        }
-        let mut outfile = fs::File::create(&outpath)?;
-        std::io::copy(&mut entry, &mut outfile)?;
-        files_extracted += 1;
+/// Builds the download URL of a dataset archive, optionally for a specific version.
+fn archive_url(dataset_path: &str, version: Option<&str>) -> Result<String, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let segments = super::encode_dataset_segments(&owner, &dataset);
+    Ok(match version {
+        Some(v) => format!(
+            "{}/datasets/download/{}/versions/{}",
+            get_api_base(),
+            segments,
+            v
+        ),
+        None => format!("{}/datasets/download/{}", get_api_base(), segments),
+    })
+}
+
+/// Checks the status of an archive download, and that `dir` has room for the archive.
+fn check_archive_response(
+    status: reqwest::StatusCode,
+    content_length: Option<u64>,
+    dir: &Path,
+    dataset_path: &str,
+) -> Result<(), GaggleError> {
+    if !status.is_success() {
+        return Err(GaggleError::HttpRequestError(format!(
+            "Failed to download dataset: HTTP {}",
+            status
+        )));
     }
 
-    Ok(files_extracted)
+    // The archive is written to disk before it is extracted, so check up front that there
+    // is room for both, instead of failing halfway through the extraction
+    if let Some(len) = content_length {
+        ensure_free_space(dir, len.saturating_mul(ARCHIVE_SPACE_FACTOR), dataset_path)?;
+    }
+    Ok(())
 }
 
-/// Lists the files in a dataset.
-///
-/// If the dataset is cached locally, the function lists the files from the disk. Otherwise, it
-/// attempts to list them from the remote metadata. If the remote metadata is unavailable,
-/// it falls back to downloading the dataset and then listing the files.
-pub fn list_dataset_files(dataset_path: &str) -> Result<Vec<DatasetFile>, GaggleError> {
-    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
-    let dataset_dir = crate::config::cache_dir_runtime()
-        .join("datasets")
-        .join(&owner)
-        .join(&dataset);
+/// Bytes at the start of a download that identify it as an archive
+const ARCHIVE_MAGIC_LEN: usize = 4;
+/// Bytes of a download that is not an archive read to explain the failure
+const ERROR_BODY_LIMIT: usize = 64 * 1024;
+
+/// Whether the first bytes of a download are a ZIP archive (including an empty or spanned
+/// one), or a gzip, bzip2, or xz stream.
+fn is_archive_head(head: &[u8]) -> bool {
+    const SIGNATURES: &[&[u8]] = &[
+        b"PK\x03\x04",
+        b"PK\x05\x06",
+        b"PK\x07\x08",
+        &[0x1f, 0x8b],
+        b"BZh",
+        &[0xfd, 0x37, 0x7a],
+    ];
+    SIGNATURES.iter().any(|sig| head.starts_with(sig))
+}
 
-    // If directory exists and has content, enumerate locally
-    if dataset_dir.exists() {
-        let mut files = Vec::new();
-        for entry in fs::read_dir(&dataset_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(file_name) = path.file_name() {
-                    if file_name != ".downloaded" {
-                        let metadata = fs::metadata(&path)?;
-                        if let Some(name) = path.file_name() {
-                            files.push(DatasetFile {
-                                name: name.to_string_lossy().to_string(),
-                                size: metadata.len(),
-                            });
-                        }
-                    }
-                }
-            }
+/// Reads the first bytes of an archive download, and fails with [`non_archive_error`] if
+/// they are not an archive.
+fn read_archive_head<R: Read>(
+    reader: &mut R,
+    content_type: Option<&str>,
+) -> Result<Vec<u8>, GaggleError> {
+    let mut head = Vec::with_capacity(ARCHIVE_MAGIC_LEN);
+    reader
+        .by_ref()
+        .take(ARCHIVE_MAGIC_LEN as u64)
+        .read_to_end(&mut head)?;
+    if is_archive_head(&head) {
+        return Ok(head);
+    }
+    reader
+        .take((ERROR_BODY_LIMIT - head.len()) as u64)
+        .read_to_end(&mut head)?;
+    Err(non_archive_error(&head, content_type))
+}
+
+/// Explains a download that is not an archive, from the start of its body.
+///
+/// During incidents, Kaggle can answer with HTTP 200 and an error page instead of the
+/// archive. A sign-in page means the credentials were refused, and a JSON body carries a
+/// message.
+fn non_archive_error(body: &[u8], content_type: Option<&str>) -> GaggleError {
+    if looks_like_html(content_type, body)
+        && String::from_utf8_lossy(body)
+            .to_ascii_lowercase()
+            .contains("sign in")
+    {
+        return GaggleError::CredentialsError(
+            "Kaggle returned a sign-in page instead of the dataset archive".to_string(),
+        );
+    }
+    if let Ok(json) = serde_json::from_slice::<serde_json::Value>(body) {
+        if let Some(message) = json.get("message").and_then(|m| m.as_str()) {
+            return GaggleError::HttpRequestError(format!(
+                "Failed to download dataset: {}",
+                message
+            ));
         }
-        return Ok(files);
     }
+    GaggleError::ZipError(format!(
+        "Download is not an archive (received {})",
+        content_type.unwrap_or("no content type")
+    ))
+}
 
-    // Not cached: try remote listing via metadata
-    if !crate::config::offline_mode() {
-        if let Ok(list) = list_dataset_files_from_metadata(dataset_path) {
-            if !list.is_empty() {
-                debug!(
-                    dataset = dataset_path,
-                    count = list.len(),
-                    "listing files from remote metadata"
-                );
-                return Ok(list);
-            } else {
-                debug!(
-                    dataset = dataset_path,
-                    "remote metadata listing empty; will attempt download"
-                );
-            }
+/// Detects the format of a downloaded archive and renames it to `{stem}.{format}`.
+fn identify_archive(
+    zip_path: &Path,
+    dir: &Path,
+    stem: &str,
+) -> Result<(PathBuf, ArchiveFormat), GaggleError> {
+    // Kaggle serves most datasets as ZIP, but some are tarballs; detect by magic bytes
+    let format = ArchiveFormat::detect(zip_path)?;
+    let archive_path = dir.join(format!("{}.{}", stem, format.as_str()));
+    if archive_path != zip_path {
+        fs::rename(zip_path, &archive_path)?;
+    }
+    Ok((archive_path, format))
+}
+
+/// Name of the marker written by a download in dry-run mode (see `GAGGLE_DRY_RUN`). Unlike
+/// `.downloaded`, it does not mark the dataset as cached.
+const DRY_RUN_MARKER: &str = ".dry_run";
+/// Name of the marker listing the entries of a dataset that was only partially extracted.
+const PARTIAL_MARKER: &str = ".partial";
+/// Name of the marker that protects a cached dataset from clearing and eviction.
+const PINNED_MARKER: &str = ".pinned";
+/// File stem of an archive kept for later filtered downloads (see `GAGGLE_KEEP_ARCHIVE`).
+const RETAINED_ARCHIVE_STEM: &str = ".archive";
+
+/// The contents of the `.partial` marker written by [`download_dataset_files`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PartialMarker {
+    /// The names and patterns extracted so far.
+    patterns: Vec<String>,
+    /// The relative paths of the extracted files, with forward slashes.
+    entries: Vec<String>,
+    /// The file name of the retained archive, if it was kept.
+    archive: Option<String>,
+}
+
+fn read_partial_marker(dataset_dir: &Path) -> Option<PartialMarker> {
+    let content = fs::read_to_string(dataset_dir.join(PARTIAL_MARKER)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Matches a relative path against a file name or glob pattern.
+///
+/// `*` matches any run of characters and `?` any single character, but neither crosses a `/`.
+/// `[abc]` and `[a-z]` match one character from a set, and `[!abc]` one character outside it.
+/// `**` matches across directories, and `**/` also matches no directory at all, so
+/// `**/*.parquet` selects Parquet files at any depth. Patterns with a `/` are matched against
+/// the whole path; patterns without one are matched against the file name, so `*.parquet`
+/// also selects Parquet files in every directory.
+fn entry_matches(pattern: &str, rel_path: &str) -> bool {
+    let target = if pattern.contains('/') {
+        rel_path
+    } else {
+        rel_path.rsplit('/').next().unwrap_or(rel_path)
+    };
+    glob_match(pattern.as_bytes(), target.as_bytes())
+}
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    // Results for (pattern position, text position) pairs, so backtracking over several
+    // wildcards stays polynomial
+    let mut memo = vec![None; (pattern.len() + 1) * (text.len() + 1)];
+    glob_match_at(pattern, text, 0, 0, &mut memo)
+}
+
+fn glob_match_at(
+    pattern: &[u8],
+    text: &[u8],
+    p: usize,
+    t: usize,
+    memo: &mut [Option<bool>],
+) -> bool {
+    let key = p * (text.len() + 1) + t;
+    if let Some(matched) = memo[key] {
+        return matched;
+    }
+    let matched = match pattern.get(p) {
+        None => t == text.len(),
+        Some(b'*') if pattern.get(p + 1) == Some(&b'*') => {
+            let rest = p + 2;
+            (pattern.get(rest) == Some(&b'/') && glob_match_at(pattern, text, rest + 1, t, memo))
+                || (t..=text.len()).any(|i| glob_match_at(pattern, text, rest, i, memo))
+        }
+        Some(b'*') => {
+            // Let the `*` absorb characters up to the next `/`
+            let stop = text[t..]
+                .iter()
+                .position(|&c| c == b'/')
+                .map_or(text.len(), |i| t + i);
+            (t..=stop).any(|i| glob_match_at(pattern, text, p + 1, i, memo))
+        }
+        Some(b'?') => {
+            text.get(t).is_some_and(|&c| c != b'/')
+                && glob_match_at(pattern, text, p + 1, t + 1, memo)
+        }
+        Some(b'[') => match char_class_match(&pattern[p..], text.get(t).copied()) {
+            Some((is_match, len)) => is_match && glob_match_at(pattern, text, p + len, t + 1, memo),
+            // Without a closing `]`, the `[` is an ordinary character
+            None => text.get(t) == Some(&b'[') && glob_match_at(pattern, text, p + 1, t + 1, memo),
+        },
+        Some(&c) => text.get(t) == Some(&c) && glob_match_at(pattern, text, p + 1, t + 1, memo),
+    };
+    memo[key] = Some(matched);
+    matched
+}
+
+/// Matches `c` against the character class at the start of `pattern`, such as `[a-z]` or
+/// `[!0-9]`. Returns whether it matched and the length of the class, or `None` if the class
+/// is not closed. A class never matches `/`.
+fn char_class_match(pattern: &[u8], c: Option<u8>) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some(b'!') | Some(b'^'));
+    if negated {
+        i += 1;
+    }
+    let mut found = false;
+    let mut first = true;
+    loop {
+        let lo = *pattern.get(i)?;
+        // A `]` right after the opening bracket is part of the set
+        if lo == b']' && !first {
+            break;
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&b'-') && pattern.get(i + 2).is_some_and(|&hi| hi != b']') {
+            let hi = pattern[i + 2];
+            found |= c.is_some_and(|c| (lo..=hi).contains(&c));
+            i += 3;
         } else {
-            debug!(
-                dataset = dataset_path,
-                "failed to fetch remote metadata; will attempt download"
-            );
+            found |= c == Some(lo);
+            i += 1;
         }
     }
+    let is_match = c.is_some_and(|c| c != b'/') && found != negated;
+    Some((is_match, i + 1))
+}
 
-    // As a last resort, download and list
-    let dataset_dir = download_dataset(dataset_path)?;
-    let mut files = Vec::new();
-    for entry in fs::read_dir(&dataset_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(file_name) = path.file_name() {
-                if file_name != ".downloaded" {
-                    let metadata = fs::metadata(&path)?;
-                    if let Some(name) = path.file_name() {
-                        files.push(DatasetFile {
-                            name: name.to_string_lossy().to_string(),
-                            size: metadata.len(),
-                        });
+/// Downloads a Kaggle dataset but extracts only the entries that match `patterns`.
+///
+/// Each pattern is an exact file name or path, or a glob such as `"*.parquet"` (see
+/// [`entry_matches`]). The extracted entries are listed in a `.partial` marker, and later
+/// calls whose patterns were all extracted before are served from the cache. Other calls
+/// download the archive again, unless `GAGGLE_KEEP_ARCHIVE` kept it in the cache directory.
+/// The archive is downloaded to the staging directory, and with a hard cache limit, room
+/// is made for the whole dataset before it is downloaded.
+///
+/// Returns the paths of the cached files that match `patterns`. Version pinning and Kaggle
+/// URLs are supported as in [`download_dataset`].
+pub fn download_dataset_files(
+    dataset_path: &str,
+    patterns: &[&str],
+) -> Result<Vec<PathBuf>, GaggleError> {
+    if patterns.is_empty() {
+        return Err(GaggleError::InvalidDatasetPath(
+            "At least one file name or pattern is required".to_string(),
+        ));
+    }
+    for pattern in patterns {
+        validate_relative_filename(pattern)?;
+    }
+
+    let (owner, dataset, version) = super::parse_dataset_path_with_version(dataset_path)?;
+    let base_path = format!("{}/{}", owner, dataset);
+    let cache_subdir = if let Some(ref v) = version {
+        format!("{}-v{}", dataset, v)
+    } else {
+        dataset.clone()
+    };
+    let cache_dir = dataset_cache_dir(&owner, &cache_subdir);
+    let marker_file = cache_dir.join(".downloaded");
+
+    let is_covered = || {
+        read_partial_marker(&cache_dir)
+            .is_some_and(|m| patterns.iter().all(|p| m.patterns.iter().any(|q| q == p)))
+    };
+
+    // Same lock as full downloads of the dataset, so the two never extract into one directory at once
+    let lock_key = dataset_lock_key(&owner, &cache_subdir);
+    let guard = loop {
+        // A fully downloaded dataset already has every entry
+        if marker_file.exists() {
+            record_cache_access(&cache_dir);
+            return matching_cached_files(&cache_dir, patterns);
+        }
+        if is_covered() {
+            return matching_partial_files(&cache_dir, patterns);
+        }
+        // Another thread may have downloaded other entries meanwhile, so check again after waiting
+        if let Some(guard) = acquire_download_lock(&lock_key, dataset_path, || {
+            marker_file.exists() || is_covered()
+        })? {
+            break guard;
+        }
+    };
+
+    let mut staging: Option<PathBuf> = None;
+    let result = (|| -> Result<Vec<PathBuf>, GaggleError> {
+        let mut marker = read_partial_marker(&cache_dir).unwrap_or_default();
+        let retained = marker
+            .archive
+            .as_ref()
+            .map(|name| cache_dir.join(name))
+            .filter(|path| path.exists());
+
+        let (archive_path, format) = match retained {
+            Some(path) => {
+                debug!(dataset = dataset_path, "extracting from retained archive");
+                let format = ArchiveFormat::detect(&path)?;
+                (path, format)
+            }
+            None => {
+                if crate::config::offline_mode() {
+                    return Err(GaggleError::HttpRequestError(format!(
+                        "Offline mode enabled; cannot download '{}'. Unset GAGGLE_OFFLINE to enable network.",
+                        dataset_path
+                    )));
+                }
+                super::metadata::ensure_dataset_exists(&base_path)?;
+                if let Some(limit_mb) = crate::config::max_dataset_size_mb() {
+                    check_dataset_size(&base_path, limit_mb)?;
+                }
+                // With a hard limit, make room before downloading instead of cleaning up afterwards
+                if !crate::config::cache_limit_is_soft() {
+                    if let Some(limit_mb) = crate::config::cache_size_limit_mb() {
+                        reserve_cache_space(&base_path, &cache_dir, limit_mb)?;
                     }
                 }
+                let creds = get_credentials()?;
+                let dir = staging.insert(new_staging_dir()?);
+                fetch_archive(
+                    &base_path,
+                    &creds,
+                    version.as_deref(),
+                    dir,
+                    RETAINED_ARCHIVE_STEM,
+                )?
+            }
+        };
+        fs::create_dir_all(&cache_dir)?;
+
+        // Record the entries as they are selected; directories are filtered out afterwards
+        let selected = std::cell::RefCell::new(Vec::new());
+        let extracted = extract_archive_filtered(&archive_path, &cache_dir, format, |rel| {
+            let name = zip_entry_name(rel);
+            let keep = patterns.iter().any(|p| entry_matches(p, &name));
+            if keep {
+                selected.borrow_mut().push(name);
+            }
+            keep
+        });
+        if let Err(err) = extracted {
+            let _ = fs::remove_file(&archive_path);
+            let _ = fs::remove_file(cache_dir.join(PARTIAL_MARKER));
+            return Err(err);
+        }
+
+        for name in selected.into_inner() {
+            if cache_dir.join(&name).is_file() && !marker.entries.contains(&name) {
+                marker.entries.push(name);
+            }
+        }
+        marker.entries.sort();
+        for pattern in patterns {
+            if !marker.patterns.iter().any(|p| p == pattern) {
+                marker.patterns.push(pattern.to_string());
+            }
+        }
+        if crate::config::keep_archive() {
+            let name = archive_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string());
+            if let Some(ref name) = name {
+                let kept = cache_dir.join(name);
+                if kept != archive_path && fs::rename(&archive_path, &kept).is_err() {
+                    // Renaming fails across file systems
+                    fs::copy(&archive_path, &kept)?;
+                }
             }
+            marker.archive = name;
+        } else {
+            let _ = fs::remove_file(&archive_path);
+            marker.archive = None;
+        }
+        crate::utils::write_marker_atomic(
+            &cache_dir.join(PARTIAL_MARKER),
+            &serde_json::to_string(&marker)?,
+        )?;
+
+        if crate::config::cache_limit_is_soft() {
+            let _ = enforce_cache_limit(); // Don't fail the download if cleanup fails
         }
+
+        matching_partial_files(&cache_dir, patterns)
+    })();
+    if let Some(dir) = staging {
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            debug!(path = %dir.display(), error = %e, "failed to remove staging directory");
+        }
+    }
+
+    guard.finish(&result);
+    result
+}
+
+/// Paths of the entries in the partial marker of `dataset_dir` that match `patterns`.
+fn matching_partial_files(
+    dataset_dir: &Path,
+    patterns: &[&str],
+) -> Result<Vec<PathBuf>, GaggleError> {
+    let marker = read_partial_marker(dataset_dir).unwrap_or_default();
+    let files: Vec<PathBuf> = marker
+        .entries
+        .iter()
+        .filter(|e| patterns.iter().any(|p| entry_matches(p, e)))
+        .map(|e| dataset_dir.join(e))
+        .filter(|path| path.is_file())
+        .collect();
+    if files.is_empty() {
+        return Err(no_matching_files(patterns));
     }
+    record_cache_access(dataset_dir);
     Ok(files)
 }
 
-/// Retrieves the local path to a specific file in a dataset.
-pub fn get_dataset_file_path(dataset_path: &str, filename: &str) -> Result<PathBuf, GaggleError> {
-    // Validate filename to prevent path traversal or absolute paths
+/// Paths of the files of a fully downloaded dataset that match `patterns`.
+fn matching_cached_files(
+    dataset_dir: &Path,
+    patterns: &[&str],
+) -> Result<Vec<PathBuf>, GaggleError> {
+    let mut all = Vec::new();
+    collect_cache_files(dataset_dir, &mut all)?;
+    let mut files: Vec<PathBuf> = all
+        .into_iter()
+        .filter(|path| {
+            path.strip_prefix(dataset_dir).is_ok_and(|rel| {
+                patterns
+                    .iter()
+                    .any(|p| entry_matches(p, &zip_entry_name(rel)))
+            })
+        })
+        .collect();
+    if files.is_empty() {
+        return Err(no_matching_files(patterns));
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn no_matching_files(patterns: &[&str]) -> GaggleError {
+    GaggleError::DatasetNotFound(format!(
+        "No files in the dataset match {}",
+        patterns.join(", ")
+    ))
+}
+
+/// Rejects filenames that are absolute or contain parent or root components.
+pub(crate) fn validate_relative_filename(filename: &str) -> Result<(), GaggleError> {
     use std::path::Component;
     let fname_path = Path::new(filename);
     if fname_path.is_absolute() {
@@ -581,789 +1944,5830 @@ pub fn get_dataset_file_path(dataset_path: &str, filename: &str) -> Result<PathB
             _ => {}
         }
     }
+    Ok(())
+}
 
-    // Compute dataset dir and desired file path
-    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
-    let dataset_dir = crate::config::cache_dir_runtime()
-        .join("datasets")
-        .join(&owner)
-        .join(&dataset);
-    let file_path = dataset_dir.join(fname_path);
+/// Downloads a single file from a Kaggle dataset into the cache, without extracting the entire archive.
+pub fn download_single_file(dataset_path: &str, filename: &str) -> Result<PathBuf, GaggleError> {
+    let pending = match begin_single_file_download(dataset_path, filename)? {
+        DownloadStart::Ready(path) => return Ok(path),
+        DownloadStart::Pending(pending) => pending,
+    };
 
-    // Fast path: file already present
-    if file_path.exists() {
-        return Ok(file_path);
-    }
+    let result = (|| -> Result<PathBuf, GaggleError> {
+        debug!(url = %sanitize_message(&pending.url), "downloading single file");
+        let client = get_download_client()?;
+        let mut response = with_retries(|| {
+            send(
+                client
+                    .get(&pending.url)
+                    .basic_auth(&pending.creds.username, Some(pending.creds.key_str())),
+            )
+        })?;
+        pending.check_response(response.status(), response.content_length())?;
+        let shape = FileResponse::from_headers(response.headers());
+
+        // Stream to disk; avoid loading whole file into memory
+        let part_path = pending.part_path();
+        let copied = fs::File::create(&part_path)
+            .map_err(GaggleError::from)
+            .and_then(|mut outfile| {
+                response
+                    .copy_to(&mut ThrottledWriter::new(&mut outfile))
+                    .map_err(GaggleError::from)
+            });
+        let stored = copied.and_then(|_| {
+            store_single_file(&pending.filename, &part_path, &pending.target_path, &shape)
+        });
+        if stored.is_err() {
+            let _ = fs::remove_file(&part_path);
+        }
+        stored?;
 
-    // Try on-demand single-file download (without fetching whole archive)
-    match download_single_file(dataset_path, filename) {
-        Ok(p) => Ok(p),
-        Err(e) => {
-            // In strict on-demand mode, do not fall back to full download
-            if crate::config::strict_on_demand() {
-                debug!(dataset = dataset_path, file = filename, error = %e, "on-demand fetch failed and strict mode enabled; not falling back");
-                return Err(e);
+        Ok(pending.target_path.clone())
+    })();
+
+    pending.guard.finish(&result);
+    result
+}
+
+/// The async counterpart of [`download_single_file`].
+#[cfg(feature = "async")]
+async fn download_single_file_async(
+    dataset_path: &str,
+    filename: &str,
+) -> Result<PathBuf, GaggleError> {
+    use tokio::io::AsyncWriteExt;
+
+    let (path, name) = (dataset_path.to_string(), filename.to_string());
+    let pending =
+        match crate::utils::run_blocking(move || begin_single_file_download(&path, &name)).await? {
+            DownloadStart::Ready(path) => return Ok(path),
+            DownloadStart::Pending(pending) => pending,
+        };
+
+    let result = async {
+        debug!(url = %sanitize_message(&pending.url), "downloading single file");
+        let client = super::api::get_async_download_client()?;
+        let mut response = super::api::with_retries_async(|| {
+            super::api::send_async(
+                client
+                    .get(&pending.url)
+                    .basic_auth(&pending.creds.username, Some(pending.creds.key_str())),
+            )
+        })
+        .await?;
+        pending.check_response(response.status(), response.content_length())?;
+        let shape = FileResponse::from_headers(response.headers());
+
+        let part_path = pending.part_path();
+        let copied: Result<(), GaggleError> = async {
+            let mut outfile = tokio::fs::File::create(&part_path).await?;
+            let limit = super::throttle::limit_bytes_per_sec();
+            while let Some(chunk) = response.chunk().await? {
+                outfile.write_all(&chunk).await?;
+                super::metrics::add(Counter::BytesDownloaded, chunk.len() as u64);
+                tokio::time::sleep(super::throttle::reserve(limit, chunk.len())).await;
             }
-            // If single-file download fails and dataset isn't cached, fall back to full dataset download
-            if !dataset_dir.exists()
-                || fs::read_dir(&dataset_dir)
-                    .map(|mut i| i.next().is_none())
-                    .unwrap_or(true)
-            {
-                debug!(dataset = dataset_path, file = filename, error = %e, "on-demand fetch failed; falling back to full dataset download");
-                let dir = download_dataset(dataset_path)?;
-                let p = dir.join(fname_path);
-                if p.exists() {
-                    return Ok(p);
-                }
+            outfile.flush().await?;
+            Ok(())
+        }
+        .await;
+        let (filename, part, target) = (
+            pending.filename.clone(),
+            part_path.clone(),
+            pending.target_path.clone(),
+        );
+        let stored = match copied {
+            Ok(()) => {
+                crate::utils::run_blocking(move || {
+                    store_single_file(&filename, &part, &target, &shape)
+                })
+                .await
             }
-            Err(e)
+            Err(e) => Err(e),
+        };
+        if stored.is_err() {
+            let _ = tokio::fs::remove_file(&part_path).await;
         }
+        stored?;
+
+        Ok(pending.target_path.clone())
     }
+    .await;
+
+    pending.guard.finish(&result);
+    result
 }
 
-/// Get all cached datasets with their metadata
-fn get_cached_datasets() -> Result<Vec<(PathBuf, CacheMetadata)>, GaggleError> {
-    let cache_root = crate::config::cache_dir_runtime().join("datasets");
-    if !cache_root.exists() {
-        return Ok(Vec::new());
+/// A single-file download that holds its lock and is waiting for the file to be fetched.
+struct PendingFile {
+    filename: String,
+    url: String,
+    creds: super::credentials::KaggleCredentials,
+    target_path: PathBuf,
+    guard: LockGuard,
+}
+
+impl PendingFile {
+    /// The file the response body is downloaded to before it is stored at the target path.
+    fn part_path(&self) -> PathBuf {
+        let mut name = self.target_path.as_os_str().to_os_string();
+        name.push(format!(".part.{}", std::process::id()));
+        PathBuf::from(name)
     }
 
-    let mut datasets = Vec::new();
+    /// Checks the status of the download, and that there is room for the file.
+    fn check_response(
+        &self,
+        status: reqwest::StatusCode,
+        content_length: Option<u64>,
+    ) -> Result<(), GaggleError> {
+        if !status.is_success() {
+            return Err(GaggleError::HttpRequestError(format!(
+                "Failed to download file '{}': HTTP {}",
+                self.filename, status
+            )));
+        }
+        if let (Some(len), Some(parent)) = (content_length, self.target_path.parent()) {
+            ensure_free_space(parent, len, &self.filename)?;
+        }
+        Ok(())
+    }
+}
 
-    // Iterate through owner directories
-    for owner_entry in fs::read_dir(&cache_root)? {
-        let owner_entry = owner_entry?;
-        if !owner_entry.path().is_dir() {
-            continue;
+/// What the headers of a single-file download say about its body.
+struct FileResponse {
+    /// The media type from `Content-Type`, lowercased and without parameters
+    content_type: Option<String>,
+    /// The file name from `Content-Disposition`, percent-decoded
+    disposition_name: Option<String>,
+}
+
+impl FileResponse {
+    fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        let content_type = header(reqwest::header::CONTENT_TYPE).and_then(|v| {
+            let media_type = v.split(';').next()?.trim().to_ascii_lowercase();
+            (!media_type.is_empty()).then_some(media_type)
+        });
+        Self {
+            content_type,
+            disposition_name: header(reqwest::header::CONTENT_DISPOSITION)
+                .and_then(disposition_filename),
         }
+    }
 
-        // Iterate through dataset directories
-        for dataset_entry in fs::read_dir(owner_entry.path())? {
-            let dataset_entry = dataset_entry?;
-            let dataset_path = dataset_entry.path();
-            if !dataset_path.is_dir() {
-                continue;
-            }
+    /// Whether the headers say the body is a ZIP archive wrapping `filename`.
+    fn is_zip_wrapper(&self, filename: &str) -> bool {
+        if has_extension(filename, &["zip"]) {
+            return false;
+        }
+        matches!(
+            self.content_type.as_deref(),
+            Some("application/zip" | "application/x-zip-compressed")
+        ) || self
+            .disposition_name
+            .as_deref()
+            .is_some_and(|name| has_extension(name, &["zip"]))
+    }
+}
 
-            let marker_file = dataset_path.join(".downloaded");
-            if marker_file.exists() {
-                match fs::read_to_string(&marker_file) {
-                    Ok(content) if !content.is_empty() => {
-                        // Try to parse metadata
-                        match serde_json::from_str::<CacheMetadata>(&content) {
-                            Ok(metadata) => {
-                                datasets.push((dataset_path, metadata));
-                            }
-                            Err(e) => {
-                                // Legacy or invalid marker - calculate size and synthesize metadata
-                                warn!(path = %marker_file.display(), error = %e, "Invalid cache metadata; synthesizing");
-                                let size_mb = crate::utils::calculate_dir_size(&dataset_path)
-                                    .unwrap_or(0)
-                                    .saturating_div(1024 * 1024);
-                                let owner = owner_entry.file_name().to_string_lossy().to_string();
-                                let dataset =
-                                    dataset_entry.file_name().to_string_lossy().to_string();
-                                let metadata =
-                                    CacheMetadata::new(format!("{}/{}", owner, dataset), size_mb);
-                                // retain None version
-                                datasets.push((dataset_path, metadata));
-                            }
-                        }
-                    }
-                    Ok(_) => {
-                        // Empty marker - synthesize
-                        warn!(path = %marker_file.display(), "Empty cache metadata; synthesizing");
-                        let size_mb = crate::utils::calculate_dir_size(&dataset_path)
-                            .unwrap_or(0)
-                            .saturating_div(1024 * 1024);
-                        let owner = owner_entry.file_name().to_string_lossy().to_string();
-                        let dataset = dataset_entry.file_name().to_string_lossy().to_string();
-                        let metadata =
-                            CacheMetadata::new(format!("{}/{}", owner, dataset), size_mb);
-                        datasets.push((dataset_path, metadata));
-                    }
-                    Err(e) => {
-                        warn!(path = %marker_file.display(), error = %e, "Failed reading cache metadata; synthesizing");
-                        let size_mb = crate::utils::calculate_dir_size(&dataset_path)
-                            .unwrap_or(0)
-                            .saturating_div(1024 * 1024);
-                        let owner = owner_entry.file_name().to_string_lossy().to_string();
-                        let dataset = dataset_entry.file_name().to_string_lossy().to_string();
-                        let metadata =
-                            CacheMetadata::new(format!("{}/{}", owner, dataset), size_mb);
-                        datasets.push((dataset_path, metadata));
-                    }
-                }
-            } else {
-                // No marker (e.g., partial on-demand downloads). Include in accounting.
-                let size_mb = crate::utils::calculate_dir_size(&dataset_path)
-                    .unwrap_or(0)
-                    .saturating_div(1024 * 1024);
-                // Skip empty directories with zero size
-                if size_mb > 0 {
-                    let owner = owner_entry.file_name().to_string_lossy().to_string();
-                    let dataset = dataset_entry.file_name().to_string_lossy().to_string();
-                    let metadata = CacheMetadata::new(format!("{}/{}", owner, dataset), size_mb);
-                    datasets.push((dataset_path, metadata));
+/// Reads the file name from a `Content-Disposition` header value. The RFC 5987
+/// `filename*` parameter is preferred over `filename`.
+fn disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    for param in value.split(';').skip(1) {
+        let Some((key, val)) = param.split_once('=') else {
+            continue;
+        };
+        let val = val.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                // charset'language'percent-encoded-name
+                let encoded = val.splitn(3, '\'').nth(2).unwrap_or(val);
+                if let Ok(name) = urlencoding::decode(encoded) {
+                    return Some(name.into_owned());
                 }
             }
+            "filename" => {
+                let name = val.trim_matches('"');
+                plain = Some(
+                    urlencoding::decode(name)
+                        .map(|n| n.into_owned())
+                        .unwrap_or_else(|_| name.to_string()),
+                );
+            }
+            _ => {}
         }
     }
+    plain
+}
 
-    Ok(datasets)
+fn has_extension(name: &str, extensions: &[&str]) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
 }
 
-/// Calculates the total size of the cache in megabytes.
-pub fn get_total_cache_size_mb() -> Result<u64, GaggleError> {
-    let datasets = get_cached_datasets()?;
-    Ok(datasets.iter().map(|(_, meta)| meta.size_mb).sum())
+/// Whether the start of a response body is an HTML document.
+fn looks_like_html(content_type: Option<&str>, head: &[u8]) -> bool {
+    if content_type == Some("text/html") {
+        return true;
+    }
+    let start = head
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .map_or(&[][..], |i| &head[i..]);
+    let start = String::from_utf8_lossy(&start[..start.len().min(16)]).to_ascii_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
 }
 
-/// Enforce cache size limit using LRU eviction
-fn enforce_cache_limit() -> Result<(), GaggleError> {
-    let limit_mb = match crate::config::cache_size_limit_mb() {
-        Some(limit) => limit,
-        None => return Ok(()), // No limit set
-    };
+/// Moves a downloaded single file from `part_path` to `target_path`.
+///
+/// A ZIP archive wrapping the file has the file extracted instead, and an HTML page (such
+/// as a login page) in place of a file that is not HTML is rejected as a credentials error.
+/// The file is always stored under the requested name, whatever `Content-Disposition` says.
+fn store_single_file(
+    filename: &str,
+    part_path: &Path,
+    target_path: &Path,
+    response: &FileResponse,
+) -> Result<(), GaggleError> {
+    let mut head = Vec::with_capacity(512);
+    fs::File::open(part_path)?
+        .take(512)
+        .read_to_end(&mut head)?;
+
+    if !has_extension(filename, &["html", "htm"])
+        && looks_like_html(response.content_type.as_deref(), &head)
+    {
+        return Err(GaggleError::CredentialsError(format!(
+            "Kaggle returned an HTML page instead of '{}'. Check your credentials, and that \
+             you have accepted the rules of the dataset on kaggle.com.",
+            filename
+        )));
+    }
 
-    let mut datasets = get_cached_datasets()?;
-    let mut total_size_mb: u64 = datasets.iter().map(|(_, meta)| meta.size_mb).sum();
+    if let Some(name) = response.disposition_name.as_deref() {
+        if Path::new(name).file_name() != Path::new(filename).file_name() {
+            debug!(
+                file = filename,
+                disposition = name,
+                "Content-Disposition names a different file; storing under the requested name"
+            );
+        }
+    }
 
-    if total_size_mb <= limit_mb {
-        return Ok(()); // Within limit
+    if response.is_zip_wrapper(filename) && head.starts_with(b"PK\x03\x04") {
+        // The file is replaced either way, so the extracted entry can take its place
+        if target_path.exists() {
+            fs::remove_file(target_path)?;
+        }
+        if crate::utils::extract_zip_entry(part_path, filename, target_path)? {
+            fs::remove_file(part_path)?;
+            return Ok(());
+        }
+        debug!(
+            file = filename,
+            "ZIP response has no entry for the file; storing it as is"
+        );
     }
 
-    // Sort by age (oldest first) for LRU eviction
-    datasets.sort_by_key(|(_, meta)| meta.downloaded_at_secs);
+    fs::rename(part_path, target_path)?;
+    Ok(())
+}
+
+/// Runs the checks before a single file is fetched: path validation, offline mode, and
+/// the per-file download lock.
+fn begin_single_file_download(
+    dataset_path: &str,
+    filename: &str,
+) -> Result<DownloadStart<PendingFile>, GaggleError> {
+    // Validate dataset path and filename to prevent traversal
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    validate_relative_filename(filename)?;
+    let fname_path = Path::new(filename);
+
+    // Offline mode: fail if file isn't already present
+    let base_dir = dataset_cache_dir(&owner, &dataset);
+    let target_path = base_dir.join(fname_path);
+    if crate::config::offline_mode() {
+        if target_path.exists() {
+            return Ok(DownloadStart::Ready(target_path));
+        }
+        return Err(GaggleError::HttpRequestError(format!(
+            "Offline mode enabled; cannot download '{}' from '{}'.",
+            filename, dataset_path
+        )));
+    }
+
+    // Lock per file, so different files of a dataset download in parallel but the same
+    // file is never written by two threads at once
+    let lock_key = format!("{}::{}", dataset_lock_key(&owner, &dataset), filename);
+    let was_present = target_path.exists();
+    let Some(guard) =
+        acquire_download_lock(&lock_key, filename, || !was_present && target_path.exists())?
+    else {
+        return Ok(DownloadStart::Ready(target_path));
+    };
+
+    let prepared = (|| -> Result<super::credentials::KaggleCredentials, GaggleError> {
+        // Make sure the parent directories exist
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        get_credentials()
+    })();
+    let creds = match prepared {
+        Ok(creds) => creds,
+        Err(e) => {
+            let result = Err(e);
+            guard.finish(&result);
+            return result;
+        }
+    };
+
+    // Build single-file download URL
+    // We use an endpoint shape that is easy to mock in tests and aligns with typical Kaggle CLI patterns
+    let url = format!(
+        "{}/datasets/download/{}?fileName={}",
+        get_api_base(),
+        super::encode_dataset_segments(&owner, &dataset),
+        urlencoding::encode(filename)
+    );
+
+    metrics::incr(Counter::CacheMisses);
+    metrics::incr(Counter::SingleFileFetches);
+    Ok(DownloadStart::Pending(PendingFile {
+        filename: filename.to_string(),
+        url,
+        creds,
+        target_path,
+        guard,
+    }))
+}
+
+/// Entries (or tar archives) smaller than this when compressed skip the compression ratio
+/// check, since their ratio says little and they cannot expand to much
+const MIN_RATIO_CHECK_COMPRESSED_SIZE: u64 = 4096;
+/// Permission bits kept from archive entries: at most rwxr-xr-x, never setuid, setgid, or sticky
+#[cfg(unix)]
+const MAX_EXTRACTED_MODE: u32 = 0o755;
+
+/// Archive bomb protection limits applied during extraction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ExtractLimits {
+    /// Maximum total uncompressed size in bytes, or `None` for no limit.
+    pub(crate) max_total_bytes: Option<u64>,
+    /// Maximum compression ratio, or `None` for no limit.
+    pub(crate) max_compression_ratio: Option<u64>,
+    /// Maximum number of entries in a ZIP archive.
+    pub(crate) max_zip_files: usize,
+}
+
+impl Default for ExtractLimits {
+    /// The built-in limits: 10GB in total, a compression ratio of at most 100:1, and 100,000
+    /// entries in a ZIP archive.
+    fn default() -> Self {
+        Self {
+            max_total_bytes: Some(10 * 1024 * 1024 * 1024),
+            max_compression_ratio: Some(100),
+            max_zip_files: 100_000,
+        }
+    }
+}
+
+impl ExtractLimits {
+    /// Reads the limits from `GAGGLE_MAX_EXTRACT_SIZE_MB`, `GAGGLE_MAX_COMPRESSION_RATIO`, and
+    /// `GAGGLE_MAX_ZIP_FILES`.
+    pub(crate) fn from_config() -> Self {
+        Self {
+            max_total_bytes: crate::config::max_extract_size_mb()
+                .map(|mb| mb.saturating_mul(1024 * 1024)),
+            max_compression_ratio: crate::config::max_compression_ratio(),
+            max_zip_files: crate::config::max_zip_files(),
+        }
+    }
+
+    /// The most a stream of `compressed` bytes may expand to under these limits, or `None`
+    /// if it is unbounded.
+    pub(crate) fn max_output_bytes(&self, compressed: u64) -> Option<u64> {
+        let by_ratio = self
+            .max_compression_ratio
+            .filter(|_| compressed >= MIN_RATIO_CHECK_COMPRESSED_SIZE)
+            .map(|max| {
+                max.saturating_mul(compressed)
+                    .saturating_add(compressed - 1)
+            });
+        match (self.max_total_bytes, by_ratio) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    pub(crate) fn check_total_size(&self, total_size: u64, what: &str) -> Result<(), GaggleError> {
+        match self.max_total_bytes {
+            Some(max) if total_size > max => Err(GaggleError::ZipError(format!(
+                "{} too large: uncompressed size exceeds {} MB (set GAGGLE_MAX_EXTRACT_SIZE_MB to raise the limit)",
+                what,
+                max / (1024 * 1024)
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_ratio(
+        &self,
+        uncompressed: u64,
+        compressed: u64,
+        entry: &Path,
+    ) -> Result<(), GaggleError> {
+        let Some(max) = self.max_compression_ratio else {
+            return Ok(());
+        };
+        if compressed < MIN_RATIO_CHECK_COMPRESSED_SIZE {
+            return Ok(());
+        }
+        let ratio = uncompressed.saturating_div(compressed);
+        if ratio > max {
+            return Err(GaggleError::ZipError(format!(
+                "Excessive compression ratio ({}:1) for entry {} (set GAGGLE_MAX_COMPRESSION_RATIO to raise the limit)",
+                ratio,
+                entry.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The archive formats a dataset download may arrive in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveFormat {
+    Zip,
+    TarGz,
+    TarBz2,
+    TarXz,
+}
+
+impl ArchiveFormat {
+    /// Detects the archive format from the first bytes of a file.
+    ///
+    /// Gzip, bzip2, and xz streams are treated as compressed tarballs. Anything else,
+    /// including the `PK\x03\x04` ZIP signature, is assumed to be a ZIP archive.
+    pub(crate) fn detect(path: &Path) -> Result<Self, GaggleError> {
+        let mut magic = [0u8; 4];
+        let mut file = fs::File::open(path)?;
+        let n = std::io::Read::read(&mut file, &mut magic)?;
+        Ok(Self::from_magic(&magic[..n]))
+    }
+
+    fn from_magic(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            ArchiveFormat::TarGz
+        } else if magic.starts_with(&[0x42, 0x5a, 0x68]) {
+            ArchiveFormat::TarBz2
+        } else if magic.starts_with(&[0xfd, 0x37, 0x7a]) {
+            ArchiveFormat::TarXz
+        } else {
+            ArchiveFormat::Zip
+        }
+    }
+
+    /// The file extension (and name stored in the cache metadata) for this format.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarBz2 => "tar.bz2",
+            ArchiveFormat::TarXz => "tar.xz",
+        }
+    }
+}
+
+/// Extracts an archive of the given format.
+fn extract_archive(
+    archive_path: &Path,
+    dest_dir: &Path,
+    format: ArchiveFormat,
+) -> Result<usize, GaggleError> {
+    match format {
+        ArchiveFormat::Zip => extract_zip(archive_path, dest_dir, ExtractLimits::from_config()),
+        _ => extract_tar(archive_path, dest_dir, ExtractLimits::from_config()),
+    }
+}
+
+/// Extracts the entries of an archive of the given format whose relative path satisfies `include`.
+fn extract_archive_filtered<F>(
+    archive_path: &Path,
+    dest_dir: &Path,
+    format: ArchiveFormat,
+    include: F,
+) -> Result<usize, GaggleError>
+where
+    F: Fn(&Path) -> bool,
+{
+    let limits = ExtractLimits::from_config();
+    match format {
+        ArchiveFormat::Zip => extract_zip_filtered(archive_path, dest_dir, limits, include),
+        _ => extract_tar_filtered(archive_path, dest_dir, limits, include),
+    }
+}
+
+/// Extracts the contents of a ZIP file.
+pub(crate) fn extract_zip(
+    zip_path: &Path,
+    dest_dir: &Path,
+    limits: ExtractLimits,
+) -> Result<usize, GaggleError> {
+    extract_zip_filtered(zip_path, dest_dir, limits, |_| true)
+}
+
+/// Extracts the entries of a ZIP file whose relative path satisfies `include`.
+///
+/// Excluded entries are skipped before anything is written, but every entry still goes
+/// through the symlink check.
+fn extract_zip_filtered<F>(
+    zip_path: &Path,
+    dest_dir: &Path,
+    limits: ExtractLimits,
+    include: F,
+) -> Result<usize, GaggleError>
+where
+    F: Fn(&Path) -> bool,
+{
+    let file = fs::File::open(zip_path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+
+    // Refuse archives with so many entries that extracting them would exhaust inodes
+    if archive.len() > limits.max_zip_files {
+        return Err(GaggleError::ZipError(format!(
+            "ZIP contains {} files, exceeding limit of {}",
+            archive.len(),
+            limits.max_zip_files
+        )));
+    }
+
+    // ZIP bomb protection: limit total uncompressed size and per-entry compression ratio
+    let mut total_size: u64 = 0;
+    let mut files_extracted: usize = 0;
+
+    // Make sure the destination directory exists and canonicalize it once
+    fs::create_dir_all(dest_dir)?;
+    let canonical_dest = dest_dir.canonicalize().map_err(|e| {
+        GaggleError::IoError(format!(
+            "Failed to canonicalize destination directory: {}",
+            e
+        ))
+    })?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+
+        // Reject symlink entries based on UNIX mode bits if present
+        if let Some(mode) = entry.unix_mode() {
+            let file_type = mode & 0o170000;
+            if file_type == 0o120000 {
+                return Err(GaggleError::ZipError(format!(
+                    "Symlink entry not allowed in archive: {}",
+                    entry.name()
+                )));
+            }
+        }
+
+        // Verify the path is safe (prevents path traversal like ../)
+        let rel_path = match entry.enclosed_name() {
+            Some(path) => path.to_owned(),
+            None => {
+                // Skip entries with invalid names
+                continue;
+            }
+        };
+        if !include(&rel_path) {
+            continue;
+        }
+
+        // Compute output path and validate parent within dest (no dir creation before validation)
+        let outpath = dest_dir.join(&rel_path);
+        let parent = outpath.parent().unwrap_or(dest_dir);
+        // Validate the output path is still within dest_dir using canonical parent
+        // Create parent only after validation
+        let canonical_parent_candidate = if parent.exists() {
+            parent.canonicalize()
+        } else {
+            // If parent doesn't exist yet, use canonical dest and join the relative path's parent
+            Ok(canonical_dest.clone())
+        };
+        let canonical_parent = canonical_parent_candidate.map_err(|e| {
+            GaggleError::ZipError(format!(
+                "Failed to canonicalize parent directory for {}: {}",
+                rel_path.display(),
+                e
+            ))
+        })?;
+        if !canonical_parent.starts_with(&canonical_dest) {
+            return Err(GaggleError::ZipError(format!(
+                "Path traversal attempt detected: {:?}",
+                entry.name()
+            )));
+        }
+
+        // Directory entries
+        if entry.is_dir() || entry.name().ends_with('/') {
+            fs::create_dir_all(&outpath)?;
+            continue;
+        }
+
+        // Check total uncompressed size and per-entry compression ratio if possible
+        let uncompressed = entry.size();
+        total_size = total_size.saturating_add(uncompressed);
+        limits.check_total_size(total_size, "ZIP file")?;
+        limits.check_ratio(uncompressed, entry.compressed_size(), &rel_path)?;
+
+        // Finally, write the file
+        if let Some(p) = outpath.parent() {
+            fs::create_dir_all(p)?;
+        }
+        let mut outfile = fs::File::create(&outpath)?;
+        std::io::copy(&mut entry, &mut outfile)?;
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            outfile.set_permissions(fs::Permissions::from_mode(mode & MAX_EXTRACTED_MODE))?;
+        }
+        if let Some(mtime) = entry.last_modified().and_then(zip_time_to_system_time) {
+            outfile.set_modified(mtime)?;
+        }
+        files_extracted += 1;
+    }
+
+    Ok(files_extracted)
+}
+
+/// Converts a ZIP entry timestamp to a `SystemTime`.
+///
+/// ZIP timestamps carry no time zone, so they are read as UTC. Returns `None` for dates
+/// that are out of range (for example, the all-zero timestamp some tools write).
+fn zip_time_to_system_time(dt: zip::DateTime) -> Option<SystemTime> {
+    let (year, month, day) = (
+        i64::from(dt.year()),
+        i64::from(dt.month()),
+        i64::from(dt.day()),
+    );
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400
+        + i64::from(dt.hour()) * 3600
+        + i64::from(dt.minute()) * 60
+        + i64::from(dt.second());
+    let secs = u64::try_from(secs).ok()?;
+    std::time::UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Extracts the contents of a gzip-, bzip2-, or xz-compressed tar archive.
+///
+/// The compression is detected from the file's magic bytes. The same protections as
+/// [`extract_zip`] apply: the total uncompressed size and the overall compression ratio
+/// are bounded by `limits`, entries escaping the destination are rejected, and symlink and
+/// hard link entries are rejected.
+pub(crate) fn extract_tar(
+    archive_path: &Path,
+    dest_dir: &Path,
+    limits: ExtractLimits,
+) -> Result<usize, GaggleError> {
+    extract_tar_filtered(archive_path, dest_dir, limits, |_| true)
+}
+
+/// Extracts the entries of a compressed tar archive whose relative path satisfies `include`.
+///
+/// Like [`extract_zip_filtered`], every entry still goes through the traversal and link checks.
+fn extract_tar_filtered<F>(
+    archive_path: &Path,
+    dest_dir: &Path,
+    limits: ExtractLimits,
+    include: F,
+) -> Result<usize, GaggleError>
+where
+    F: Fn(&Path) -> bool,
+{
+    use std::path::Component;
+
+    let format = ArchiveFormat::detect(archive_path)?;
+    let file = std::io::BufReader::new(fs::File::open(archive_path)?);
+    let reader: Box<dyn std::io::Read> = match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        ArchiveFormat::TarBz2 => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        ArchiveFormat::TarXz => Box::new(lzma_rust2::XzReader::new(file, true)),
+        ArchiveFormat::Zip => {
+            return Err(GaggleError::ZipError(format!(
+                "Not a compressed tar archive: {}",
+                archive_path.display()
+            )))
+        }
+    };
+    let compressed_size = fs::metadata(archive_path)?.len();
+    let mut archive = tar::Archive::new(reader);
+
+    let mut total_size: u64 = 0;
+    let mut files_extracted: usize = 0;
+
+    // Make sure the destination directory exists and canonicalize it once
+    fs::create_dir_all(dest_dir)?;
+    let canonical_dest = dest_dir.canonicalize().map_err(|e| {
+        GaggleError::IoError(format!(
+            "Failed to canonicalize destination directory: {}",
+            e
+        ))
+    })?;
+
+    let tar_err = |e: std::io::Error| GaggleError::ZipError(format!("Invalid tar archive: {}", e));
+    for entry in archive.entries().map_err(tar_err)? {
+        let mut entry = entry.map_err(tar_err)?;
+        let rel_path = entry.path().map_err(tar_err)?.into_owned();
+
+        // Verify the path is safe (prevents path traversal like ../ and absolute paths)
+        if rel_path.components().any(|c| {
+            matches!(
+                c,
+                Component::ParentDir | Component::RootDir | Component::Prefix(_)
+            )
+        }) {
+            return Err(GaggleError::ZipError(format!(
+                "Path traversal attempt detected: {:?}",
+                rel_path
+            )));
+        }
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            return Err(GaggleError::ZipError(format!(
+                "Link entry not allowed in archive: {}",
+                rel_path.display()
+            )));
+        }
+
+        if !include(&rel_path) {
+            continue;
+        }
+
+        let outpath = canonical_dest.join(&rel_path);
+        if entry_type.is_dir() {
+            fs::create_dir_all(&outpath)?;
+            continue;
+        }
+        if !entry_type.is_file() {
+            // Skip devices, FIFOs, and metadata entries such as PAX headers
+            continue;
+        }
+
+        // Check total uncompressed size and overall compression ratio
+        total_size = total_size.saturating_add(entry.header().size().map_err(tar_err)?);
+        limits.check_total_size(total_size, "Tar archive")?;
+        limits.check_ratio(total_size, compressed_size, &rel_path)?;
+
+        // Validate the parent directory is still within dest_dir before writing
+        if let Some(p) = outpath.parent() {
+            fs::create_dir_all(p)?;
+            let canonical_parent = p.canonicalize()?;
+            if !canonical_parent.starts_with(&canonical_dest) {
+                return Err(GaggleError::ZipError(format!(
+                    "Path traversal attempt detected: {:?}",
+                    rel_path
+                )));
+            }
+        }
+        let mut outfile = fs::File::create(&outpath)?;
+        std::io::copy(&mut entry, &mut outfile)?;
+        files_extracted += 1;
+    }
+
+    Ok(files_extracted)
+}
+
+/// Lists the files in a dataset.
+///
+/// If the dataset is cached locally, the function lists the files from the disk. Otherwise, it
+/// attempts to list them from the remote metadata. If the remote metadata is unavailable,
+/// it falls back to downloading the dataset and then listing the files. In dry-run mode, it
+/// returns an empty list.
+pub fn list_dataset_files(dataset_path: &str) -> Result<Vec<DatasetFile>, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    // Dry run: nothing has been downloaded, and nothing is fetched
+    if crate::config::dry_run() {
+        return Ok(Vec::new());
+    }
+    let dataset_dir = dataset_cache_dir(&owner, &dataset);
+
+    // If directory exists and has content, enumerate locally
+    if dataset_dir.exists() {
+        record_cache_access(&dataset_dir);
+        return list_local_files(&dataset_dir, None);
+    }
+
+    // Offline and not cached: the metadata persisted by an earlier fetch is all there is
+    if crate::config::offline_mode() {
+        if let Some(raw) = super::metadata::load_persisted_metadata(dataset_path) {
+            debug!(
+                dataset = dataset_path,
+                "offline; listing files from persisted metadata"
+            );
+            return Ok(super::metadata::DatasetMetadata::from_value(raw).files);
+        }
+    }
+
+    // Not cached: try remote listing via metadata
+    if !crate::config::offline_mode() {
+        if let Ok(list) = list_dataset_files_from_metadata(dataset_path) {
+            if !list.is_empty() {
+                debug!(
+                    dataset = dataset_path,
+                    count = list.len(),
+                    "listing files from remote metadata"
+                );
+                return Ok(list);
+            } else {
+                debug!(
+                    dataset = dataset_path,
+                    "remote metadata listing empty; will attempt download"
+                );
+            }
+        } else {
+            debug!(
+                dataset = dataset_path,
+                "failed to fetch remote metadata; will attempt download"
+            );
+        }
+    }
+
+    // As a last resort, download and list
+    let dataset_dir = download_dataset(dataset_path)?;
+    list_local_files(&dataset_dir, None)
+}
+
+/// Lists the files in a dataset path that may name a version and a directory inside the
+/// dataset, such as `"owner/dataset@v2/Twitter"` (see [`super::parse_dataset_path_full`]).
+///
+/// Without a version or subpath, this is [`list_dataset_files`]. With a version, that version
+/// is downloaded and listed. The names of files in a subpath include the subpath.
+pub fn list_dataset_files_full(dataset_path: &str) -> Result<Vec<DatasetFile>, GaggleError> {
+    let (owner, dataset, version, subpath) = super::parse_dataset_path_full(dataset_path)?;
+    let base_path = format!("{}/{}", owner, dataset);
+
+    if let Some(version) = version {
+        if crate::config::dry_run() {
+            return Ok(Vec::new());
+        }
+        let dataset_dir = download_dataset(&format!("{}@{}", base_path, version))?;
+        let dir = match subpath.as_deref() {
+            Some(sub) => dataset_dir.join(sub),
+            None => dataset_dir,
+        };
+        if !dir.is_dir() {
+            return Err(GaggleError::IoError(format!(
+                "Directory '{}' not found in dataset '{}'",
+                subpath.unwrap_or_default(),
+                dataset_path
+            )));
+        }
+        return list_local_files(&dir, subpath.as_deref());
+    }
+
+    let mut files = list_dataset_files(&base_path)?;
+    let Some(sub) = subpath else {
+        return Ok(files);
+    };
+    // Cached datasets are listed from the subdirectory; remote listings have full names
+    let dir = dataset_cache_dir(&owner, &dataset).join(&sub);
+    if dir.is_dir() {
+        return list_local_files(&dir, Some(&sub));
+    }
+    let prefix = format!("{}/", sub);
+    files.retain(|f| f.name.starts_with(&prefix));
+    Ok(files)
+}
+
+/// Lists the files directly in `dir`, skipping cache state files. Names are prefixed with
+/// `prefix` and a '/' when one is given.
+fn list_local_files(dir: &Path, prefix: Option<&str>) -> Result<Vec<DatasetFile>, GaggleError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        if is_cache_state_file(file_name) {
+            continue;
+        }
+        let name = file_name.to_string_lossy();
+        let name = match prefix {
+            Some(prefix) => format!("{}/{}", prefix, name),
+            None => name.to_string(),
+        };
+        files.push(DatasetFile::from_local(name, &fs::metadata(&path)?));
+    }
+    Ok(files)
+}
+
+/// Lists the files in a dataset whose extension matches `format` (such as `csv`, with or
+/// without a leading dot), ignoring case. A `format` of `*` or an empty string matches
+/// every file.
+pub fn list_dataset_files_by_format(
+    dataset_path: &str,
+    format: &str,
+) -> Result<Vec<DatasetFile>, GaggleError> {
+    let mut files = list_dataset_files(dataset_path)?;
+    files.retain(|f| file_has_format(&f.name, format));
+    Ok(files)
+}
+
+/// Lists the files in a dataset whose extension does not match `format`. This is the
+/// inverse of [`list_dataset_files_by_format`], so a `format` of `*` matches no files.
+#[allow(dead_code)]
+pub fn list_dataset_files_exclude_format(
+    dataset_path: &str,
+    format: &str,
+) -> Result<Vec<DatasetFile>, GaggleError> {
+    let mut files = list_dataset_files(dataset_path)?;
+    files.retain(|f| !file_has_format(&f.name, format));
+    Ok(files)
+}
+
+/// Whether the extension of `name` is `format`, ignoring case and a leading dot.
+fn file_has_format(name: &str, format: &str) -> bool {
+    let format = format.trim().trim_start_matches('.');
+    if format.is_empty() || format == "*" {
+        return true;
+    }
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(format))
+}
+
+/// Retrieves the local path to a specific file in a dataset.
+///
+/// In dry-run mode, returns the path the file would have without downloading it. With
+/// GAGGLE_AUTO_DECOMPRESS set, a compressed file is decompressed and the path of the
+/// decompressed file is returned (see [`crate::utils::materialize_readable`]).
+pub fn get_dataset_file_path(dataset_path: &str, filename: &str) -> Result<PathBuf, GaggleError> {
+    let target = format!("{}/{}", dataset_path, filename);
+    super::api::logged_operation("get_dataset_file_path", &target, || {
+        fetch_dataset_file(dataset_path, filename).and_then(auto_decompress)
+    })
+}
+
+/// Retrieves the local path to a file named by a dataset path that may include a version and
+/// a path inside the dataset, such as `"owner/dataset@v2/Twitter/tweets.csv"` (see
+/// [`super::parse_dataset_path_full`]).
+///
+/// `filename` is joined to the subpath, and may be empty when the subpath names the file.
+/// Without a version, this is [`get_dataset_file_path`]. With a version, that version of the
+/// dataset is downloaded and the file is looked up in it.
+pub fn get_dataset_file_path_full(
+    dataset_path: &str,
+    filename: &str,
+) -> Result<PathBuf, GaggleError> {
+    let (owner, dataset, version, subpath) = super::parse_dataset_path_full(dataset_path)?;
+    let base_path = format!("{}/{}", owner, dataset);
+    let filename = filename.trim_start_matches('/');
+    let file = match subpath {
+        Some(sub) if filename.is_empty() => sub,
+        Some(sub) => format!("{}/{}", sub, filename),
+        None => filename.to_string(),
+    };
+    if file.is_empty() {
+        return Err(GaggleError::InvalidDatasetPath(format!(
+            "No file given in '{}'",
+            dataset_path
+        )));
+    }
+
+    let Some(version) = version else {
+        return get_dataset_file_path(&base_path, &file);
+    };
+    validate_relative_filename(&file)?;
+    let dataset_dir = download_dataset(&format!("{}@{}", base_path, version))?;
+    let file_path = dataset_dir.join(&file);
+    if crate::config::dry_run() {
+        return Ok(file_path);
+    }
+    if !file_path.is_file() {
+        return Err(GaggleError::IoError(format!(
+            "File '{}' not found in dataset '{}'",
+            file, dataset_path
+        )));
+    }
+    auto_decompress(file_path)
+}
+
+/// Decompresses a dataset file when GAGGLE_AUTO_DECOMPRESS is set, and returns it otherwise.
+fn auto_decompress(path: PathBuf) -> Result<PathBuf, GaggleError> {
+    if crate::config::auto_decompress() && !crate::config::dry_run() {
+        return crate::utils::materialize_readable(&path);
+    }
+    Ok(path)
+}
+
+/// Finds or downloads a file of a dataset, for [`get_dataset_file_path`].
+fn fetch_dataset_file(dataset_path: &str, filename: &str) -> Result<PathBuf, GaggleError> {
+    let (dataset_dir, file_path) = dataset_file_location(dataset_path, filename)?;
+
+    // Dry run: the path the file would have, whether or not it exists
+    if crate::config::dry_run() {
+        return Ok(file_path);
+    }
+
+    // Fast path: file already present
+    if file_path.exists() {
+        record_cache_access(&dataset_dir);
+        metrics::incr(Counter::CacheHits);
+        return Ok(file_path);
+    }
+
+    // Try on-demand single-file download (without fetching whole archive)
+    match download_single_file(dataset_path, filename) {
+        Ok(p) => Ok(p),
+        Err(e) => {
+            if should_fall_back_to_dataset(&dataset_dir, dataset_path, filename, &e) {
+                let dir = download_dataset(dataset_path)?;
+                let p = dir.join(filename);
+                if p.exists() {
+                    return Ok(p);
+                }
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Retrieves the local path to a specific file in a dataset without blocking the calling
+/// thread.
+///
+/// This is the async counterpart of [`get_dataset_file_path`], with the same on-demand
+/// download, fallback, and decompression behavior.
+#[cfg(feature = "async")]
+pub async fn get_dataset_file_path_async(
+    dataset_path: &str,
+    filename: &str,
+) -> Result<PathBuf, GaggleError> {
+    fetch_dataset_file_async(dataset_path, filename)
+        .await
+        .and_then(auto_decompress)
+}
+
+/// Finds or downloads a file of a dataset, for [`get_dataset_file_path_async`].
+#[cfg(feature = "async")]
+async fn fetch_dataset_file_async(
+    dataset_path: &str,
+    filename: &str,
+) -> Result<PathBuf, GaggleError> {
+    let (dataset_dir, file_path) = dataset_file_location(dataset_path, filename)?;
+
+    // Dry run: the path the file would have, whether or not it exists
+    if crate::config::dry_run() {
+        return Ok(file_path);
+    }
+
+    // Fast path: file already present
+    if file_path.exists() {
+        record_cache_access(&dataset_dir);
+        metrics::incr(Counter::CacheHits);
+        return Ok(file_path);
+    }
+
+    match download_single_file_async(dataset_path, filename).await {
+        Ok(p) => Ok(p),
+        Err(e) => {
+            if should_fall_back_to_dataset(&dataset_dir, dataset_path, filename, &e) {
+                let dir = download_dataset_async(dataset_path).await?;
+                let p = dir.join(filename);
+                if p.exists() {
+                    return Ok(p);
+                }
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Validates `filename` and returns the cache directory of a dataset and the path of the
+/// file within it.
+fn dataset_file_location(
+    dataset_path: &str,
+    filename: &str,
+) -> Result<(PathBuf, PathBuf), GaggleError> {
+    // Validate filename to prevent path traversal or absolute paths
+    validate_relative_filename(filename)?;
+
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let dataset_dir = dataset_cache_dir(&owner, &dataset);
+    let file_path = dataset_dir.join(Path::new(filename));
+    Ok((dataset_dir, file_path))
+}
+
+/// Decides whether a failed on-demand file download should fall back to downloading the
+/// whole dataset, which is done when the dataset is not cached yet.
+fn should_fall_back_to_dataset(
+    dataset_dir: &Path,
+    dataset_path: &str,
+    filename: &str,
+    error: &GaggleError,
+) -> bool {
+    // The whole dataset would be refused for the same reason
+    if matches!(error, GaggleError::CredentialsError(_)) {
+        return false;
+    }
+    // In strict on-demand mode, do not fall back to full download
+    if crate::config::strict_on_demand() {
+        debug!(dataset = dataset_path, file = filename, error = %error, "on-demand fetch failed and strict mode enabled; not falling back");
+        return false;
+    }
+    // A directory left by a dry run holds only its marker, which does not count
+    let not_cached = !dataset_dir.exists()
+        || fs::read_dir(dataset_dir)
+            .map(|mut entries| entries.all(|e| e.is_ok_and(|e| e.file_name() == DRY_RUN_MARKER)))
+            .unwrap_or(true);
+    if not_cached {
+        debug!(dataset = dataset_path, file = filename, error = %error, "on-demand fetch failed; falling back to full dataset download");
+    }
+    not_cached
+}
+
+/// Resolves a glob pattern to the local paths of the matching files in a dataset.
+///
+/// The pattern is matched as in [`download_dataset_files`], so `"*.csv"` selects CSV files in
+/// every directory, `"data/*.csv"` only those in `data`, and `"**/*.parquet"` Parquet files at
+/// any depth. The dataset is listed from the cache if it is fully downloaded, and from its
+/// metadata otherwise; the matching files are then fetched on demand like
+/// [`get_dataset_file_path`] does.
+///
+/// Returns the paths sorted by file name, or an empty list if nothing matches. In dry-run
+/// mode, nothing is listed or fetched and the list is empty.
+///
+/// # Errors
+///
+/// Returns `InvalidDatasetPath` if the pattern is absolute or contains `..`, since it could
+/// then match paths outside the dataset directory.
+pub fn resolve_glob(dataset_path: &str, pattern: &str) -> Result<Vec<PathBuf>, GaggleError> {
+    // The pattern follows the rules for file names, so it cannot leave the dataset directory
+    let (dataset_dir, _) = dataset_file_location(dataset_path, pattern)?;
+    if crate::config::dry_run() {
+        return Ok(Vec::new());
+    }
+
+    let mut names: Vec<String> = glob_candidates(dataset_path, &dataset_dir)?
+        .into_iter()
+        .filter(|name| validate_relative_filename(name).is_ok() && entry_matches(pattern, name))
+        .collect();
+    names.sort();
+    names.dedup();
+    debug!(
+        dataset = dataset_path,
+        pattern = pattern,
+        count = names.len(),
+        "resolved glob"
+    );
+    names
+        .iter()
+        .map(|name| get_dataset_file_path(dataset_path, name))
+        .collect()
+}
+
+/// Relative paths of the files a glob is matched against: the cached files of a fully
+/// downloaded dataset, or else the files in its metadata. Without either, the dataset is
+/// downloaded and its files are listed.
+fn glob_candidates(dataset_path: &str, dataset_dir: &Path) -> Result<Vec<String>, GaggleError> {
+    if !dataset_dir.join(".downloaded").exists() {
+        let listed = if crate::config::offline_mode() {
+            super::metadata::load_persisted_metadata(dataset_path)
+                .map(|raw| super::metadata::DatasetMetadata::from_value(raw).files)
+        } else {
+            list_dataset_files_from_metadata(dataset_path).ok()
+        };
+        if let Some(files) = listed.filter(|files| !files.is_empty()) {
+            return Ok(files.into_iter().map(|f| f.name).collect());
+        }
+        download_dataset(dataset_path)?;
+    }
+
+    let mut paths = Vec::new();
+    collect_cache_files(dataset_dir, &mut paths)?;
+    Ok(paths
+        .iter()
+        .filter_map(|path| path.strip_prefix(dataset_dir).ok())
+        .map(zip_entry_name)
+        .collect())
+}
+
+/// Get all cached datasets with their metadata
+fn get_cached_datasets() -> Result<Vec<(PathBuf, CacheMetadata)>, GaggleError> {
+    let cache_root = crate::config::cache_dir_runtime().join("datasets");
+    if !cache_root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut datasets = Vec::new();
+
+    // Iterate through owner directories
+    for owner_entry in fs::read_dir(&cache_root)? {
+        let owner_entry = owner_entry?;
+        if !owner_entry.path().is_dir() {
+            continue;
+        }
+
+        // Iterate through dataset directories
+        for dataset_entry in fs::read_dir(owner_entry.path())? {
+            let dataset_entry = dataset_entry?;
+            let dataset_path = dataset_entry.path();
+            if !dataset_path.is_dir() {
+                continue;
+            }
+
+            let marker_file = dataset_path.join(".downloaded");
+            if marker_file.exists() {
+                match fs::read_to_string(&marker_file) {
+                    Ok(content) if !content.is_empty() => {
+                        // Try to parse metadata
+                        match serde_json::from_str::<CacheMetadata>(&content) {
+                            Ok(metadata) => {
+                                datasets.push((dataset_path, metadata));
+                            }
+                            Err(e) => {
+                                // Legacy or invalid marker - calculate size and synthesize metadata
+                                warn!(path = %marker_file.display(), error = %e, "Invalid cache metadata; synthesizing");
+                                let size_mb =
+                                    crate::utils::calculate_dir_size_no_follow(&dataset_path)
+                                        .unwrap_or(0)
+                                        .saturating_div(1024 * 1024);
+                                let owner = owner_entry.file_name().to_string_lossy().to_string();
+                                let dataset =
+                                    dataset_entry.file_name().to_string_lossy().to_string();
+                                let metadata =
+                                    CacheMetadata::new(format!("{}/{}", owner, dataset), size_mb);
+                                // retain None version
+                                datasets.push((dataset_path, metadata));
+                            }
+                        }
+                    }
+                    Ok(_) => {
+                        // Empty marker - synthesize
+                        warn!(path = %marker_file.display(), "Empty cache metadata; synthesizing");
+                        let size_mb = crate::utils::calculate_dir_size_no_follow(&dataset_path)
+                            .unwrap_or(0)
+                            .saturating_div(1024 * 1024);
+                        let owner = owner_entry.file_name().to_string_lossy().to_string();
+                        let dataset = dataset_entry.file_name().to_string_lossy().to_string();
+                        let metadata =
+                            CacheMetadata::new(format!("{}/{}", owner, dataset), size_mb);
+                        datasets.push((dataset_path, metadata));
+                    }
+                    Err(e) => {
+                        warn!(path = %marker_file.display(), error = %e, "Failed reading cache metadata; synthesizing");
+                        let size_mb = crate::utils::calculate_dir_size_no_follow(&dataset_path)
+                            .unwrap_or(0)
+                            .saturating_div(1024 * 1024);
+                        let owner = owner_entry.file_name().to_string_lossy().to_string();
+                        let dataset = dataset_entry.file_name().to_string_lossy().to_string();
+                        let metadata =
+                            CacheMetadata::new(format!("{}/{}", owner, dataset), size_mb);
+                        datasets.push((dataset_path, metadata));
+                    }
+                }
+            } else {
+                // No marker (e.g., partial on-demand downloads). Include in accounting.
+                let size_bytes =
+                    crate::utils::calculate_dir_size_no_follow(&dataset_path).unwrap_or(0);
+                let size_mb = size_bytes.saturating_div(1024 * 1024);
+                // Skip empty directories with zero size
+                if size_bytes > 0 {
+                    let owner = owner_entry.file_name().to_string_lossy().to_string();
+                    let dataset = dataset_entry.file_name().to_string_lossy().to_string();
+                    let metadata = CacheMetadata::new(format!("{}/{}", owner, dataset), size_mb);
+                    datasets.push((dataset_path, metadata));
+                }
+            }
+        }
+    }
+
+    Ok(datasets)
+}
+
+/// Calculates the total size of the cache in megabytes.
+pub fn get_total_cache_size_mb() -> Result<u64, GaggleError> {
+    let datasets = get_cached_datasets()?;
+    Ok(datasets.iter().map(|(_, meta)| meta.size_mb).sum())
+}
+
+/// Measures the size of one cached dataset in megabytes by walking its directory, rather
+/// than reading the size recorded in its marker. A dataset that is not cached has a size of
+/// zero.
+pub fn get_dataset_cache_size_mb(dataset_path: &str) -> Result<u64, GaggleError> {
+    let (owner, dataset, version) = super::parse_dataset_path_with_version(dataset_path)?;
+    let cache_subdir = match version {
+        Some(ref v) => format!("{}-v{}", dataset, v),
+        None => dataset,
+    };
+    let cache_dir = dataset_cache_dir(&owner, &cache_subdir);
+    let bytes = crate::utils::calculate_dir_size(&cache_dir)?;
+    Ok(bytes.saturating_div(1024 * 1024))
+}
+
+/// A summary of one dataset in the cache, as reported by `gaggle_get_cache_info`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedDataset {
+    /// The dataset path (`owner/dataset`).
+    pub path: String,
+    /// The size recorded in the marker, or measured for partial downloads, in megabytes.
+    pub size_mb: u64,
+    /// The cached version, if known.
+    pub version: Option<String>,
+    /// Seconds since the dataset was downloaded.
+    pub age_seconds: u64,
+    /// Whether the dataset has no `.downloaded` marker (for example, on-demand file downloads).
+    pub partial: bool,
+}
+
+/// Lists the cached datasets using the sizes recorded in their markers.
+///
+/// Only partial downloads without a marker have their directory walked.
+pub fn list_cached_datasets() -> Result<Vec<CachedDataset>, GaggleError> {
+    Ok(get_cached_datasets()?
+        .into_iter()
+        .map(|(dir, meta)| CachedDataset {
+            partial: !dir.join(".downloaded").exists(),
+            age_seconds: meta.age_seconds(),
+            path: meta.dataset_path,
+            size_mb: meta.size_mb,
+            version: meta.version,
+        })
+        .collect())
+}
+
+/// Recalculates the size of every fully downloaded dataset and rewrites it in its marker.
+///
+/// Returns the number of markers whose size changed.
+pub fn refresh_cache_sizes() -> Result<usize, GaggleError> {
+    let mut updated = 0;
+    for (dir, mut meta) in get_cached_datasets()? {
+        let marker_file = dir.join(".downloaded");
+        if !marker_file.exists() {
+            continue; // Partial downloads are measured on every listing
+        }
+        let size_mb = crate::utils::calculate_dir_size_no_follow(&dir)?.saturating_div(1024 * 1024);
+        if size_mb != meta.size_mb {
+            meta.size_mb = size_mb;
+            crate::utils::write_marker_atomic(&marker_file, &serde_json::to_string(&meta)?)?;
+            updated += 1;
+        }
+    }
+    Ok(updated)
+}
+
+/// Enforce cache size limit using LRU eviction
+fn enforce_cache_limit() -> Result<(), GaggleError> {
+    let limit_mb = match crate::config::cache_size_limit_mb() {
+        Some(limit) => limit,
+        None => return Ok(()), // No limit set
+    };
+
+    evict_lru(limit_mb, None)?;
+    Ok(())
+}
+
+/// Orders `datasets` so that the first entry is the first to be evicted under `policy`.
+///
+/// * `lru` - least recently accessed first (the default, also used for unknown policies)
+/// * `lfu` - least frequently accessed first, then least recently accessed
+/// * `fifo` - oldest download first
+/// * `largest_first` - largest dataset first
+fn sort_datasets_for_eviction(policy: &str, datasets: &mut [(PathBuf, CacheMetadata)]) {
+    match policy {
+        "lfu" => datasets.sort_by_key(|(_, meta)| (meta.access_count, meta.last_accessed())),
+        "fifo" => datasets.sort_by_key(|(_, meta)| meta.downloaded_at_secs),
+        "largest_first" => {
+            datasets.sort_by_key(|(_, meta)| std::cmp::Reverse(meta.size_mb));
+        }
+        other => {
+            if other != "lru" {
+                warn!(
+                    policy = other,
+                    "unknown cache eviction policy; falling back to lru"
+                );
+            }
+            datasets.sort_by_key(|(_, meta)| meta.last_accessed());
+        }
+    }
+}
+
+/// Evicts datasets in eviction policy order until the cache is at most `target_mb`, skipping `keep`.
+///
+/// Returns the total cache size in megabytes after eviction.
+fn evict_lru(target_mb: u64, keep: Option<&Path>) -> Result<u64, GaggleError> {
+    let mut datasets = get_cached_datasets()?;
+    let mut total_size_mb: u64 = datasets.iter().map(|(_, meta)| meta.size_mb).sum();
+
+    if total_size_mb <= target_mb {
+        return Ok(total_size_mb); // Within limit
+    }
+
+    sort_datasets_for_eviction(&crate::config::cache_eviction_policy(), &mut datasets);
 
     // Evict oldest datasets until under limit
     for (dataset_path, metadata) in datasets {
-        if total_size_mb <= limit_mb {
+        if total_size_mb <= target_mb {
             break;
         }
+        if keep == Some(dataset_path.as_path()) || is_pinned(&dataset_path) {
+            continue;
+        }
+
+        // Remove dataset directory
+        if let Err(e) = fs::remove_dir_all(&dataset_path) {
+            warn!(path = %dataset_path.display(), error = %e, "Failed to evict dataset");
+            continue;
+        }
+
+        total_size_mb = total_size_mb.saturating_sub(metadata.size_mb);
+        debug!(
+            dataset = %metadata.dataset_path,
+            age_secs = metadata.age_seconds(),
+            size_mb = metadata.size_mb,
+            "Cache eviction: removed dataset to enforce limit"
+        );
+    }
+
+    Ok(total_size_mb)
+}
+
+/// A public function that manually enforces the cache limit.
+pub fn enforce_cache_limit_now() -> Result<(), GaggleError> {
+    enforce_cache_limit()
+}
+
+/// Whether the cached dataset in `dataset_dir` is pinned
+fn is_pinned(dataset_dir: &Path) -> bool {
+    dataset_dir.join(PINNED_MARKER).exists()
+}
+
+/// Removes the cached copy of one dataset, leaving the rest of the cache alone.
+///
+/// A pinned dataset is only removed with `force`, which also removes the pin. A dataset that
+/// is not cached is not an error. A download of the dataset that is in progress is waited for
+/// before the dataset is removed.
+pub fn clear_dataset_cache(dataset_path: &str, force: bool) -> Result<(), GaggleError> {
+    let (owner, dataset, version) = super::parse_dataset_path_with_version(dataset_path)?;
+    let cache_subdir = match version {
+        Some(ref v) => format!("{}-v{}", dataset, v),
+        None => dataset.clone(),
+    };
+    let cache_dir = dataset_cache_dir(&owner, &cache_subdir);
+
+    let lock_key = dataset_lock_key(&owner, &cache_subdir);
+    let guard = loop {
+        if let Some(guard) = acquire_download_lock(&lock_key, dataset_path, || false)? {
+            break guard;
+        }
+    };
+    let result = (|| -> Result<(), GaggleError> {
+        if !cache_dir.exists() {
+            return Ok(());
+        }
+        if is_pinned(&cache_dir) {
+            if !force {
+                return Err(GaggleError::IoError(
+                    "Dataset is pinned; use force=true".to_string(),
+                ));
+            }
+            fs::remove_file(cache_dir.join(PINNED_MARKER))?;
+        }
+        fs::remove_dir_all(&cache_dir)?;
+        debug!(dataset = dataset_path, "cleared cached dataset");
+        Ok(())
+    })();
+    guard.finish(&result);
+    result
+}
+
+/// Removes the cached datasets that were downloaded more than `max_age_secs` seconds ago.
+///
+/// Pinned datasets are kept. Returns the number of datasets removed.
+pub fn evict_datasets_older_than(max_age_secs: u64) -> Result<usize, GaggleError> {
+    let mut removed = 0;
+    for (dataset_dir, metadata) in get_cached_datasets()? {
+        if metadata.age_seconds() <= max_age_secs || is_pinned(&dataset_dir) {
+            continue;
+        }
+        if let Err(e) = fs::remove_dir_all(&dataset_dir) {
+            warn!(path = %dataset_dir.display(), error = %e, "Failed to evict dataset");
+            continue;
+        }
+        removed += 1;
+        debug!(
+            dataset = %metadata.dataset_path,
+            age_secs = metadata.age_seconds(),
+            "Cache eviction: removed dataset older than the maximum age"
+        );
+    }
+    Ok(removed)
+}
+
+/// Reads the marker of a cached dataset, upgrading a legacy marker in place.
+///
+/// Markers written by older versions are empty or do not parse as [`CacheMetadata`]. Outside
+/// offline mode, such a marker is rewritten with the size of the dataset directory, the
+/// current version from the API, and the marker's modification time as the download time.
+/// Returns `None` when there is no marker, or when a legacy marker cannot be upgraded
+/// because offline mode is enabled.
+fn read_or_upgrade_marker(
+    dataset_path: &str,
+    cache_dir: &Path,
+) -> Result<Option<CacheMetadata>, GaggleError> {
+    let marker_file = cache_dir.join(".downloaded");
+    if !marker_file.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&marker_file)?;
+    if let Ok(metadata) = serde_json::from_str::<CacheMetadata>(&content) {
+        return Ok(Some(metadata));
+    }
+    if crate::config::offline_mode() {
+        debug!(
+            dataset = dataset_path,
+            "legacy cache marker cannot be upgraded in offline mode"
+        );
+        return Ok(None);
+    }
+
+    let version = super::metadata::get_current_version(dataset_path)?;
+    let size_mb = crate::utils::calculate_dir_size_no_follow(cache_dir)
+        .unwrap_or(0)
+        .saturating_div(1024 * 1024);
+    let mut metadata = CacheMetadata::new(dataset_path.to_string(), size_mb);
+    metadata.version = Some(version);
+    if let Some(modified_secs) = fs::metadata(&marker_file)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+    {
+        metadata.downloaded_at_secs = modified_secs;
+        metadata.last_accessed_secs = modified_secs;
+    }
+    crate::utils::write_marker_atomic(&marker_file, &serde_json::to_string(&metadata)?)?;
+    debug!(
+        dataset = dataset_path,
+        version = ?metadata.version,
+        "upgraded legacy cache marker"
+    );
+    Ok(Some(metadata))
+}
+
+/// Checks if the cached dataset is the current version.
+///
+/// A legacy marker written by an older version is upgraded first, so the dataset is
+/// compared with the version that was current when the marker was upgraded. In offline
+/// mode, a legacy marker cannot be upgraded, so whether the dataset is current is unknown and
+/// an error saying so is returned.
+pub fn is_dataset_current(dataset_path: &str) -> Result<bool, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+
+    let cache_dir = dataset_cache_dir(&owner, &dataset);
+
+    let Some(cached_metadata) =
+        read_or_upgrade_marker(&format!("{}/{}", owner, dataset), &cache_dir)?
+    else {
+        if cache_dir.join(".downloaded").exists() {
+            return Err(GaggleError::HttpRequestError(format!(
+                "Offline mode enabled and the cache marker of '{}' was written by an older version, so its version is unknown. Unset GAGGLE_OFFLINE to upgrade the marker.",
+                dataset_path
+            )));
+        }
+        return Ok(false); // Not cached
+    };
+
+    let cached_version = cached_metadata.version.as_deref().unwrap_or("unknown");
+
+    // Get current version from Kaggle
+    let current_version = super::metadata::get_current_version(dataset_path)?;
+
+    // If we cannot determine current version, conservatively report not current
+    if current_version == "unknown" {
+        return Ok(false);
+    }
+
+    Ok(cached_version == current_version)
+}
+
+/// The outcome of [`check_for_update`].
+#[derive(Debug, Clone)]
+pub(crate) struct StaleCheck {
+    /// The local path of the dataset.
+    pub(crate) path: PathBuf,
+    /// Whether the dataset was downloaded because it was missing or outdated.
+    pub(crate) was_updated: bool,
+    /// The cached version before the check, if the dataset was cached.
+    pub(crate) previous_version: Option<String>,
+    /// The cached version after the check.
+    pub(crate) current_version: Option<String>,
+}
+
+/// Downloads a dataset only if it is not cached or the cached copy is not the latest version.
+///
+/// Returns the local path and whether the dataset was downloaded.
+#[allow(dead_code)]
+pub fn download_dataset_if_stale(dataset_path: &str) -> Result<(PathBuf, bool), GaggleError> {
+    check_for_update(dataset_path).map(|check| (check.path, check.was_updated))
+}
+
+/// Looks up the latest version of a dataset and downloads it with [`update_dataset`] unless
+/// the cached copy is that version.
+pub(crate) fn check_for_update(dataset_path: &str) -> Result<StaleCheck, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let cache_dir = dataset_cache_dir(&owner, &dataset);
+    let previous_version = cached_version(&cache_dir);
+
+    // Bypass the in-memory metadata cache so the version is really rechecked
+    super::metadata::invalidate_metadata(dataset_path);
+    if is_dataset_current(dataset_path)? {
+        record_cache_access(&cache_dir);
+        return Ok(StaleCheck {
+            path: cache_dir,
+            was_updated: false,
+            current_version: previous_version.clone(),
+            previous_version,
+        });
+    }
+
+    let path = update_dataset(dataset_path)?;
+    let current_version = cached_version(&path);
+    debug!(
+        dataset = dataset_path,
+        previous = ?previous_version,
+        current = ?current_version,
+        "downloaded stale dataset"
+    );
+    Ok(StaleCheck {
+        path,
+        was_updated: true,
+        previous_version,
+        current_version,
+    })
+}
+
+/// The version recorded in the marker of a cached dataset, if any.
+fn cached_version(cache_dir: &Path) -> Option<String> {
+    fs::read_to_string(cache_dir.join(".downloaded"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<CacheMetadata>(&content).ok())
+        .and_then(|metadata| metadata.version)
+}
+
+/// Forces an update of the dataset to the latest version, ignoring the cache.
+///
+/// The new copy is extracted in the staging directory and replaces the cached copy only
+/// once it is complete, so the cached copy is kept when the download fails. A path with a
+/// version, such as `owner/dataset@v3`, downloads the cached copy of that version again.
+pub fn update_dataset(dataset_path: &str) -> Result<PathBuf, GaggleError> {
+    let (owner, dataset, version) = super::parse_dataset_path_with_version(dataset_path)?;
+    let base_path = format!("{}/{}", owner, dataset);
+
+    // Drop cached metadata so the fresh download sees the latest version
+    super::metadata::invalidate_metadata(&base_path);
+
+    super::api::logged_operation("update_dataset", dataset_path, || {
+        download_dataset_version(&base_path, version, true)
+    })
+}
+
+/// Retrieves version information for a dataset.
+///
+/// A legacy marker written by an older version is upgraded first, as in
+/// [`is_dataset_current`]. In offline mode, the cached version of a legacy marker is
+/// reported as `"unknown"`.
+pub fn get_dataset_version_info(dataset_path: &str) -> Result<serde_json::Value, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+
+    let cache_dir = dataset_cache_dir(&owner, &dataset);
+
+    let marker_file = cache_dir.join(".downloaded");
+
+    let cached_version =
+        match read_or_upgrade_marker(&format!("{}/{}", owner, dataset), &cache_dir)? {
+            Some(metadata) => metadata.version,
+            None if marker_file.exists() => Some("unknown".to_string()),
+            None => None,
+        };
+
+    // Get current version from Kaggle API
+    let current_version = super::metadata::get_current_version(dataset_path)?;
+
+    // Consider unknown latest version as not current
+    let is_current = if current_version == "unknown" {
+        false
+    } else {
+        cached_version
+            .as_ref()
+            .map(|v| v == &current_version)
+            .unwrap_or(false)
+    };
+
+    let info = serde_json::json!({
+        "cached_version": cached_version,
+        "latest_version": current_version,
+        "is_current": is_current,
+        "is_cached": marker_file.exists()
+    });
+
+    Ok(info)
+}
+
+/// Reports what downloading a dataset would do, without downloading or removing anything.
+///
+/// The plan is a JSON object with these fields:
+///
+/// * `dataset`, `version`, and `cache_dir` - the dataset, the version requested with
+///   `owner/dataset@N` (or `null`), and the directory it is cached in
+/// * `is_cached`, `cached_version`, `latest_version`, and `is_current` - the cached copy
+///   compared with the latest version
+/// * `would_download` - whether the dataset is not cached yet
+/// * `remote_size_bytes` - the total size of the dataset from its metadata
+/// * `metadata_source` - `api`, `local` (metadata saved by an earlier fetch), or `null`
+/// * `cache` - `usage_mb`, `limit_mb`, `headroom_mb`, `min_free_mb`, `is_soft_limit`, and
+///   `eviction_policy`, where `limit_mb` and `headroom_mb` are `null` without a limit
+/// * `evictions` - the `dataset` and `size_mb` of each cached dataset that eviction would
+///   remove to make room, in eviction order
+/// * `fits` - whether the dataset fits under the cache limit after those evictions
+/// * `undetermined` - the names of the fields that could not be determined, which are `null`
+///
+/// In offline mode, only local information is used: the `.downloaded` marker and the
+/// metadata saved by an earlier fetch, if any.
+pub fn plan_download(dataset_path: &str) -> Result<serde_json::Value, GaggleError> {
+    let (owner, dataset, version) = super::parse_dataset_path_with_version(dataset_path)?;
+    let base_path = format!("{}/{}", owner, dataset);
+    let cache_subdir = match version {
+        Some(ref v) => format!("{}-v{}", dataset, v),
+        None => dataset.clone(),
+    };
+    let cache_dir = dataset_cache_dir(&owner, &cache_subdir);
+    let offline = crate::config::offline_mode();
+    let mut undetermined: Vec<&str> = Vec::new();
+
+    let marker = fs::read_to_string(cache_dir.join(".downloaded"))
+        .ok()
+        .map(|content| serde_json::from_str::<CacheMetadata>(&content).ok());
+    let is_cached = marker.is_some();
+    let cached_version = marker.flatten().and_then(|m| m.version);
+
+    let (metadata, metadata_source) = if offline {
+        match super::metadata::load_persisted_metadata(&base_path) {
+            Some(raw) => (
+                Some(super::metadata::DatasetMetadata::from_value(raw)),
+                Some("local"),
+            ),
+            None => (None, None),
+        }
+    } else {
+        (
+            Some(super::metadata::get_dataset_metadata_typed(&base_path)?),
+            Some("api"),
+        )
+    };
+
+    let latest_version = metadata.as_ref().and_then(|m| m.current_version.clone());
+    if latest_version.is_none() {
+        undetermined.push("latest_version");
+    }
+    let is_current = match (&cached_version, &latest_version) {
+        (Some(cached), Some(latest)) => Some(cached == latest),
+        (None, _) if !is_cached => Some(false),
+        _ => {
+            undetermined.push("is_current");
+            None
+        }
+    };
+    let remote_size_bytes = metadata.as_ref().and_then(|m| m.total_bytes);
+    if remote_size_bytes.is_none() {
+        undetermined.push("remote_size_bytes");
+    }
+
+    let usage_mb = get_total_cache_size_mb()?;
+    let limit_mb = crate::config::cache_size_limit_mb();
+    let min_free_mb = crate::config::min_free_cache_mb();
+
+    // Mirrors the hard limit check: evict until the dataset and the free headroom fit
+    let mut evictions = Vec::new();
+    let fits = match (is_cached, limit_mb, remote_size_bytes) {
+        (true, _, _) | (false, None, _) => Some(true),
+        (false, Some(_), None) => None,
+        (false, Some(limit_mb), Some(total_bytes)) => {
+            let required_mb = total_bytes.div_ceil(1_048_576);
+            let reserved_mb = required_mb.saturating_add(min_free_mb);
+            let mut usage_after_mb = usage_mb;
+            if usage_mb.saturating_add(reserved_mb) > limit_mb && reserved_mb <= limit_mb {
+                let target_mb = limit_mb.saturating_sub(reserved_mb);
+                let mut datasets = get_cached_datasets()?;
+                sort_datasets_for_eviction(&crate::config::cache_eviction_policy(), &mut datasets);
+                for (dir, meta) in datasets {
+                    if usage_after_mb <= target_mb {
+                        break;
+                    }
+                    if dir == cache_dir || is_pinned(&dir) {
+                        continue;
+                    }
+                    usage_after_mb = usage_after_mb.saturating_sub(meta.size_mb);
+                    evictions.push(serde_json::json!({
+                        "dataset": meta.dataset_path,
+                        "size_mb": meta.size_mb,
+                    }));
+                }
+            }
+            Some(required_mb <= limit_mb.saturating_sub(usage_after_mb))
+        }
+    };
+    if fits.is_none() {
+        undetermined.push("fits");
+    }
+
+    Ok(serde_json::json!({
+        "dataset": base_path,
+        "version": version,
+        "cache_dir": cache_dir.to_string_lossy(),
+        "offline": offline,
+        "is_cached": is_cached,
+        "cached_version": cached_version,
+        "latest_version": latest_version,
+        "is_current": is_current,
+        "would_download": !is_cached,
+        "remote_size_bytes": remote_size_bytes,
+        "metadata_source": metadata_source,
+        "cache": {
+            "usage_mb": usage_mb,
+            "limit_mb": limit_mb,
+            "headroom_mb": limit_mb.map(|limit| limit.saturating_sub(usage_mb)),
+            "min_free_mb": min_free_mb,
+            "is_soft_limit": crate::config::cache_limit_is_soft(),
+            "eviction_policy": crate::config::cache_eviction_policy(),
+        },
+        "evictions": evictions,
+        "fits": fits,
+        "undetermined": undetermined,
+    }))
+}
+
+/// Checks the files of a downloaded dataset against the checksums recorded by the last check.
+///
+/// The SHA-256 checksum of every file is stored in the `.downloaded` marker together with
+/// the file's modification time. A file whose modification time has not changed since is
+/// not hashed again, so repeated checks only need to stat the files. The report lists the
+/// files whose content changed and the recorded files that are missing.
+pub fn verify_dataset_integrity(dataset_path: &str) -> Result<serde_json::Value, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+
+    let cache_dir = dataset_cache_dir(&owner, &dataset);
+
+    let marker_file = cache_dir.join(".downloaded");
+    if !marker_file.exists() {
+        return Err(GaggleError::DatasetNotFound(format!(
+            "Dataset '{}' is not downloaded",
+            dataset_path
+        )));
+    }
+    let content = fs::read_to_string(&marker_file)?;
+    let mut metadata = if content.is_empty() {
+        // Legacy marker without metadata
+        CacheMetadata::new(dataset_path.to_string(), 0)
+    } else {
+        serde_json::from_str::<CacheMetadata>(&content)
+            .map_err(|e| GaggleError::IoError(format!("Failed to parse cache metadata: {}", e)))?
+    };
+
+    let mut files = Vec::new();
+    collect_cache_files(&cache_dir, &mut files)?;
+
+    let mut checksums = HashMap::new();
+    let mut mtimes = HashMap::new();
+    let mut modified = Vec::new();
+    let mut rehashed = 0usize;
+    for path in files {
+        let rel = zip_entry_name(path.strip_prefix(&cache_dir).unwrap_or(&path));
+        let mtime = fs::metadata(&path)?
+            .modified()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let recorded = metadata.file_checksums.get(&rel);
+        let checksum = match recorded {
+            Some(sum) if metadata.file_mtimes.get(&rel) == Some(&mtime) => sum.clone(),
+            _ => {
+                rehashed += 1;
+                let sum = sha256_file(&path)?;
+                if recorded.is_some_and(|old| *old != sum) {
+                    modified.push(rel.clone());
+                }
+                sum
+            }
+        };
+        checksums.insert(rel.clone(), checksum);
+        mtimes.insert(rel, mtime);
+    }
+
+    let mut missing: Vec<String> = metadata
+        .file_checksums
+        .keys()
+        .filter(|rel| !checksums.contains_key(*rel))
+        .cloned()
+        .collect();
+    missing.sort();
+    modified.sort();
+
+    metadata.file_checksums = checksums;
+    metadata.file_mtimes = mtimes;
+    metadata.checksum_computed_at_secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    crate::utils::write_marker_atomic(&marker_file, &serde_json::to_string(&metadata)?)?;
+
+    let checksums: std::collections::BTreeMap<_, _> = metadata.file_checksums.iter().collect();
+    Ok(serde_json::json!({
+        "dataset": dataset_path,
+        "valid": modified.is_empty() && missing.is_empty(),
+        "files": checksums.len(),
+        "rehashed": rehashed,
+        "modified": modified,
+        "missing": missing,
+        "checksums": checksums,
+    }))
+}
+
+/// The result of [`check_dataset_integrity`], as reported by `gaggle_check_dataset`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IntegrityReport {
+    /// Whether every file Kaggle lists is cached with the expected size. Extra local files
+    /// do not make a dataset incomplete.
+    pub complete: bool,
+    /// The number of files Kaggle lists for the dataset.
+    pub remote_files: usize,
+    /// The number of files in the local cache.
+    pub local_files: usize,
+    /// The files Kaggle lists that are not cached.
+    pub missing: Vec<String>,
+    /// The cached files whose size differs from the size Kaggle lists.
+    pub size_mismatches: Vec<SizeMismatch>,
+    /// The cached files that Kaggle does not list.
+    pub extra: Vec<String>,
+}
+
+/// A cached file whose size differs from the one in the remote listing.
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeMismatch {
+    /// The path of the file relative to the dataset directory.
+    pub file: String,
+    /// The size in bytes that Kaggle lists.
+    pub expected_bytes: u64,
+    /// The size in bytes of the cached file.
+    pub actual_bytes: u64,
+}
+
+/// Compares the cached files of a dataset with the files Kaggle lists for it.
+///
+/// The remote listing comes from the dataset metadata, or in offline mode from the metadata
+/// persisted by the last fetch. The report lists the remote files that are not cached, the
+/// cached files with a different size, and the cached files that are not listed. Sizes are
+/// only compared for files whose remote size is known. This also works for partially
+/// downloaded datasets and for datasets that are not cached at all.
+pub fn check_dataset_integrity(dataset_path: &str) -> Result<IntegrityReport, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let remote = if crate::config::offline_mode() {
+        super::metadata::load_persisted_metadata(dataset_path)
+            .map(|raw| super::metadata::DatasetMetadata::from_value(raw).files)
+            .ok_or_else(|| {
+                GaggleError::HttpRequestError(format!(
+                    "Offline mode enabled and no metadata of '{}' is saved; cannot check it. Unset GAGGLE_OFFLINE to enable network.",
+                    dataset_path
+                ))
+            })?
+    } else {
+        list_dataset_files_from_metadata(dataset_path)?
+    };
+    if remote.is_empty() {
+        return Err(GaggleError::HttpRequestError(format!(
+            "Kaggle metadata of '{}' does not list any files",
+            dataset_path
+        )));
+    }
+
+    let cache_dir = dataset_cache_dir(&owner, &dataset);
+    let mut local = HashMap::new();
+    if cache_dir.is_dir() {
+        let mut paths = Vec::new();
+        collect_cache_files(&cache_dir, &mut paths)?;
+        for path in paths {
+            let rel = zip_entry_name(path.strip_prefix(&cache_dir).unwrap_or(&path));
+            local.insert(rel, fs::metadata(&path)?.len());
+        }
+    }
+
+    let mut report = IntegrityReport {
+        remote_files: remote.len(),
+        local_files: local.len(),
+        ..Default::default()
+    };
+    for file in &remote {
+        match local.get(&file.name) {
+            None => report.missing.push(file.name.clone()),
+            // A size of 0 means Kaggle did not report one
+            Some(&actual) if file.size > 0 && actual != file.size => {
+                report.size_mismatches.push(SizeMismatch {
+                    file: file.name.clone(),
+                    expected_bytes: file.size,
+                    actual_bytes: actual,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    let listed: std::collections::HashSet<&str> = remote.iter().map(|f| f.name.as_str()).collect();
+    report.extra = local
+        .keys()
+        .filter(|rel| !listed.contains(rel.as_str()))
+        .cloned()
+        .collect();
+    report.missing.sort();
+    report.size_mismatches.sort_by(|a, b| a.file.cmp(&b.file));
+    report.extra.sort();
+    report.complete = report.missing.is_empty() && report.size_mismatches.is_empty();
+    debug!(
+        dataset = dataset_path,
+        complete = report.complete,
+        missing = report.missing.len(),
+        mismatched = report.size_mismatches.len(),
+        extra = report.extra.len(),
+        "checked dataset against remote listing"
+    );
+    Ok(report)
+}
+
+/// Computes the SHA-256 checksum of a file as a hex string, reading it in chunks.
+fn sha256_file(path: &Path) -> Result<String, GaggleError> {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    let mut file = fs::File::open(path)?;
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Copies a dataset to `dest_dir`, downloading it first if it is not cached.
+///
+/// Files keep their paths relative to the dataset directory. Gaggle's own state files, such
+/// as the `.downloaded` marker, are not copied. Returns the number of bytes copied.
+pub fn copy_dataset_to(dataset_path: &str, dest_dir: &Path) -> Result<u64, GaggleError> {
+    check_copy_destination(dest_dir)?;
+    let dataset_dir = download_dataset(dataset_path)?;
+
+    let mut files = Vec::new();
+    collect_cache_files(&dataset_dir, &mut files)?;
+    files.sort();
+
+    fs::create_dir_all(dest_dir)?;
+    let mut total_bytes: u64 = 0;
+    for path in files {
+        let rel = path.strip_prefix(&dataset_dir).unwrap_or(&path);
+        let target = dest_dir.join(rel);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        total_bytes = total_bytes.saturating_add(fs::copy(&path, &target)?);
+    }
+    debug!(
+        dataset = dataset_path,
+        bytes = total_bytes,
+        dest = %dest_dir.display(),
+        "copied dataset"
+    );
+    Ok(total_bytes)
+}
+
+/// Copies one file of a dataset to `dest_file`, downloading it first if it is not cached.
+/// Returns the number of bytes copied.
+pub fn copy_file_to(
+    dataset_path: &str,
+    filename: &str,
+    dest_file: &Path,
+) -> Result<u64, GaggleError> {
+    check_copy_destination(dest_file)?;
+    let source = get_dataset_file_path(dataset_path, filename)?;
+    if !source.is_file() {
+        return Err(GaggleError::IoError(format!(
+            "File '{}' not found in dataset '{}'",
+            filename, dataset_path
+        )));
+    }
+    if let Some(parent) = dest_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::copy(&source, dest_file)?)
+}
+
+/// A summary of [`export_dataset`], as reported by `gaggle_export_dataset`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportSummary {
+    /// The number of files exported, including hard-linked ones.
+    pub files_copied: usize,
+    /// The number of files exported as hard links instead of copies.
+    pub files_linked: usize,
+    /// The total size of the exported files in bytes.
+    pub bytes: u64,
+    /// The files that could not be exported.
+    pub errors: Vec<ExportError>,
+}
+
+/// A file that [`export_dataset`] could not export.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportError {
+    /// The path of the file relative to the dataset directory.
+    pub file: String,
+    /// Why the file was not exported.
+    pub error: String,
+}
+
+/// Exports the files of a dataset to `dest_dir`, downloading the dataset first if it is not
+/// cached.
+///
+/// Files keep their paths relative to the dataset directory, and Gaggle's own state files
+/// are left out. Each file is hard-linked when `dest_dir` is on the same file system as the
+/// cache, and copied otherwise; a hard-linked file shares its contents with the cached copy,
+/// so it should not be modified in place. Existing files are replaced only if `overwrite` is
+/// set. A file that cannot be exported is reported in [`ExportSummary::errors`] without
+/// stopping the export of the others.
+///
+/// # Errors
+///
+/// Returns an error if `dest_dir` is inside the cache directory, or if the dataset cannot be
+/// downloaded or listed.
+pub fn export_dataset(
+    dataset_path: &str,
+    dest_dir: &Path,
+    overwrite: bool,
+) -> Result<ExportSummary, GaggleError> {
+    check_copy_destination(dest_dir)?;
+    let dataset_dir = download_dataset(dataset_path)?;
+
+    let mut files = Vec::new();
+    collect_cache_files(&dataset_dir, &mut files)?;
+    files.sort();
+
+    fs::create_dir_all(dest_dir)?;
+    let mut summary = ExportSummary::default();
+    for path in files {
+        let rel = path.strip_prefix(&dataset_dir).unwrap_or(&path);
+        match export_file(&path, &dest_dir.join(rel), overwrite) {
+            Ok((bytes, linked)) => {
+                summary.files_copied += 1;
+                summary.files_linked += usize::from(linked);
+                summary.bytes = summary.bytes.saturating_add(bytes);
+            }
+            Err(e) => summary.errors.push(ExportError {
+                file: zip_entry_name(rel),
+                error: e.to_string(),
+            }),
+        }
+    }
+    debug!(
+        dataset = dataset_path,
+        files = summary.files_copied,
+        linked = summary.files_linked,
+        errors = summary.errors.len(),
+        dest = %dest_dir.display(),
+        "exported dataset"
+    );
+    Ok(summary)
+}
+
+/// Hard-links or copies `source` to `target`. Returns the size of the file and whether it
+/// was hard-linked.
+fn export_file(source: &Path, target: &Path, overwrite: bool) -> Result<(u64, bool), GaggleError> {
+    if target.symlink_metadata().is_ok() {
+        if !overwrite {
+            return Err(GaggleError::IoError(format!(
+                "{} already exists",
+                target.display()
+            )));
+        }
+        fs::remove_file(target)?;
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let bytes = fs::metadata(source)?.len();
+    // Linking fails across file systems, in which case the file is copied
+    if fs::hard_link(source, target).is_ok() {
+        return Ok((bytes, true));
+    }
+    Ok((fs::copy(source, target)?, false))
+}
+
+/// Imports the files of a local directory into the cache as the dataset `dataset_path`.
+///
+/// The files are copied into the cache layout and a `.downloaded` marker is written with
+/// `version`, so the dataset is served like a downloaded one afterwards, including in offline
+/// mode. A cached copy of the dataset is replaced. Symbolic links and cache state files in
+/// `source_dir` are skipped.
+///
+/// Returns the directory of the imported dataset.
+pub fn import_dataset(
+    dataset_path: &str,
+    source_dir: &Path,
+    version: Option<&str>,
+) -> Result<PathBuf, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    if !source_dir.is_dir() {
+        return Err(GaggleError::IoError(format!(
+            "Import source {} is not a directory",
+            source_dir.display()
+        )));
+    }
+    let cache_root = crate::config::cache_dir_runtime();
+    if is_inside_dir(source_dir, &cache_root)? {
+        return Err(GaggleError::IoError(format!(
+            "Import source {} is inside the cache directory {}",
+            source_dir.display(),
+            cache_root.display()
+        )));
+    }
+
+    let mut files = Vec::new();
+    collect_cache_files(source_dir, &mut files)?;
+    files.sort();
+
+    // Wait for a download of the same dataset to finish before replacing it
+    let lock_key = dataset_lock_key(&owner, &dataset);
+    let guard = loop {
+        if let Some(guard) = acquire_download_lock(&lock_key, dataset_path, || false)? {
+            break guard;
+        }
+    };
+
+    let cache_dir = dataset_cache_dir(&owner, &dataset);
+    let result = (|| -> Result<PathBuf, GaggleError> {
+        if cache_dir.exists() {
+            fs::remove_dir_all(&cache_dir)?;
+        }
+        fs::create_dir_all(&cache_dir)?;
+        for path in &files {
+            let rel = path.strip_prefix(source_dir).unwrap_or(path);
+            let target = cache_dir.join(rel);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(path, &target)?;
+        }
+
+        let size_mb = crate::utils::calculate_dir_size_no_follow(&cache_dir)
+            .unwrap_or(0)
+            .saturating_div(1024 * 1024);
+        let mut metadata = CacheMetadata::new(dataset_path.to_string(), size_mb);
+        metadata.version = version.map(str::to_string);
+        crate::utils::write_marker_atomic(
+            &cache_dir.join(".downloaded"),
+            &serde_json::to_string(&metadata)?,
+        )?;
+        debug!(
+            dataset = dataset_path,
+            files = files.len(),
+            source = %source_dir.display(),
+            "imported dataset"
+        );
+        Ok(cache_dir.clone())
+    })();
+    if result.is_err() && cache_dir.exists() {
+        // Do not leave a partial copy behind that looks like a cached dataset
+        let _ = fs::remove_dir_all(&cache_dir);
+    }
+    guard.finish(&result);
+    result
+}
+
+/// Rejects copy destinations inside the cache directory, where copies would be mistaken for
+/// cached data or evicted with it.
+fn check_copy_destination(dest: &Path) -> Result<(), GaggleError> {
+    let cache_dir = crate::config::cache_dir_runtime();
+    if is_inside_dir(dest, &cache_dir)? {
+        return Err(GaggleError::IoError(format!(
+            "Copy destination {} is inside the cache directory {}",
+            dest.display(),
+            cache_dir.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Exports all cached datasets to a ZIP archive at `dest_path`.
+///
+/// Dataset files keep their `datasets/{owner}/{dataset}/...` layout inside the archive. The
+/// `.downloaded` markers and cached schemas are left out; instead, a top-level `manifest.json`
+/// lists the path, version, and size of every exported dataset. Files are streamed into the
+/// archive one at a time, so the cache is never loaded into memory as a whole.
+///
+/// # Returns
+///
+/// A JSON object of the form `{"datasets": 2, "total_mb": 10, "path": "/tmp/cache.zip"}`.
+pub fn export_cache(dest_path: &Path) -> Result<serde_json::Value, GaggleError> {
+    let cache_dir = crate::config::cache_dir_runtime();
+    if is_inside_dir(dest_path, &cache_dir)? {
+        return Err(GaggleError::IoError(format!(
+            "Export destination {} is inside the cache directory {}",
+            dest_path.display(),
+            cache_dir.display()
+        )));
+    }
+
+    let mut datasets = get_cached_datasets()?;
+    datasets.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut manifest = Vec::with_capacity(datasets.len());
+    for (dir, meta) in &datasets {
+        let rel_dir = dir.strip_prefix(&cache_dir).unwrap_or(dir);
+        manifest.push(serde_json::json!({
+            "path": meta.dataset_path,
+            "directory": zip_entry_name(rel_dir),
+            "version": meta.version,
+            "size_mb": meta.size_mb,
+        }));
+    }
+
+    let file = fs::File::create(dest_path)?;
+    let mut zip = zip::ZipWriter::new(BufWriter::new(file));
+    let options: zip::write::FileOptions<()> =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(
+        &serde_json::json!({ "datasets": manifest }),
+    )?)?;
+
+    let mut total_bytes: u64 = 0;
+    for (dir, _) in &datasets {
+        let mut files = Vec::new();
+        collect_cache_files(dir, &mut files)?;
+        files.sort();
+        for path in files {
+            let rel = path.strip_prefix(&cache_dir).unwrap_or(&path);
+            let len = fs::metadata(&path)?.len();
+            zip.start_file(
+                zip_entry_name(rel),
+                options.large_file(len >= u32::MAX as u64),
+            )?;
+            let mut input = fs::File::open(&path)?;
+            std::io::copy(&mut input, &mut zip)?;
+            total_bytes = total_bytes.saturating_add(len);
+        }
+    }
+    let mut writer = zip.finish()?;
+    writer.flush()?;
+
+    debug!(
+        datasets = datasets.len(),
+        bytes = total_bytes,
+        path = %dest_path.display(),
+        "exported cache"
+    );
+    Ok(serde_json::json!({
+        "datasets": datasets.len(),
+        "total_mb": total_bytes / (1024 * 1024),
+        "path": dest_path.to_string_lossy(),
+    }))
+}
+
+/// Imports datasets from a ZIP archive created by [`export_cache`].
+///
+/// The archive's `manifest.json` is validated first; only the dataset directories it lists
+/// are extracted. With `merge`, datasets that are already cached (have a `.downloaded`
+/// marker) are skipped; otherwise they are replaced. Every imported dataset gets a fresh
+/// `.downloaded` marker.
+///
+/// The datasets are extracted in the staging directory and replace the cached copies only
+/// once the whole archive is extracted, so the cached copies are kept when the import fails.
+///
+/// # Returns
+///
+/// A JSON object of the form `{"imported": 2, "skipped": 1}`.
+pub fn import_cache(archive_path: &Path, merge: bool) -> Result<serde_json::Value, GaggleError> {
+    let entries = read_export_manifest(archive_path)?;
+    let cache_dir = crate::config::cache_dir_runtime();
+
+    let mut to_import = Vec::new();
+    let mut skipped: usize = 0;
+    for entry in entries {
+        let dest = cache_dir.join(&entry.directory);
+        if merge && dest.join(".downloaded").exists() {
+            debug!(dataset = %entry.path, "dataset already cached; skipping import");
+            skipped += 1;
+            continue;
+        }
+        to_import.push(entry);
+    }
+
+    let staging = new_staging_dir()?;
+    let result = (|| -> Result<(), GaggleError> {
+        let directories: Vec<PathBuf> = to_import
+            .iter()
+            .map(|e| PathBuf::from(&e.directory))
+            .collect();
+        extract_zip_filtered(
+            archive_path,
+            &staging,
+            ExtractLimits::from_config(),
+            |rel| directories.iter().any(|d| rel.starts_with(d) && rel != d),
+        )?;
+
+        for entry in &to_import {
+            let staged = staging.join(&entry.directory);
+            fs::create_dir_all(&staged)?;
+            let size_mb = crate::utils::calculate_dir_size_no_follow(&staged)
+                .unwrap_or(0)
+                .saturating_div(1024 * 1024);
+            let mut metadata = CacheMetadata::new(entry.path.clone(), size_mb);
+            metadata.version = entry.version.clone();
+            crate::utils::write_marker_atomic(
+                &staged.join(".downloaded"),
+                &serde_json::to_string(&metadata)?,
+            )?;
+        }
+
+        for entry in &to_import {
+            let dest = cache_dir.join(&entry.directory);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            publish_staged(&staging.join(&entry.directory), &dest)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        debug!(path = %staging.display(), error = %e, "failed to remove staging directory");
+    }
+    result?;
+
+    Ok(serde_json::json!({
+        "imported": to_import.len(),
+        "skipped": skipped,
+    }))
+}
+
+/// A dataset listed in the `manifest.json` of an exported cache.
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    /// The dataset path, in the format `owner/dataset`.
+    path: String,
+    /// The dataset directory inside the archive, in the format `datasets/{owner}/{dir}`.
+    directory: String,
+    /// The cached version of the dataset.
+    version: Option<String>,
+}
+
+/// Reads and validates the `manifest.json` of an exported cache archive.
+fn read_export_manifest(archive_path: &Path) -> Result<Vec<ManifestEntry>, GaggleError> {
+    let file = fs::File::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let manifest = archive
+        .by_name("manifest.json")
+        .map_err(|_| GaggleError::ZipError("Archive has no manifest.json".to_string()))?;
+
+    #[derive(Deserialize)]
+    struct Manifest {
+        datasets: Vec<ManifestEntry>,
+    }
+    let manifest: Manifest = serde_json::from_reader(manifest)
+        .map_err(|e| GaggleError::ZipError(format!("Malformed manifest.json: {}", e)))?;
+
+    for entry in &manifest.datasets {
+        let (owner, _) = super::parse_dataset_path(&entry.path)
+            .map_err(|e| GaggleError::ZipError(format!("Malformed manifest.json: {}", e)))?;
+        // The directory must be exactly datasets/{owner}/{name} with plain components
+        let parts: Vec<&str> = entry.directory.split('/').collect();
+        let valid = parts.len() == 3
+            && parts[0] == "datasets"
+            && parts[1] == owner
+            && parts[1..]
+                .iter()
+                .all(|p| !p.is_empty() && *p != "." && *p != ".." && !p.contains('\\'));
+        if !valid {
+            return Err(GaggleError::ZipError(format!(
+                "Malformed manifest.json: invalid directory '{}' for dataset '{}'",
+                entry.directory, entry.path
+            )));
+        }
+    }
+    Ok(manifest.datasets)
+}
+
+/// Recursively collects the regular files of a cached dataset, skipping internal state files
+/// and symlinks.
+fn collect_cache_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), GaggleError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect_cache_files(&path, out)?;
+        } else if file_type.is_file() && !is_cache_state_file(&entry.file_name()) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether a file name belongs to Gaggle's own state in a dataset directory rather than to the dataset.
+pub(crate) fn is_cache_state_file(name: &std::ffi::OsStr) -> bool {
+    let Some(name) = name.to_str() else {
+        return false;
+    };
+    matches!(
+        name,
+        ".downloaded"
+            | DRY_RUN_MARKER
+            | PARTIAL_MARKER
+            | PINNED_MARKER
+            | super::metadata::SCHEMA_FILE
+            | super::metadata::README_FILE
+            | super::metadata::LICENSE_FILE
+    ) || name.ends_with(crate::utils::DECOMPRESSED_MARKER_SUFFIX)
+        || is_marker_tmp_file(name)
+        || name
+            .strip_prefix(RETAINED_ARCHIVE_STEM)
+            .is_some_and(|ext| ext.starts_with('.'))
+}
+
+/// Whether `name` is a temporary file left by an interrupted write of a cache marker, such
+/// as `.downloaded.tmp.1234`.
+fn is_marker_tmp_file(name: &str) -> bool {
+    name.starts_with('.')
+        && name
+            .rsplit_once(crate::utils::ATOMIC_TMP_INFIX)
+            .is_some_and(|(_, pid)| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Converts a relative path to a ZIP entry name with forward slashes.
+fn zip_entry_name(rel: &Path) -> String {
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether `path` (which need not exist yet) is `dir` or lies inside it.
+fn is_inside_dir(path: &Path, dir: &Path) -> Result<bool, GaggleError> {
+    let Ok(dir) = dir.canonicalize() else {
+        // A directory that does not exist cannot contain anything
+        return Ok(false);
+    };
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    // Resolve the deepest existing ancestor so that symlinks and `..` are accounted for
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = existing.canonicalize()?;
+    for name in rest.into_iter().rev() {
+        resolved.push(name);
+    }
+    Ok(resolved.starts_with(&dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_split_ranges_covers_every_byte() {
+        assert_eq!(split_ranges(10, 3), vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(split_ranges(9, 3), vec![(0, 2), (3, 5), (6, 8)]);
+        assert_eq!(split_ranges(2, 4), vec![(0, 0), (1, 1)]);
+        assert_eq!(split_ranges(5, 1), vec![(0, 4)]);
+        assert!(split_ranges(0, 4).is_empty());
+    }
+
+    #[test]
+    fn test_content_range_total() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(content_range_total(&headers), None);
+        headers.insert(
+            reqwest::header::CONTENT_RANGE,
+            "bytes 0-0/12345".parse().unwrap(),
+        );
+        assert_eq!(content_range_total(&headers), Some(12345));
+        headers.insert(
+            reqwest::header::CONTENT_RANGE,
+            "bytes 0-0/*".parse().unwrap(),
+        );
+        assert_eq!(content_range_total(&headers), None);
+    }
+
+    #[test]
+    fn test_dataset_file_struct() {
+        let file = DatasetFile {
+            name: "test.csv".to_string(),
+            size: 1024,
+            modified: None,
+            columns: None,
+        };
+        assert_eq!(file.name, "test.csv");
+        assert_eq!(file.size, 1024);
+    }
+
+    #[test]
+    fn test_lock_guard_cleanup() {
+        let lock_key = "test/dataset";
+
+        // Acquire and drop guard
+        {
+            let guard = acquire_download_lock(lock_key, lock_key, || false)
+                .unwrap()
+                .unwrap();
+            // Guard exists, lock should still be present
+            let locks = DOWNLOAD_LOCKS.lock();
+            assert!(Arc::ptr_eq(&locks[lock_key], &guard.slot));
+        }
+
+        // After guard drop, lock should be removed
+        let locks = DOWNLOAD_LOCKS.lock();
+        assert!(!locks.contains_key(lock_key));
+    }
+
+    #[test]
+    fn test_download_lock_skips_completed_work() {
+        assert!(acquire_download_lock("test/done", "done", || true)
+            .unwrap()
+            .is_none());
+        assert!(!DOWNLOAD_LOCKS.lock().contains_key("test/done"));
+    }
+
+    #[test]
+    fn test_download_lock_waiters_get_the_outcome() {
+        for outcome in [
+            Ok(()),
+            Err(GaggleError::ZipError("corrupt archive".to_string())),
+        ] {
+            let key = "test/outcome";
+            let guard = acquire_download_lock(key, key, || false).unwrap().unwrap();
+            let waiters: Vec<_> = (0..4)
+                .map(|_| std::thread::spawn(|| acquire_download_lock(key, key, || false)))
+                .collect();
+            std::thread::sleep(Duration::from_millis(50));
+            guard.finish(&outcome);
+            drop(guard);
+
+            for waiter in waiters {
+                match (waiter.join().unwrap(), &outcome) {
+                    (Ok(None), Ok(())) => {}
+                    (Err(GaggleError::ZipError(msg)), Err(_)) => {
+                        assert_eq!(msg, "corrupt archive")
+                    }
+                    (other, _) => panic!("Unexpected outcome: {:?}", other.map(|g| g.is_some())),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_download_lock_waiters_wake_without_polling() {
+        // Waiters block on a condition variable instead of sleeping in a loop, so all of
+        // them return as soon as the download finishes
+        let key = "test/herd";
+        let guard = acquire_download_lock(key, key, || false).unwrap().unwrap();
+        let waiters: Vec<_> = (0..7)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    let result = acquire_download_lock(key, key, || false);
+                    (result.map(|g| g.is_none()), Instant::now())
+                })
+            })
+            .collect();
+        std::thread::sleep(Duration::from_millis(100));
+        let finished_at = Instant::now();
+        guard.finish(&Ok(()));
+        drop(guard);
+
+        for waiter in waiters {
+            let (result, returned_at) = waiter.join().unwrap();
+            assert!(matches!(result, Ok(true)));
+            assert!(returned_at.duration_since(finished_at) < Duration::from_millis(100));
+        }
+        assert!(!DOWNLOAD_LOCKS.lock().contains_key(key));
+    }
+
+    #[test]
+    fn test_download_lock_abandoned_without_outcome_fails_waiters() {
+        let key = "test/abandoned";
+        let guard = acquire_download_lock(key, key, || false).unwrap().unwrap();
+        let waiter = std::thread::spawn(|| acquire_download_lock(key, key, || false));
+        std::thread::sleep(Duration::from_millis(50));
+        drop(guard);
+
+        match waiter.join().unwrap() {
+            Err(GaggleError::HttpRequestError(msg)) => assert!(msg.contains("abandoned")),
+            other => panic!("Unexpected outcome: {:?}", other.map(|g| g.is_some())),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_download_lock_wait_times_out() {
+        std::env::set_var("GAGGLE_DOWNLOAD_WAIT_TIMEOUT", "0.05");
+        let key = "test/stalled";
+        let _guard = acquire_download_lock(key, key, || false).unwrap().unwrap();
+
+        let started = Instant::now();
+        let result = std::thread::spawn(|| acquire_download_lock(key, "stalled", || false))
+            .join()
+            .unwrap();
+        match result {
+            Err(GaggleError::HttpRequestError(msg)) => {
+                assert!(msg.contains("Timeout waiting for download of stalled"))
+            }
+            other => panic!("Unexpected outcome: {:?}", other.map(|g| g.is_some())),
+        }
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        std::env::remove_var("GAGGLE_DOWNLOAD_WAIT_TIMEOUT");
+    }
+
+    #[test]
+    fn test_check_free_space() {
+        let dir = Path::new("/cache");
+        assert!(check_free_space(dir, 10, 10, "owner/ds").is_ok());
+        match check_free_space(dir, 3 * 1024 * 1024 + 1, 2 * 1024 * 1024, "owner/ds") {
+            Err(GaggleError::IoError(msg)) => {
+                assert!(msg.contains("owner/ds"));
+                assert!(msg.contains("about 4 MB is needed in /cache"));
+                assert!(msg.contains("only 2 MB is available"));
+            }
+            other => panic!("Expected IoError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ensure_free_space_uses_the_file_system() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(ensure_free_space(temp_dir.path(), 1, "small").is_ok());
+        assert!(matches!(
+            ensure_free_space(temp_dir.path(), u64::MAX, "huge"),
+            Err(GaggleError::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_extract_zip_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("empty.zip");
+
+        // Create an empty ZIP file
+        let file = fs::File::create(&zip_path).unwrap();
+        let zip = zip::ZipWriter::new(file);
+        zip.finish().unwrap();
+
+        let dest_dir = temp_dir.path().join("extracted");
+        let result = extract_zip(&zip_path, &dest_dir, ExtractLimits::default());
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_extract_zip_with_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("test.zip");
+
+        // Create a ZIP with one file
+        let file = fs::File::create(&zip_path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("test.txt", options).unwrap();
+        zip.write_all(b"test content").unwrap();
+        zip.finish().unwrap();
+
+        let dest_dir = temp_dir.path().join("extracted");
+        let result = extract_zip(&zip_path, &dest_dir, ExtractLimits::default());
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 1);
+
+        let extracted_file = dest_dir.join("test.txt");
+        assert!(extracted_file.exists());
+        let content = fs::read_to_string(extracted_file).unwrap();
+        assert_eq!(content, "test content");
+    }
+
+    #[test]
+    fn test_extract_zip_preserves_mode_and_mtime() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("test.zip");
+
+        let file = fs::File::create(&zip_path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+        let modified = zip::DateTime::from_date_and_time(2021, 3, 14, 15, 9, 26).unwrap();
+        let options: zip::write::FileOptions<()> = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .last_modified_time(modified);
+        zip.start_file("run.sh", options.unix_permissions(0o4777))
+            .unwrap();
+        zip.write_all(b"#!/bin/sh\n").unwrap();
+        zip.start_file("data.csv", options.unix_permissions(0o644))
+            .unwrap();
+        zip.write_all(b"a,b\n").unwrap();
+        zip.finish().unwrap();
+
+        let dest_dir = temp_dir.path().join("extracted");
+        assert_eq!(
+            extract_zip(&zip_path, &dest_dir, ExtractLimits::default()).unwrap(),
+            2
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |name: &str| {
+                fs::metadata(dest_dir.join(name))
+                    .unwrap()
+                    .permissions()
+                    .mode()
+                    & 0o7777
+            };
+            assert_eq!(mode("run.sh"), 0o755);
+            assert_eq!(mode("data.csv"), 0o644);
+        }
+        let mtime = fs::metadata(dest_dir.join("data.csv"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        // 2021-03-14T15:09:26Z
+        assert_eq!(
+            mtime,
+            std::time::UNIX_EPOCH + Duration::from_secs(1_615_734_566)
+        );
+    }
+
+    #[test]
+    fn test_zip_time_to_system_time() {
+        let epoch = zip::DateTime::from_date_and_time(1980, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            zip_time_to_system_time(epoch),
+            Some(std::time::UNIX_EPOCH + Duration::from_secs(315_532_800))
+        );
+        let leap = zip::DateTime::from_date_and_time(2024, 2, 29, 23, 59, 58).unwrap();
+        assert_eq!(
+            zip_time_to_system_time(leap),
+            Some(std::time::UNIX_EPOCH + Duration::from_secs(1_709_251_198))
+        );
+    }
+
+    #[test]
+    fn test_extract_zip_with_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("test.zip");
+
+        // Create a ZIP with a directory
+        let file = fs::File::create(&zip_path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.add_directory("subdir/", options).unwrap();
+        zip.start_file("subdir/test.txt", options).unwrap();
+        zip.write_all(b"nested content").unwrap();
+        zip.finish().unwrap();
+
+        let dest_dir = temp_dir.path().join("extracted");
+        let result = extract_zip(&zip_path, &dest_dir, ExtractLimits::default());
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 1);
+
+        let extracted_file = dest_dir.join("subdir").join("test.txt");
+        assert!(extracted_file.exists());
+    }
+
+    #[test]
+    fn test_extract_zip_filtered_only_writes_matching_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("test.zip");
+
+        let file = fs::File::create(&zip_path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.add_directory("part/", options).unwrap();
+        for name in ["big.csv", "part/a.parquet", "b.parquet"] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(b"content").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let dest_dir = temp_dir.path().join("extracted");
+        let n = extract_zip_filtered(&zip_path, &dest_dir, ExtractLimits::default(), |rel| {
+            entry_matches("*.parquet", &zip_entry_name(rel))
+        })
+        .unwrap();
+        assert_eq!(n, 2);
+        assert!(dest_dir.join("part/a.parquet").exists());
+        assert!(dest_dir.join("b.parquet").exists());
+        assert!(!dest_dir.join("big.csv").exists());
+    }
+
+    #[test]
+    fn test_entry_matches_names_and_globs() {
+        assert!(entry_matches("train.csv", "train.csv"));
+        assert!(entry_matches("train.csv", "data/train.csv"));
+        assert!(!entry_matches("train.csv", "train.csv.gz"));
+        assert!(entry_matches("*.parquet", "data/part-0.parquet"));
+        assert!(!entry_matches("*.parquet", "data.parquet/readme.txt"));
+        assert!(entry_matches("data/*.csv", "data/a.csv"));
+        assert!(!entry_matches("data/*.csv", "data/sub/a.csv"));
+        assert!(!entry_matches("data/*.csv", "other/a.csv"));
+        assert!(entry_matches("part-?.csv", "part-1.csv"));
+        assert!(!entry_matches("part-?.csv", "part-10.csv"));
+        assert!(entry_matches("*", "anything"));
+        assert!(entry_matches("a*b*c", "aXXbYYc"));
+        assert!(!entry_matches("a*b*c", "aXXbYY"));
+    }
+
+    #[test]
+    fn test_entry_matches_recursive_globs_and_classes() {
+        assert!(entry_matches("**/*.parquet", "part-0.parquet"));
+        assert!(entry_matches("**/*.parquet", "a/b/part-0.parquet"));
+        assert!(!entry_matches("**/*.parquet", "a/b/part-0.csv"));
+        assert!(entry_matches("data/**", "data/a/b.csv"));
+        assert!(entry_matches("data/**/x.csv", "data/x.csv"));
+        assert!(entry_matches("data/**/x.csv", "data/a/b/x.csv"));
+        assert!(!entry_matches("data/**/x.csv", "other/x.csv"));
+        assert!(entry_matches("part-[0-4].csv", "part-3.csv"));
+        assert!(!entry_matches("part-[0-4].csv", "part-7.csv"));
+        assert!(entry_matches("part-[!0-4].csv", "part-7.csv"));
+        assert!(entry_matches("[]]x", "]x"));
+        assert!(entry_matches("a[b", "a[b"));
+        assert!(!entry_matches("a[/]b", "a/b"));
+        // Many wildcards against a long name finish quickly
+        let name = "a".repeat(200);
+        assert!(!entry_matches(&format!("{}b", "*a".repeat(20)), &name));
+    }
+
+    #[test]
+    #[serial]
+    fn test_download_dataset_files_rejects_unsafe_patterns() {
+        for pattern in ["../outside.csv", "/etc/passwd", "data/../../x"] {
+            let result = download_dataset_files("owner/dataset", &[pattern]);
+            assert!(
+                matches!(result, Err(GaggleError::InvalidDatasetPath(_))),
+                "{} was accepted",
+                pattern
+            );
+        }
+        assert!(matches!(
+            download_dataset_files("owner/dataset", &[]),
+            Err(GaggleError::InvalidDatasetPath(_))
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn test_download_dataset_files_served_from_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+
+        // A fully downloaded dataset
+        let full = temp_dir.path().join("datasets/owner/full");
+        fs::create_dir_all(full.join("sub")).unwrap();
+        fs::write(full.join("a.csv"), "a").unwrap();
+        fs::write(full.join("sub/b.csv"), "b").unwrap();
+        fs::write(full.join("c.json"), "{}").unwrap();
+        let metadata = CacheMetadata::new("owner/full".to_string(), 0);
+        fs::write(
+            full.join(".downloaded"),
+            serde_json::to_string(&metadata).unwrap(),
+        )
+        .unwrap();
+        let files = download_dataset_files("owner/full", &["*.csv"]).unwrap();
+        assert_eq!(files, vec![full.join("a.csv"), full.join("sub/b.csv")]);
+        assert!(matches!(
+            download_dataset_files("owner/full", &["*.parquet"]),
+            Err(GaggleError::DatasetNotFound(_))
+        ));
+
+        // A partially extracted dataset serves the patterns it has extracted
+        let partial = temp_dir.path().join("datasets/owner/partial");
+        fs::create_dir_all(&partial).unwrap();
+        fs::write(partial.join("x.parquet"), "x").unwrap();
+        let marker = PartialMarker {
+            patterns: vec!["*.parquet".to_string()],
+            entries: vec!["x.parquet".to_string()],
+            archive: None,
+        };
+        fs::write(
+            partial.join(PARTIAL_MARKER),
+            serde_json::to_string(&marker).unwrap(),
+        )
+        .unwrap();
+        let files = download_dataset_files("owner/partial", &["*.parquet"]).unwrap();
+        assert_eq!(files, vec![partial.join("x.parquet")]);
+        // Patterns that were never extracted need the archive, which offline mode refuses
+        assert!(matches!(
+            download_dataset_files("owner/partial", &["*.csv"]),
+            Err(GaggleError::HttpRequestError(_))
+        ));
+
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    fn test_extract_zip_path_traversal_blocked() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("malicious.zip");
+
+        // Create a ZIP with path traversal attempt
+        let file = fs::File::create(&zip_path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+
+        // Note: ZipWriter may normalize paths, so this test verifies the check exists
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+        // Try to create a file with .. in the path
+        // The zip crate may reject this, but we test our extraction logic
+        let result = zip.start_file("../escape.txt", options);
+        if result.is_ok() {
+            zip.write_all(b"malicious").unwrap();
+            zip.finish().unwrap();
+
+            let dest_dir = temp_dir.path().join("extracted");
+            // Our extraction should either skip invalid names or reject them
+            let extract_result = extract_zip(&zip_path, &dest_dir, ExtractLimits::default());
+            // Should succeed but not extract the malicious file outside dest_dir
+            if extract_result.is_ok() {
+                let escape_file = temp_dir.path().join("escape.txt");
+                assert!(!escape_file.exists());
+            }
+        }
+    }
+
+    #[test]
+    fn test_extract_zip_size_limit() {
+        // This test verifies that the size check logic works correctly
+        // by creating a small ZIP with metadata that claims large size
+        // We test the cumulative size check, not actual file creation
+
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("test.zip");
+
+        // Create a small ZIP file with a few tiny files
+        let file = fs::File::create(&zip_path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+        // Create just a few small files - the actual limit check happens
+        // during extraction based on the reported uncompressed size
+        for i in 0..5 {
+            zip.start_file(format!("file{}.txt", i), options).unwrap();
+            zip.write_all(b"test content").unwrap();
+        }
+
+        zip.finish().unwrap();
+
+        let dest_dir = temp_dir.path().join("extracted");
+
+        // This test primarily verifies that:
+        // 1. Small files extract successfully (under 10GB limit)
+        // 2. The size checking logic is in place
+        let result = extract_zip(&zip_path, &dest_dir, ExtractLimits::default());
+
+        // Should succeed because total size is well under 10GB
+        assert!(result.is_ok());
+        let extracted_count = result.unwrap();
+        assert_eq!(extracted_count, 5);
+
+        // Verify the files were actually extracted
+        for i in 0..5 {
+            let file_path = dest_dir.join(format!("file{}.txt", i));
+            assert!(file_path.exists());
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_extract_zip_file_count_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("test.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        let options: zip::write::FileOptions<()> = zip::write::FileOptions::default();
+        for i in 0..3 {
+            zip.start_file(format!("file{}.txt", i), options).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        zip.finish().unwrap();
+
+        std::env::set_var("GAGGLE_MAX_ZIP_FILES", "2");
+        let dest_dir = temp_dir.path().join("extracted");
+        let result = extract_zip(&zip_path, &dest_dir, ExtractLimits::from_config());
+        std::env::remove_var("GAGGLE_MAX_ZIP_FILES");
+        assert!(
+            matches!(result, Err(GaggleError::ZipError(ref m)) if m == "ZIP contains 3 files, exceeding limit of 2"),
+            "got {:?}",
+            result
+        );
+        assert!(!dest_dir.join("file0.txt").exists());
+
+        assert_eq!(
+            extract_zip(&zip_path, &dest_dir, ExtractLimits::from_config()).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_extract_zip_size_check_logic() {
+        // Test that the size limit constant is correctly defined
+        // The actual limit is 10GB = 10 * 1024 * 1024 * 1024 bytes
+        const EXPECTED_LIMIT: u64 = 10 * 1024 * 1024 * 1024;
+
+        // We can't easily test the actual size limit without creating large files,
+        // but we can verify the constant exists and has the right value
+        // by checking it would trigger on cumulative sizes > 10GB
+
+        let size_under_limit = 5 * 1024 * 1024 * 1024u64; // 5GB
+        let size_over_limit = 11 * 1024 * 1024 * 1024u64; // 11GB
+
+        assert!(size_under_limit < EXPECTED_LIMIT);
+        assert!(size_over_limit > EXPECTED_LIMIT);
+    }
+
+    /// Writes a ZIP with a single deflated entry of `size` zero bytes.
+    fn write_zeros_zip(dir: &Path, name: &str, size: usize) -> PathBuf {
+        let zip_path = dir.join(format!("{}.zip", name));
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file(name, options).unwrap();
+        zip.write_all(&vec![0u8; size]).unwrap();
+        zip.finish().unwrap();
+        zip_path
+    }
+
+    #[test]
+    fn test_extract_zip_ratio_check_skips_tiny_entries() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // 1MB of zeros deflates to about 1KB, so the ratio is far above 100:1 but ignored
+        let tiny = write_zeros_zip(temp_dir.path(), "tiny.bin", 1024 * 1024);
+        let dest_dir = temp_dir.path().join("tiny");
+        assert_eq!(
+            extract_zip(&tiny, &dest_dir, ExtractLimits::default()).unwrap(),
+            1
+        );
+
+        // 20MB of zeros is well above the tiny-entry threshold once compressed
+        let big = write_zeros_zip(temp_dir.path(), "big.bin", 20 * 1024 * 1024);
+        let dest_dir = temp_dir.path().join("big");
+        let result = extract_zip(&big, &dest_dir, ExtractLimits::default());
+        assert!(
+            matches!(result, Err(GaggleError::ZipError(ref m))
+                if m.contains("compression ratio") && m.contains("GAGGLE_MAX_COMPRESSION_RATIO")),
+            "got {:?}",
+            result
+        );
+        assert!(!dest_dir.join("big.bin").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_extract_limits_from_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let big = write_zeros_zip(temp_dir.path(), "big.bin", 20 * 1024 * 1024);
+
+        std::env::set_var("GAGGLE_MAX_COMPRESSION_RATIO", "unlimited");
+        let dest_dir = temp_dir.path().join("unlimited");
+        assert_eq!(
+            extract_zip(&big, &dest_dir, ExtractLimits::from_config()).unwrap(),
+            1
+        );
+
+        std::env::set_var("GAGGLE_MAX_EXTRACT_SIZE_MB", "10");
+        let dest_dir = temp_dir.path().join("limited");
+        let result = extract_zip(&big, &dest_dir, ExtractLimits::from_config());
+        assert!(
+            matches!(result, Err(GaggleError::ZipError(ref m))
+                if m.contains("exceeds 10 MB") && m.contains("GAGGLE_MAX_EXTRACT_SIZE_MB")),
+            "got {:?}",
+            result
+        );
+
+        std::env::remove_var("GAGGLE_MAX_EXTRACT_SIZE_MB");
+        std::env::remove_var("GAGGLE_MAX_COMPRESSION_RATIO");
+    }
+
+    /// Compresses raw tar bytes in the given format.
+    fn compress(format: ArchiveFormat, tar_bytes: &[u8]) -> Vec<u8> {
+        match format {
+            ArchiveFormat::TarGz => {
+                let mut enc =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                enc.write_all(tar_bytes).unwrap();
+                enc.finish().unwrap()
+            }
+            ArchiveFormat::TarBz2 => {
+                let mut enc =
+                    bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                enc.write_all(tar_bytes).unwrap();
+                enc.finish().unwrap()
+            }
+            ArchiveFormat::TarXz => {
+                let mut enc =
+                    lzma_rust2::XzWriter::new(Vec::new(), lzma_rust2::XzOptions::with_preset(6))
+                        .unwrap();
+                enc.write_all(tar_bytes).unwrap();
+                enc.finish().unwrap()
+            }
+            ArchiveFormat::Zip => panic!("not a tar format"),
+        }
+    }
+
+    /// Builds a tar archive with regular files.
+    fn tar_with_files(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// Builds a tar archive with one entry whose raw name bypasses the builder's validation.
+    fn tar_with_raw_entry(name: &str, entry_type: tar::EntryType, content: &[u8]) -> Vec<u8> {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(entry_type);
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        if entry_type.is_symlink() {
+            header.set_link_name("/etc/passwd").unwrap();
+        }
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append(&header, content).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn write_tar(dir: &Path, format: ArchiveFormat, tar_bytes: &[u8]) -> PathBuf {
+        let path = dir.join(format!("dataset.{}", format.as_str()));
+        fs::write(&path, compress(format, tar_bytes)).unwrap();
+        path
+    }
+
+    const TAR_FORMATS: [ArchiveFormat; 3] = [
+        ArchiveFormat::TarGz,
+        ArchiveFormat::TarBz2,
+        ArchiveFormat::TarXz,
+    ];
+
+    #[test]
+    fn test_archive_format_from_magic() {
+        assert_eq!(ArchiveFormat::from_magic(b"PK\x03\x04"), ArchiveFormat::Zip);
+        assert_eq!(
+            ArchiveFormat::from_magic(&[0x1f, 0x8b, 0x08, 0x00]),
+            ArchiveFormat::TarGz
+        );
+        assert_eq!(ArchiveFormat::from_magic(b"BZh9"), ArchiveFormat::TarBz2);
+        assert_eq!(
+            ArchiveFormat::from_magic(&[0xfd, 0x37, 0x7a, 0x58]),
+            ArchiveFormat::TarXz
+        );
+        // Unknown or truncated signatures fall back to ZIP
+        assert_eq!(ArchiveFormat::from_magic(b""), ArchiveFormat::Zip);
+        assert_eq!(ArchiveFormat::from_magic(b"\x1f"), ArchiveFormat::Zip);
+    }
+
+    #[test]
+    fn test_archive_format_detect_from_file() {
+        let temp_dir = TempDir::new().unwrap();
+        for format in TAR_FORMATS {
+            let path = write_tar(temp_dir.path(), format, &tar_with_files(&[]));
+            assert_eq!(ArchiveFormat::detect(&path).unwrap(), format);
+        }
+    }
+
+    #[test]
+    fn test_extract_tar_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        for format in TAR_FORMATS {
+            let path = write_tar(temp_dir.path(), format, &tar_with_files(&[]));
+            let dest_dir = temp_dir.path().join(format!("out-{}", format.as_str()));
+            assert_eq!(
+                extract_tar(&path, &dest_dir, ExtractLimits::default()).unwrap(),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_tar_with_files_and_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let tar_bytes = tar_with_files(&[
+            ("test.txt", b"test content"),
+            ("subdir/nested/deep.txt", b"nested content"),
+        ]);
+        for format in TAR_FORMATS {
+            let path = write_tar(temp_dir.path(), format, &tar_bytes);
+            let dest_dir = temp_dir.path().join(format!("out-{}", format.as_str()));
+            assert_eq!(
+                extract_tar(&path, &dest_dir, ExtractLimits::default()).unwrap(),
+                2
+            );
+            assert_eq!(
+                fs::read_to_string(dest_dir.join("test.txt")).unwrap(),
+                "test content"
+            );
+            assert_eq!(
+                fs::read_to_string(dest_dir.join("subdir/nested/deep.txt")).unwrap(),
+                "nested content"
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_tar_path_traversal_blocked() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["../escape.txt", "a/../../escape.txt", "/tmp/escape.txt"] {
+            let tar_bytes = tar_with_raw_entry(name, tar::EntryType::Regular, b"malicious");
+            let path = write_tar(temp_dir.path(), ArchiveFormat::TarGz, &tar_bytes);
+            let dest_dir = temp_dir.path().join("extracted");
+            let result = extract_tar(&path, &dest_dir, ExtractLimits::default());
+            assert!(
+                matches!(result, Err(GaggleError::ZipError(ref m)) if m.contains("Path traversal")),
+                "expected traversal rejection for {}, got {:?}",
+                name,
+                result
+            );
+            assert!(!temp_dir.path().join("escape.txt").exists());
+        }
+    }
+
+    #[test]
+    fn test_extract_tar_link_entries_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        for entry_type in [tar::EntryType::Symlink, tar::EntryType::Link] {
+            let tar_bytes = tar_with_raw_entry("link.txt", entry_type, b"");
+            let path = write_tar(temp_dir.path(), ArchiveFormat::TarBz2, &tar_bytes);
+            let dest_dir = temp_dir.path().join("extracted");
+            let result = extract_tar(&path, &dest_dir, ExtractLimits::default());
+            assert!(
+                matches!(result, Err(GaggleError::ZipError(ref m)) if m.contains("Link entry")),
+                "got {:?}",
+                result
+            );
+            assert!(!dest_dir.join("link.txt").exists());
+        }
+    }
+
+    #[test]
+    fn test_extract_tar_size_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let tar_bytes = tar_with_files(&[("a.txt", b"0123456789"), ("b.txt", b"0123456789")]);
+        let path = write_tar(temp_dir.path(), ArchiveFormat::TarXz, &tar_bytes);
+
+        // Within the limit
+        let dest_dir = temp_dir.path().join("ok");
+        let limits = |max| ExtractLimits {
+            max_total_bytes: Some(max),
+            ..ExtractLimits::default()
+        };
+        assert_eq!(extract_tar(&path, &dest_dir, limits(20)).unwrap(), 2);
+
+        // The cumulative size of the second entry exceeds the limit
+        let dest_dir = temp_dir.path().join("too-big");
+        let result = extract_tar(&path, &dest_dir, limits(15));
+        assert!(
+            matches!(result, Err(GaggleError::ZipError(ref m)) if m.contains("too large")),
+            "got {:?}",
+            result
+        );
+        assert!(!dest_dir.join("b.txt").exists());
+    }
+
+    #[test]
+    fn test_extract_tar_compression_ratio_limit() {
+        // 10MB of zeros compresses far better than 100:1
+        let zeros = vec![0u8; 10 * 1024 * 1024];
+        let tar_bytes = tar_with_files(&[("zeros.bin", zeros.as_slice())]);
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_tar(temp_dir.path(), ArchiveFormat::TarGz, &tar_bytes);
+        let dest_dir = temp_dir.path().join("extracted");
+        let result = extract_tar(&path, &dest_dir, ExtractLimits::default());
+        assert!(
+            matches!(result, Err(GaggleError::ZipError(ref m)) if m.contains("compression ratio")),
+            "got {:?}",
+            result
+        );
+        assert!(!dest_dir.join("zeros.bin").exists());
+    }
+
+    #[test]
+    fn test_extract_tar_rejects_zip_and_garbage() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("test.zip");
+        let zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        zip.finish().unwrap();
+        assert!(extract_tar(
+            &zip_path,
+            &temp_dir.path().join("a"),
+            ExtractLimits::default()
+        )
+        .is_err());
+
+        let garbage = temp_dir.path().join("garbage.tar.gz");
+        fs::write(&garbage, [0x1f, 0x8b, 0x00, 0x01, 0x02]).unwrap();
+        assert!(matches!(
+            extract_tar(
+                &garbage,
+                &temp_dir.path().join("b"),
+                ExtractLimits::default()
+            ),
+            Err(GaggleError::ZipError(_))
+        ));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_export_cache_roundtrip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+
+        let d1 = cache.join("datasets/owner1/ds1");
+        let d2 = cache.join("datasets/owner2/ds2");
+        fs::create_dir_all(d1.join("nested")).unwrap();
+        fs::create_dir_all(&d2).unwrap();
+        fs::write(d1.join("a.csv"), b"x,y\n1,2\n").unwrap();
+        fs::write(d1.join("nested/b.json"), b"{}").unwrap();
+        fs::write(d2.join("c.bin"), vec![7u8; 1024]).unwrap();
+        let mut meta = CacheMetadata::new("owner1/ds1".to_string(), 0);
+        meta.version = Some("3".to_string());
+        fs::write(
+            d1.join(".downloaded"),
+            serde_json::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+        fs::write(d1.join(super::super::metadata::SCHEMA_FILE), b"{}").unwrap();
+
+        let dest = temp_dir.path().join("export.zip");
+        let summary = export_cache(&dest).unwrap();
+        assert_eq!(summary["datasets"], 2);
+        assert_eq!(summary["total_mb"], 0);
+        assert_eq!(summary["path"], dest.to_string_lossy().as_ref());
+
+        // Importing the archive reproduces the dataset layout without internal state files
+        let imported = temp_dir.path().join("imported");
+        extract_zip(&dest, &imported, ExtractLimits::default()).unwrap();
+        assert_eq!(
+            fs::read(imported.join("datasets/owner1/ds1/a.csv")).unwrap(),
+            b"x,y\n1,2\n"
+        );
+        assert_eq!(
+            fs::read(imported.join("datasets/owner1/ds1/nested/b.json")).unwrap(),
+            b"{}"
+        );
+        assert_eq!(
+            fs::read(imported.join("datasets/owner2/ds2/c.bin")).unwrap(),
+            vec![7u8; 1024]
+        );
+        assert!(!imported.join("datasets/owner1/ds1/.downloaded").exists());
+        assert!(!imported
+            .join("datasets/owner1/ds1")
+            .join(super::super::metadata::SCHEMA_FILE)
+            .exists());
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(imported.join("manifest.json")).unwrap()).unwrap();
+        let entries = manifest["datasets"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["path"], "owner1/ds1");
+        assert_eq!(entries[0]["directory"], "datasets/owner1/ds1");
+        assert_eq!(entries[0]["version"], "3");
+        assert_eq!(entries[1]["path"], "owner2/ds2");
+        assert!(entries[1]["version"].is_null());
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_export_then_import_cache_end_to_end() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+
+        let d1 = cache.join("datasets/owner1/ds1");
+        let d2 = cache.join("datasets/owner2/ds2");
+        fs::create_dir_all(d1.join("nested")).unwrap();
+        fs::create_dir_all(&d2).unwrap();
+        fs::write(d1.join("a.csv"), b"x,y\n1,2\n").unwrap();
+        fs::write(d1.join("nested/b.json"), b"{\"k\":1}").unwrap();
+        fs::write(d2.join("c.txt"), b"hello").unwrap();
+        let mut meta = CacheMetadata::new("owner1/ds1".to_string(), 0);
+        meta.version = Some("5".to_string());
+        meta.downloaded_at_secs = 1;
+        fs::write(
+            d1.join(".downloaded"),
+            serde_json::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+
+        let archive = temp_dir.path().join("export.zip");
+        export_cache(&archive).unwrap();
+
+        // Clear the cache and restore it from the archive
+        fs::remove_dir_all(cache.join("datasets")).unwrap();
+        let summary = import_cache(&archive, true).unwrap();
+        assert_eq!(summary, serde_json::json!({"imported": 2, "skipped": 0}));
+
+        assert_eq!(fs::read(d1.join("a.csv")).unwrap(), b"x,y\n1,2\n");
+        assert_eq!(fs::read(d1.join("nested/b.json")).unwrap(), b"{\"k\":1}");
+        assert_eq!(fs::read(d2.join("c.txt")).unwrap(), b"hello");
+        assert!(!cache.join("manifest.json").exists());
+
+        // Markers are written fresh, keeping the exported version
+        let marker: CacheMetadata =
+            serde_json::from_str(&fs::read_to_string(d1.join(".downloaded")).unwrap()).unwrap();
+        assert_eq!(marker.dataset_path, "owner1/ds1");
+        assert_eq!(marker.version.as_deref(), Some("5"));
+        assert!(marker.downloaded_at_secs > 1);
+        assert!(d2.join(".downloaded").exists());
+
+        // Merging keeps datasets that are already cached
+        fs::write(d2.join("c.txt"), b"changed").unwrap();
+        let summary = import_cache(&archive, true).unwrap();
+        assert_eq!(summary, serde_json::json!({"imported": 0, "skipped": 2}));
+        assert_eq!(fs::read(d2.join("c.txt")).unwrap(), b"changed");
+
+        // Without merging, cached datasets are replaced
+        fs::write(d2.join("extra.txt"), b"stale").unwrap();
+        let summary = import_cache(&archive, false).unwrap();
+        assert_eq!(summary, serde_json::json!({"imported": 2, "skipped": 0}));
+        assert_eq!(fs::read(d2.join("c.txt")).unwrap(), b"hello");
+        assert!(!d2.join("extra.txt").exists());
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_failed_import_keeps_cached_datasets() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+
+        let dest = cache.join("datasets/o/d");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("a.csv"), b"old").unwrap();
+        fs::write(dest.join(".downloaded"), "{}").unwrap();
+
+        let archive = temp_dir.path().join("import.zip");
+        write_cache_archive(
+            &archive,
+            Some(r#"{"datasets": [{"path": "o/d", "directory": "datasets/o/d"}]}"#),
+            &[
+                ("datasets/o/d/a.csv", b"new"),
+                ("datasets/o/d/b.csv", b"new"),
+            ],
+        );
+
+        // The archive has more entries than allowed, so extraction fails
+        std::env::set_var("GAGGLE_MAX_ZIP_FILES", "1");
+        assert!(import_cache(&archive, false).is_err());
+        std::env::remove_var("GAGGLE_MAX_ZIP_FILES");
+        assert_eq!(fs::read(dest.join("a.csv")).unwrap(), b"old");
+        assert_eq!(fs::read_to_string(dest.join(".downloaded")).unwrap(), "{}");
+        assert!(!dest.join("b.csv").exists());
+        assert_eq!(staging_entries(&cache), 0);
+
+        let summary = import_cache(&archive, false).unwrap();
+        assert_eq!(summary["imported"], 1);
+        assert_eq!(fs::read(dest.join("a.csv")).unwrap(), b"new");
+        assert_eq!(fs::read(dest.join("b.csv")).unwrap(), b"new");
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    /// Writes a ZIP archive with the given manifest (if any) and files.
+    fn write_cache_archive(path: &Path, manifest: Option<&str>, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        let options: zip::write::FileOptions<()> = zip::write::FileOptions::default();
+        if let Some(manifest) = manifest {
+            zip.start_file("manifest.json", options).unwrap();
+            zip.write_all(manifest.as_bytes()).unwrap();
+        }
+        for (name, content) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
 
-        // Remove dataset directory
-        if let Err(e) = fs::remove_dir_all(&dataset_path) {
-            warn!(path = %dataset_path.display(), error = %e, "Failed to evict dataset");
-            continue;
+    #[test]
+    #[serial_test::serial]
+    fn test_import_cache_rejects_bad_manifests() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+        let archive = temp_dir.path().join("import.zip");
+        let file: (&str, &[u8]) = ("datasets/o/d/a.csv", b"1");
+
+        let manifests = [
+            None,
+            Some("not json"),
+            Some(r#"{"datasets": "nope"}"#),
+            Some(r#"{"datasets": [{"path": "invalid", "directory": "datasets/o/d"}]}"#),
+            Some(r#"{"datasets": [{"path": "o/d", "directory": "datasets/o/../../x"}]}"#),
+            Some(r#"{"datasets": [{"path": "o/d", "directory": "datasets/other/d"}]}"#),
+            Some(r#"{"datasets": [{"path": "o/d", "directory": "/etc"}]}"#),
+        ];
+        for manifest in manifests {
+            write_cache_archive(&archive, manifest, &[file]);
+            match import_cache(&archive, false) {
+                Err(GaggleError::ZipError(_)) => {}
+                other => panic!("expected ZipError for {:?}, got {:?}", manifest, other),
+            }
+            assert!(!cache.join("datasets").exists());
         }
 
-        total_size_mb = total_size_mb.saturating_sub(metadata.size_mb);
-        debug!(
-            dataset = %metadata.dataset_path,
-            age_secs = metadata.age_seconds(),
-            size_mb = metadata.size_mb,
-            "Cache eviction: removed dataset to enforce limit"
+        // Entries outside the listed dataset directories are ignored
+        write_cache_archive(
+            &archive,
+            Some(r#"{"datasets": [{"path": "o/d", "directory": "datasets/o/d"}]}"#),
+            &[file, ("datasets/o/unlisted/b.csv", b"2"), ("top.txt", b"3")],
+        );
+        let summary = import_cache(&archive, false).unwrap();
+        assert_eq!(summary["imported"], 1);
+        assert!(cache.join("datasets/o/d/a.csv").exists());
+        assert!(!cache.join("datasets/o/unlisted").exists());
+        assert!(!cache.join("top.txt").exists());
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_copy_dataset_to_skips_markers_and_keeps_layout() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+        std::env::set_var("KAGGLE_USERNAME", "user");
+        std::env::set_var("KAGGLE_KEY", "key");
+
+        let dir = cache.join("datasets/owner/copyable");
+        fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+        fs::write(dir.join("a.csv"), b"x,y\n1,2\n").unwrap();
+        fs::write(dir.join("nested/deeper/b.json"), b"{}").unwrap();
+        let meta = CacheMetadata::new("owner/copyable".to_string(), 0);
+        fs::write(
+            dir.join(".downloaded"),
+            serde_json::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join(super::super::metadata::SCHEMA_FILE), b"{}").unwrap();
+        fs::write(dir.join(PARTIAL_MARKER), b"{}").unwrap();
+
+        let dest = temp_dir.path().join("project/data");
+        let copied = copy_dataset_to("owner/copyable", &dest).unwrap();
+        assert_eq!(copied, 10);
+        assert_eq!(fs::read(dest.join("a.csv")).unwrap(), b"x,y\n1,2\n");
+        assert_eq!(fs::read(dest.join("nested/deeper/b.json")).unwrap(), b"{}");
+        assert!(!dest.join(".downloaded").exists());
+        assert!(!dest.join(PARTIAL_MARKER).exists());
+        assert!(!dest.join(super::super::metadata::SCHEMA_FILE).exists());
+
+        let single = temp_dir.path().join("single/b.json");
+        assert_eq!(
+            copy_file_to("owner/copyable", "nested/deeper/b.json", &single).unwrap(),
+            2
+        );
+        assert_eq!(fs::read(&single).unwrap(), b"{}");
+
+        // Destinations inside the cache are rejected
+        for result in [
+            copy_dataset_to("owner/copyable", &cache.join("copy")),
+            copy_file_to("owner/copyable", "a.csv", &dir.join("a-copy.csv")),
+        ] {
+            match result {
+                Err(GaggleError::IoError(msg)) => assert!(msg.contains("inside the cache")),
+                other => panic!("expected IoError, got {:?}", other),
+            }
+        }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_export_cache_rejects_destination_inside_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+
+        for dest in [
+            temp_dir.path().join("export.zip"),
+            temp_dir.path().join("datasets/new/dir/export.zip"),
+        ] {
+            match export_cache(&dest) {
+                Err(GaggleError::IoError(msg)) => assert!(msg.contains("inside the cache")),
+                other => panic!("expected IoError, got {:?}", other),
+            }
+            assert!(!dest.exists());
+        }
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    fn test_get_dataset_file_path_absolute_rejected() {
+        let result = get_dataset_file_path("owner/dataset", "/etc/passwd");
+        assert!(result.is_err());
+        if let Err(GaggleError::InvalidDatasetPath(msg)) = result {
+            assert!(msg.contains("Absolute"));
+        }
+    }
+
+    #[test]
+    fn test_get_dataset_file_path_parent_component_rejected() {
+        let result = get_dataset_file_path("owner/dataset", "../secrets.csv");
+        assert!(result.is_err());
+        if let Err(GaggleError::InvalidDatasetPath(msg)) = result {
+            assert!(msg.contains("parent") || msg.contains("root"));
+        }
+    }
+
+    #[test]
+    fn test_get_dataset_file_path_root_component_rejected() {
+        let result = get_dataset_file_path("owner/dataset", "/root/file.csv");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_export_dataset_links_files_and_reports_conflicts() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+        std::env::set_var("KAGGLE_USERNAME", "user");
+        std::env::set_var("KAGGLE_KEY", "key");
+
+        let dir = cache.join("datasets/owner/exportable");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("a.csv"), b"x,y\n1,2\n").unwrap();
+        fs::write(dir.join("nested/b.json"), b"{}").unwrap();
+        fs::write(dir.join(".downloaded"), b"{}").unwrap();
+
+        let dest = temp_dir.path().join("export");
+        let summary = export_dataset("owner/exportable", &dest, false).unwrap();
+        assert_eq!(summary.files_copied, 2);
+        // The destination is on the same file system as the cache
+        assert_eq!(summary.files_linked, 2);
+        assert_eq!(summary.bytes, 10);
+        assert!(summary.errors.is_empty());
+        assert_eq!(fs::read(dest.join("nested/b.json")).unwrap(), b"{}");
+        assert!(!dest.join(".downloaded").exists());
+
+        // Existing files are reported one by one without overwrite
+        fs::remove_file(dest.join("a.csv")).unwrap();
+        let summary = export_dataset("owner/exportable", &dest, false).unwrap();
+        assert_eq!(summary.files_copied, 1);
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.errors[0].file, "nested/b.json");
+        assert!(summary.errors[0].error.contains("already exists"));
+
+        let summary = export_dataset("owner/exportable", &dest, true).unwrap();
+        assert_eq!(summary.files_copied, 2);
+        assert!(summary.errors.is_empty());
+
+        assert!(export_dataset("owner/exportable", &cache.join("out"), true).is_err());
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_import_dataset_is_served_like_a_download() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        std::env::set_var("GAGGLE_CACHE_DIR", &cache);
+        std::env::set_var("KAGGLE_USERNAME", "user");
+        std::env::set_var("KAGGLE_KEY", "key");
+
+        let source = temp_dir.path().join("local");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("a.csv"), b"x,y\n1,2\n").unwrap();
+        fs::write(source.join("nested/b.json"), b"{}").unwrap();
+        // State files in the source are not imported
+        fs::write(source.join(".downloaded"), b"not json").unwrap();
+
+        let dir = import_dataset("owner/imported", &source, Some("7")).unwrap();
+        assert_eq!(dir, cache.join("datasets/owner/imported"));
+        let metadata: CacheMetadata =
+            serde_json::from_str(&fs::read_to_string(dir.join(".downloaded")).unwrap()).unwrap();
+        assert_eq!(metadata.dataset_path, "owner/imported");
+        assert_eq!(metadata.version.as_deref(), Some("7"));
+
+        // Offline mode serves the imported dataset without any request
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        let files = list_dataset_files("owner/imported").unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "a.csv");
+        assert_eq!(files[0].size, 8);
+        assert_eq!(
+            get_dataset_file_path("owner/imported", "nested/b.json").unwrap(),
+            dir.join("nested/b.json")
+        );
+        assert_eq!(download_dataset("owner/imported").unwrap(), dir);
+        std::env::remove_var("GAGGLE_OFFLINE");
+
+        // Importing again replaces the cached copy
+        fs::remove_file(source.join("a.csv")).unwrap();
+        import_dataset("owner/imported", &source, None).unwrap();
+        assert!(!dir.join("a.csv").exists());
+        assert!(dir.join("nested/b.json").exists());
+
+        assert!(matches!(
+            import_dataset("not-a-dataset-path", &source, None),
+            Err(GaggleError::InvalidDatasetPath(_))
+        ));
+        assert!(import_dataset("owner/other", &dir, None).is_err());
+        assert!(import_dataset("owner/other", &temp_dir.path().join("missing"), None).is_err());
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+    }
+
+    #[test]
+    fn test_download_dataset_if_stale_validates_path() {
+        assert!(matches!(
+            download_dataset_if_stale("not-a-dataset-path"),
+            Err(GaggleError::InvalidDatasetPath(_))
+        ));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_resolve_glob_lists_cached_dataset() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dir = temp_dir.path().join("datasets/owner/cached-glob");
+        fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+        fs::write(dir.join("a.csv"), "a\n").unwrap();
+        fs::write(dir.join("nested/b.csv"), "b\n").unwrap();
+        fs::write(dir.join("nested/deeper/c.parquet"), "c").unwrap();
+        fs::write(dir.join(".downloaded"), "{}").unwrap();
+
+        let csvs = resolve_glob("owner/cached-glob", "*.csv").unwrap();
+        assert_eq!(csvs, vec![dir.join("a.csv"), dir.join("nested/b.csv")]);
+        let deep = resolve_glob("owner/cached-glob", "nested/**/*.parquet").unwrap();
+        assert_eq!(deep, vec![dir.join("nested/deeper/c.parquet")]);
+        assert!(resolve_glob("owner/cached-glob", "*.json")
+            .unwrap()
+            .is_empty());
+        // State files are never matched
+        assert!(resolve_glob("owner/cached-glob", ".*").unwrap().is_empty());
+        assert!(matches!(
+            resolve_glob("owner/cached-glob", "nested/../../*"),
+            Err(GaggleError::InvalidDatasetPath(_))
+        ));
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_list_dataset_files_reports_local_modification_time() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dir = temp_dir.path().join("datasets/owner/local-files");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("data.csv"), "a,b\n").unwrap();
+        fs::write(dir.join(".downloaded"), "{}").unwrap();
+
+        let files = list_dataset_files("owner/local-files").unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].size, 4);
+        let modified = files[0].modified.as_deref().unwrap();
+        assert!(
+            modified.ends_with('Z') && modified.len() == 20,
+            "{}",
+            modified
         );
+        assert_eq!(files[0].columns, None);
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial]
+    fn test_list_dataset_files_by_format() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let dir = temp_dir.path().join("datasets/owner/mixed-files");
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "train.csv",
+            "TEST.CSV",
+            "meta.json",
+            "data.parquet",
+            "README",
+        ] {
+            fs::write(dir.join(name), "x").unwrap();
+        }
+        fs::write(dir.join(".downloaded"), "{}").unwrap();
+        let names = |files: Vec<DatasetFile>| {
+            let mut names: Vec<String> = files.into_iter().map(|f| f.name).collect();
+            names.sort();
+            names
+        };
+
+        let csv = list_dataset_files_by_format("owner/mixed-files", "csv").unwrap();
+        assert_eq!(names(csv), vec!["TEST.CSV", "train.csv"]);
+        let json = list_dataset_files_by_format("owner/mixed-files", ".JSON").unwrap();
+        assert_eq!(names(json), vec!["meta.json"]);
+        for all in ["*", ""] {
+            let files = list_dataset_files_by_format("owner/mixed-files", all).unwrap();
+            assert_eq!(files.len(), 5);
+        }
+
+        let rest = list_dataset_files_exclude_format("owner/mixed-files", "csv").unwrap();
+        assert_eq!(names(rest), vec!["README", "data.parquet", "meta.json"]);
+        assert!(list_dataset_files_exclude_format("owner/mixed-files", "*")
+            .unwrap()
+            .is_empty());
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    fn test_list_dataset_files_skips_marker() {
+        // This test requires mocking or a real download, which is complex
+        // For now, we test the structure of DatasetFile
+        let files = [
+            DatasetFile {
+                name: "data.csv".to_string(),
+                size: 1000,
+                modified: None,
+                columns: None,
+            },
+            DatasetFile {
+                name: "info.json".to_string(),
+                size: 500,
+                modified: None,
+                columns: None,
+            },
+        ];
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name, "data.csv");
+        assert_eq!(files[1].size, 500);
+    }
+
+    #[test]
+    fn test_is_cache_state_file_skips_interrupted_marker_writes() {
+        let state = |name: &str| is_cache_state_file(std::ffi::OsStr::new(name));
+        assert!(state(".downloaded"));
+        assert!(state(".downloaded.tmp.4321"));
+        assert!(state(".license.json.tmp.7"));
+        assert!(!state("data.tmp.1"));
+        assert!(!state(".notes.tmp."));
+        assert!(!state("data.csv"));
+    }
+
+    #[test]
+    fn test_extract_zip_with_nested_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("nested.zip");
+
+        let file = fs::File::create(&zip_path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+        // Create nested structure
+        zip.add_directory("level1/", options).unwrap();
+        zip.add_directory("level1/level2/", options).unwrap();
+        zip.start_file("level1/level2/deep.txt", options).unwrap();
+        zip.write_all(b"deep content").unwrap();
+        zip.finish().unwrap();
+
+        let dest_dir = temp_dir.path().join("extracted");
+        let result = extract_zip(&zip_path, &dest_dir, ExtractLimits::default());
+        assert!(result.is_ok());
+
+        let deep_file = dest_dir.join("level1").join("level2").join("deep.txt");
+        assert!(deep_file.exists());
+        let content = fs::read_to_string(deep_file).unwrap();
+        assert_eq!(content, "deep content");
+    }
+
+    #[test]
+    fn test_dataset_file_serialization() {
+        let file = DatasetFile {
+            name: "test.csv".to_string(),
+            size: 2048,
+            modified: None,
+            columns: Some(3),
+        };
+
+        let json = serde_json::to_string(&file).unwrap();
+        assert!(json.contains("test.csv"));
+        assert!(json.contains("2048"));
+        // Unknown values are serialized as null
+        assert!(json.contains("\"modified\":null"));
+
+        let deserialized: DatasetFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.name, file.name);
+        assert_eq!(deserialized.size, 2048);
+        assert_eq!(deserialized.columns, Some(3));
+
+        // JSON written before the new fields existed still deserializes
+        let old: DatasetFile = serde_json::from_str(r#"{"name":"a.csv","size":1}"#).unwrap();
+        assert_eq!(old.modified, None);
+        assert_eq!(old.columns, None);
+    }
+
+    #[test]
+    fn test_cache_metadata_creation() {
+        let metadata = CacheMetadata::new("owner/dataset".to_string(), 100);
+        assert_eq!(metadata.dataset_path, "owner/dataset");
+        assert_eq!(metadata.size_mb, 100);
+        assert!(metadata.downloaded_at_secs > 0);
+        assert!(metadata.version.is_none());
+    }
+
+    #[test]
+    fn test_cache_metadata_age() {
+        let mut metadata = CacheMetadata::new("owner/dataset".to_string(), 100);
+        metadata.downloaded_at_secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .saturating_sub(3600); // 1 hour ago
+
+        let age = metadata.age_seconds();
+        assert!(age >= 3600); // At least 1 hour
+        assert!(age < 3700); // Less than ~1 hour + 2 minutes
+    }
+
+    #[test]
+    fn test_cache_metadata_serialization() {
+        let metadata = CacheMetadata::new("owner/dataset".to_string(), 500);
+        let json = serde_json::to_string(&metadata).unwrap();
+        let deserialized: CacheMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.dataset_path, metadata.dataset_path);
+        assert_eq!(deserialized.size_mb, metadata.size_mb);
+    }
+
+    #[test]
+    #[serial]
+    fn test_get_cached_datasets_empty() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+
+        let datasets = get_cached_datasets().unwrap();
+        assert_eq!(datasets.len(), 0);
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
-    Ok(())
-}
+    #[test]
+    #[serial]
+    fn test_get_total_cache_size_empty() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+
+        let size = get_total_cache_size_mb().unwrap();
+        assert_eq!(size, 0);
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 
-/// A public function that manually enforces the cache limit.
-pub fn enforce_cache_limit_now() -> Result<(), GaggleError> {
-    enforce_cache_limit()
-}
+    #[test]
+    fn test_enforce_cache_limit_no_limit() {
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "unlimited");
+        let result = enforce_cache_limit_now();
+        assert!(result.is_ok());
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+    }
 
-/// Checks if the cached dataset is the current version.
-pub fn is_dataset_current(dataset_path: &str) -> Result<bool, GaggleError> {
-    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    #[test]
+    #[serial]
+    fn test_enforce_cache_limit_within_limit() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "1000");
 
-    let cache_dir = crate::config::cache_dir_runtime()
-        .join("datasets")
-        .join(&owner)
-        .join(&dataset);
+        let result = enforce_cache_limit_now();
+        assert!(result.is_ok());
 
-    let marker_file = cache_dir.join(".downloaded");
-    if !marker_file.exists() {
-        return Ok(false); // Not cached, so not current
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
     }
 
-    // Read cached metadata
-    let content = fs::read_to_string(&marker_file)?;
-    if content.is_empty() {
-        return Ok(false); // Legacy marker without metadata
+    #[test]
+    fn test_cache_metadata_with_version() {
+        let mut metadata = CacheMetadata::new("owner/dataset".to_string(), 100);
+        metadata.version = Some("5".to_string());
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        let deserialized: CacheMetadata = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.version, Some("5".to_string()));
+        assert_eq!(deserialized.dataset_path, "owner/dataset");
     }
 
-    let cached_metadata: CacheMetadata = serde_json::from_str(&content)
-        .map_err(|e| GaggleError::IoError(format!("Failed to parse cache metadata: {}", e)))?;
+    #[test]
+    #[serial]
+    fn test_is_dataset_current_not_cached() {
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
 
-    let cached_version = cached_metadata.version.as_deref().unwrap_or("unknown");
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
 
-    // Get current version from Kaggle
-    let current_version = super::metadata::get_current_version(dataset_path)?;
+        let result = is_dataset_current("owner/dataset");
+        // Should return false (not cached) or error (network issue)
+        match result {
+            Ok(false) => {} // Expected: not cached
+            Err(_) => {}    // Expected: network error
+            Ok(true) => panic!("Uncached dataset should not be current"),
+        }
 
-    // If we cannot determine current version, conservatively report not current
-    if current_version == "unknown" {
-        return Ok(false);
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
     }
 
-    Ok(cached_version == current_version)
-}
+    #[test]
+    #[serial]
+    fn test_get_dataset_version_info_structure() {
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
 
-/// Forces an update of the dataset to the latest version, ignoring the cache.
-pub fn update_dataset(dataset_path: &str) -> Result<PathBuf, GaggleError> {
-    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
 
-    let cache_dir = crate::config::cache_dir_runtime()
-        .join("datasets")
-        .join(&owner)
-        .join(&dataset);
+        let result = get_dataset_version_info("owner/dataset");
+        // May fail due to network, but if it succeeds, check structure
+        if let Ok(info) = result {
+            assert!(info.get("cached_version").is_some());
+            assert!(info.get("latest_version").is_some());
+            assert!(info.get("is_current").is_some());
+            assert!(info.get("is_cached").is_some());
+        }
 
-    // Remove existing cache
-    if cache_dir.exists() {
-        fs::remove_dir_all(&cache_dir)?;
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
     }
 
-    // Download fresh copy
-    download_dataset(dataset_path)
-}
+    #[test]
+    #[serial]
+    fn test_download_with_version_parsing() {
+        // Test that version syntax is properly parsed
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
 
-/// Retrieves version information for a dataset.
-pub fn get_dataset_version_info(dataset_path: &str) -> Result<serde_json::Value, GaggleError> {
-    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
 
-    let cache_dir = crate::config::cache_dir_runtime()
-        .join("datasets")
-        .join(&owner)
-        .join(&dataset);
+        // Test path parsing (won't actually download without network)
+        let result = crate::kaggle::parse_dataset_path_with_version("owner/dataset@v2");
+        assert!(result.is_ok());
+        let (_owner, _dataset, version) = result.unwrap();
+        assert_eq!(version, Some("2".to_string()));
 
-    let marker_file = cache_dir.join(".downloaded");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+    }
 
-    let cached_version = if marker_file.exists() {
-        let content = fs::read_to_string(&marker_file)?;
-        if !content.is_empty() {
-            serde_json::from_str::<CacheMetadata>(&content)
-                .ok()
-                .and_then(|m| m.version)
-        } else {
-            None
-        }
-    } else {
-        None
-    };
+    #[test]
+    #[serial]
+    fn test_versioned_cache_directory() {
+        // Verify that versioned downloads use different cache directories
 
-    // Get current version from Kaggle API
-    let current_version = super::metadata::get_current_version(dataset_path)?;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
 
-    // Consider unknown latest version as not current
-    let is_current = if current_version == "unknown" {
-        false
-    } else {
-        cached_version
-            .as_ref()
-            .map(|v| v == &current_version)
-            .unwrap_or(false)
-    };
+        // Simulate cache directory structure
+        let base = temp_dir.path().join("datasets").join("owner");
 
-    let info = serde_json::json!({
-        "cached_version": cached_version,
-        "latest_version": current_version,
-        "is_current": is_current,
-        "is_cached": marker_file.exists()
-    });
+        // Latest version (no version specified)
+        let latest_cache = base.join("dataset");
 
-    Ok(info)
-}
+        // Version 2
+        let v2_cache = base.join("dataset-v2");
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serial_test::serial;
-    use std::io::Write;
-    use tempfile::TempDir;
+        // Version 3
+        let v3_cache = base.join("dataset-v3");
 
-    #[test]
-    fn test_dataset_file_struct() {
-        let file = DatasetFile {
-            name: "test.csv".to_string(),
-            size: 1024,
-        };
-        assert_eq!(file.name, "test.csv");
-        assert_eq!(file.size, 1024);
+        // Verify they're different paths
+        assert_ne!(latest_cache, v2_cache);
+        assert_ne!(latest_cache, v3_cache);
+        assert_ne!(v2_cache, v3_cache);
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_lock_guard_cleanup() {
-        let lock_key = "test/dataset".to_string();
+    #[serial]
+    fn test_partial_cache_counts_and_eviction() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
 
-        // Insert into locks
-        {
-            let mut locks = DOWNLOAD_LOCKS.lock();
-            locks.insert(lock_key.clone(), ());
-            assert!(locks.contains_key(&lock_key));
-        }
+        // Create two partial cached datasets
+        let d1 = temp_dir.path().join("datasets/owner1/ds1");
+        let d2 = temp_dir.path().join("datasets/owner2/ds2");
+        fs::create_dir_all(&d1).unwrap();
+        fs::create_dir_all(&d2).unwrap();
+        fs::write(d1.join("a.bin"), vec![0u8; 2 * 1024 * 1024]).unwrap(); // 2MB
+        fs::write(d2.join("b.bin"), vec![0u8; 2 * 1024 * 1024]).unwrap(); // 2MB
 
-        // Create and drop guard
-        {
-            let _guard = LockGuard {
-                key: lock_key.clone(),
-            };
-            // Guard exists, lock should still be present
-            let locks = DOWNLOAD_LOCKS.lock();
-            assert!(locks.contains_key(&lock_key));
-        }
+        // Total ~4MB; set limit to 2MB so eviction must occur
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "2");
+        enforce_cache_limit_now().unwrap();
 
-        // After guard drop, lock should be removed
-        let locks = DOWNLOAD_LOCKS.lock();
-        assert!(!locks.contains_key(&lock_key));
+        // After eviction, the total size must be <= 2MB
+        let total = get_total_cache_size_mb().unwrap();
+        assert!(total <= 2);
+
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_extract_zip_empty() {
+    #[serial]
+    fn test_verify_dataset_integrity_reuses_checksums_until_mtime_changes() {
         let temp_dir = TempDir::new().unwrap();
-        let zip_path = temp_dir.path().join("empty.zip");
-
-        // Create an empty ZIP file
-        let file = fs::File::create(&zip_path).unwrap();
-        let zip = zip::ZipWriter::new(file);
-        zip.finish().unwrap();
-
-        let dest_dir = temp_dir.path().join("extracted");
-        let result = extract_zip(&zip_path, &dest_dir);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 0);
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        write_cached_dataset(temp_dir.path(), "owner/checked", 0, 1);
+        let dir = temp_dir.path().join("datasets/owner/checked");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/a.csv"), "a\n1\n").unwrap();
+
+        let report = verify_dataset_integrity("owner/checked").unwrap();
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["files"], 2);
+        assert_eq!(report["rehashed"], 2);
+        let empty_sha = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(report["checksums"]["data.bin"], empty_sha);
+
+        // Nothing changed, so nothing is hashed again
+        let report = verify_dataset_integrity("owner/checked").unwrap();
+        assert_eq!(report["rehashed"], 0);
+
+        // A modified file gets a new mtime, so its old checksum is discarded
+        fs::write(dir.join("data.bin"), "changed").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(dir.join("data.bin"))
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000))
+            .unwrap();
+        fs::remove_file(dir.join("sub/a.csv")).unwrap();
+        let report = verify_dataset_integrity("owner/checked").unwrap();
+        assert_eq!(report["valid"], false);
+        assert_eq!(report["rehashed"], 1);
+        assert_eq!(report["modified"], serde_json::json!(["data.bin"]));
+        assert_eq!(report["missing"], serde_json::json!(["sub/a.csv"]));
+
+        let meta: CacheMetadata =
+            serde_json::from_str(&fs::read_to_string(dir.join(".downloaded")).unwrap()).unwrap();
+        assert_ne!(meta.file_checksums["data.bin"], empty_sha);
+        assert_eq!(meta.file_mtimes["data.bin"], 1_000_000);
+        assert!(meta.checksum_computed_at_secs > 0);
+
+        assert!(matches!(
+            verify_dataset_integrity("owner/missing"),
+            Err(GaggleError::DatasetNotFound(_))
+        ));
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_extract_zip_with_file() {
-        let temp_dir = TempDir::new().unwrap();
-        let zip_path = temp_dir.path().join("test.zip");
-
-        // Create a ZIP with one file
-        let file = fs::File::create(&zip_path).unwrap();
-        let mut zip = zip::ZipWriter::new(file);
-
-        let options: zip::write::FileOptions<()> =
-            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
-        zip.start_file("test.txt", options).unwrap();
-        zip.write_all(b"test content").unwrap();
-        zip.finish().unwrap();
-
-        let dest_dir = temp_dir.path().join("extracted");
-        let result = extract_zip(&zip_path, &dest_dir);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 1);
-
-        let extracted_file = dest_dir.join("test.txt");
-        assert!(extracted_file.exists());
-        let content = fs::read_to_string(extracted_file).unwrap();
-        assert_eq!(content, "test content");
+    fn test_cache_metadata_without_checksums_deserializes() {
+        let json = r#"{"downloaded_at_secs":1,"dataset_path":"o/d","size_mb":1,"version":null,"archive_format":null}"#;
+        let meta: CacheMetadata = serde_json::from_str(json).unwrap();
+        assert!(meta.file_checksums.is_empty());
+        assert!(meta.file_mtimes.is_empty());
+        assert_eq!(meta.checksum_computed_at_secs, 0);
     }
 
     #[test]
-    fn test_extract_zip_with_directory() {
+    #[serial]
+    fn test_eviction_prefers_least_recently_accessed() {
         let temp_dir = TempDir::new().unwrap();
-        let zip_path = temp_dir.path().join("test.zip");
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        // "used" was downloaded first but is read afterwards; "unused" is never read
+        write_cached_dataset(temp_dir.path(), "owner/used", 2, 1);
+        write_cached_dataset(temp_dir.path(), "owner/unused", 2, 2);
+
+        get_dataset_file_path("owner/used", "data.bin").unwrap();
+        let marker = temp_dir.path().join("datasets/owner/used/.downloaded");
+        let meta: CacheMetadata =
+            serde_json::from_str(&fs::read_to_string(&marker).unwrap()).unwrap();
+        assert_eq!(meta.downloaded_at_secs, 1);
+        assert!(meta.last_accessed_secs > 2);
+
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "3");
+        enforce_cache_limit_now().unwrap();
 
-        // Create a ZIP with a directory
-        let file = fs::File::create(&zip_path).unwrap();
-        let mut zip = zip::ZipWriter::new(file);
+        assert!(temp_dir
+            .path()
+            .join("datasets/owner/used/data.bin")
+            .exists());
+        assert!(!temp_dir.path().join("datasets/owner/unused").exists());
 
-        let options: zip::write::FileOptions<()> =
-            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
-        zip.add_directory("subdir/", options).unwrap();
-        zip.start_file("subdir/test.txt", options).unwrap();
-        zip.write_all(b"nested content").unwrap();
-        zip.finish().unwrap();
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 
-        let dest_dir = temp_dir.path().join("extracted");
-        let result = extract_zip(&zip_path, &dest_dir);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 1);
+    #[test]
+    #[serial]
+    fn test_clear_dataset_cache_keeps_pinned_datasets() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        write_cached_dataset(temp_dir.path(), "owner/plain", 1, 1);
+        write_cached_dataset(temp_dir.path(), "owner/pinned", 1, 1);
+        let pinned = temp_dir.path().join("datasets/owner/pinned");
+        fs::write(pinned.join(PINNED_MARKER), b"").unwrap();
+
+        clear_dataset_cache("owner/plain", false).unwrap();
+        assert!(!temp_dir.path().join("datasets/owner/plain").exists());
+        // Clearing a dataset that is not cached succeeds
+        clear_dataset_cache("owner/plain", false).unwrap();
+
+        match clear_dataset_cache("owner/pinned", false) {
+            Err(GaggleError::IoError(msg)) => assert!(msg.contains("pinned")),
+            other => panic!("expected a pinned error, got {:?}", other),
+        }
+        assert!(pinned.join("data.bin").exists());
 
-        let extracted_file = dest_dir.join("subdir").join("test.txt");
-        assert!(extracted_file.exists());
+        clear_dataset_cache("owner/pinned", true).unwrap();
+        assert!(!pinned.exists());
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_extract_zip_path_traversal_blocked() {
+    #[serial]
+    fn test_evict_datasets_older_than_skips_pinned() {
         let temp_dir = TempDir::new().unwrap();
-        let zip_path = temp_dir.path().join("malicious.zip");
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        write_cached_dataset(temp_dir.path(), "owner/old", 1, now - 7200);
+        write_cached_dataset(temp_dir.path(), "owner/old-pinned", 1, now - 7200);
+        write_cached_dataset(temp_dir.path(), "owner/new", 1, now);
+        fs::write(
+            temp_dir
+                .path()
+                .join("datasets/owner/old-pinned")
+                .join(PINNED_MARKER),
+            b"",
+        )
+        .unwrap();
 
-        // Create a ZIP with path traversal attempt
-        let file = fs::File::create(&zip_path).unwrap();
-        let mut zip = zip::ZipWriter::new(file);
+        assert_eq!(evict_datasets_older_than(3600).unwrap(), 1);
+        assert!(!temp_dir.path().join("datasets/owner/old").exists());
+        assert!(temp_dir.path().join("datasets/owner/old-pinned").exists());
+        assert!(temp_dir.path().join("datasets/owner/new").exists());
 
-        // Note: ZipWriter may normalize paths, so this test verifies the check exists
-        let options: zip::write::FileOptions<()> =
-            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 
-        // Try to create a file with .. in the path
-        // The zip crate may reject this, but we test our extraction logic
-        let result = zip.start_file("../escape.txt", options);
-        if result.is_ok() {
-            zip.write_all(b"malicious").unwrap();
-            zip.finish().unwrap();
+    #[test]
+    #[serial]
+    fn test_list_cached_datasets_and_refresh_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        write_cached_dataset(temp_dir.path(), "owner/full", 2, 1);
+        let partial = temp_dir.path().join("datasets/owner/partial");
+        fs::create_dir_all(&partial).unwrap();
+        fs::write(partial.join("one.bin"), vec![0u8; 1024 * 1024]).unwrap();
+
+        // The marker size is reported as recorded, without walking the directory
+        let marker = temp_dir.path().join("datasets/owner/full/.downloaded");
+        let mut meta: CacheMetadata =
+            serde_json::from_str(&fs::read_to_string(&marker).unwrap()).unwrap();
+        meta.size_mb = 7;
+        meta.version = Some("3".to_string());
+        fs::write(&marker, serde_json::to_string(&meta).unwrap()).unwrap();
+
+        let mut datasets = list_cached_datasets().unwrap();
+        datasets.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(datasets.len(), 2);
+        assert_eq!(datasets[0].path, "owner/full");
+        assert_eq!(datasets[0].size_mb, 7);
+        assert_eq!(datasets[0].version.as_deref(), Some("3"));
+        assert!(!datasets[0].partial);
+        assert!(datasets[0].age_seconds > 0);
+        assert_eq!(datasets[1].path, "owner/partial");
+        assert_eq!(datasets[1].size_mb, 1);
+        assert!(datasets[1].partial);
+
+        assert_eq!(refresh_cache_sizes().unwrap(), 1);
+        let full = list_cached_datasets()
+            .unwrap()
+            .into_iter()
+            .find(|d| d.path == "owner/full")
+            .unwrap();
+        assert_eq!(full.size_mb, 2);
+        assert_eq!(refresh_cache_sizes().unwrap(), 0);
 
-            let dest_dir = temp_dir.path().join("extracted");
-            // Our extraction should either skip invalid names or reject them
-            let extract_result = extract_zip(&zip_path, &dest_dir);
-            // Should succeed but not extract the malicious file outside dest_dir
-            if extract_result.is_ok() {
-                let escape_file = temp_dir.path().join("escape.txt");
-                assert!(!escape_file.exists());
-            }
-        }
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_extract_zip_size_limit() {
-        // This test verifies that the size check logic works correctly
-        // by creating a small ZIP with metadata that claims large size
-        // We test the cumulative size check, not actual file creation
+    fn test_last_accessed_defaults_to_download_time_for_old_markers() {
+        let meta: CacheMetadata = serde_json::from_str(
+            r#"{"downloaded_at_secs":42,"dataset_path":"o/d","size_mb":1,"version":null}"#,
+        )
+        .unwrap();
+        assert_eq!(meta.last_accessed_secs, 0);
+        assert_eq!(meta.last_accessed(), 42);
+    }
 
+    #[test]
+    fn test_cache_access_updates_time_and_count() {
         let temp_dir = TempDir::new().unwrap();
-        let zip_path = temp_dir.path().join("test.zip");
+        let dir = temp_dir.path().join("ds");
+        fs::create_dir_all(&dir).unwrap();
+        let mut meta = CacheMetadata::new("o/d".to_string(), 0);
+        meta.last_accessed_secs = 5;
+        fs::write(
+            dir.join(".downloaded"),
+            serde_json::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+
+        record_cache_access(&dir);
+        record_cache_access(&dir);
+        let meta: CacheMetadata =
+            serde_json::from_str(&fs::read_to_string(dir.join(".downloaded")).unwrap()).unwrap();
+        assert!(meta.last_accessed_secs > 5);
+        assert_eq!(meta.access_count, 2);
+    }
 
-        // Create a small ZIP file with a few tiny files
-        let file = fs::File::create(&zip_path).unwrap();
-        let mut zip = zip::ZipWriter::new(file);
+    #[test]
+    #[serial]
+    fn test_concurrent_single_file_downloads_share_one_request() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let file = server
+            .mock("GET", "/datasets/download/owner/ds")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "fileName".into(),
+                "a.csv".into(),
+            ))
+            .with_status(200)
+            .with_chunked_body(|w| {
+                std::thread::sleep(Duration::from_millis(300));
+                w.write_all(b"x\n1\n")
+            })
+            .expect(1)
+            .create();
+
+        let barrier = std::sync::Barrier::new(2);
+        let paths: Vec<PathBuf> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        download_single_file("owner/ds", "a.csv").unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        file.assert();
+        assert_eq!(paths[0], paths[1]);
+        assert_eq!(fs::read(&paths[0]).unwrap(), b"x\n1\n");
+
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 
-        let options: zip::write::FileOptions<()> =
-            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    /// Points single-file downloads at a mock server and a fresh cache directory.
+    fn single_file_server() -> (TempDir, mockito::ServerGuard) {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        let server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        (temp_dir, server)
+    }
 
-        // Create just a few small files - the actual limit check happens
-        // during extraction based on the reported uncompressed size
-        for i in 0..5 {
-            zip.start_file(format!("file{}.txt", i), options).unwrap();
-            zip.write_all(b"test content").unwrap();
-        }
+    fn cleanup_single_file_server() {
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 
+    fn zip_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut buf));
+        for (name, content) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content).unwrap();
+        }
         zip.finish().unwrap();
+        buf
+    }
 
-        let dest_dir = temp_dir.path().join("extracted");
+    #[test]
+    #[serial]
+    fn test_single_file_download_unwraps_zip_response() {
+        let (temp_dir, mut server) = single_file_server();
+        let _file = server
+            .mock("GET", "/datasets/download/owner/ds")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "fileName".into(),
+                "data.csv".into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/zip")
+            .with_header("content-disposition", "attachment; filename=data.csv.zip")
+            .with_body(zip_of(&[("data.csv", b"a,b\n1,2\n")]))
+            .create();
+        let _xlsx = server
+            .mock("GET", "/datasets/download/owner/ds")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "fileName".into(),
+                "book.xlsx".into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/zip")
+            .with_body(zip_of(&[
+                ("xl/workbook.xml", b"<x/>"),
+                ("xl/styles.xml", b"<s/>"),
+            ]))
+            .create();
+
+        let path = download_single_file("owner/ds", "data.csv").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"a,b\n1,2\n");
+        // An archive without an entry for the file, such as an XLSX workbook, is kept as is
+        let path = download_single_file("owner/ds", "book.xlsx").unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(b"PK\x03\x04"));
+
+        let mut names: Vec<_> = fs::read_dir(temp_dir.path().join("datasets/owner/ds"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["book.xlsx", "data.csv"]);
 
-        // This test primarily verifies that:
-        // 1. Small files extract successfully (under 10GB limit)
-        // 2. The size checking logic is in place
-        let result = extract_zip(&zip_path, &dest_dir);
+        cleanup_single_file_server();
+    }
 
-        // Should succeed because total size is well under 10GB
-        assert!(result.is_ok());
-        let extracted_count = result.unwrap();
-        assert_eq!(extracted_count, 5);
+    #[test]
+    #[serial]
+    fn test_single_file_download_keeps_requested_name() {
+        let (_temp_dir, mut server) = single_file_server();
+        let _file = server
+            .mock("GET", "/datasets/download/owner/ds")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "fileName".into(),
+                "my data.csv".into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "text/csv; charset=utf-8")
+            .with_header(
+                "content-disposition",
+                "attachment; filename=\"my%20data.csv\"; filename*=UTF-8''my%20data.csv",
+            )
+            .with_body("x\n1\n")
+            .create();
+
+        let path = download_single_file("owner/ds", "my data.csv").unwrap();
+        assert!(path.ends_with("my data.csv"));
+        assert_eq!(fs::read(&path).unwrap(), b"x\n1\n");
+
+        cleanup_single_file_server();
+    }
 
-        // Verify the files were actually extracted
-        for i in 0..5 {
-            let file_path = dest_dir.join(format!("file{}.txt", i));
-            assert!(file_path.exists());
+    #[test]
+    #[serial]
+    fn test_single_file_download_rejects_html_page() {
+        let (temp_dir, mut server) = single_file_server();
+        let _file = server
+            .mock("GET", "/datasets/download/owner/ds")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "fileName".into(),
+                "data.csv".into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "text/html; charset=utf-8")
+            .with_body("\n<!DOCTYPE html><html><body>Sign in</body></html>")
+            .create();
+
+        match download_single_file("owner/ds", "data.csv") {
+            Err(GaggleError::CredentialsError(msg)) => assert!(msg.contains("HTML"), "{}", msg),
+            other => panic!("expected a credentials error, got {:?}", other),
         }
+        // Nothing is cached, not even the partial download
+        let dir = temp_dir.path().join("datasets/owner/ds");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        cleanup_single_file_server();
     }
 
     #[test]
-    fn test_extract_zip_size_check_logic() {
-        // Test that the size limit constant is correctly defined
-        // The actual limit is 10GB = 10 * 1024 * 1024 * 1024 bytes
-        const EXPECTED_LIMIT: u64 = 10 * 1024 * 1024 * 1024;
+    fn test_disposition_filename() {
+        assert_eq!(
+            disposition_filename("attachment; filename=\"a b.csv\"").as_deref(),
+            Some("a b.csv")
+        );
+        assert_eq!(
+            disposition_filename("attachment; filename=x.csv; filename*=UTF-8''caf%C3%A9.csv")
+                .as_deref(),
+            Some("café.csv")
+        );
+        assert_eq!(disposition_filename("inline"), None);
+    }
 
-        // We can't easily test the actual size limit without creating large files,
-        // but we can verify the constant exists and has the right value
-        // by checking it would trigger on cumulative sizes > 10GB
+    /// Writes a cached `owner/ttl` dataset at version 1, downloaded `age_secs` ago.
+    fn write_expired_dataset(cache: &Path, age_secs: u64) -> PathBuf {
+        let dir = cache.join("datasets/owner/ttl");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("old.csv"), b"a\n1\n").unwrap();
+        let mut meta = CacheMetadata::new("owner/ttl".to_string(), 0);
+        meta.downloaded_at_secs -= age_secs;
+        meta.version = Some("1".to_string());
+        fs::write(
+            dir.join(".downloaded"),
+            serde_json::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+        dir
+    }
 
-        let size_under_limit = 5 * 1024 * 1024 * 1024u64; // 5GB
-        let size_over_limit = 11 * 1024 * 1024 * 1024u64; // 11GB
+    fn read_marker(dir: &Path) -> CacheMetadata {
+        serde_json::from_str(&fs::read_to_string(dir.join(".downloaded")).unwrap()).unwrap()
+    }
 
-        assert!(size_under_limit < EXPECTED_LIMIT);
-        assert!(size_over_limit > EXPECTED_LIMIT);
+    fn set_ttl_env(cache: &Path, server: &mockito::Server) {
+        std::env::set_var("GAGGLE_CACHE_DIR", cache);
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        std::env::set_var("GAGGLE_CACHE_TTL_SECS", "3600");
+        super::super::metadata::clear_metadata_cache();
     }
 
-    #[test]
-    fn test_get_dataset_file_path_absolute_rejected() {
-        let result = get_dataset_file_path("owner/dataset", "/etc/passwd");
-        assert!(result.is_err());
-        if let Err(GaggleError::InvalidDatasetPath(msg)) = result {
-            assert!(msg.contains("Absolute"));
-        }
+    fn clear_ttl_env() {
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_CACHE_TTL_SECS");
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_get_dataset_file_path_parent_component_rejected() {
-        let result = get_dataset_file_path("owner/dataset", "../secrets.csv");
-        assert!(result.is_err());
-        if let Err(GaggleError::InvalidDatasetPath(msg)) = result {
-            assert!(msg.contains("parent") || msg.contains("root"));
+    #[serial]
+    fn test_expired_outdated_cache_is_downloaded_again() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut server = mockito::Server::new();
+        set_ttl_env(temp_dir.path(), &server);
+        let dir = write_expired_dataset(temp_dir.path(), 7200);
+
+        let mut zip_bytes = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_bytes));
+            let options: zip::write::FileOptions<()> = zip::write::FileOptions::default();
+            zip.start_file("new.csv", options).unwrap();
+            zip.write_all(b"a\n2\n").unwrap();
+            zip.finish().unwrap();
         }
+        let _meta = server
+            .mock("GET", "/datasets/view/owner/ttl")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/ttl","currentVersionNumber":2}"#)
+            .create();
+        let download = server
+            .mock("GET", "/datasets/download/owner/ttl")
+            .with_status(200)
+            .with_body(zip_bytes)
+            .expect(1)
+            .create();
+
+        let path = download_dataset("owner/ttl").unwrap();
+        download.assert();
+        assert!(path.join("new.csv").exists());
+        assert!(!path.join("old.csv").exists());
+        assert_eq!(read_marker(&dir).version.as_deref(), Some("2"));
+
+        clear_ttl_env();
     }
 
     #[test]
-    fn test_get_dataset_file_path_root_component_rejected() {
-        let result = get_dataset_file_path("owner/dataset", "/root/file.csv");
-        assert!(result.is_err());
+    #[serial]
+    fn test_expired_current_cache_refreshes_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut server = mockito::Server::new();
+        set_ttl_env(temp_dir.path(), &server);
+        let dir = write_expired_dataset(temp_dir.path(), 7200);
+
+        let meta = server
+            .mock("GET", "/datasets/view/owner/ttl")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/ttl","currentVersionNumber":1}"#)
+            .expect(1)
+            .create();
+        let download = server
+            .mock("GET", "/datasets/download/owner/ttl")
+            .expect(0)
+            .create();
+
+        download_dataset("owner/ttl").unwrap();
+        assert!(dir.join("old.csv").exists());
+        assert!(read_marker(&dir).age_seconds() < 60);
+
+        // The refreshed marker is within the TTL, so the version is not checked again
+        download_dataset("owner/ttl").unwrap();
+        meta.assert();
+        download.assert();
+
+        clear_ttl_env();
     }
 
     #[test]
-    fn test_list_dataset_files_skips_marker() {
-        // This test requires mocking or a real download, which is complex
-        // For now, we test the structure of DatasetFile
-        let files = [
-            DatasetFile {
-                name: "data.csv".to_string(),
-                size: 1000,
-            },
-            DatasetFile {
-                name: "info.json".to_string(),
-                size: 500,
-            },
-        ];
-
-        assert_eq!(files.len(), 2);
-        assert_eq!(files[0].name, "data.csv");
-        assert_eq!(files[1].size, 500);
+    #[serial]
+    fn test_cache_ttl_skipped_when_fresh_or_offline() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut server = mockito::Server::new();
+        set_ttl_env(temp_dir.path(), &server);
+        let any = server.mock("GET", mockito::Matcher::Any).expect(0).create();
+
+        // Within the TTL
+        let dir = write_expired_dataset(temp_dir.path(), 60);
+        download_dataset("owner/ttl").unwrap();
+
+        // Expired, but offline
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        write_expired_dataset(temp_dir.path(), 7200);
+        download_dataset("owner/ttl").unwrap();
+        assert!(read_marker(&dir).age_seconds() >= 7200);
+        any.assert();
+
+        clear_ttl_env();
     }
 
-    #[test]
-    fn test_extract_zip_with_nested_directories() {
+    /// Writes four datasets with distinct download times, access times, access counts,
+    /// and sizes, sets a limit that forces exactly one eviction, and returns the survivors.
+    fn evict_one_with_policy(policy: &str) -> Vec<String> {
         let temp_dir = TempDir::new().unwrap();
-        let zip_path = temp_dir.path().join("nested.zip");
-
-        let file = fs::File::create(&zip_path).unwrap();
-        let mut zip = zip::ZipWriter::new(file);
-
-        let options: zip::write::FileOptions<()> =
-            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
-
-        // Create nested structure
-        zip.add_directory("level1/", options).unwrap();
-        zip.add_directory("level1/level2/", options).unwrap();
-        zip.start_file("level1/level2/deep.txt", options).unwrap();
-        zip.write_all(b"deep content").unwrap();
-        zip.finish().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        // (name, size_mb, downloaded_at, last_accessed, access_count)
+        let entries = [
+            ("owner/oldest", 1, 10, 400, 9),
+            ("owner/stale", 1, 20, 100, 5),
+            ("owner/rare", 1, 30, 300, 1),
+            ("owner/big", 3, 40, 200, 7),
+        ];
+        for (name, size_mb, downloaded_at, last_accessed, access_count) in entries {
+            write_cached_dataset(temp_dir.path(), name, size_mb, downloaded_at);
+            let marker = temp_dir
+                .path()
+                .join("datasets")
+                .join(name)
+                .join(".downloaded");
+            let mut meta: CacheMetadata =
+                serde_json::from_str(&fs::read_to_string(&marker).unwrap()).unwrap();
+            meta.last_accessed_secs = last_accessed;
+            meta.access_count = access_count;
+            fs::write(&marker, serde_json::to_string(&meta).unwrap()).unwrap();
+        }
 
-        let dest_dir = temp_dir.path().join("extracted");
-        let result = extract_zip(&zip_path, &dest_dir);
-        assert!(result.is_ok());
+        // Total is 6MB; evicting any single dataset gets under 5MB
+        std::env::set_var("GAGGLE_CACHE_EVICTION_POLICY", policy);
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "5");
+        enforce_cache_limit_now().unwrap();
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_CACHE_EVICTION_POLICY");
 
-        let deep_file = dest_dir.join("level1").join("level2").join("deep.txt");
-        assert!(deep_file.exists());
-        let content = fs::read_to_string(deep_file).unwrap();
-        assert_eq!(content, "deep content");
+        let mut survivors: Vec<String> = list_cached_datasets()
+            .unwrap()
+            .into_iter()
+            .map(|d| d.path)
+            .collect();
+        survivors.sort();
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        survivors
     }
 
     #[test]
-    fn test_dataset_file_serialization() {
-        let file = DatasetFile {
-            name: "test.csv".to_string(),
-            size: 2048,
-        };
+    #[serial]
+    fn test_eviction_policy_lru() {
+        assert_eq!(
+            evict_one_with_policy("lru"),
+            ["owner/big", "owner/oldest", "owner/rare"]
+        );
+    }
 
-        let json = serde_json::to_string(&file).unwrap();
-        assert!(json.contains("test.csv"));
-        assert!(json.contains("2048"));
+    #[test]
+    #[serial]
+    fn test_eviction_policy_lfu() {
+        assert_eq!(
+            evict_one_with_policy("lfu"),
+            ["owner/big", "owner/oldest", "owner/stale"]
+        );
+    }
 
-        let deserialized: DatasetFile = serde_json::from_str(&json).unwrap();
-        assert_eq!(deserialized.name, file.name);
-        assert_eq!(deserialized.size, 2048);
+    #[test]
+    #[serial]
+    fn test_eviction_policy_fifo() {
+        assert_eq!(
+            evict_one_with_policy("fifo"),
+            ["owner/big", "owner/rare", "owner/stale"]
+        );
     }
 
     #[test]
-    fn test_cache_metadata_creation() {
-        let metadata = CacheMetadata::new("owner/dataset".to_string(), 100);
-        assert_eq!(metadata.dataset_path, "owner/dataset");
-        assert_eq!(metadata.size_mb, 100);
-        assert!(metadata.downloaded_at_secs > 0);
-        assert!(metadata.version.is_none());
+    #[serial]
+    fn test_eviction_policy_largest_first() {
+        assert_eq!(
+            evict_one_with_policy("largest_first"),
+            ["owner/oldest", "owner/rare", "owner/stale"]
+        );
     }
 
     #[test]
-    fn test_cache_metadata_age() {
-        let mut metadata = CacheMetadata::new("owner/dataset".to_string(), 100);
-        metadata.downloaded_at_secs = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            .saturating_sub(3600); // 1 hour ago
+    #[serial]
+    fn test_unknown_eviction_policy_falls_back_to_lru() {
+        assert_eq!(evict_one_with_policy("mru"), evict_one_with_policy("lru"));
+    }
 
-        let age = metadata.age_seconds();
-        assert!(age >= 3600); // At least 1 hour
-        assert!(age < 3700); // Less than ~1 hour + 2 minutes
+    /// Creates a marked cache entry of `size_mb` megabytes downloaded at `downloaded_at_secs`.
+    fn write_cached_dataset(
+        cache: &Path,
+        dataset_path: &str,
+        size_mb: u64,
+        downloaded_at_secs: u64,
+    ) {
+        let dir = cache.join("datasets").join(dataset_path);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("data.bin"),
+            vec![0u8; (size_mb * 1024 * 1024) as usize],
+        )
+        .unwrap();
+        let mut meta = CacheMetadata::new(dataset_path.to_string(), size_mb);
+        meta.downloaded_at_secs = downloaded_at_secs;
+        meta.last_accessed_secs = downloaded_at_secs;
+        fs::write(
+            dir.join(".downloaded"),
+            serde_json::to_string(&meta).unwrap(),
+        )
+        .unwrap();
     }
 
     #[test]
-    fn test_cache_metadata_serialization() {
-        let metadata = CacheMetadata::new("owner/dataset".to_string(), 500);
-        let json = serde_json::to_string(&metadata).unwrap();
-        let deserialized: CacheMetadata = serde_json::from_str(&json).unwrap();
-        assert_eq!(deserialized.dataset_path, metadata.dataset_path);
-        assert_eq!(deserialized.size_mb, metadata.size_mb);
+    #[serial]
+    fn test_hard_cache_limit_refuses_download_that_cannot_fit() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        std::env::set_var("GAGGLE_CACHE_HARD_LIMIT", "true");
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "5");
+        super::super::metadata::clear_metadata_cache();
+        write_cached_dataset(temp_dir.path(), "owner/small", 2, 1);
+
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let _meta = server
+            .mock("GET", "/datasets/view/owner/huge")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/huge","totalBytes":53687091200}"#)
+            .create();
+        let download = server
+            .mock("GET", "/datasets/download/owner/huge")
+            .expect(0)
+            .create();
+
+        match download_dataset("owner/huge") {
+            Err(e @ GaggleError::CacheLimitExceeded { .. }) => {
+                let msg = e.to_string();
+                assert!(msg.contains("requires 51200 MB"), "{}", msg);
+                assert!(msg.contains("only 3 MB is available"), "{}", msg);
+            }
+            other => panic!("expected CacheLimitExceeded, got {:?}", other),
+        }
+        download.assert();
+        // Nothing is evicted when the dataset could never fit
+        assert!(temp_dir
+            .path()
+            .join("datasets/owner/small/.downloaded")
+            .exists());
+        assert!(!temp_dir.path().join("datasets/owner/huge").exists());
+
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_CACHE_HARD_LIMIT");
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
     #[serial]
-    fn test_get_cached_datasets_empty() {
-        let temp_dir = tempfile::TempDir::new().unwrap();
+    fn test_hard_cache_limit_applies_to_filtered_downloads() {
+        let mut zip_bytes = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_bytes));
+            let options: zip::write::FileOptions<()> = zip::write::FileOptions::default();
+            zip.start_file("a.csv", options).unwrap();
+            zip.write_all(b"1").unwrap();
+            zip.start_file("b.txt", options).unwrap();
+            zip.write_all(b"2").unwrap();
+            zip.finish().unwrap();
+        }
+        let temp_dir = TempDir::new().unwrap();
         std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        std::env::set_var("GAGGLE_CACHE_HARD_LIMIT", "true");
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "5");
+        super::super::metadata::clear_metadata_cache();
+        write_cached_dataset(temp_dir.path(), "owner/old", 2, 1);
+        write_cached_dataset(temp_dir.path(), "owner/recent", 2, 2);
+
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let _huge = server
+            .mock("GET", "/datasets/view/owner/huge")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/huge","totalBytes":53687091200}"#)
+            .create();
+        let huge_download = server
+            .mock("GET", "/datasets/download/owner/huge")
+            .expect(0)
+            .create();
+        let _parts = server
+            .mock("GET", "/datasets/view/owner/parts")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/parts","totalBytes":2097152}"#)
+            .create();
+        let parts_download = server
+            .mock("GET", "/datasets/download/owner/parts")
+            .with_status(200)
+            .with_header("content-type", "application/zip")
+            .with_body(zip_bytes)
+            .expect(1)
+            .create();
+
+        match download_dataset_files("owner/huge", &["*.csv"]) {
+            Err(GaggleError::CacheLimitExceeded { .. }) => {}
+            other => panic!("expected CacheLimitExceeded, got {:?}", other),
+        }
+        huge_download.assert();
+        assert!(!temp_dir.path().join("datasets/owner/huge").exists());
+
+        // Room for the whole dataset is made by evicting the oldest one
+        let files = download_dataset_files("owner/parts", &["*.csv"]).unwrap();
+        assert_eq!(
+            files,
+            vec![temp_dir.path().join("datasets/owner/parts/a.csv")]
+        );
+        parts_download.assert();
+        assert!(!temp_dir.path().join("datasets/owner/old").exists());
+        assert!(temp_dir.path().join("datasets/owner/recent").exists());
+        assert_eq!(staging_entries(temp_dir.path()), 0);
 
-        let datasets = get_cached_datasets().unwrap();
-        assert_eq!(datasets.len(), 0);
-
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_CACHE_HARD_LIMIT");
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
         std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
     #[serial]
-    fn test_get_total_cache_size_empty() {
-        let temp_dir = tempfile::TempDir::new().unwrap();
+    fn test_plan_download_reports_evictions_without_changes() {
+        let temp_dir = TempDir::new().unwrap();
         std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "5");
+        super::super::metadata::clear_metadata_cache();
+        write_cached_dataset(temp_dir.path(), "owner/older", 2, 1);
+        write_cached_dataset(temp_dir.path(), "owner/newer", 2, 2);
+
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let meta = server
+            .mock("GET", "/datasets/view/owner/planned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/planned","totalBytes":3145728,"currentVersionNumber":4}"#)
+            .expect(1)
+            .create();
+        let download = server
+            .mock("GET", "/datasets/download/owner/planned")
+            .expect(0)
+            .create();
+
+        let plan = plan_download("owner/planned").unwrap();
+        assert_eq!(plan["is_cached"], false);
+        assert_eq!(plan["would_download"], true);
+        assert_eq!(plan["latest_version"], "4");
+        assert_eq!(plan["is_current"], false);
+        assert_eq!(plan["remote_size_bytes"], 3145728);
+        assert_eq!(plan["metadata_source"], "api");
+        assert_eq!(plan["cache"]["usage_mb"], 4);
+        assert_eq!(plan["cache"]["limit_mb"], 5);
+        assert_eq!(plan["cache"]["headroom_mb"], 1);
+        assert_eq!(
+            plan["evictions"],
+            serde_json::json!([{"dataset": "owner/older", "size_mb": 2}])
+        );
+        assert_eq!(plan["fits"], true);
+        assert_eq!(plan["undetermined"], serde_json::json!([]));
+
+        // Nothing was downloaded or evicted
+        download.assert();
+        assert!(temp_dir.path().join("datasets/owner/older").exists());
+        assert!(!temp_dir.path().join("datasets/owner/planned").exists());
+
+        // Offline, the metadata saved by the fetch above is used
+        super::super::metadata::clear_metadata_cache();
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        let offline = plan_download("owner/planned").unwrap();
+        assert_eq!(offline["metadata_source"], "local");
+        assert_eq!(offline["latest_version"], "4");
+        assert_eq!(offline["evictions"], plan["evictions"]);
+
+        // Without any local metadata, the fields that need it are undetermined
+        let unknown = plan_download("owner/never-fetched").unwrap();
+        assert!(unknown["metadata_source"].is_null());
+        assert!(unknown["remote_size_bytes"].is_null());
+        assert!(unknown["fits"].is_null());
+        assert_eq!(
+            unknown["undetermined"],
+            serde_json::json!(["latest_version", "remote_size_bytes", "fits"])
+        );
+        meta.assert();
 
-        let size = get_total_cache_size_mb().unwrap();
-        assert_eq!(size, 0);
-
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
         std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
-    fn test_enforce_cache_limit_no_limit() {
+    #[serial]
+    fn test_pre_download_cache_check_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        super::super::metadata::clear_metadata_cache();
+        write_cached_dataset(temp_dir.path(), "owner/cached", 2, 1);
+
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let _meta = server
+            .mock("GET", "/datasets/view/owner/three-mb")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/three-mb","totalBytes":3145728}"#)
+            .create();
+        let _missing = server
+            .mock("GET", "/datasets/view/owner/no-meta")
+            .with_status(404)
+            .create();
+
+        // 2 MB cached plus 3 MB expected
         std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "unlimited");
-        let result = enforce_cache_limit_now();
-        assert!(result.is_ok());
+        assert!(pre_download_cache_check("owner/three-mb").is_ok());
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "5");
+        assert!(pre_download_cache_check("owner/three-mb").is_ok());
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "4");
+        match pre_download_cache_check("owner/three-mb") {
+            Err(GaggleError::CacheLimitExceeded {
+                required_mb,
+                available_mb,
+                ..
+            }) => assert_eq!((required_mb, available_mb), (3, 2)),
+            other => panic!("expected CacheLimitExceeded, got {:?}", other),
+        }
+
+        // The floor applies even when the download itself fits
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "6");
+        std::env::set_var("GAGGLE_MIN_FREE_CACHE_MB", "2");
+        match pre_download_cache_check("owner/three-mb") {
+            Err(GaggleError::CacheLimitExceeded {
+                required_mb,
+                available_mb,
+                ..
+            }) => assert_eq!((required_mb, available_mb), (5, 4)),
+            other => panic!("expected CacheLimitExceeded, got {:?}", other),
+        }
+        std::env::set_var("GAGGLE_MIN_FREE_CACHE_MB", "1");
+        assert!(pre_download_cache_check("owner/three-mb").is_ok());
+
+        // The dataset's own cached copy is replaced, so it is not counted
+        std::env::set_var("GAGGLE_MIN_FREE_CACHE_MB", "0");
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "5");
+        write_cached_dataset(temp_dir.path(), "owner/three-mb", 3, 2);
+        assert!(pre_download_cache_check("owner/three-mb").is_ok());
+
+        // Without a known size, the download is not blocked
+        std::env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "1");
+        assert!(pre_download_cache_check("owner/no-meta").is_ok());
+
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+        std::env::remove_var("GAGGLE_MIN_FREE_CACHE_MB");
         std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
     #[serial]
-    fn test_enforce_cache_limit_within_limit() {
-        let temp_dir = tempfile::TempDir::new().unwrap();
+    fn test_download_of_missing_dataset_fails_before_the_download_endpoint() {
+        let temp_dir = TempDir::new().unwrap();
         std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
-        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "1000");
-
-        let result = enforce_cache_limit_now();
-        assert!(result.is_ok());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        super::super::metadata::clear_metadata_cache();
+
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let meta = server
+            .mock("GET", "/datasets/view/owner/datset")
+            .with_status(404)
+            .expect(1)
+            .create();
+        let download = server
+            .mock("GET", "/datasets/download/owner/datset")
+            .with_status(404)
+            .expect(0)
+            .create();
+
+        // The second attempt is answered from the negative cache
+        for _ in 0..2 {
+            match download_dataset("owner/datset") {
+                Err(GaggleError::DatasetNotFound(msg)) => {
+                    assert!(msg.contains("owner/datset"), "{}", msg);
+                    assert!(msg.contains("kaggle.com/datasets?search=datset"), "{}", msg);
+                }
+                other => panic!("expected DatasetNotFound, got {:?}", other),
+            }
+        }
+        meta.assert();
+        download.assert();
+
+        // Skipping the check goes straight to the download endpoint
+        std::env::set_var("GAGGLE_SKIP_EXISTENCE_CHECK", "1");
+        std::env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+        let download = server
+            .mock("GET", "/datasets/download/owner/datset")
+            .with_status(404)
+            .expect(1)
+            .create();
+        assert!(matches!(
+            download_dataset("owner/datset"),
+            Err(GaggleError::HttpRequestError(_))
+        ));
+        download.assert();
 
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_SKIP_EXISTENCE_CHECK");
+        std::env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
         std::env::remove_var("GAGGLE_CACHE_DIR");
-        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
     }
 
     #[test]
-    fn test_cache_metadata_with_version() {
-        let mut metadata = CacheMetadata::new("owner/dataset".to_string(), 100);
-        metadata.version = Some("5".to_string());
-
-        let json = serde_json::to_string(&metadata).unwrap();
-        let deserialized: CacheMetadata = serde_json::from_str(&json).unwrap();
+    #[serial]
+    fn test_hard_cache_limit_keeps_min_free_headroom() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        std::env::set_var("GAGGLE_CACHE_HARD_LIMIT", "true");
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "5");
+        super::super::metadata::clear_metadata_cache();
+        write_cached_dataset(temp_dir.path(), "owner/old", 1, 1);
+        write_cached_dataset(temp_dir.path(), "owner/recent", 1, 2);
+
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let _meta = server
+            .mock("GET", "/datasets/view/owner/new")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/new","totalBytes":2097152}"#)
+            .create();
+        let download = server
+            .mock("GET", "/datasets/download/owner/new")
+            .expect(0)
+            .create();
+
+        // 2 MB plus 4 MB of headroom can never fit under 5 MB: refused without evicting
+        std::env::set_var("GAGGLE_MIN_FREE_CACHE_MB", "4");
+        match download_dataset("owner/new") {
+            Err(GaggleError::CacheLimitExceeded { required_mb, .. }) => assert_eq!(required_mb, 6),
+            other => panic!("expected CacheLimitExceeded, got {:?}", other),
+        }
+        download.assert();
+        assert!(temp_dir.path().join("datasets/owner/old").exists());
+        assert!(temp_dir.path().join("datasets/owner/recent").exists());
+
+        // 2 MB plus 2 MB of headroom fits once the oldest dataset is evicted
+        std::env::set_var("GAGGLE_MIN_FREE_CACHE_MB", "2");
+        assert!(
+            reserve_cache_space("owner/new", &temp_dir.path().join("datasets/owner/new"), 5)
+                .is_ok()
+        );
+        assert!(pre_download_cache_check("owner/new").is_ok());
+        assert!(!temp_dir.path().join("datasets/owner/old").exists());
+        assert!(temp_dir.path().join("datasets/owner/recent").exists());
 
-        assert_eq!(deserialized.version, Some("5".to_string()));
-        assert_eq!(deserialized.dataset_path, "owner/dataset");
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_MIN_FREE_CACHE_MB");
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_CACHE_HARD_LIMIT");
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
     #[serial]
-    fn test_is_dataset_current_not_cached() {
+    fn test_hard_cache_limit_evicts_oldest_to_make_room() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
         std::env::set_var("KAGGLE_USERNAME", "test");
         std::env::set_var("KAGGLE_KEY", "test");
+        std::env::set_var("GAGGLE_CACHE_HARD_LIMIT", "true");
+        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "5");
+        super::super::metadata::clear_metadata_cache();
+        write_cached_dataset(temp_dir.path(), "owner/old", 2, 1);
+        write_cached_dataset(temp_dir.path(), "owner/recent", 2, 2);
 
-        let temp_dir = tempfile::TempDir::new().unwrap();
-        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
-
-        let result = is_dataset_current("owner/dataset");
-        // Should return false (not cached) or error (network issue)
-        match result {
-            Ok(false) => {} // Expected: not cached
-            Err(_) => {}    // Expected: network error
-            Ok(true) => panic!("Uncached dataset should not be current"),
+        let mut zip_bytes = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_bytes));
+            let options: zip::write::FileOptions<()> = zip::write::FileOptions::default();
+            zip.start_file("data.csv", options).unwrap();
+            zip.write_all(b"a,b\n1,2\n").unwrap();
+            zip.finish().unwrap();
         }
 
-        std::env::remove_var("GAGGLE_CACHE_DIR");
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let _meta = server
+            .mock("GET", "/datasets/view/owner/new")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/new","totalBytes":2097152,"currentVersionNumber":1}"#)
+            .create();
+        let download = server
+            .mock("GET", "/datasets/download/owner/new")
+            .with_status(200)
+            .with_body(zip_bytes)
+            .create();
+
+        let path = download_dataset("owner/new").unwrap();
+        download.assert();
+        assert!(path.join("data.csv").exists());
+        // Only the oldest dataset had to go to fit 2 MB under the 5 MB limit
+        assert!(!temp_dir.path().join("datasets/owner/old").exists());
+        assert!(temp_dir
+            .path()
+            .join("datasets/owner/recent/.downloaded")
+            .exists());
+
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_CACHE_HARD_LIMIT");
+        std::env::remove_var("GAGGLE_API_BASE");
         std::env::remove_var("KAGGLE_USERNAME");
         std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
     #[serial]
-    fn test_get_dataset_version_info_structure() {
+    fn test_download_aborted_when_dataset_too_large() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
         std::env::set_var("KAGGLE_USERNAME", "test");
         std::env::set_var("KAGGLE_KEY", "test");
-
-        let temp_dir = tempfile::TempDir::new().unwrap();
-        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
-
-        let result = get_dataset_version_info("owner/dataset");
-        // May fail due to network, but if it succeeds, check structure
-        if let Ok(info) = result {
-            assert!(info.get("cached_version").is_some());
-            assert!(info.get("latest_version").is_some());
-            assert!(info.get("is_current").is_some());
-            assert!(info.get("is_cached").is_some());
+        std::env::set_var("GAGGLE_MAX_DATASET_SIZE_MB", "10");
+        super::super::metadata::clear_metadata_cache();
+
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let meta = server
+            .mock("GET", "/datasets/view/owner/huge")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/huge","totalBytes":52428800}"#)
+            .create();
+        let download = server
+            .mock("GET", "/datasets/download/owner/huge")
+            .expect(0)
+            .create();
+
+        match download_dataset("owner/huge") {
+            Err(GaggleError::DatasetTooLarge {
+                size_mb, limit_mb, ..
+            }) => {
+                assert_eq!(size_mb, 50);
+                assert_eq!(limit_mb, 10);
+            }
+            other => panic!("expected DatasetTooLarge, got {:?}", other),
         }
+        meta.assert();
+        download.assert();
+        assert!(!temp_dir.path().join("datasets/owner/huge").exists());
 
-        std::env::remove_var("GAGGLE_CACHE_DIR");
+        super::super::metadata::clear_metadata_cache();
+        std::env::remove_var("GAGGLE_MAX_DATASET_SIZE_MB");
+        std::env::remove_var("GAGGLE_API_BASE");
         std::env::remove_var("KAGGLE_USERNAME");
         std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
     #[serial]
-    fn test_download_with_version_parsing() {
-        // Test that version syntax is properly parsed
+    fn test_size_check_proceeds_when_metadata_unavailable() {
         std::env::set_var("KAGGLE_USERNAME", "test");
         std::env::set_var("KAGGLE_KEY", "test");
+        std::env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+        super::super::metadata::clear_metadata_cache();
 
-        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let _m = server
+            .mock("GET", "/datasets/view/owner/unknown")
+            .with_status(500)
+            .create();
+
+        assert!(check_dataset_size("owner/unknown", 1).is_ok());
+
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+    }
+
+    /// Points dataset downloads at a mock server serving `body` with `content_type` as the
+    /// archive of `owner/staged`.
+    fn archive_server(body: &[u8], content_type: &str) -> (TempDir, mockito::ServerGuard) {
+        let temp_dir = TempDir::new().unwrap();
         std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        super::super::metadata::clear_metadata_cache();
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        server
+            .mock("GET", "/datasets/view/owner/staged")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/staged","currentVersionNumber":1}"#)
+            .create();
+        server
+            .mock("GET", "/datasets/download/owner/staged")
+            .with_status(200)
+            .with_header("content-type", content_type)
+            .with_body(body)
+            .create();
+        (temp_dir, server)
+    }
 
-        // Test path parsing (won't actually download without network)
-        let result = crate::kaggle::parse_dataset_path_with_version("owner/dataset@v2");
-        assert!(result.is_ok());
-        let (_owner, _dataset, version) = result.unwrap();
-        assert_eq!(version, Some("2".to_string()));
+    #[test]
+    #[serial]
+    fn test_legacy_marker_is_upgraded_in_place() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        super::super::metadata::clear_metadata_cache();
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let view = server
+            .mock("GET", "/datasets/view/owner/legacy")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/legacy","currentVersionNumber":4}"#)
+            .create();
+
+        let dir = temp_dir.path().join("datasets/owner/legacy");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("data.bin"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+        fs::write(dir.join(".downloaded"), "").unwrap();
+        let marker_mtime = fs::metadata(dir.join(".downloaded"))
+            .unwrap()
+            .modified()
+            .unwrap()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
 
+        // Offline, the legacy marker is kept and its version is unknown
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        let info = get_dataset_version_info("owner/legacy").unwrap();
+        assert_eq!(info["cached_version"], "unknown");
+        assert_eq!(info["is_current"], false);
+        match is_dataset_current("owner/legacy") {
+            Err(GaggleError::HttpRequestError(msg)) => {
+                assert!(msg.contains("version is unknown"), "{}", msg)
+            }
+            other => panic!("expected HttpRequestError, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(dir.join(".downloaded")).unwrap(), "");
+        std::env::remove_var("GAGGLE_OFFLINE");
+
+        assert!(is_dataset_current("owner/legacy").unwrap());
+        let metadata: CacheMetadata =
+            serde_json::from_str(&fs::read_to_string(dir.join(".downloaded")).unwrap()).unwrap();
+        assert_eq!(metadata.version.as_deref(), Some("4"));
+        assert_eq!(metadata.dataset_path, "owner/legacy");
+        assert_eq!(metadata.size_mb, 2);
+        assert_eq!(metadata.downloaded_at_secs, marker_mtime);
+
+        let info = get_dataset_version_info("owner/legacy").unwrap();
+        assert_eq!(info["cached_version"], "4");
+        assert_eq!(info["is_current"], true);
+        view.assert();
+
+        std::env::remove_var("GAGGLE_API_BASE");
         std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    fn staging_entries(cache: &Path) -> usize {
+        fs::read_dir(cache.join(".staging"))
+            .map(|entries| entries.count())
+            .unwrap_or(0)
+    }
+
+    #[test]
+    #[serial]
+    fn test_download_extracts_through_staging_dir() {
+        let mut zip_bytes = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_bytes));
+            let options: zip::write::FileOptions<()> = zip::write::FileOptions::default();
+            zip.start_file("data/new.csv", options).unwrap();
+            zip.write_all(b"a\n1\n").unwrap();
+            zip.finish().unwrap();
+        }
+        let (temp_dir, _server) = archive_server(&zip_bytes, "application/zip");
+        // A pin and the files of an earlier filtered download are already cached
+        let dir = temp_dir.path().join("datasets/owner/staged");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(PINNED_MARKER), b"").unwrap();
+        fs::write(dir.join(PARTIAL_MARKER), b"{}").unwrap();
+        fs::write(dir.join("old.csv"), b"x").unwrap();
+
+        let path = download_dataset("owner/staged").unwrap();
+        assert_eq!(path, dir);
+        assert_eq!(fs::read(dir.join("data/new.csv")).unwrap(), b"a\n1\n");
+        assert!(dir.join(".downloaded").exists());
+        assert!(dir.join(PINNED_MARKER).exists());
+        assert!(!dir.join(PARTIAL_MARKER).exists());
+        assert!(!dir.join("old.csv").exists());
+        assert_eq!(staging_entries(temp_dir.path()), 0);
+
+        std::env::remove_var("GAGGLE_API_BASE");
         std::env::remove_var("KAGGLE_USERNAME");
         std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
     #[serial]
-    fn test_versioned_cache_directory() {
-        // Verify that versioned downloads use different cache directories
+    fn test_failed_extraction_removes_staging_dir() {
+        let (temp_dir, _server) =
+            archive_server(b"PK\x03\x04 not really a zip archive", "application/zip");
 
-        let temp_dir = tempfile::TempDir::new().unwrap();
-        std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        assert!(download_dataset("owner/staged").is_err());
+        assert!(!temp_dir.path().join("datasets/owner/staged").exists());
+        assert_eq!(staging_entries(temp_dir.path()), 0);
 
-        // Simulate cache directory structure
-        let base = temp_dir.path().join("datasets").join("owner");
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 
-        // Latest version (no version specified)
-        let latest_cache = base.join("dataset");
+    #[test]
+    #[serial]
+    fn test_failed_update_keeps_cached_copy() {
+        let (temp_dir, mut server) =
+            archive_server(b"PK\x03\x04 not really a zip archive", "application/zip");
+        write_cached_dataset(temp_dir.path(), "owner/staged", 0, 1);
+        let dir = temp_dir.path().join("datasets/owner/staged");
+        fs::write(dir.join("old.csv"), b"x").unwrap();
+
+        assert!(update_dataset("owner/staged").is_err());
+        assert_eq!(fs::read(dir.join("old.csv")).unwrap(), b"x");
+        assert!(dir.join(".downloaded").exists());
+        assert!(!dir.join("dataset.zip").exists());
+        assert_eq!(staging_entries(temp_dir.path()), 0);
+
+        // A versioned path updates the cached copy of that version only
+        let mut zip_bytes = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_bytes));
+            let options: zip::write::FileOptions<()> = zip::write::FileOptions::default();
+            zip.start_file("new.csv", options).unwrap();
+            zip.write_all(b"a\n1\n").unwrap();
+            zip.finish().unwrap();
+        }
+        let download = server
+            .mock("GET", "/datasets/download/owner/staged/versions/3")
+            .with_status(200)
+            .with_header("content-type", "application/zip")
+            .with_body(zip_bytes)
+            .expect(1)
+            .create();
+        write_cached_dataset(temp_dir.path(), "owner/staged-v3", 0, 1);
+        let versioned = temp_dir.path().join("datasets/owner/staged-v3");
+
+        assert_eq!(update_dataset("owner/staged@v3").unwrap(), versioned);
+        assert_eq!(fs::read(versioned.join("new.csv")).unwrap(), b"a\n1\n");
+        assert!(!versioned.join("data.bin").exists());
+        assert_eq!(cached_version(&versioned).as_deref(), Some("3"));
+        assert_eq!(fs::read(dir.join("old.csv")).unwrap(), b"x");
+        download.assert();
+
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 
-        // Version 2
-        let v2_cache = base.join("dataset-v2");
+    #[test]
+    #[serial]
+    fn test_error_page_with_http_200_is_not_extracted() {
+        let download = |body: &[u8], content_type: &str| {
+            let (temp_dir, _server) = archive_server(body, content_type);
+            let err = download_dataset("owner/staged").unwrap_err();
+            // Nothing was written to the cache
+            assert!(!temp_dir.path().join("datasets/owner/staged").exists());
+            err
+        };
 
-        // Version 3
-        let v3_cache = base.join("dataset-v3");
+        let sign_in =
+            b"<!DOCTYPE html><html><body><a href=\"/account/login\">Sign In</a></body></html>";
+        let err = download(sign_in, "text/html; charset=utf-8");
+        assert!(matches!(err, GaggleError::CredentialsError(_)), "{:?}", err);
 
-        // Verify they're different paths
-        assert_ne!(latest_cache, v2_cache);
-        assert_ne!(latest_cache, v3_cache);
-        assert_ne!(v2_cache, v3_cache);
+        let json = br#"{"code":503,"message":"Service temporarily unavailable"}"#;
+        match download(json, "application/json") {
+            GaggleError::HttpRequestError(msg) => {
+                assert!(msg.contains("Service temporarily unavailable"))
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
 
+        match download(b"<html><body>Maintenance</body></html>", "text/html") {
+            GaggleError::ZipError(msg) => assert!(msg.contains("text/html"), "{}", msg),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(is_archive_head(b"PK\x05\x06"));
+        assert!(!is_archive_head(b"PK"));
+
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
         std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 
     #[test]
     #[serial]
-    fn test_partial_cache_counts_and_eviction() {
-        let temp_dir = tempfile::TempDir::new().unwrap();
+    fn test_cleanup_staging_dir_removes_only_stale_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let staging = temp_dir.path().join("staging");
+        std::env::set_var("GAGGLE_STAGING_DIR", &staging);
+        assert_eq!(cleanup_staging_dir().unwrap(), 0);
+
+        let stale = staging.join("1-0");
+        let fresh = staging.join("1-1");
+        fs::create_dir_all(stale.join("sub")).unwrap();
+        fs::create_dir_all(&fresh).unwrap();
+        let two_hours_ago = SystemTime::now() - Duration::from_secs(7200);
+        fs::File::open(&stale)
+            .unwrap()
+            .set_modified(two_hours_ago)
+            .unwrap();
+
+        assert_eq!(cleanup_staging_dir().unwrap(), 1);
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+
+        std::env::remove_var("GAGGLE_STAGING_DIR");
+    }
+
+    #[test]
+    #[serial]
+    fn test_dataset_paths_of_any_casing_share_the_cache() {
+        let temp_dir = TempDir::new().unwrap();
         std::env::set_var("GAGGLE_CACHE_DIR", temp_dir.path());
+        write_cached_dataset(temp_dir.path(), "owner/dataset", 0, 1);
+        metrics::reset_stats();
+
+        // No API base or credentials are set, so only the cache fast path can succeed
+        let dir = download_dataset("OWNER/DATASET").unwrap();
+        assert_eq!(dir, temp_dir.path().join("datasets/owner/dataset"));
+        assert_eq!(metrics::stats().cache_hits, 1);
+        assert_eq!(metrics::stats().cache_misses, 0);
+        assert_eq!(download_dataset("Owner/DataSet").unwrap(), dir);
+        assert_eq!(
+            dataset_lock_key("Owner", "DataSet-v2"),
+            dataset_lock_key("owner", "dataset-v2")
+        );
 
-        // Create two partial cached datasets
-        let d1 = temp_dir.path().join("datasets/owner1/ds1");
-        let d2 = temp_dir.path().join("datasets/owner2/ds2");
-        fs::create_dir_all(&d1).unwrap();
-        fs::create_dir_all(&d2).unwrap();
-        fs::write(d1.join("a.bin"), vec![0u8; 2 * 1024 * 1024]).unwrap(); // 2MB
-        fs::write(d2.join("b.bin"), vec![0u8; 2 * 1024 * 1024]).unwrap(); // 2MB
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
 
-        // Total ~4MB; set limit to 2MB so eviction must occur
-        std::env::set_var("GAGGLE_CACHE_SIZE_LIMIT", "2");
-        enforce_cache_limit_now().unwrap();
+    #[test]
+    #[serial]
+    fn test_migrate_cache_case_moves_or_reports_mixed_case_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = temp_dir.path();
+        std::env::set_var("GAGGLE_CACHE_DIR", cache);
+        assert_eq!(
+            migrate_cache_case().unwrap(),
+            CaseMigrationReport::default()
+        );
 
-        // After eviction, the total size must be <= 2MB
-        let total = get_total_cache_size_mb().unwrap();
-        assert!(total <= 2);
+        let datasets = cache.join("datasets");
+        fs::create_dir_all(datasets.join("Owner/Data-v2")).unwrap();
+        fs::write(datasets.join("Owner/Data-v2/a.csv"), "a").unwrap();
+        fs::create_dir_all(datasets.join("other/Dup")).unwrap();
+        fs::create_dir_all(datasets.join("other/dup")).unwrap();
+        fs::create_dir_all(datasets.join("other/plain")).unwrap();
+
+        let report = migrate_cache_case().unwrap();
+        if datasets.join("other/dup").exists() && datasets.join("other/Dup").exists() {
+            assert_eq!(report.moved, vec!["Owner/Data-v2".to_string()]);
+            assert_eq!(report.conflicts, vec!["other/Dup".to_string()]);
+        }
+        assert_eq!(
+            fs::read_to_string(datasets.join("owner/data-v2/a.csv")).unwrap(),
+            "a"
+        );
+        assert!(datasets.join("other/plain").is_dir());
+        assert!(fs::read_dir(&datasets).unwrap().all(|e| !e
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with('.')));
 
-        std::env::remove_var("GAGGLE_CACHE_SIZE_LIMIT");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    /// Relative paths and contents of the files under `dir`
+    fn tree_contents(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(d) = pending.pop() {
+            for entry in fs::read_dir(d).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    let rel = path.strip_prefix(dir).unwrap().to_path_buf();
+                    files.push((rel, fs::read(&path).unwrap()));
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    #[serial]
+    fn test_move_cache_dir_keeps_contents_and_records_location() {
+        let temp_dir = TempDir::new().unwrap();
+        let old_cache = temp_dir.path().join("old");
+        let new_cache = temp_dir.path().join("disk2").join("gaggle");
+        let override_file = temp_dir.path().join("config").join("cache_override");
+        std::env::set_var("GAGGLE_CACHE_DIR", &old_cache);
+        std::env::set_var("GAGGLE_CACHE_OVERRIDE_FILE", &override_file);
+
+        write_cached_dataset(&old_cache, "owner/moved", 1, 1_700_000_000);
+        fs::create_dir_all(old_cache.join("metadata")).unwrap();
+        fs::write(old_cache.join("metadata").join("owner__moved.json"), "{}").unwrap();
+        let before = tree_contents(&old_cache);
+
+        // A directory inside the cache is rejected
+        assert!(move_cache_dir(&old_cache.join("nested")).is_err());
+        assert!(!override_file.exists());
+
+        move_cache_dir(&new_cache).unwrap();
+        assert!(!old_cache.exists());
+        assert_eq!(tree_contents(&new_cache), before);
+        assert_eq!(
+            fs::read_to_string(&override_file).unwrap(),
+            new_cache.to_str().unwrap()
+        );
+        // The recorded location takes precedence over GAGGLE_CACHE_DIR
+        assert_eq!(crate::config::cache_dir_runtime(), new_cache);
+
+        // A directory that is not empty is rejected
+        let occupied = temp_dir.path().join("occupied");
+        fs::create_dir_all(&occupied).unwrap();
+        fs::write(occupied.join("file"), "x").unwrap();
+        assert!(move_cache_dir(&occupied).is_err());
+        assert_eq!(crate::config::cache_dir_runtime(), new_cache);
+
+        crate::config::set_cache_dir_override(None);
+        assert_eq!(crate::config::cache_dir_runtime(), old_cache);
+        std::env::remove_var("GAGGLE_CACHE_OVERRIDE_FILE");
         std::env::remove_var("GAGGLE_CACHE_DIR");
     }
 }
@@ -0,0 +1,173 @@
+// health.rs
+//
+// This module provides a health check that helps users diagnose setup problems.
+// It reports whether Kaggle credentials are configured, whether the Kaggle API can
+// be reached (and how long a minimal request takes), and whether the cache
+// directory is writable. Every check runs independently, so a single failure does
+// not hide the results of the others.
+
+use crate::error::GaggleError;
+
+use super::api::{get_api_base, get_client, send};
+use super::credentials::{get_credentials, kaggle_json_path};
+use serde_json::json;
+use std::fs;
+use std::time::Instant;
+
+/// Runs the health checks and returns their results as a JSON object.
+///
+/// The object has the keys `credentials_present`, `credentials_file`, `api_reachable`,
+/// `api_latency_ms`, `api_status`, `api_error`, `cache_writable`, and `cache_error`. In offline mode the
+/// API is not contacted, and the API keys are `null`.
+pub fn health_check() -> Result<serde_json::Value, GaggleError> {
+    let creds = get_credentials().ok();
+
+    let (api_reachable, api_latency_ms, api_status, api_error) = if crate::config::offline_mode() {
+        (None, None, None, None)
+    } else {
+        let url = format!(
+            "{}/datasets/list?search=health&page=1&pageSize=1",
+            get_api_base()
+        );
+        let started = Instant::now();
+        // A single attempt, without retries, so that the check stays fast
+        let result = get_client().and_then(|client| {
+            let request = client.get(&url);
+            let request = match &creds {
+                Some(c) => request.basic_auth(&c.username, Some(c.key_str())),
+                None => request,
+            };
+            send(request)
+        });
+        let latency_ms = started.elapsed().as_millis() as u64;
+        match result {
+            Ok(response) => (
+                Some(true),
+                Some(latency_ms),
+                Some(response.status().as_u16()),
+                None,
+            ),
+            Err(e) => (Some(false), Some(latency_ms), None, Some(e.to_string())),
+        }
+    };
+
+    let cache_error = check_cache_writable().err().map(|e| e.to_string());
+
+    Ok(json!({
+        "credentials_present": creds.is_some(),
+        "credentials_file": kaggle_json_path().display().to_string(),
+        "api_reachable": api_reachable,
+        "api_latency_ms": api_latency_ms,
+        "api_status": api_status,
+        "api_error": api_error,
+        "cache_writable": cache_error.is_none(),
+        "cache_error": cache_error,
+    }))
+}
+
+/// Writes and deletes a temporary file in the cache directory.
+fn check_cache_writable() -> Result<(), GaggleError> {
+    let cache_dir = crate::config::cache_dir_runtime();
+    fs::create_dir_all(&cache_dir)?;
+    let probe = cache_dir.join(format!(".gaggle_health_{}", std::process::id()));
+    fs::write(&probe, b"ok")?;
+    fs::remove_file(&probe)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    fn set_test_env(cache_dir: &std::path::Path) {
+        std::env::set_var("GAGGLE_CACHE_DIR", cache_dir);
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+    }
+
+    fn clear_test_env() {
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial]
+    fn test_health_check_all_ok() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        set_test_env(temp_dir.path());
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let list = server
+            .mock("GET", "/datasets/list")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("search".into(), "health".into()),
+                mockito::Matcher::UrlEncoded("pageSize".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body("[]")
+            .create();
+
+        let report = health_check().unwrap();
+        list.assert();
+        assert_eq!(report["credentials_present"], true);
+        assert!(report["credentials_file"]
+            .as_str()
+            .is_some_and(|p| p.ends_with("kaggle.json")));
+        assert_eq!(report["api_reachable"], true);
+        assert_eq!(report["api_status"], 200);
+        assert!(report["api_latency_ms"].is_u64());
+        assert!(report["api_error"].is_null());
+        assert_eq!(report["cache_writable"], true);
+        // The probe file is removed again
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+        clear_test_env();
+    }
+
+    #[test]
+    #[serial]
+    fn test_health_check_reports_failures() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // A regular file cannot be used as the cache directory
+        let not_a_dir = temp_dir.path().join("file");
+        fs::write(&not_a_dir, b"x").unwrap();
+        set_test_env(&not_a_dir);
+        // Nothing listens on port 1
+        std::env::set_var("GAGGLE_API_BASE", "http://127.0.0.1:1");
+        std::env::set_var("GAGGLE_HTTP_TIMEOUT", "2");
+
+        let report = health_check().unwrap();
+        assert_eq!(report["api_reachable"], false);
+        assert!(report["api_status"].is_null());
+        assert!(report["api_error"].is_string());
+        assert_eq!(report["cache_writable"], false);
+        assert!(report["cache_error"].is_string());
+
+        std::env::remove_var("GAGGLE_HTTP_TIMEOUT");
+        clear_test_env();
+    }
+
+    #[test]
+    #[serial]
+    fn test_health_check_offline_skips_api() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        set_test_env(temp_dir.path());
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        let list = server.mock("GET", mockito::Matcher::Any).expect(0).create();
+
+        let report = health_check().unwrap();
+        list.assert();
+        assert!(report["api_reachable"].is_null());
+        assert!(report["api_latency_ms"].is_null());
+        assert!(report["api_status"].is_null());
+        assert_eq!(report["cache_writable"], true);
+
+        clear_test_env();
+    }
+}
@@ -9,8 +9,9 @@
 use crate::error::GaggleError;
 use serde::{Deserialize, Serialize};
 
-use super::api::{build_client, get_api_base, with_retries};
+use super::api::{get_api_base, get_client, read_json_response, send, with_retries};
 use super::credentials::get_credentials;
+use super::download::DatasetFile;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
@@ -31,12 +32,175 @@ pub struct DatasetInfo {
     pub last_updated: String,
 }
 
-/// Simple in-memory cache for dataset metadata with TTL
-static META_CACHE: once_cell::sync::Lazy<RwLock<HashMap<String, (serde_json::Value, Instant)>>> =
+/// Normalized metadata of a Kaggle dataset.
+///
+/// The Kaggle API is inconsistent about field names and types (for example, version numbers
+/// may be numbers or strings), so this struct is deserialized leniently from the raw payload.
+/// Missing or malformed fields are left empty rather than causing an error. The raw payload
+/// is kept in `raw` for fields that are not normalized.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DatasetMetadata {
+    /// The reference path of the dataset, in the format `owner/dataset`.
+    #[serde(rename = "ref")]
+    pub ref_path: Option<String>,
+    /// The title of the dataset.
+    pub title: Option<String>,
+    /// The subtitle of the dataset.
+    pub subtitle: Option<String>,
+    /// The URL of the dataset.
+    pub url: Option<String>,
+    /// The current version number of the dataset.
+    pub current_version: Option<String>,
+    /// The total size of the dataset in bytes.
+    pub total_bytes: Option<u64>,
+    /// The number of files in the dataset.
+    pub file_count: usize,
+    /// The license name of the dataset.
+    pub license: Option<String>,
+    /// The date the dataset was last updated.
+    pub last_updated: Option<String>,
+    /// The number of times the dataset was downloaded.
+    pub download_count: Option<u64>,
+    /// The files in the dataset.
+    pub files: Vec<DatasetFile>,
+    /// The tags attached to the dataset.
+    pub tags: Vec<String>,
+    /// The raw API payload.
+    #[serde(skip)]
+    #[allow(dead_code)]
+    pub raw: serde_json::Value,
+}
+
+impl DatasetMetadata {
+    /// Builds normalized metadata from a raw Kaggle API payload.
+    pub fn from_value(raw: serde_json::Value) -> Self {
+        let files: Vec<DatasetFile> = raw
+            .get("files")
+            .and_then(|v| v.as_array())
+            .map(|files| {
+                files
+                    .iter()
+                    .filter_map(|f| {
+                        let name = f.get("name").and_then(|n| n.as_str())?;
+                        // support size keys in different schemas
+                        let size = lenient_u64(f.get("totalBytes"))
+                            .or_else(|| lenient_u64(f.get("size")))
+                            .unwrap_or(0);
+                        // Kaggle lists the columns of tabular files
+                        let columns = match f.get("columns") {
+                            Some(serde_json::Value::Array(cols)) => u32::try_from(cols.len()).ok(),
+                            other => lenient_u64(other).and_then(|n| u32::try_from(n).ok()),
+                        };
+                        Some(DatasetFile {
+                            name: name.to_string(),
+                            size,
+                            modified: lenient_string(f.get("creationDate")),
+                            columns,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let current_version = lenient_string(raw.get("currentVersionNumber")).or_else(|| {
+            raw.get("versions")
+                .and_then(|v| v.as_array())
+                .and_then(|arr| arr.first())
+                .and_then(|latest| lenient_string(latest.get("versionNumber")))
+        });
+
+        let license = lenient_string(raw.get("licenseName")).or_else(|| {
+            raw.get("licenses")
+                .and_then(|v| v.as_array())
+                .and_then(|arr| arr.first())
+                .and_then(|l| lenient_string(l.get("name")))
+        });
+
+        let file_count = lenient_u64(raw.get("fileCount"))
+            .map(|c| c as usize)
+            .unwrap_or(files.len());
+
+        Self {
+            ref_path: lenient_string(raw.get("ref")),
+            title: lenient_string(raw.get("title")),
+            subtitle: lenient_string(raw.get("subtitle")),
+            url: lenient_string(raw.get("url")),
+            current_version,
+            total_bytes: lenient_u64(raw.get("totalBytes")),
+            file_count,
+            license,
+            last_updated: lenient_string(raw.get("lastUpdated")),
+            download_count: lenient_u64(raw.get("downloadCount")),
+            files,
+            tags: extract_tags(&raw),
+            raw,
+        }
+    }
+}
+
+/// Extracts tag names from a raw metadata payload.
+///
+/// Each element of the `tags` array may be a plain string or an object with a `name`
+/// or `ref` field. Duplicates are removed while keeping the original order.
+fn extract_tags(raw: &serde_json::Value) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let items = raw.get("tags").and_then(|v| v.as_array());
+    for item in items.into_iter().flatten() {
+        let name = match item {
+            serde_json::Value::String(s) => Some(s.trim().to_string()),
+            _ => lenient_string(item.get("name"))
+                .or_else(|| lenient_string(item.get("ref")))
+                .map(|s| s.trim().to_string()),
+        };
+        if let Some(name) = name.filter(|n| !n.is_empty()) {
+            if !tags.contains(&name) {
+                tags.push(name);
+            }
+        }
+    }
+    tags
+}
+
+impl<'de> Deserialize<'de> for DatasetMetadata {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serde_json::Value::deserialize(deserializer).map(DatasetMetadata::from_value)
+    }
+}
+
+/// Reads a string field that may also be encoded as a number.
+fn lenient_string(v: Option<&serde_json::Value>) -> Option<String> {
+    match v? {
+        serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Reads an unsigned integer field that may also be encoded as a string.
+fn lenient_u64(v: Option<&serde_json::Value>) -> Option<u64> {
+    match v? {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// A cached metadata response along with when it was fetched and last read.
+struct MetaEntry {
+    value: serde_json::Value,
+    fetched_at: Instant,
+    last_used: Instant,
+}
+
+/// Simple in-memory LRU cache for dataset metadata with TTL
+static META_CACHE: once_cell::sync::Lazy<RwLock<HashMap<String, MetaEntry>>> =
     once_cell::sync::Lazy::new(|| RwLock::new(HashMap::new()));
 
 /// Metadata cache TTL (seconds), configurable via GAGGLE_METADATA_TTL (default 600s)
-fn metadata_ttl() -> Duration {
+pub(crate) fn metadata_ttl() -> Duration {
     let secs = std::env::var("GAGGLE_METADATA_TTL")
         .ok()
         .and_then(|v| v.parse::<u64>().ok())
@@ -44,9 +208,228 @@ fn metadata_ttl() -> Duration {
     Duration::from_secs(secs)
 }
 
+/// Maximum number of metadata cache entries, configurable via GAGGLE_METADATA_CACHE_MAX (default 256)
+fn metadata_cache_max() -> usize {
+    std::env::var("GAGGLE_METADATA_CACHE_MAX")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(256)
+}
+
+/// Returns a fresh cached metadata entry and marks it as recently used.
+fn cached_metadata(dataset_path: &str) -> Option<serde_json::Value> {
+    let mut cache = META_CACHE.write();
+    let entry = cache.get_mut(dataset_path)?;
+    if entry.fetched_at.elapsed() >= metadata_ttl() {
+        return None;
+    }
+    entry.last_used = Instant::now();
+    Some(entry.value.clone())
+}
+
+/// Stores a metadata entry, evicting the least recently used entries when full.
+fn store_metadata(dataset_path: &str, value: serde_json::Value) {
+    let max = metadata_cache_max();
+    let mut cache = META_CACHE.write();
+    cache.remove(dataset_path);
+    while cache.len() >= max {
+        let lru = cache
+            .iter()
+            .min_by_key(|(_, e)| e.last_used)
+            .map(|(k, _)| k.clone());
+        match lru {
+            Some(k) => {
+                cache.remove(&k);
+            }
+            None => break,
+        }
+    }
+    let now = Instant::now();
+    cache.insert(
+        dataset_path.to_string(),
+        MetaEntry {
+            value,
+            fetched_at: now,
+            last_used: now,
+        },
+    );
+}
+
+/// Removes the cached metadata for a single dataset, if present.
+pub fn invalidate_metadata(dataset_path: &str) {
+    META_CACHE.write().remove(dataset_path);
+}
+
+/// Removes all cached metadata entries.
+pub fn clear_metadata_cache() {
+    META_CACHE.write().clear();
+    NOT_FOUND_CACHE.write().clear();
+}
+
+/// Directory under the cache root where fetched metadata is kept for offline use.
+const PERSISTED_METADATA_DIR: &str = "metadata";
+
+fn persisted_metadata_path(owner: &str, dataset: &str) -> std::path::PathBuf {
+    crate::config::cache_dir_runtime()
+        .join(PERSISTED_METADATA_DIR)
+        .join(owner.to_lowercase())
+        .join(format!("{}.json", dataset.to_lowercase()))
+}
+
+/// Saves a metadata response to disk, so offline mode can still list the dataset's files.
+/// Failures are only logged, since the response itself was fetched successfully.
+fn persist_metadata(owner: &str, dataset: &str, value: &serde_json::Value) {
+    let path = persisted_metadata_path(owner, dataset);
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| crate::utils::write_atomic(&path, value.to_string().as_bytes()));
+    if let Err(e) = result {
+        tracing::debug!(path = %path.display(), error = %e, "failed to persist dataset metadata");
+    }
+}
+
+/// Reads the metadata persisted by the last successful fetch of a dataset, if any.
+pub(crate) fn load_persisted_metadata(dataset_path: &str) -> Option<serde_json::Value> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path).ok()?;
+    let content = std::fs::read_to_string(persisted_metadata_path(&owner, &dataset)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Lists the persisted metadata of every dataset as `(owner/dataset, metadata)` pairs.
+pub(crate) fn list_persisted_metadata() -> Vec<(String, serde_json::Value)> {
+    let root = crate::config::cache_dir_runtime().join(PERSISTED_METADATA_DIR);
+    let mut out = Vec::new();
+    let Ok(owners) = std::fs::read_dir(&root) else {
+        return out;
+    };
+    for owner in owners.flatten() {
+        let Ok(files) = std::fs::read_dir(owner.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            let Some(dataset) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".json"))
+            else {
+                continue;
+            };
+            let value = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok());
+            if let Some(value) = value {
+                let dataset_path = format!("{}/{}", owner.file_name().to_string_lossy(), dataset);
+                out.push((dataset_path, value));
+            }
+        }
+    }
+    out
+}
+
 /// Retrieves the metadata for a specific dataset.
 pub fn get_dataset_metadata(dataset_path: &str) -> Result<serde_json::Value, GaggleError> {
-    if crate::config::offline_mode() {
+    super::api::logged_operation("get_dataset_metadata", dataset_path, || {
+        load_dataset_metadata(dataset_path)
+    })
+}
+
+/// Serves the metadata of a dataset from the cache or fetches it, for [`get_dataset_metadata`].
+fn load_dataset_metadata(dataset_path: &str) -> Result<serde_json::Value, GaggleError> {
+    let request = match prepare_metadata_request(dataset_path)? {
+        MetadataRequest::Cached(json) => return Ok(json),
+        MetadataRequest::Fetch(request) => request,
+    };
+
+    let client = get_client()?;
+    let response = with_retries(|| {
+        send(
+            client
+                .get(&request.url)
+                .basic_auth(&request.creds.username, Some(request.creds.key_str())),
+        )
+    })?;
+    request.check_status(response.status())?;
+
+    let json = read_json_response(response, crate::config::max_response_bytes())?;
+    request.store(&json);
+    Ok(json)
+}
+
+/// Retrieves the metadata for a specific dataset without blocking the calling thread.
+///
+/// This is the async counterpart of [`get_dataset_metadata`], sharing its cache.
+#[cfg(feature = "async")]
+pub async fn get_dataset_metadata_async(
+    dataset_path: &str,
+) -> Result<serde_json::Value, GaggleError> {
+    let request = match prepare_metadata_request(dataset_path)? {
+        MetadataRequest::Cached(json) => return Ok(json),
+        MetadataRequest::Fetch(request) => request,
+    };
+
+    let client = super::api::get_async_client()?;
+    let response = super::api::with_retries_async(|| {
+        super::api::send_async(
+            client
+                .get(&request.url)
+                .basic_auth(&request.creds.username, Some(request.creds.key_str())),
+        )
+    })
+    .await?;
+    request.check_status(response.status())?;
+
+    let json =
+        super::api::read_json_response_async(response, crate::config::max_response_bytes()).await?;
+    request.store(&json);
+    Ok(json)
+}
+
+/// A metadata lookup, either served from the cache or to be fetched.
+enum MetadataRequest {
+    Cached(serde_json::Value),
+    Fetch(MetadataFetch),
+}
+
+/// The details needed to fetch and cache the metadata of one dataset.
+struct MetadataFetch {
+    dataset_path: String,
+    owner: String,
+    dataset: String,
+    url: String,
+    creds: super::credentials::KaggleCredentials,
+}
+
+impl MetadataFetch {
+    /// Turns an unsuccessful status into an error, with a 404 meaning the dataset does not
+    /// exist.
+    fn check_status(&self, status: reqwest::StatusCode) -> Result<(), GaggleError> {
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(dataset_not_found(&self.dataset_path, &self.dataset));
+        }
+        if !status.is_success() {
+            return Err(GaggleError::HttpRequestError(format!(
+                "Failed to get dataset metadata: HTTP {}",
+                status
+            )));
+        }
+        Ok(())
+    }
+
+    /// Stores fetched metadata in the in-memory cache and on disk.
+    fn store(&self, json: &serde_json::Value) {
+        store_metadata(&self.dataset_path, json.clone());
+        persist_metadata(&self.owner, &self.dataset, json);
+    }
+}
+
+/// Checks offline mode and the cache, then returns either the cached metadata or the
+/// request to send.
+fn prepare_metadata_request(dataset_path: &str) -> Result<MetadataRequest, GaggleError> {
+    // Offline mode still allows recorded responses to be played back
+    if crate::config::offline_mode() && !super::api::http_playback_enabled() {
         return Err(GaggleError::HttpRequestError(
             format!(
                 "Offline mode enabled; metadata fetch for '{}' is disabled. Unset GAGGLE_OFFLINE to enable network.",
@@ -56,41 +439,113 @@ pub fn get_dataset_metadata(dataset_path: &str) -> Result<serde_json::Value, Gag
     }
 
     // Serve from cache when fresh
-    if let Some((val, ts)) = META_CACHE.read().get(dataset_path).cloned() {
-        if ts.elapsed() < metadata_ttl() {
-            return Ok(val);
-        }
+    if let Some(val) = cached_metadata(dataset_path) {
+        return Ok(MetadataRequest::Cached(val));
     }
 
     let creds = get_credentials()?;
     let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let url = format!(
+        "{}/datasets/view/{}",
+        get_api_base(),
+        super::encode_dataset_segments(&owner, &dataset)
+    );
 
-    let url = format!("{}/datasets/view/{}/{}", get_api_base(), owner, dataset);
+    Ok(MetadataRequest::Fetch(MetadataFetch {
+        dataset_path: dataset_path.to_string(),
+        owner,
+        dataset,
+        url,
+        creds,
+    }))
+}
 
-    let client = build_client()?;
-    let response = with_retries(|| {
-        client
-            .get(&url)
-            .basic_auth(&creds.username, Some(&creds.key))
-            .send()
-            .map_err(|e| GaggleError::HttpRequestError(e.to_string()))
-    })?;
+fn dataset_not_found(dataset_path: &str, dataset: &str) -> GaggleError {
+    GaggleError::DatasetNotFound(format!(
+        "'{}' does not exist on Kaggle. Check the owner/dataset path, or search for it at https://www.kaggle.com/datasets?search={}",
+        dataset_path, dataset
+    ))
+}
 
-    if !response.status().is_success() {
-        return Err(GaggleError::HttpRequestError(format!(
-            "Failed to get dataset metadata: HTTP {}",
-            response.status()
-        )));
+/// How long a dataset that was not found is remembered by [`ensure_dataset_exists`].
+const NOT_FOUND_TTL: Duration = Duration::from_secs(60);
+
+/// Datasets whose metadata lookup returned 404, and when.
+static NOT_FOUND_CACHE: once_cell::sync::Lazy<RwLock<HashMap<String, Instant>>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Checks that a dataset exists before its archive is downloaded.
+///
+/// A mistyped path would otherwise only fail after the download endpoint is retried, with
+/// a generic HTTP error. This looks up the metadata once and returns `DatasetNotFound` on a
+/// 404. Missing datasets are remembered for a minute, so repeated calls from a query do not
+/// repeat the lookup. Any other lookup failure lets the download proceed. Skipped when
+/// GAGGLE_SKIP_EXISTENCE_CHECK is enabled.
+pub(crate) fn ensure_dataset_exists(dataset_path: &str) -> Result<(), GaggleError> {
+    if crate::config::skip_existence_check() || cached_metadata(dataset_path).is_some() {
+        return Ok(());
     }
+    let (_, dataset) = super::parse_dataset_path(dataset_path)?;
 
-    let json: serde_json::Value = response.json()?;
+    let known_missing = NOT_FOUND_CACHE
+        .read()
+        .get(dataset_path)
+        .is_some_and(|at| at.elapsed() < NOT_FOUND_TTL);
+    if known_missing {
+        return Err(dataset_not_found(dataset_path, &dataset));
+    }
 
-    // Store in cache
-    META_CACHE
-        .write()
-        .insert(dataset_path.to_string(), (json.clone(), Instant::now()));
+    match get_dataset_metadata(dataset_path) {
+        Err(e @ GaggleError::DatasetNotFound(_)) => {
+            let mut cache = NOT_FOUND_CACHE.write();
+            cache.retain(|_, at| at.elapsed() < NOT_FOUND_TTL);
+            cache.insert(dataset_path.to_string(), Instant::now());
+            Err(e)
+        }
+        Err(e) => {
+            tracing::debug!(dataset = dataset_path, error = %e, "existence check failed; proceeding with download");
+            Ok(())
+        }
+        Ok(_) => {
+            NOT_FOUND_CACHE.write().remove(dataset_path);
+            Ok(())
+        }
+    }
+}
 
-    Ok(json)
+/// Retrieves the normalized metadata for a specific dataset.
+pub fn get_dataset_metadata_typed(dataset_path: &str) -> Result<DatasetMetadata, GaggleError> {
+    get_dataset_metadata(dataset_path).map(DatasetMetadata::from_value)
+}
+
+/// Retrieves the tag names of a dataset.
+pub fn get_dataset_tags(dataset_path: &str) -> Result<Vec<String>, GaggleError> {
+    get_dataset_metadata_typed(dataset_path).map(|m| m.tags)
+}
+
+/// Returns how often each tag appears across the metadata currently held in the cache.
+///
+/// The Kaggle API has no endpoint for listing tags, so this only reflects datasets
+/// whose metadata was fetched in this session.
+///
+/// # Returns
+///
+/// A JSON array of `{"name": ..., "count": ...}` objects, most frequent first.
+pub fn list_popular_tags() -> Result<serde_json::Value, GaggleError> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for entry in META_CACHE.read().values() {
+        for tag in extract_tags(&entry.value) {
+            *counts.entry(tag).or_insert(0) += 1;
+        }
+    }
+    let mut counts: Vec<(String, u64)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(serde_json::Value::Array(
+        counts
+            .into_iter()
+            .map(|(name, count)| serde_json::json!({"name": name, "count": count}))
+            .collect(),
+    ))
 }
 
 /// Retrieves the current version number of a dataset from the Kaggle API.
@@ -98,10 +553,7 @@ pub fn get_current_version(dataset_path: &str) -> Result<String, GaggleError> {
     if crate::config::offline_mode() {
         // In offline mode, try to use cached marker file version if available
         let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
-        let cache_dir = crate::config::cache_dir_runtime()
-            .join("datasets")
-            .join(&owner)
-            .join(&dataset);
+        let cache_dir = super::download::dataset_cache_dir(&owner, &dataset);
         let marker = cache_dir.join(".downloaded");
         if let Ok(content) = std::fs::read_to_string(&marker) {
             if !content.is_empty() {
@@ -116,33 +568,307 @@ pub fn get_current_version(dataset_path: &str) -> Result<String, GaggleError> {
         return Ok("unknown".to_string());
     }
 
-    let metadata = get_dataset_metadata(dataset_path)?;
+    let metadata = get_dataset_metadata_typed(dataset_path)?;
+
+    // Default to "1" if version info not available
+    Ok(metadata.current_version.unwrap_or_else(|| "1".to_string()))
+}
+
+/// Name of the file that caches the README inside a dataset directory.
+pub(crate) const README_FILE: &str = ".readme.md";
+
+/// Returns the documentation of a dataset as Markdown.
+///
+/// The README combines the `description` from the dataset metadata with the `README.md`
+/// shipped in the cached dataset, if any. For cached datasets, the result is saved to
+/// `.readme.md` in the dataset directory and reused while it is younger than
+/// `GAGGLE_METADATA_TTL`.
+///
+/// # Returns
+///
+/// The Markdown text, which is empty if the dataset has no documentation.
+pub fn get_dataset_readme(dataset_path: &str) -> Result<String, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let dataset_dir = super::download::dataset_cache_dir(&owner, &dataset);
+
+    let saved_path = dataset_dir.join(README_FILE);
+    if is_fresh(&saved_path) {
+        if let Ok(content) = std::fs::read_to_string(&saved_path) {
+            return Ok(content);
+        }
+    }
+
+    let shipped = std::fs::read_to_string(dataset_dir.join("README.md")).ok();
+    let description = match get_dataset_metadata(dataset_path) {
+        Ok(raw) => lenient_string(raw.get("description")),
+        // The shipped README is still worth returning when the API cannot be reached
+        Err(e) if shipped.is_none() => return Err(e),
+        Err(_) => None,
+    };
+
+    let readme = [description, shipped]
+        .into_iter()
+        .flatten()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    // Only cached datasets get a saved copy; creating the directory would make an empty
+    // dataset appear in the cache
+    if dataset_dir.is_dir() {
+        crate::utils::write_marker_atomic(&saved_path, &readme)?;
+    }
+    Ok(readme)
+}
+
+/// Whether a file saved in a dataset directory is younger than `GAGGLE_METADATA_TTL`.
+fn is_fresh(path: &std::path::Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < metadata_ttl())
+}
+
+/// Name of the file that caches the license inside a dataset directory.
+pub(crate) const LICENSE_FILE: &str = ".license.json";
+
+/// Returns the license information of a dataset.
+///
+/// The result holds `licenseName`, `licenseShortName`, `licenseUrl`, `ownerName`,
+/// `ownerRef`, and `usabilityRating` from the dataset metadata, with `null` for fields the
+/// API does not report. For cached datasets, it is saved to `.license.json` in the dataset
+/// directory and reused while it is younger than `GAGGLE_METADATA_TTL`. In offline mode, a
+/// saved copy of any age is used (see [`get_license_from_cache`]).
+pub fn get_dataset_license(dataset_path: &str) -> Result<serde_json::Value, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let dataset_dir = super::download::dataset_cache_dir(&owner, &dataset);
 
-    // Try to extract version from metadata
-    // Kaggle API returns version in various fields depending on endpoint
-    if let Some(version) = metadata.get("currentVersionNumber") {
-        if let Some(v) = version.as_i64() {
-            return Ok(v.to_string());
+    let saved_path = dataset_dir.join(LICENSE_FILE);
+    if is_fresh(&saved_path) {
+        if let Some(license) = read_saved_license(&saved_path) {
+            return Ok(license);
         }
-        if let Some(v) = version.as_str() {
-            return Ok(v.to_string());
+    }
+    if crate::config::offline_mode() {
+        if let Some(license) = get_license_from_cache(dataset_path) {
+            return Ok(license);
         }
     }
 
-    if let Some(version) = metadata.get("versions") {
-        if let Some(arr) = version.as_array() {
-            if let Some(latest) = arr.first() {
-                if let Some(v) = latest.get("versionNumber") {
-                    if let Some(num) = v.as_i64() {
-                        return Ok(num.to_string());
+    let license = license_from_metadata(&get_dataset_metadata(dataset_path)?);
+    // Like the README, only cached datasets get a saved copy
+    if dataset_dir.is_dir() {
+        crate::utils::write_marker_atomic(&saved_path, &serde_json::to_string(&license)?)?;
+    }
+    Ok(license)
+}
+
+/// Returns the license information of a dataset without making any requests.
+///
+/// The copy saved in `.license.json` is used regardless of its age. Without one, the
+/// license is taken from the metadata persisted by an earlier fetch, if any.
+pub fn get_license_from_cache(dataset_path: &str) -> Option<serde_json::Value> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path).ok()?;
+    let saved_path = super::download::dataset_cache_dir(&owner, &dataset).join(LICENSE_FILE);
+    read_saved_license(&saved_path)
+        .or_else(|| load_persisted_metadata(dataset_path).map(|raw| license_from_metadata(&raw)))
+}
+
+fn read_saved_license(path: &std::path::Path) -> Option<serde_json::Value> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// Extracts the license fields from a raw metadata payload.
+fn license_from_metadata(raw: &serde_json::Value) -> serde_json::Value {
+    let first_license = raw
+        .get("licenses")
+        .and_then(|v| v.as_array())
+        .and_then(|arr| arr.first());
+    let license_name = lenient_string(raw.get("licenseName"))
+        .or_else(|| first_license.and_then(|l| lenient_string(l.get("name"))));
+    let usability_rating = raw.get("usabilityRating").and_then(|v| {
+        v.as_f64()
+            .or_else(|| v.as_str().and_then(|s| s.trim().parse().ok()))
+    });
+    serde_json::json!({
+        "licenseName": license_name,
+        "licenseShortName": lenient_string(raw.get("licenseShortName")),
+        "licenseUrl": lenient_string(raw.get("licenseUrl")),
+        "ownerName": lenient_string(raw.get("ownerName")),
+        "ownerRef": lenient_string(raw.get("ownerRef")),
+        "usabilityRating": usability_rating,
+    })
+}
+
+/// Name of the file that caches the inferred schema inside a dataset directory.
+pub(crate) const SCHEMA_FILE: &str = ".schema.json";
+
+/// Infers column names and types for the CSV and Parquet files of a cached dataset.
+///
+/// CSV files only have their header line read (all columns are reported as `string`), and
+/// Parquet files only have their footer read. A file that cannot be read is listed with an
+/// `error` instead of `columns`. The result is cached in `.schema.json` inside the dataset
+/// directory and reused until the dataset is downloaded again.
+///
+/// # Returns
+///
+/// A JSON object of the form `{"files": [{"name": "f.csv", "columns": [{"name": "id", "type": "string"}]}]}`.
+pub fn infer_dataset_schema(dataset_path: &str) -> Result<serde_json::Value, GaggleError> {
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let dataset_dir = super::download::dataset_cache_dir(&owner, &dataset);
+    let marker = dataset_dir.join(".downloaded");
+    if !marker.exists() {
+        return Err(GaggleError::DatasetNotFound(format!(
+            "Dataset '{}' is not cached; download it first",
+            dataset_path
+        )));
+    }
+
+    // Reuse the cached schema if it was written after the last download
+    let schema_path = dataset_dir.join(SCHEMA_FILE);
+    if let (Ok(schema_meta), Ok(marker_meta)) =
+        (std::fs::metadata(&schema_path), std::fs::metadata(&marker))
+    {
+        if let (Ok(schema_time), Ok(marker_time)) = (schema_meta.modified(), marker_meta.modified())
+        {
+            if schema_time >= marker_time {
+                if let Ok(content) = std::fs::read_to_string(&schema_path) {
+                    if let Ok(val) = serde_json::from_str::<serde_json::Value>(&content) {
+                        return Ok(val);
                     }
                 }
             }
         }
     }
 
-    // Default to "1" if version info not available
-    Ok("1".to_string())
+    let mut paths = Vec::new();
+    collect_files(&dataset_dir, &mut paths)?;
+    paths.sort();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let columns = match ext.as_str() {
+            "csv" => csv_header_columns(&path),
+            "parquet" => parquet_columns(&path),
+            _ => continue,
+        };
+        let name = path
+            .strip_prefix(&dataset_dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        match columns {
+            Ok(columns) => files.push(serde_json::json!({"name": name, "columns": columns})),
+            Err(e) => {
+                tracing::debug!(path = %path.display(), error = %e, "failed to infer file schema");
+                files.push(serde_json::json!({"name": name, "error": e.to_string()}));
+            }
+        }
+    }
+
+    let schema = serde_json::json!({ "files": files });
+    crate::utils::write_marker_atomic(&schema_path, &serde_json::to_string(&schema)?)?;
+    Ok(schema)
+}
+
+/// Recursively collects regular files under `dir`, skipping hidden files.
+fn collect_files(
+    dir: &std::path::Path,
+    out: &mut Vec<std::path::PathBuf>,
+) -> Result<(), GaggleError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .map(|n| n.to_string_lossy().starts_with('.'))
+            .unwrap_or(false);
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else if path.is_file() {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Most bytes read from the start of a CSV file to find its header line
+const MAX_CSV_HEADER_BYTES: u64 = 1024 * 1024;
+
+/// Reads the header line of a CSV file and returns its columns.
+///
+/// The delimiter is chosen as in [`crate::utils::sniff_csv`]. Only the first
+/// `MAX_CSV_HEADER_BYTES` of the file are read, so a longer header is cut short.
+fn csv_header_columns(path: &std::path::Path) -> Result<Vec<serde_json::Value>, GaggleError> {
+    use std::io::Read;
+
+    let delimiter = crate::utils::detect_delimiter(path)?;
+    let file = std::fs::File::open(path)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file.take(MAX_CSV_HEADER_BYTES));
+    let mut record = csv::StringRecord::new();
+    let found = reader
+        .read_record(&mut record)
+        .map_err(|e| GaggleError::CsvError(format!("{}: {}", path.display(), e)))?;
+    if !found {
+        return Ok(Vec::new());
+    }
+    Ok(record
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let name = if i == 0 {
+                name.trim_start_matches('\u{feff}')
+            } else {
+                name
+            };
+            serde_json::json!({"name": name.trim(), "type": "string"})
+        })
+        .collect())
+}
+
+/// Reads the footer of a Parquet file and returns its top-level columns.
+fn parquet_columns(path: &std::path::Path) -> Result<Vec<serde_json::Value>, GaggleError> {
+    use parquet::basic::ConvertedType;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let file = std::fs::File::open(path)?;
+    let reader = SerializedFileReader::new(file).map_err(|e| {
+        GaggleError::IoError(format!(
+            "Failed to read Parquet footer of {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let schema = reader.metadata().file_metadata().schema();
+    Ok(schema
+        .get_fields()
+        .iter()
+        .map(|field| {
+            let info = field.get_basic_info();
+            let ty = if info.converted_type() != ConvertedType::NONE {
+                info.converted_type().to_string()
+            } else if field.is_primitive() {
+                field.get_physical_type().to_string()
+            } else {
+                "STRUCT".to_string()
+            };
+            serde_json::json!({"name": field.name(), "type": ty.to_ascii_lowercase()})
+        })
+        .collect())
 }
 
 #[cfg(test)]
@@ -212,4 +938,443 @@ mod tests {
         std::env::remove_var("KAGGLE_USERNAME");
         std::env::remove_var("KAGGLE_KEY");
     }
+
+    fn metadata_server(datasets: &[&str]) -> (mockito::ServerGuard, Vec<mockito::Mock>) {
+        let mut server = mockito::Server::new();
+        std::env::set_var("GAGGLE_API_BASE", server.url());
+        std::env::set_var("KAGGLE_USERNAME", "test");
+        std::env::set_var("KAGGLE_KEY", "test");
+        let mocks = datasets
+            .iter()
+            .map(|d| {
+                server
+                    .mock("GET", format!("/datasets/view/owner/{}", d).as_str())
+                    .with_status(200)
+                    .with_header("content-type", "application/json")
+                    .with_body(format!("{{\"ref\":\"owner/{}\"}}", d))
+                    .create()
+            })
+            .collect();
+        (server, mocks)
+    }
+
+    fn cleanup_metadata_env() {
+        clear_metadata_cache();
+        std::env::remove_var("GAGGLE_API_BASE");
+        std::env::remove_var("GAGGLE_METADATA_CACHE_MAX");
+        std::env::remove_var("KAGGLE_USERNAME");
+        std::env::remove_var("KAGGLE_KEY");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_metadata_cache_evicts_least_recently_used() {
+        clear_metadata_cache();
+        std::env::set_var("GAGGLE_METADATA_CACHE_MAX", "2");
+        let (_server, _mocks) = metadata_server(&["a", "b", "c"]);
+
+        get_dataset_metadata("owner/a").unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        get_dataset_metadata("owner/b").unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        // Touch "a" so that "b" becomes the least recently used entry
+        get_dataset_metadata("owner/a").unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        get_dataset_metadata("owner/c").unwrap();
+
+        let cache = META_CACHE.read();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains_key("owner/a"));
+        assert!(cache.contains_key("owner/c"));
+        assert!(!cache.contains_key("owner/b"));
+        drop(cache);
+
+        cleanup_metadata_env();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_metadata_invalidation() {
+        clear_metadata_cache();
+        let (_server, _mocks) = metadata_server(&["a", "b"]);
+
+        get_dataset_metadata("owner/a").unwrap();
+        get_dataset_metadata("owner/b").unwrap();
+        invalidate_metadata("owner/a");
+        assert!(!META_CACHE.read().contains_key("owner/a"));
+        assert!(META_CACHE.read().contains_key("owner/b"));
+
+        // The invalidated entry is fetched again from the API
+        get_dataset_metadata("owner/a").unwrap();
+        assert!(META_CACHE.read().contains_key("owner/a"));
+
+        clear_metadata_cache();
+        assert!(META_CACHE.read().is_empty());
+
+        cleanup_metadata_env();
+    }
+
+    #[test]
+    fn test_dataset_metadata_from_value_normalizes_fields() {
+        let raw = serde_json::json!({
+            "ref": "owner/dataset",
+            "title": "Title",
+            "subtitle": "Sub",
+            "currentVersionNumber": 3,
+            "totalBytes": "2048",
+            "licenseName": "CC0-1.0",
+            "lastUpdated": "2024-01-01T00:00:00Z",
+            "downloadCount": 42,
+            "files": [
+                {"name": "a.csv", "totalBytes": 1024, "creationDate": "2024-01-01T00:00:00Z",
+                 "columns": [{"name": "x"}, {"name": "y"}]},
+                {"name": "b.csv", "size": "1024"},
+                {"size": 1}
+            ],
+            "extra": true
+        });
+        let meta = DatasetMetadata::from_value(raw);
+        assert_eq!(meta.ref_path.as_deref(), Some("owner/dataset"));
+        assert_eq!(meta.current_version.as_deref(), Some("3"));
+        assert_eq!(meta.total_bytes, Some(2048));
+        assert_eq!(meta.file_count, 2);
+        assert_eq!(meta.files[1].size, 1024);
+        assert_eq!(
+            meta.files[0].modified.as_deref(),
+            Some("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(meta.files[0].columns, Some(2));
+        assert_eq!(meta.files[1].modified, None);
+        assert_eq!(meta.files[1].columns, None);
+        assert_eq!(meta.license.as_deref(), Some("CC0-1.0"));
+        assert_eq!(meta.download_count, Some(42));
+        assert_eq!(meta.raw["extra"], true);
+
+        let json = serde_json::to_value(&meta).unwrap();
+        assert_eq!(json["ref"], "owner/dataset");
+        assert_eq!(json["current_version"], "3");
+        assert!(json.get("raw").is_none());
+    }
+
+    #[test]
+    fn test_dataset_metadata_is_lenient() {
+        let meta: DatasetMetadata = serde_json::from_str(
+            r#"{"title": 5, "versions": [{"versionNumber": "7"}], "licenses": [{"name": "MIT"}], "files": "nope"}"#,
+        )
+        .unwrap();
+        assert_eq!(meta.title.as_deref(), Some("5"));
+        assert_eq!(meta.current_version.as_deref(), Some("7"));
+        assert_eq!(meta.license.as_deref(), Some("MIT"));
+        assert!(meta.files.is_empty());
+        assert_eq!(meta.total_bytes, None);
+
+        let empty = DatasetMetadata::from_value(serde_json::json!([]));
+        assert_eq!(empty.current_version, None);
+        assert_eq!(empty.file_count, 0);
+    }
+
+    #[test]
+    fn test_extract_tags_from_names_and_refs() {
+        let raw = serde_json::json!({
+            "tags": [
+                {"ref": "finance", "name": "Finance"},
+                {"ref": "tabular"},
+                "nlp",
+                {"name": "Finance"},
+                {"id": 7},
+                ""
+            ]
+        });
+        assert_eq!(extract_tags(&raw), vec!["Finance", "tabular", "nlp"]);
+        assert!(extract_tags(&serde_json::json!({"tags": "nope"})).is_empty());
+        assert!(extract_tags(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_get_dataset_tags_and_popular_tags_with_mock() {
+        clear_metadata_cache();
+        let (mut server, _mocks) = metadata_server(&[]);
+        let bodies = [
+            (
+                "a",
+                r#"{"ref":"owner/a","tags":[{"name":"finance"},{"name":"tabular"}]}"#,
+            ),
+            ("b", r#"{"ref":"owner/b","tags":[{"ref":"finance"}]}"#),
+        ];
+        let _tag_mocks: Vec<mockito::Mock> = bodies
+            .iter()
+            .map(|(d, body)| {
+                server
+                    .mock("GET", format!("/datasets/view/owner/{}", d).as_str())
+                    .with_status(200)
+                    .with_header("content-type", "application/json")
+                    .with_body(*body)
+                    .create()
+            })
+            .collect();
+
+        assert_eq!(
+            get_dataset_tags("owner/a").unwrap(),
+            vec!["finance", "tabular"]
+        );
+        assert_eq!(get_dataset_tags("owner/b").unwrap(), vec!["finance"]);
+
+        let popular = list_popular_tags().unwrap();
+        assert_eq!(
+            popular,
+            serde_json::json!([
+                {"name": "finance", "count": 2},
+                {"name": "tabular", "count": 1}
+            ])
+        );
+
+        cleanup_metadata_env();
+        assert_eq!(list_popular_tags().unwrap(), serde_json::json!([]));
+    }
+
+    fn write_parquet(path: &std::path::Path) {
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+        use std::sync::Arc;
+
+        let schema = Arc::new(
+            parse_message_type(
+                "message schema { required int64 id; optional binary name (UTF8); optional double score; }",
+            )
+            .unwrap(),
+        );
+        let file = std::fs::File::create(path).unwrap();
+        let writer =
+            SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::builder().build()))
+                .unwrap();
+        writer.close().unwrap();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_infer_dataset_schema_csv_and_parquet() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+        let dir = temp.path().join("datasets/owner/schema");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join(".downloaded"), "{}").unwrap();
+        std::fs::write(dir.join("a.csv"), "id,\"full, name\",age\r\n1,x,2\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        std::fs::write(dir.join("semi.csv"), "x;y\n1;2\n").unwrap();
+        write_parquet(&dir.join("sub/b.parquet"));
+        std::fs::write(dir.join("sub/broken.parquet"), "not parquet").unwrap();
+
+        let schema = infer_dataset_schema("owner/schema").unwrap();
+        let files = schema["files"].as_array().unwrap();
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0]["name"], "a.csv");
+        let cols = files[0]["columns"].as_array().unwrap();
+        assert_eq!(cols.len(), 3);
+        assert_eq!(cols[1]["name"], "full, name");
+        assert_eq!(cols[1]["type"], "string");
+
+        assert_eq!(files[2]["name"], "sub/b.parquet");
+        let cols = files[2]["columns"].as_array().unwrap();
+        assert_eq!(cols[0], serde_json::json!({"name": "id", "type": "int64"}));
+        assert_eq!(cols[1], serde_json::json!({"name": "name", "type": "utf8"}));
+        assert_eq!(
+            cols[2],
+            serde_json::json!({"name": "score", "type": "double"})
+        );
+
+        // The delimiter is detected, and an unreadable file does not fail the others
+        assert_eq!(files[1]["name"], "semi.csv");
+        assert_eq!(
+            files[1]["columns"],
+            serde_json::json!([
+                {"name": "x", "type": "string"},
+                {"name": "y", "type": "string"}
+            ])
+        );
+        assert_eq!(files[3]["name"], "sub/broken.parquet");
+        assert!(files[3].get("columns").is_none());
+        assert!(files[3]["error"].as_str().unwrap().contains("Parquet"));
+
+        // Served from the cached schema file on the next call, which is written atomically
+        assert!(dir.join(SCHEMA_FILE).exists());
+        let leftovers: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().contains(".tmp."))
+            .collect();
+        assert!(leftovers.is_empty());
+        std::fs::remove_file(dir.join("a.csv")).unwrap();
+        assert_eq!(infer_dataset_schema("owner/schema").unwrap(), schema);
+
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_infer_dataset_schema_requires_cached_dataset() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+        let result = infer_dataset_schema("owner/missing");
+        assert!(matches!(result, Err(GaggleError::DatasetNotFound(_))));
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_get_dataset_readme_combines_and_saves() {
+        clear_metadata_cache();
+        let temp = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+        let (mut server, _mocks) = metadata_server(&[]);
+        let view = server
+            .mock("GET", "/datasets/view/owner/docs")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r##"{"ref":"owner/docs","description":"# Docs\n\nAbout the data."}"##)
+            .expect(1)
+            .create();
+        let dir = temp.path().join("datasets/owner/docs");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("README.md"), "## Columns\n").unwrap();
+
+        let readme = get_dataset_readme("owner/docs").unwrap();
+        assert_eq!(readme, "# Docs\n\nAbout the data.\n\n## Columns");
+        assert_eq!(
+            std::fs::read_to_string(dir.join(README_FILE)).unwrap(),
+            readme
+        );
+
+        // The saved copy is served without an API call, even with the metadata cache cleared
+        clear_metadata_cache();
+        assert_eq!(get_dataset_readme("owner/docs").unwrap(), readme);
+        view.assert();
+
+        // Once the saved copy expires, it is rebuilt
+        std::env::set_var("GAGGLE_METADATA_TTL", "0");
+        std::fs::write(dir.join("README.md"), "## Changed\n").unwrap();
+        let view = view.expect(2);
+        assert!(get_dataset_readme("owner/docs")
+            .unwrap()
+            .ends_with("## Changed"));
+        view.assert();
+
+        std::env::remove_var("GAGGLE_METADATA_TTL");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        cleanup_metadata_env();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_get_dataset_readme_without_documentation() {
+        clear_metadata_cache();
+        let temp = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+        let (_server, _mocks) = metadata_server(&["plain"]);
+
+        // Not cached, so nothing is saved and no dataset directory is created
+        assert_eq!(get_dataset_readme("owner/plain").unwrap(), "");
+        assert!(!temp.path().join("datasets/owner/plain").exists());
+
+        // Offline, a shipped README is still returned, while a missing one is an error
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        let dir = temp.path().join("datasets/owner/shipped");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("README.md"), "Shipped").unwrap();
+        assert_eq!(get_dataset_readme("owner/shipped").unwrap(), "Shipped");
+        assert!(get_dataset_readme("owner/other").is_err());
+
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        cleanup_metadata_env();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_get_dataset_license_saves_and_serves_from_cache() {
+        clear_metadata_cache();
+        let temp = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+        let (mut server, _mocks) = metadata_server(&[]);
+        let view = server
+            .mock("GET", "/datasets/view/owner/licensed")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"ref":"owner/licensed","licenseName":"CC0: Public Domain",
+                "licenseShortName":"CC0-1.0","licenseUrl":"https://creativecommons.org/publicdomain/zero/1.0/",
+                "ownerName":"Owner Name","ownerRef":"owner","usabilityRating":0.88}"#,
+            )
+            .expect(1)
+            .create();
+        let dir = temp.path().join("datasets/owner/licensed");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let license = get_dataset_license("owner/licensed").unwrap();
+        assert_eq!(license["licenseName"], "CC0: Public Domain");
+        assert_eq!(license["licenseShortName"], "CC0-1.0");
+        assert_eq!(
+            license["licenseUrl"],
+            "https://creativecommons.org/publicdomain/zero/1.0/"
+        );
+        assert_eq!(license["ownerName"], "Owner Name");
+        assert_eq!(license["ownerRef"], "owner");
+        assert_eq!(license["usabilityRating"], 0.88);
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join(LICENSE_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(saved, license);
+
+        // The saved copy is served without an API call, even with the metadata cache cleared
+        clear_metadata_cache();
+        assert_eq!(get_dataset_license("owner/licensed").unwrap(), license);
+        view.assert();
+
+        // Offline, an expired saved copy is still used
+        std::env::set_var("GAGGLE_METADATA_TTL", "0");
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        assert_eq!(get_dataset_license("owner/licensed").unwrap(), license);
+        assert_eq!(get_license_from_cache("owner/licensed").unwrap(), license);
+        view.assert();
+
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_METADATA_TTL");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        cleanup_metadata_env();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_get_license_from_cache_uses_persisted_metadata() {
+        clear_metadata_cache();
+        let temp = tempfile::TempDir::new().unwrap();
+        std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+        let (mut server, _mocks) = metadata_server(&[]);
+        let _view = server
+            .mock("GET", "/datasets/view/owner/uncached")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ref":"owner/uncached","licenses":[{"name":"MIT"}]}"#)
+            .create();
+
+        assert!(get_license_from_cache("owner/uncached").is_none());
+
+        // Not cached, so nothing is saved, but the license falls back to `licenses`
+        let license = get_dataset_license("owner/uncached").unwrap();
+        assert_eq!(license["licenseName"], "MIT");
+        assert!(license["licenseUrl"].is_null());
+        assert!(license["usabilityRating"].is_null());
+        assert!(!temp.path().join("datasets/owner/uncached").exists());
+
+        // Offline, the license is derived from the metadata persisted by the fetch
+        clear_metadata_cache();
+        std::env::set_var("GAGGLE_OFFLINE", "1");
+        assert_eq!(get_dataset_license("owner/uncached").unwrap(), license);
+        assert!(get_dataset_license("owner/never-fetched").is_err());
+
+        std::env::remove_var("GAGGLE_OFFLINE");
+        std::env::remove_var("GAGGLE_CACHE_DIR");
+        cleanup_metadata_env();
+    }
 }
@@ -0,0 +1,109 @@
+// metrics.rs
+//
+// This module counts the work done by the library in the current process: API calls,
+// retries, cache hits and misses, dataset downloads, single-file fetches, and bytes
+// downloaded. The counters are relaxed atomics, so updating them is cheap and safe from any
+// thread. They are only meant for reporting, and are not used to synchronize anything.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A counter updated by the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Counter {
+    /// HTTP requests sent, including ones answered from recorded responses
+    ApiCalls,
+    /// Attempts of an HTTP call retried after a failure or rate limiting
+    Retries,
+    /// Datasets and files served from the cache without a download
+    CacheHits,
+    /// Datasets and files that had to be downloaded
+    CacheMisses,
+    /// Dataset archive downloads started
+    DownloadsStarted,
+    /// Dataset archive downloads extracted into the cache
+    DownloadsCompleted,
+    /// Dataset archive downloads that failed to fetch or extract
+    DownloadsFailed,
+    /// Single files fetched without downloading their dataset's archive
+    SingleFileFetches,
+    /// Bytes of archives and files downloaded
+    BytesDownloaded,
+}
+
+const COUNTERS: usize = 9;
+
+static VALUES: [AtomicU64; COUNTERS] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+fn value(counter: Counter) -> &'static AtomicU64 {
+    &VALUES[counter as usize]
+}
+
+/// Adds `n` to a counter.
+pub(crate) fn add(counter: Counter, n: u64) {
+    value(counter).fetch_add(n, Ordering::Relaxed);
+}
+
+/// Adds one to a counter.
+pub(crate) fn incr(counter: Counter) {
+    add(counter, 1);
+}
+
+/// The values of the counters at one point in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// HTTP requests sent, including ones answered from recorded responses.
+    pub api_calls: u64,
+    /// Attempts of an HTTP call retried after a failure or rate limiting.
+    pub retries: u64,
+    /// Datasets and files served from the cache without a download.
+    pub cache_hits: u64,
+    /// Datasets and files that had to be downloaded.
+    pub cache_misses: u64,
+    /// Dataset archive downloads started.
+    pub downloads_started: u64,
+    /// Dataset archive downloads extracted into the cache.
+    pub downloads_completed: u64,
+    /// Dataset archive downloads that failed to fetch or extract.
+    pub downloads_failed: u64,
+    /// Single files fetched without downloading their dataset's archive.
+    pub single_file_fetches: u64,
+    /// Bytes of archives and files downloaded.
+    pub bytes_downloaded: u64,
+}
+
+/// Returns the current values of the counters.
+///
+/// The counters are read one at a time, so a snapshot taken while other threads are working
+/// may combine values from slightly different moments.
+pub fn stats() -> Stats {
+    let get = |counter| value(counter).load(Ordering::Relaxed);
+    Stats {
+        api_calls: get(Counter::ApiCalls),
+        retries: get(Counter::Retries),
+        cache_hits: get(Counter::CacheHits),
+        cache_misses: get(Counter::CacheMisses),
+        downloads_started: get(Counter::DownloadsStarted),
+        downloads_completed: get(Counter::DownloadsCompleted),
+        downloads_failed: get(Counter::DownloadsFailed),
+        single_file_fetches: get(Counter::SingleFileFetches),
+        bytes_downloaded: get(Counter::BytesDownloaded),
+    }
+}
+
+/// Sets every counter back to zero.
+pub fn reset_stats() {
+    for value in &VALUES {
+        value.store(0, Ordering::Relaxed);
+    }
+}
@@ -9,15 +9,39 @@
 pub mod api;
 pub mod credentials;
 pub mod download;
+pub mod health;
 pub mod metadata;
+pub mod metrics;
 pub mod search;
+pub mod throttle;
+pub mod upload;
 
 pub use download::{
-    download_dataset, get_dataset_file_path, get_dataset_version_info, is_dataset_current,
-    list_dataset_files, update_dataset,
+    check_dataset_integrity, clear_dataset_cache, copy_dataset_to, copy_file_to, download_dataset,
+    download_dataset_files, download_dataset_to, download_dataset_with_mode, export_cache,
+    export_dataset, get_dataset_file_path, get_dataset_file_path_full, get_dataset_version_info,
+    import_cache, import_dataset, is_dataset_current, list_cached_datasets, list_dataset_files,
+    list_dataset_files_by_format, list_dataset_files_full, plan_download, refresh_cache_sizes,
+    resolve_glob, update_dataset, verify_dataset_integrity,
 };
-pub use metadata::get_dataset_metadata;
-pub use search::search_datasets;
+pub use health::health_check;
+pub use metadata::{
+    get_dataset_license, get_dataset_metadata_typed, get_dataset_readme, get_dataset_tags,
+    infer_dataset_schema, list_popular_tags,
+};
+pub use search::{
+    list_datasets_by_owner, list_my_datasets, list_new_datasets, list_trending_datasets,
+    list_updated_datasets, search_all, search_cached, search_datasets, search_datasets_by_format,
+    search_datasets_by_tag, search_datasets_cursor,
+};
+pub use upload::{create_dataset, update_dataset_files};
+
+#[cfg(feature = "async")]
+pub use download::{download_dataset_async, get_dataset_file_path_async};
+#[cfg(feature = "async")]
+pub use metadata::get_dataset_metadata_async;
+#[cfg(feature = "async")]
+pub use search::search_datasets_async;
 
 /// Parse dataset path like "username/dataset-name"
 ///
@@ -33,6 +57,7 @@ pub use search::search_datasets;
 /// - Owner or dataset name is empty after trimming
 /// - Path contains traversal segments (. or ..)
 /// - Path contains control characters
+/// - Owner or dataset name contains whitespace, which Kaggle never accepts
 /// - Path exceeds maximum length (4096 characters)
 pub fn parse_dataset_path(path: &str) -> Result<(String, String), crate::error::GaggleError> {
     // Validate maximum path length to prevent resource exhaustion
@@ -82,18 +107,175 @@ pub fn parse_dataset_path(path: &str) -> Result<(String, String), crate::error::
         )));
     }
 
+    // Kaggle slugs never contain whitespace, so such a path can only fail at the API
+    if owner.chars().any(char::is_whitespace) || dataset.chars().any(char::is_whitespace) {
+        tracing::warn!(
+            dataset = path,
+            "dataset path contains whitespace inside a segment"
+        );
+        return Err(crate::error::GaggleError::InvalidDatasetPath(format!(
+            "Dataset path contains whitespace: {}",
+            path
+        )));
+    }
+
     Ok((owner.to_string(), dataset.to_string()))
 }
 
+/// Percent-encodes the owner and dataset of a parsed path for use in an API URL, as
+/// "owner/dataset".
+pub(crate) fn encode_dataset_segments(owner: &str, dataset: &str) -> String {
+    format!(
+        "{}/{}",
+        urlencoding::encode(owner),
+        urlencoding::encode(dataset)
+    )
+}
+
+/// Parse a Kaggle dataset URL copied from the browser, or a bare "owner/dataset" path
+///
+/// Supports formats:
+///   "https://www.kaggle.com/datasets/owner/dataset" (trailing segments such as `/data` are ignored)
+///   "https://kaggle.com/datasets/owner/dataset"
+///   "https://www.kaggle.com/owner/dataset" (legacy URL)
+///   "owner/dataset"
+///
+/// Percent-encoded characters in the owner and dataset names of a URL are decoded, and a
+/// query string or fragment is ignored. The result is validated like [`parse_dataset_path`].
+/// A version in the URL (`/versions/{n}`) is ignored; see [`parse_dataset_path_with_version`].
+pub fn parse_dataset_path_from_url(
+    input: &str,
+) -> Result<(String, String), crate::error::GaggleError> {
+    let (owner, dataset, _) = parse_dataset_url(input)?;
+    Ok((owner, dataset))
+}
+
+/// Whether `input` starts with an `http://` or `https://` scheme
+fn is_url(input: &str) -> bool {
+    let lower: String = input.trim_start().chars().take(8).collect();
+    let lower = lower.to_ascii_lowercase();
+    lower.starts_with("https://") || lower.starts_with("http://")
+}
+
+/// Parses a Kaggle dataset URL or a bare "owner/dataset" path into the owner, the dataset,
+/// and the version given by a `/versions/{n}` segment of the URL.
+fn parse_dataset_url(
+    input: &str,
+) -> Result<(String, String, Option<String>), crate::error::GaggleError> {
+    // First path segments of kaggle.com URLs that are not owners
+    const RESERVED_SEGMENTS: &[&str] = &[
+        "c",
+        "code",
+        "competitions",
+        "datasets",
+        "discussions",
+        "docs",
+        "learn",
+        "models",
+        "search",
+    ];
+
+    let trimmed = input.trim();
+    let lower = trimmed.to_ascii_lowercase();
+    let Some(rest) = ["https://", "http://"]
+        .iter()
+        .find(|scheme| lower.starts_with(*scheme))
+        .map(|scheme| &trimmed[scheme.len()..])
+    else {
+        let (owner, dataset) = parse_dataset_path(trimmed)?;
+        return Ok((owner, dataset, None));
+    };
+
+    let invalid = || {
+        crate::error::GaggleError::InvalidDatasetPath(format!(
+            "Not a Kaggle dataset URL: {}. Expected a URL like \
+             https://www.kaggle.com/datasets/owner/dataset or \
+             https://www.kaggle.com/datasets/owner/dataset/versions/2",
+            input
+        ))
+    };
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    if !host.eq_ignore_ascii_case("www.kaggle.com") && !host.eq_ignore_ascii_case("kaggle.com") {
+        return Err(invalid());
+    }
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let (owner, dataset, version) = match segments.as_slice() {
+        ["datasets", owner, dataset, "versions", version, ..] => {
+            (*owner, *dataset, parse_version(version)?)
+        }
+        ["datasets", _, _, "versions"] => return Err(invalid()),
+        ["datasets", owner, dataset, ..] => (*owner, *dataset, None),
+        [owner, dataset] if !RESERVED_SEGMENTS.contains(owner) => (*owner, *dataset, None),
+        _ => return Err(invalid()),
+    };
+    let owner = urlencoding::decode(owner).map_err(|_| invalid())?;
+    let dataset = urlencoding::decode(dataset).map_err(|_| invalid())?;
+    if owner.contains('/') || dataset.contains('/') {
+        tracing::warn!(
+            url = input,
+            "dataset URL segment contains a slash after decoding"
+        );
+        return Err(invalid());
+    }
+    let (owner, dataset) = parse_dataset_path(&format!("{}/{}", owner, dataset))?;
+    Ok((owner, dataset, version))
+}
+
+/// Parses the version of a dataset path, given with or without a leading `v`. `latest` and
+/// an empty string mean the latest version.
+fn parse_version(v: &str) -> Result<Option<String>, crate::error::GaggleError> {
+    let v = v.trim();
+    if v == "latest" || v.is_empty() {
+        return Ok(None);
+    }
+    // Remove 'v' prefix if present (both @v2 and @2 are valid)
+    let version_str = v.strip_prefix('v').unwrap_or(v);
+    // Validate it's a positive integer (>0)
+    match version_str.parse::<u32>() {
+        Ok(n) if n > 0 => Ok(Some(version_str.to_string())),
+        _ => Err(crate::error::GaggleError::InvalidDatasetPath(format!(
+            "Invalid version number '{}'. Version must be a positive integer > 0.",
+            v
+        ))),
+    }
+}
+
+/// Normalizes a dataset reference given as a Kaggle URL to the "owner/dataset" or
+/// "owner/dataset@{version}" form accepted everywhere, so URLs pasted from the browser work
+/// with every function. A `/versions/{n}` segment becomes `@{n}`, and a query string or
+/// fragment is dropped. Anything that is not a URL is returned unchanged, to be validated by
+/// the function it is passed to.
+pub fn normalize_dataset_ref(input: &str) -> Result<String, crate::error::GaggleError> {
+    if !is_url(input) {
+        return Ok(input.to_string());
+    }
+    let (owner, dataset, version) = parse_dataset_path_with_version(input)?;
+    Ok(match version {
+        Some(v) => format!("{}/{}@{}", owner, dataset, v),
+        None => format!("{}/{}", owner, dataset),
+    })
+}
+
 /// Parse dataset path with optional version
 /// Supports formats:
 ///   "owner/dataset" -> (owner, dataset, None)
 ///   "owner/dataset@v2" -> (owner, dataset, Some("2"))
 ///   "owner/dataset@5" -> (owner, dataset, Some("5"))
 ///   "owner/dataset@latest" -> (owner, dataset, None)
+///   "https://www.kaggle.com/datasets/owner/dataset@v2" -> (owner, dataset, Some("2"))
+///   "https://www.kaggle.com/datasets/owner/dataset/versions/2" -> (owner, dataset, Some("2"))
 pub fn parse_dataset_path_with_version(
     path: &str,
 ) -> Result<(String, String, Option<String>), crate::error::GaggleError> {
+    // The query string and fragment of a URL are ignored, and may contain '@'
+    let path = if is_url(path) {
+        path.split(['?', '#']).next().unwrap_or_default()
+    } else {
+        path
+    };
+
     // Split on @ to extract version
     let parts: Vec<&str> = path.split('@').collect();
 
@@ -104,60 +286,150 @@ pub fn parse_dataset_path_with_version(
     }
 
     let dataset_path = parts[0];
-    let version = if parts.len() == 2 {
-        let v = parts[1].trim();
-        if v == "latest" || v.is_empty() {
-            None
-        } else {
-            // Remove 'v' prefix if present (both @v2 and @2 are valid)
-            let version_str = v.strip_prefix('v').unwrap_or(v);
-            // Validate it's a positive integer (>0)
-            match version_str.parse::<u32>() {
-                Ok(n) if n > 0 => Some(version_str.to_string()),
-                _ => {
-                    return Err(crate::error::GaggleError::InvalidDatasetPath(format!(
-                        "Invalid version number '{}'. Version must be a positive integer > 0.",
-                        v
-                    )));
-                }
-            }
-        }
-    } else {
-        None
+    let version = match parts.get(1) {
+        Some(v) => parse_version(v)?,
+        None => None,
     };
 
-    // Parse owner/dataset from the base path
-    let (owner, dataset) = parse_dataset_path(dataset_path)?;
+    // Parse owner/dataset from the base path, which may also be a Kaggle URL
+    let (owner, dataset, url_version) = parse_dataset_url(dataset_path)?;
+    if version.is_some() && url_version.is_some() && version != url_version {
+        return Err(crate::error::GaggleError::InvalidDatasetPath(format!(
+            "Dataset path gives two different versions: {}",
+            path
+        )));
+    }
 
-    Ok((owner, dataset, version))
+    Ok((owner, dataset, version.or(url_version)))
+}
+
+/// Parse dataset path with optional version and a path inside the dataset
+/// Supports formats:
+///   "owner/dataset" -> (owner, dataset, None, None)
+///   "owner/dataset@v2" -> (owner, dataset, Some("2"), None)
+///   "owner/dataset/Twitter/tweets.csv" -> (owner, dataset, None, Some("Twitter/tweets.csv"))
+///   "owner/dataset@v2/Twitter/tweets.csv" -> (owner, dataset, Some("2"), Some("Twitter/tweets.csv"))
+///
+/// The version is read from the dataset segment only, so an `@` in the subpath is part of a
+/// file name. The subpath is validated like filenames: it must be relative and must not
+/// contain `..`. Kaggle URLs are parsed like [`parse_dataset_path_with_version`] and never
+/// have a subpath.
+pub fn parse_dataset_path_full(
+    path: &str,
+) -> Result<(String, String, Option<String>, Option<String>), crate::error::GaggleError> {
+    const MAX_PATH_LENGTH: usize = 4096;
+    if path.len() > MAX_PATH_LENGTH {
+        return Err(crate::error::GaggleError::InvalidDatasetPath(format!(
+            "Dataset path exceeds maximum length of {} characters",
+            MAX_PATH_LENGTH
+        )));
+    }
+
+    let trimmed = path.trim();
+    let lower = trimmed.to_ascii_lowercase();
+    if lower.starts_with("https://") || lower.starts_with("http://") {
+        let (owner, dataset, version) = parse_dataset_path_with_version(trimmed)?;
+        return Ok((owner, dataset, version, None));
+    }
+
+    // Everything after the second '/' is the subpath
+    let (head, subpath) = match trimmed.match_indices('/').nth(1) {
+        Some((i, _)) => (&trimmed[..i], Some(&trimmed[i + 1..])),
+        None => (trimmed, None),
+    };
+    let (owner, dataset, version) = parse_dataset_path_with_version(head)?;
+
+    let subpath = subpath
+        .map(|s| s.trim_end_matches('/'))
+        .filter(|s| !s.is_empty());
+    if let Some(sub) = subpath {
+        if sub.chars().any(|c| c.is_control()) {
+            return Err(crate::error::GaggleError::InvalidDatasetPath(format!(
+                "Dataset path contains control characters: {}",
+                path
+            )));
+        }
+        download::validate_relative_filename(sub)?;
+    }
+
+    Ok((owner, dataset, version, subpath.map(str::to_string)))
+}
+
+/// Splits a dataset path into the dataset reference to download, such as `"owner/dataset"`
+/// or `"owner/dataset@2"`, and the path inside the dataset, validated by
+/// [`parse_dataset_path_full`].
+pub fn split_dataset_path(
+    path: &str,
+) -> Result<(String, Option<String>), crate::error::GaggleError> {
+    let (owner, dataset, version, subpath) = parse_dataset_path_full(path)?;
+    let dataset_ref = match version {
+        Some(v) => format!("{}/{}@{}", owner, dataset, v),
+        None => format!("{}/{}", owner, dataset),
+    };
+    Ok((dataset_ref, subpath))
 }
 
 /// Prefetch multiple files within a dataset without downloading the entire archive.
 /// Returns a JSON string with an array of objects: {"name": ..., "status": "ok"|"error", "path"?: ..., "error"?: ...}
+///
+/// Files are fetched by up to `GAGGLE_PREFETCH_CONCURRENCY` worker threads. The results keep
+/// the order of `files`, and a failed file does not stop the others.
 #[allow(dead_code)]
 pub fn prefetch_files(
     dataset_path: &str,
     files: &[&str],
 ) -> Result<serde_json::Value, crate::error::GaggleError> {
-    let mut results = Vec::with_capacity(files.len());
-    for f in files {
-        match download::get_dataset_file_path(dataset_path, f) {
-            Ok(path) => {
-                results.push(serde_json::json!({
-                    "name": f,
-                    "status": "ok",
-                    "path": path.to_string_lossy(),
-                }));
-            }
-            Err(e) => {
-                results.push(serde_json::json!({
-                    "name": f,
-                    "status": "error",
-                    "error": e.to_string(),
-                }));
-            }
-        }
-    }
+    let fetch = |f: &str| match download::get_dataset_file_path(dataset_path, f) {
+        Ok(path) => serde_json::json!({
+            "name": f,
+            "status": "ok",
+            "path": path.to_string_lossy(),
+        }),
+        Err(e) => serde_json::json!({
+            "name": f,
+            "status": "error",
+            "error": e.to_string(),
+        }),
+    };
+
+    let workers = crate::config::prefetch_concurrency().min(files.len());
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let indexed: Vec<(usize, serde_json::Value)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let Some(f) = files.get(i) else {
+                            break;
+                        };
+                        done.push((i, fetch(f)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_default())
+            .collect()
+    });
+
+    // Put results back in input order; a file is only missing if its worker panicked
+    let mut slots: Vec<Option<serde_json::Value>> = vec![None; files.len()];
+    for (i, v) in indexed {
+        slots[i] = Some(v);
+    }
+    let results: Vec<serde_json::Value> = slots
+        .into_iter()
+        .zip(files)
+        .map(|(slot, f)| {
+            slot.unwrap_or_else(|| {
+                serde_json::json!({"name": f, "status": "error", "error": "prefetch worker panicked"})
+            })
+        })
+        .collect();
     Ok(serde_json::json!({"dataset": dataset_path, "files": results}))
 }
 
@@ -201,6 +473,122 @@ mod tests {
         assert_eq!(dataset, "data_set");
     }
 
+    #[test]
+    fn test_parse_dataset_path_from_url() {
+        let expected = ("owner".to_string(), "my-data".to_string());
+        for input in [
+            "https://www.kaggle.com/datasets/owner/my-data",
+            "https://kaggle.com/datasets/owner/my-data",
+            "http://www.kaggle.com/datasets/owner/my-data/",
+            "https://www.kaggle.com/datasets/owner/my-data/data?select=train.csv",
+            "HTTPS://WWW.KAGGLE.COM/datasets/owner/my-data#files",
+            "https://www.kaggle.com/owner/my-data",
+            "https://www.kaggle.com/datasets/%6Fwner/my%2Ddata",
+            " owner/my-data ",
+        ] {
+            assert_eq!(
+                parse_dataset_path_from_url(input).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_dataset_path_from_url_rejects_other_urls() {
+        for input in [
+            "https://example.com/datasets/owner/my-data",
+            "https://www.kaggle.com/datasets/owner",
+            "https://www.kaggle.com/competitions/titanic",
+            "https://www.kaggle.com/code/owner/notebook",
+            "https://www.kaggle.com/datasets/owner/a%2Fb",
+            "https://www.kaggle.com/datasets/owner/%2E%2E",
+            "https://www.kaggle.com/",
+            "owner",
+        ] {
+            assert!(
+                matches!(
+                    parse_dataset_path_from_url(input),
+                    Err(crate::error::GaggleError::InvalidDatasetPath(_))
+                ),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_dataset_url_versions() {
+        for (input, version) in [
+            (
+                "https://www.kaggle.com/datasets/owner/data/versions/4",
+                Some("4"),
+            ),
+            (
+                "https://www.kaggle.com/datasets/owner/data/versions/4?select=a.csv",
+                Some("4"),
+            ),
+            (
+                "https://www.kaggle.com/datasets/owner/data/versions/4@v4",
+                Some("4"),
+            ),
+            ("https://www.kaggle.com/datasets/owner/data?x=a@b", None),
+        ] {
+            let (owner, dataset, parsed) = parse_dataset_path_with_version(input).unwrap();
+            assert_eq!((owner.as_str(), dataset.as_str()), ("owner", "data"));
+            assert_eq!(parsed.as_deref(), version, "{}", input);
+        }
+        for input in [
+            "https://www.kaggle.com/datasets/owner/data/versions",
+            "https://www.kaggle.com/datasets/owner/data/versions/0",
+            "https://www.kaggle.com/datasets/owner/data/versions/x",
+            "https://www.kaggle.com/datasets/owner/data/versions/4@v5",
+        ] {
+            assert!(parse_dataset_path_with_version(input).is_err(), "{}", input);
+        }
+        // The version is ignored when only the owner and dataset are asked for
+        assert_eq!(
+            parse_dataset_path_from_url("https://www.kaggle.com/datasets/owner/data/versions/4")
+                .unwrap(),
+            ("owner".to_string(), "data".to_string())
+        );
+    }
+
+    #[test]
+    fn test_normalize_dataset_ref() {
+        assert_eq!(
+            normalize_dataset_ref("https://www.kaggle.com/datasets/owner/data-set").unwrap(),
+            "owner/data-set"
+        );
+        assert_eq!(
+            normalize_dataset_ref("https://kaggle.com/datasets/owner/data/versions/7?select=a.csv")
+                .unwrap(),
+            "owner/data@7"
+        );
+        // Anything that is not a URL is left for the caller to validate
+        for input in [
+            "owner/data",
+            "owner/data@v2",
+            "owner/data/sub/file.csv",
+            "bad",
+        ] {
+            assert_eq!(normalize_dataset_ref(input).unwrap(), input);
+        }
+        let err = normalize_dataset_ref("https://www.kaggle.com/code/owner/notebook").unwrap_err();
+        assert!(err.to_string().contains("Expected a URL like"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_dataset_path_with_version_from_url() {
+        let (owner, dataset, version) =
+            parse_dataset_path_with_version("https://www.kaggle.com/datasets/owner/data@v3")
+                .unwrap();
+        assert_eq!(owner, "owner");
+        assert_eq!(dataset, "data");
+        assert_eq!(version.as_deref(), Some("3"));
+    }
+
     #[test]
     fn test_parse_dataset_path_no_slash() {
         let result = parse_dataset_path("ownerdataset");
@@ -261,6 +649,32 @@ mod tests {
         assert_eq!(dataset, "dataset");
     }
 
+    #[test]
+    fn test_parse_dataset_path_rejects_inner_whitespace() {
+        for path in [
+            "owner.name/data set",
+            "own er/dataset",
+            "owner/data\u{a0}set",
+        ] {
+            let err = parse_dataset_path(path).unwrap_err();
+            assert!(err.to_string().contains("whitespace"), "{}", path);
+        }
+        let err = parse_dataset_path_from_url("https://www.kaggle.com/datasets/owner/data%20set");
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_encode_dataset_segments() {
+        assert_eq!(
+            encode_dataset_segments("Owner-Name", "data.set-v1.0"),
+            "Owner-Name/data.set-v1.0"
+        );
+        assert_eq!(
+            encode_dataset_segments("user@domain.com", "a+b%c"),
+            "user%40domain.com/a%2Bb%25c"
+        );
+    }
+
     #[test]
     fn test_parse_dataset_path_rejects_dot_segments() {
         assert!(parse_dataset_path("./owner").is_err());
@@ -345,6 +759,59 @@ mod tests {
         assert_eq!(version, Some("10".to_string()));
     }
 
+    #[test]
+    fn test_parse_dataset_path_full() {
+        assert_eq!(
+            parse_dataset_path_full("owner/dataset@v2/Twitter/tweets.csv").unwrap(),
+            (
+                "owner".to_string(),
+                "dataset".to_string(),
+                Some("2".to_string()),
+                Some("Twitter/tweets.csv".to_string())
+            )
+        );
+        let (_, _, version, subpath) = parse_dataset_path_full("owner/dataset/data/").unwrap();
+        assert_eq!(version, None);
+        assert_eq!(subpath.as_deref(), Some("data"));
+        let (_, _, version, subpath) = parse_dataset_path_full("owner/dataset@latest").unwrap();
+        assert_eq!((version, subpath), (None, None));
+        // An '@' in the subpath is part of a file name
+        let (_, _, version, subpath) =
+            parse_dataset_path_full("owner/dataset/logo@2x.png").unwrap();
+        assert_eq!(version, None);
+        assert_eq!(subpath.as_deref(), Some("logo@2x.png"));
+        let (owner, dataset, version, subpath) =
+            parse_dataset_path_full("https://www.kaggle.com/datasets/owner/data@v3").unwrap();
+        assert_eq!((owner.as_str(), dataset.as_str()), ("owner", "data"));
+        assert_eq!((version.as_deref(), subpath), (Some("3"), None));
+    }
+
+    #[test]
+    fn test_parse_dataset_path_full_rejects_traversal() {
+        for path in [
+            "owner/dataset@v2/../other/file.csv",
+            "owner/dataset/data/../../x",
+            "owner/dataset//etc/passwd",
+            "owner/dataset@v0/file.csv",
+            "owner/dataset/bad\u{7}name",
+        ] {
+            assert!(parse_dataset_path_full(path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_split_dataset_path() {
+        assert_eq!(
+            split_dataset_path("owner/dataset@v2/Twitter/").unwrap(),
+            ("owner/dataset@2".to_string(), Some("Twitter".to_string()))
+        );
+        assert_eq!(
+            split_dataset_path("owner/dataset").unwrap(),
+            ("owner/dataset".to_string(), None)
+        );
+        assert!(split_dataset_path("owner/dataset/../../x").is_err());
+    }
+
     #[test]
     fn test_parse_version_zero_rejected() {
         let result = parse_dataset_path_with_version("owner/dataset@0");
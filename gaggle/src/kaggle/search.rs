@@ -4,21 +4,450 @@
 // includes a function for sending a search query to the Kaggle API and for
 // returning the results as a JSON value. The module handles the details of
 // building the API request, including URL encoding the query and handling
-// pagination.
+// pagination. Results are kept in a small in-memory cache with a TTL so that
+// repeated queries in an interactive session do not hit the API every time.
 
 use crate::error::GaggleError;
 
-use super::api::{build_client, get_api_base, with_retries};
+use super::api::{get_api_base, get_client, read_json_response, send, with_retries};
 use super::credentials::get_credentials;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Maximum number of entries kept in the search cache
+const SEARCH_CACHE_MAX_ENTRIES: usize = 500;
+
+/// Simple in-memory cache for search results with TTL
+static SEARCH_CACHE: Lazy<RwLock<HashMap<String, (serde_json::Value, Instant)>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Search cache TTL (seconds), configurable via GAGGLE_SEARCH_TTL (default 60s)
+fn search_ttl() -> Duration {
+    let secs = std::env::var("GAGGLE_SEARCH_TTL")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(60);
+    Duration::from_secs(secs)
+}
+
+/// Builds the cache key for a search request.
+///
+/// `filters` holds any extra query parameters that affect the result set; it is
+/// hashed so that keys stay short regardless of how many filters are applied.
+fn search_cache_key(query: &str, page: i32, page_size: i32, filters: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    filters.hash(&mut hasher);
+    format!("{}:{}:{}:{:x}", query, page, page_size, hasher.finish())
+}
+
+/// Returns a cached search result if present and still fresh.
+fn cached_search_result(key: &str) -> Option<serde_json::Value> {
+    let cache = SEARCH_CACHE.read();
+    match cache.get(key) {
+        Some((val, ts)) if ts.elapsed() < search_ttl() => Some(val.clone()),
+        _ => None,
+    }
+}
+
+/// Stores a search result in the cache, evicting the oldest entry when full.
+fn store_search_result(key: String, value: serde_json::Value) {
+    let mut cache = SEARCH_CACHE.write();
+    if !cache.contains_key(&key) && cache.len() >= SEARCH_CACHE_MAX_ENTRIES {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, (_, ts))| *ts)
+            .map(|(k, _)| k.clone());
+        if let Some(k) = oldest {
+            cache.remove(&k);
+        }
+    }
+    cache.insert(key, (value, Instant::now()));
+}
+
+/// Removes all entries from the search cache.
+pub fn clear_search_cache() {
+    SEARCH_CACHE.write().clear();
+}
+
+/// Returns the number of entries currently held in the search cache.
+#[allow(dead_code)]
+pub fn search_cache_size() -> usize {
+    SEARCH_CACHE.read().len()
+}
+
+/// Removes a single entry from the search cache.
+#[allow(dead_code)]
+pub fn invalidate_search_cache_entry(key: &str) {
+    SEARCH_CACHE.write().remove(key);
+}
 
 /// Search for datasets on Kaggle
+///
+/// With GAGGLE_SEARCH_DEDUPLICATE set, results repeated within the page are dropped (see
+/// [`deduplicate_search_results`]).
 pub fn search_datasets(
     query: &str,
     page: i32,
     page_size: i32,
 ) -> Result<serde_json::Value, GaggleError> {
-    // Strict offline: fail fast
-    if crate::config::offline_mode() {
+    let json = super::api::logged_operation("search_datasets", query, || {
+        list_datasets(Some(query), &[], page, page_size)
+    })?;
+    Ok(if crate::config::search_deduplicate() {
+        deduplicate_search_results(json)
+    } else {
+        json
+    })
+}
+
+/// Searches for datasets on Kaggle like [`search_datasets`], and always drops results
+/// repeated within the page.
+#[allow(dead_code)]
+pub fn search_datasets_deduped(
+    query: &str,
+    page: i32,
+    page_size: i32,
+) -> Result<serde_json::Value, GaggleError> {
+    search_datasets(query, page, page_size).map(deduplicate_search_results)
+}
+
+/// Drops the results of a page whose `ref` was already seen in it, keeping the first one.
+///
+/// When the ranking changes while a page is built, the same dataset can be listed twice.
+/// Responses that are not arrays, and results without a `ref`, are returned unchanged.
+pub fn deduplicate_search_results(results: serde_json::Value) -> serde_json::Value {
+    let serde_json::Value::Array(items) = results else {
+        return results;
+    };
+    let mut seen = std::collections::HashSet::new();
+    let items = items
+        .into_iter()
+        .filter(|item| match item.get("ref").and_then(|r| r.as_str()) {
+            Some(r) => seen.insert(r.to_string()),
+            None => true,
+        })
+        .collect();
+    serde_json::Value::Array(items)
+}
+
+/// Searches for datasets on Kaggle, paging with a cursor instead of a page number.
+///
+/// Page numbers can skip or repeat results when the ranking changes between requests, while
+/// a cursor continues where the previous page ended. Pass `None` for the first page, and the
+/// returned cursor for the next one. The cursor is `None` on the last page, and whenever the
+/// API responds without one.
+///
+/// Returns the results of the page and the cursor of the next page.
+pub fn search_datasets_cursor(
+    query: &str,
+    cursor: Option<&str>,
+    page_size: i32,
+) -> Result<(serde_json::Value, Option<String>), GaggleError> {
+    let filters: Vec<(&str, &str)> = cursor.map(|c| ("cursor", c)).into_iter().collect();
+    let json = list_datasets(Some(query), &filters, 1, page_size)?;
+    Ok(split_cursor_page(json))
+}
+
+/// Splits a `datasets/list` response into its results and the cursor of the next page.
+///
+/// A plain array holds the results of the last page. A paged response is an object with the
+/// results under `datasets` or `results` and the cursor under `nextPageToken` or `cursor`.
+fn split_cursor_page(json: serde_json::Value) -> (serde_json::Value, Option<String>) {
+    let serde_json::Value::Object(mut body) = json else {
+        return (json, None);
+    };
+    let next_cursor = ["nextPageToken", "cursor"].iter().find_map(|key| {
+        body.get(*key)
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    });
+    let results = ["datasets", "results"]
+        .iter()
+        .find_map(|key| body.remove(*key).filter(|v| v.is_array()))
+        .unwrap_or_else(|| serde_json::Value::Array(Vec::new()));
+    (results, next_cursor)
+}
+
+/// Lists trending datasets on Kaggle (`sortBy=hottest`)
+pub fn list_trending_datasets(page: i32, page_size: i32) -> Result<serde_json::Value, GaggleError> {
+    list_datasets(None, &[("sortBy", "hottest")], page, page_size)
+}
+
+/// Lists the most recently published datasets on Kaggle (`sortBy=published`)
+pub fn list_new_datasets(page: i32, page_size: i32) -> Result<serde_json::Value, GaggleError> {
+    list_datasets(None, &[("sortBy", "published")], page, page_size)
+}
+
+/// Lists the most recently updated datasets on Kaggle (`sortBy=updated`)
+pub fn list_updated_datasets(page: i32, page_size: i32) -> Result<serde_json::Value, GaggleError> {
+    list_datasets(None, &[("sortBy", "updated")], page, page_size)
+}
+
+/// Lists the datasets owned by a Kaggle user or organization (`ownerSlug={owner}`)
+pub fn list_datasets_by_owner(
+    owner: &str,
+    page: i32,
+    page_size: i32,
+) -> Result<serde_json::Value, GaggleError> {
+    let owner = owner.trim();
+    if owner.is_empty() || owner.contains('/') {
+        return Err(GaggleError::InvalidDatasetPath(format!(
+            "Invalid dataset owner: '{}'",
+            owner
+        )));
+    }
+    list_datasets(None, &[("ownerSlug", owner)], page, page_size)
+}
+
+/// Lists the datasets owned by the user of the current credentials
+pub fn list_my_datasets(page: i32, page_size: i32) -> Result<serde_json::Value, GaggleError> {
+    let creds = get_credentials()?;
+    list_datasets_by_owner(&creds.username, page, page_size)
+}
+
+/// Searches for datasets with the given tag (`tagIds={tag}`).
+///
+/// If the tag filter yields no results, the tag name is used as a free-text search
+/// query instead, since the API only matches the filter against tag slugs.
+pub fn search_datasets_by_tag(
+    tag_name: &str,
+    page: i32,
+    page_size: i32,
+) -> Result<serde_json::Value, GaggleError> {
+    let tag_name = tag_name.trim();
+    if tag_name.is_empty() {
+        return Err(GaggleError::InvalidDatasetPath(
+            "Tag name cannot be empty".to_string(),
+        ));
+    }
+    let json = list_datasets(None, &[("tagIds", tag_name)], page, page_size)?;
+    match json.as_array() {
+        Some(items) if items.is_empty() => search_datasets(tag_name, page, page_size),
+        _ => Ok(json),
+    }
+}
+
+/// File types accepted by the `fileType` filter of the Kaggle API
+const KNOWN_FILE_TYPES: &[&str] = &[
+    "csv", "json", "sqlite", "bigquery", "parquet", "xlsx", "zip", "other",
+];
+
+/// Lists the datasets containing files of the given type (`fileType={file_type}`).
+///
+/// `file_type` is matched case-insensitively against the types the API supports:
+/// `csv`, `json`, `sqlite`, `bigquery`, `parquet`, `xlsx`, `zip`, and `other`.
+pub fn search_datasets_by_format(
+    file_type: &str,
+    page: i32,
+    page_size: i32,
+) -> Result<serde_json::Value, GaggleError> {
+    let file_type = file_type.trim().to_ascii_lowercase();
+    if !KNOWN_FILE_TYPES.contains(&file_type.as_str()) {
+        return Err(GaggleError::InvalidDatasetPath(format!(
+            "Unknown file type: {}",
+            file_type
+        )));
+    }
+    list_datasets(None, &[("fileType", &file_type)], page, page_size)
+}
+
+/// Error returned by [`search_all`] when a page request fails part-way through.
+///
+/// It carries the results collected before the failure so that callers can still
+/// use them.
+#[derive(Debug, thiserror::Error)]
+#[error("{source} (after fetching {} results)", .partial.len())]
+pub struct PartialSearchError {
+    /// Results fetched from the pages that succeeded.
+    pub partial: Vec<serde_json::Value>,
+    /// The error that stopped pagination.
+    pub source: GaggleError,
+}
+
+/// Searches for datasets and aggregates results across pages.
+///
+/// Pages are requested with the largest page size allowed by the API (capped by
+/// `max_results`) and go through the same retry and rate-limiting logic as
+/// `search_datasets`. Pagination stops at the first short page or once
+/// `max_results` results have been collected.
+pub fn search_all(
+    query: &str,
+    max_results: usize,
+) -> Result<Vec<serde_json::Value>, PartialSearchError> {
+    let mut results: Vec<serde_json::Value> = Vec::new();
+    if max_results == 0 {
+        return Err(PartialSearchError {
+            partial: results,
+            source: GaggleError::InvalidDatasetPath(
+                "Maximum number of results must be >= 1".to_string(),
+            ),
+        });
+    }
+
+    let page_size = max_results.min(100);
+    let mut page: i32 = 1;
+    while results.len() < max_results {
+        let json = match search_datasets(query, page, page_size as i32) {
+            Ok(v) => v,
+            Err(source) => {
+                return Err(PartialSearchError {
+                    partial: results,
+                    source,
+                })
+            }
+        };
+        let items = match json {
+            serde_json::Value::Array(items) => items,
+            _ => Vec::new(),
+        };
+        let fetched = items.len();
+        let remaining = max_results - results.len();
+        results.extend(items.into_iter().take(remaining));
+        if fetched < page_size {
+            break;
+        }
+        page = match page.checked_add(1) {
+            Some(p) => p,
+            None => break,
+        };
+    }
+    Ok(results)
+}
+
+/// Searches the datasets known locally, without any network access.
+///
+/// Both the datasets in the cache and those with persisted metadata are considered. A dataset
+/// matches if `query` is a case-insensitive substring of its `owner/dataset` path or its
+/// title; an empty query matches everything. Each result has `ref`, `title` (`null` without
+/// persisted metadata), and `cached` (whether any of its files are in the cache), sorted by
+/// `ref`.
+pub fn search_cached(query: &str) -> Result<Vec<serde_json::Value>, GaggleError> {
+    let mut known: std::collections::BTreeMap<String, (Option<String>, bool)> =
+        std::collections::BTreeMap::new();
+    for dataset in super::download::list_cached_datasets()? {
+        known.entry(dataset.path).or_default().1 = true;
+    }
+    for (path, raw) in super::metadata::list_persisted_metadata() {
+        known.entry(path).or_default().0 = super::metadata::DatasetMetadata::from_value(raw).title;
+    }
+
+    let needle = query.trim().to_lowercase();
+    Ok(known
+        .into_iter()
+        .filter(|(path, (title, _))| {
+            path.to_lowercase().contains(&needle)
+                || title
+                    .as_ref()
+                    .is_some_and(|t| t.to_lowercase().contains(&needle))
+        })
+        .map(|(path, (title, cached))| {
+            serde_json::json!({"ref": path, "title": title, "cached": cached})
+        })
+        .collect())
+}
+
+/// Searches for datasets on Kaggle without blocking the calling thread.
+///
+/// This is the async counterpart of [`search_datasets`], sharing its validation and
+/// cache.
+#[cfg(feature = "async")]
+pub async fn search_datasets_async(
+    query: &str,
+    page: i32,
+    page_size: i32,
+) -> Result<serde_json::Value, GaggleError> {
+    let (cache_key, url, creds) = match prepare_list_request(Some(query), &[], page, page_size)? {
+        ListRequest::Cached(json) => return Ok(json),
+        ListRequest::Fetch {
+            cache_key,
+            url,
+            creds,
+        } => (cache_key, url, creds),
+    };
+
+    let client = super::api::get_async_client()?;
+    let response = super::api::with_retries_async(|| {
+        super::api::send_async(
+            client
+                .get(&url)
+                .basic_auth(&creds.username, Some(creds.key_str())),
+        )
+    })
+    .await?;
+    check_list_status(response.status(), true)?;
+
+    let json =
+        super::api::read_json_response_async(response, crate::config::max_response_bytes()).await?;
+    store_search_result(cache_key, json.clone());
+    Ok(if crate::config::search_deduplicate() {
+        deduplicate_search_results(json)
+    } else {
+        json
+    })
+}
+
+/// A validated request to the `datasets/list` endpoint.
+enum ListRequest {
+    /// A fresh result was found in the search cache.
+    Cached(serde_json::Value),
+    /// The result has to be fetched from `url` and stored under `cache_key`.
+    Fetch {
+        cache_key: String,
+        url: String,
+        creds: super::credentials::KaggleCredentials,
+    },
+}
+
+/// Shared implementation for the `datasets/list` endpoint.
+///
+/// Handles offline mode, input validation, caching, credentials, and retries for
+/// free-text search, the sorted listings, and the filtered listings. `filters` are
+/// extra query parameters, such as `("sortBy", "hottest")`.
+fn list_datasets(
+    query: Option<&str>,
+    filters: &[(&str, &str)],
+    page: i32,
+    page_size: i32,
+) -> Result<serde_json::Value, GaggleError> {
+    let (cache_key, url, creds) = match prepare_list_request(query, filters, page, page_size)? {
+        ListRequest::Cached(json) => return Ok(json),
+        ListRequest::Fetch {
+            cache_key,
+            url,
+            creds,
+        } => (cache_key, url, creds),
+    };
+
+    let client = get_client()?;
+    let response = with_retries(|| {
+        send(
+            client
+                .get(&url)
+                .basic_auth(&creds.username, Some(creds.key_str())),
+        )
+    })?;
+    check_list_status(response.status(), query.is_some())?;
+
+    let json = read_json_response(response, crate::config::max_response_bytes())?;
+    store_search_result(cache_key, json.clone());
+    Ok(json)
+}
+
+/// Checks offline mode and the inputs of a `datasets/list` request, then returns either
+/// the cached result or the URL to fetch.
+fn prepare_list_request(
+    query: Option<&str>,
+    filters: &[(&str, &str)],
+    page: i32,
+    page_size: i32,
+) -> Result<ListRequest, GaggleError> {
+    // Strict offline: fail fast (unless recorded responses can be played back)
+    if crate::config::offline_mode() && !super::api::http_playback_enabled() {
         return Err(GaggleError::HttpRequestError(
             "Offline mode enabled; search is disabled. Unset GAGGLE_OFFLINE to enable network."
                 .to_string(),
@@ -39,40 +468,88 @@ pub fn search_datasets(
         )));
     }
 
+    let filters = filters
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let cache_key = search_cache_key(query.unwrap_or(""), page, page_size, &filters);
+    if let Some(val) = cached_search_result(&cache_key) {
+        return Ok(ListRequest::Cached(val));
+    }
+
     let creds = get_credentials()?;
 
-    let url = format!(
-        "{}/datasets/list?search={}&page={}&pageSize={}",
-        get_api_base(),
-        urlencoding::encode(query),
-        page,
-        page_size
-    );
+    let mut url = format!("{}/datasets/list?", get_api_base());
+    if let Some(q) = query {
+        url.push_str(&format!("search={}&", urlencoding::encode(q)));
+    }
+    if !filters.is_empty() {
+        url.push_str(&format!("{}&", filters));
+    }
+    url.push_str(&format!("page={}&pageSize={}", page, page_size));
 
-    let client = build_client()?;
-    let response = with_retries(|| {
-        client
-            .get(&url)
-            .basic_auth(&creds.username, Some(&creds.key))
-            .send()
-            .map_err(|e| GaggleError::HttpRequestError(e.to_string()))
-    })?;
+    Ok(ListRequest::Fetch {
+        cache_key,
+        url,
+        creds,
+    })
+}
 
-    if !response.status().is_success() {
-        return Err(GaggleError::HttpRequestError(format!(
-            "Failed to search datasets: HTTP {}",
-            response.status()
-        )));
+/// Turns an unsuccessful `datasets/list` status into an error.
+fn check_list_status(status: reqwest::StatusCode, is_search: bool) -> Result<(), GaggleError> {
+    if status.is_success() {
+        return Ok(());
     }
-
-    let json: serde_json::Value = response.json()?;
-    Ok(json)
+    Err(GaggleError::HttpRequestError(format!(
+        "Failed to {} datasets: HTTP {}",
+        if is_search { "search" } else { "list" },
+        status
+    )))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_deduplicate_search_results() {
+        let results = serde_json::json!([
+            {"ref": "a/one", "title": "First"},
+            {"ref": "a/two"},
+            {"ref": "a/one", "title": "Repeated"},
+            {"title": "No ref"},
+        ]);
+        let deduped = deduplicate_search_results(results.clone());
+        assert_eq!(
+            deduped.as_array().unwrap().len(),
+            results.as_array().unwrap().len() - 1
+        );
+        assert_eq!(deduped[0]["title"], "First");
+        assert_eq!(deduped[2]["title"], "No ref");
+
+        let object = serde_json::json!({"datasets": []});
+        assert_eq!(deduplicate_search_results(object.clone()), object);
+    }
+
+    #[test]
+    fn test_split_cursor_page() {
+        let (results, next) = split_cursor_page(serde_json::json!([{"ref": "a/b"}]));
+        assert_eq!(results[0]["ref"], "a/b");
+        assert_eq!(next, None);
+
+        let (results, next) = split_cursor_page(serde_json::json!({
+            "datasets": [{"ref": "a/b"}],
+            "nextPageToken": "tok"
+        }));
+        assert_eq!(results.as_array().map(Vec::len), Some(1));
+        assert_eq!(next.as_deref(), Some("tok"));
+
+        let (results, next) = split_cursor_page(serde_json::json!({"results": [], "cursor": ""}));
+        assert_eq!(results, serde_json::json!([]));
+        assert_eq!(next, None);
+    }
+
     #[test]
     fn test_search_datasets_validates_page() {
         // Mock credentials to avoid actual API calls
@@ -166,6 +643,109 @@ mod tests {
         std::env::remove_var("KAGGLE_KEY");
     }
 
+    #[test]
+    #[serial_test::serial]
+    fn test_search_cache_store_and_invalidate() {
+        clear_search_cache();
+        let key = search_cache_key("cache test", 1, 10, "");
+        store_search_result(key.clone(), serde_json::json!([{"ref": "a/b"}]));
+        assert_eq!(search_cache_size(), 1);
+        assert!(cached_search_result(&key).is_some());
+
+        invalidate_search_cache_entry(&key);
+        assert_eq!(search_cache_size(), 0);
+        assert!(cached_search_result(&key).is_none());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_search_cache_evicts_oldest_when_full() {
+        clear_search_cache();
+        {
+            // Insert directly with distinct timestamps so the oldest entry is well-defined
+            let mut cache = SEARCH_CACHE.write();
+            let now = Instant::now();
+            for i in 0..SEARCH_CACHE_MAX_ENTRIES {
+                let age = Duration::from_millis((SEARCH_CACHE_MAX_ENTRIES - i) as u64);
+                cache.insert(
+                    search_cache_key("q", i as i32 + 1, 10, ""),
+                    (serde_json::json!(i), now - age),
+                );
+            }
+        }
+        assert_eq!(search_cache_size(), SEARCH_CACHE_MAX_ENTRIES);
+
+        store_search_result(
+            search_cache_key("overflow", 1, 10, ""),
+            serde_json::json!([]),
+        );
+        assert_eq!(search_cache_size(), SEARCH_CACHE_MAX_ENTRIES);
+        assert!(cached_search_result(&search_cache_key("q", 1, 10, "")).is_none());
+        assert!(cached_search_result(&search_cache_key("overflow", 1, 10, "")).is_some());
+        clear_search_cache();
+    }
+
+    #[test]
+    fn test_search_cache_key_includes_filters() {
+        let a = search_cache_key("q", 1, 10, "");
+        let b = search_cache_key("q", 1, 10, "sortBy=hottest");
+        assert_ne!(a, b);
+        assert!(a.starts_with("q:1:10:"));
+    }
+
+    #[test]
+    fn test_sorted_listings_validate_pagination() {
+        for f in [
+            list_trending_datasets,
+            list_new_datasets,
+            list_updated_datasets,
+        ] {
+            match f(0, 10) {
+                Err(GaggleError::InvalidDatasetPath(msg)) => {
+                    assert!(msg.contains("Page number must be >= 1"))
+                }
+                other => panic!("Expected validation error, got {:?}", other),
+            }
+            match f(1, 101) {
+                Err(GaggleError::InvalidDatasetPath(msg)) => {
+                    assert!(msg.contains("Page size must be between 1 and 100"))
+                }
+                other => panic!("Expected validation error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_datasets_by_tag_rejects_empty_tag() {
+        for tag in ["", "   "] {
+            match search_datasets_by_tag(tag, 1, 10) {
+                Err(GaggleError::InvalidDatasetPath(msg)) => {
+                    assert!(msg.contains("Tag name cannot be empty"))
+                }
+                other => panic!("Expected validation error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_datasets_by_format_rejects_unknown_type() {
+        for file_type in ["", "tsv", "csv;drop", "parquet&x=1"] {
+            match search_datasets_by_format(file_type, 1, 10) {
+                Err(GaggleError::InvalidDatasetPath(msg)) => {
+                    assert!(msg.starts_with("Unknown file type:"), "{}", msg)
+                }
+                other => panic!("Expected validation error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_all_rejects_zero_max_results() {
+        let err = search_all("query", 0).unwrap_err();
+        assert!(err.partial.is_empty());
+        assert!(matches!(err.source, GaggleError::InvalidDatasetPath(_)));
+    }
+
     #[test]
     fn test_search_datasets_url_encoding() {
         std::env::set_var("KAGGLE_USERNAME", "test");
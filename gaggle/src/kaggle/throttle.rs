@@ -0,0 +1,189 @@
+// throttle.rs
+//
+// This module limits the bandwidth used by downloads. A single token bucket is shared by
+// every download in the process, so concurrent downloads together stay under the limit.
+// The limit comes from GAGGLE_MAX_BANDWIDTH_MBPS, or from `set_bandwidth_limit`, which
+// takes precedence over the environment. The token bucket is also used by `api.rs` to
+// limit the rate of API calls.
+
+use super::metrics::Counter;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// How much unused bandwidth can be saved up for a burst, as a duration at the full rate
+const BURST: Duration = Duration::from_millis(100);
+
+/// A token bucket holding bytes or API calls. It starts full, refills at `rate_per_sec`, and
+/// holds at most `capacity` tokens. Taking more than it holds puts it in debt, which the
+/// caller waits off.
+pub(crate) struct TokenBucket {
+    rate_per_sec: f64,
+    capacity: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(rate_per_sec: f64, capacity: f64) -> Self {
+        Self {
+            rate_per_sec,
+            capacity,
+            tokens: capacity,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes `cost` tokens and returns how long to wait before using them.
+    pub(crate) fn take(&mut self, cost: f64) -> Duration {
+        self.take_at(cost, Instant::now())
+    }
+
+    /// Takes `cost` tokens at time `now` and returns how long to wait before using them.
+    pub(crate) fn take_at(&mut self, cost: f64, now: Instant) -> Duration {
+        let now = now.max(self.refilled_at);
+        let refill = now.duration_since(self.refilled_at).as_secs_f64() * self.rate_per_sec;
+        self.tokens = (self.tokens + refill).min(self.capacity) - cost;
+        self.refilled_at = now;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate_per_sec)
+        }
+    }
+}
+
+/// Takes `cost` tokens from the bucket in `shared`, and returns how long to wait before using
+/// them. The bucket is rebuilt when `rate_per_sec` or `capacity` has changed since it was made.
+pub(crate) fn take_shared(
+    shared: &Mutex<Option<TokenBucket>>,
+    rate_per_sec: f64,
+    capacity: f64,
+    cost: f64,
+) -> Duration {
+    let mut bucket = shared.lock();
+    let bucket = match bucket.as_mut() {
+        Some(b) if b.rate_per_sec == rate_per_sec && b.capacity == capacity => b,
+        _ => bucket.insert(TokenBucket::new(rate_per_sec, capacity)),
+    };
+    bucket.take(cost)
+}
+
+/// The bucket shared by all downloads, rebuilt when the limit changes
+static BUCKET: Lazy<Mutex<Option<TokenBucket>>> = Lazy::new(|| Mutex::new(None));
+
+/// Limit set with [`set_bandwidth_limit`]. `Some(None)` means unlimited regardless of the
+/// environment, and `None` defers to GAGGLE_MAX_BANDWIDTH_MBPS.
+static RUNTIME_LIMIT: Lazy<RwLock<Option<Option<f64>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Sets the download bandwidth limit for this process, in megabits per second.
+///
+/// `Some(mbps)` with a positive value sets the limit, `Some(0.0)` removes it, and `None`
+/// reverts to GAGGLE_MAX_BANDWIDTH_MBPS.
+pub fn set_bandwidth_limit(mbps: Option<f64>) {
+    *RUNTIME_LIMIT.write() = mbps.map(|v| (v.is_finite() && v > 0.0).then_some(v));
+}
+
+/// The current bandwidth limit in bytes per second, if any
+pub(crate) fn limit_bytes_per_sec() -> Option<f64> {
+    let mbps = match *RUNTIME_LIMIT.read() {
+        Some(limit) => limit,
+        None => crate::config::max_bandwidth_mbps(),
+    };
+    // 1 Mbps is 125,000 bytes per second
+    mbps.map(|v| v * 125_000.0)
+}
+
+/// Accounts for `bytes` downloaded under `limit` (in bytes per second) and returns how long
+/// to wait before continuing.
+pub(crate) fn reserve(limit: Option<f64>, bytes: usize) -> Duration {
+    let Some(bytes_per_sec) = limit else {
+        return Duration::ZERO;
+    };
+    let capacity = bytes_per_sec * BURST.as_secs_f64();
+    take_shared(&BUCKET, bytes_per_sec, capacity, bytes as f64)
+}
+
+/// A writer that holds downloads to the bandwidth limit in effect when it was created.
+pub(crate) struct ThrottledWriter<W> {
+    inner: W,
+    limit: Option<f64>,
+}
+
+impl<W: Write> ThrottledWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            limit: limit_bytes_per_sec(),
+        }
+    }
+}
+
+impl<W: Write> Write for ThrottledWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        super::metrics::add(Counter::BytesDownloaded, written as u64);
+        let wait = reserve(self.limit, written);
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    fn test_token_bucket_allows_a_burst_then_waits() {
+        // 1,000 bytes per second with a burst of 100 bytes
+        let mut bucket = TokenBucket::new(1000.0, 100.0);
+        let start = bucket.refilled_at;
+        assert_eq!(bucket.take_at(100.0, start), Duration::ZERO);
+        assert_eq!(bucket.take_at(500.0, start), Duration::from_millis(500));
+        // Callers in debt wait for each other
+        assert_eq!(bucket.take_at(1.0, start), Duration::from_millis(501));
+        // Tokens refill over time, up to the capacity
+        let later = start + Duration::from_secs(10);
+        assert_eq!(bucket.take_at(100.0, later), Duration::ZERO);
+        assert_eq!(bucket.take_at(100.0, later), Duration::from_millis(100));
+    }
+
+    #[test]
+    #[serial]
+    fn test_reserve_is_shared_across_downloads() {
+        *BUCKET.lock() = None;
+        // Two downloads taking 1,000 bytes each at 10,000 bytes per second wait for each other
+        let first = reserve(Some(10_000.0), 1000);
+        let second = reserve(Some(10_000.0), 1000);
+        assert_eq!(first, Duration::ZERO);
+        assert!(second > Duration::from_millis(90) && second <= Duration::from_millis(100));
+        assert_eq!(reserve(None, 1000), Duration::ZERO);
+    }
+
+    #[test]
+    #[serial]
+    fn test_set_bandwidth_limit_overrides_environment() {
+        std::env::set_var("GAGGLE_MAX_BANDWIDTH_MBPS", "8");
+        set_bandwidth_limit(None);
+        assert_eq!(limit_bytes_per_sec(), Some(1_000_000.0));
+
+        set_bandwidth_limit(Some(16.0));
+        assert_eq!(limit_bytes_per_sec(), Some(2_000_000.0));
+
+        // Zero removes the limit, even when the environment sets one
+        set_bandwidth_limit(Some(0.0));
+        assert_eq!(limit_bytes_per_sec(), None);
+
+        set_bandwidth_limit(None);
+        std::env::remove_var("GAGGLE_MAX_BANDWIDTH_MBPS");
+        assert_eq!(limit_bytes_per_sec(), None);
+    }
+}
@@ -0,0 +1,301 @@
+// upload.rs
+//
+// This module provides functionality for publishing datasets to Kaggle. It packs
+// the given local files into an in-memory ZIP archive, registers the dataset (or a
+// new version of an existing dataset) through the Kaggle API, and then uploads the
+// archive to the blob URL returned by the API.
+
+use crate::error::{sanitize_message, GaggleError};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use super::api::{
+    get_api_base, get_client, rate_limit_wait, read_json_response, send, with_retries,
+};
+use super::credentials::get_credentials;
+use tracing::debug;
+
+/// Creates a new dataset on Kaggle from the given local files.
+///
+/// # Arguments
+///
+/// * `owner` - The Kaggle user or organization that will own the dataset.
+/// * `title` - The human-readable title of the dataset.
+/// * `files` - Local files to include; each is stored at the archive root under its file name.
+/// * `license` - The license name, for example `"CC0-1.0"`.
+/// * `is_private` - Whether the dataset should be private.
+///
+/// # Returns
+///
+/// The JSON response of the dataset creation request.
+pub fn create_dataset(
+    owner: &str,
+    title: &str,
+    files: &[&Path],
+    license: &str,
+    is_private: bool,
+) -> Result<serde_json::Value, GaggleError> {
+    ensure_online("create a dataset")?;
+
+    let owner = owner.trim();
+    let title = title.trim();
+    if owner.is_empty() || owner.contains('/') {
+        return Err(GaggleError::InvalidDatasetPath(format!(
+            "Invalid dataset owner: '{}'",
+            owner
+        )));
+    }
+    if title.is_empty() {
+        return Err(GaggleError::InvalidDatasetPath(
+            "Dataset title cannot be empty".to_string(),
+        ));
+    }
+
+    let slug = slugify(title);
+    if slug.is_empty() {
+        return Err(GaggleError::InvalidDatasetPath(format!(
+            "Dataset title '{}' has no ASCII letters or digits to derive a slug from",
+            title
+        )));
+    }
+
+    let archive = zip_files(files)?;
+    let body = serde_json::json!({
+        "ownerSlug": owner,
+        "slug": slug,
+        "title": title,
+        "licenseName": license,
+        "isPrivate": is_private,
+    });
+    let url = format!("{}/datasets", get_api_base());
+    register_and_upload(&url, &body, archive)
+}
+
+/// Uploads the given local files as a new version of an existing dataset.
+///
+/// # Returns
+///
+/// The JSON response of the version creation request.
+pub fn update_dataset_files(
+    dataset_path: &str,
+    files: &[&Path],
+) -> Result<serde_json::Value, GaggleError> {
+    ensure_online("update dataset files")?;
+
+    let (owner, dataset) = super::parse_dataset_path(dataset_path)?;
+    let archive = zip_files(files)?;
+    let body = serde_json::json!({
+        "versionNotes": format!("Updated {} file(s) with Gaggle", files.len()),
+    });
+    let url = format!(
+        "{}/datasets/{}/versions",
+        get_api_base(),
+        super::encode_dataset_segments(&owner, &dataset)
+    );
+    register_and_upload(&url, &body, archive)
+}
+
+fn ensure_online(action: &str) -> Result<(), GaggleError> {
+    if crate::config::offline_mode() {
+        return Err(GaggleError::HttpRequestError(format!(
+            "Offline mode enabled; cannot {}. Unset GAGGLE_OFFLINE to enable network.",
+            action
+        )));
+    }
+    Ok(())
+}
+
+/// Posts the metadata request, then uploads the archive to the returned blob URL.
+///
+/// The metadata request is sent once. Retrying it after a timeout could create the dataset
+/// or version twice, since the server may have handled the first request.
+fn register_and_upload(
+    url: &str,
+    body: &serde_json::Value,
+    archive: Vec<u8>,
+) -> Result<serde_json::Value, GaggleError> {
+    let creds = get_credentials()?;
+    let client = get_client()?;
+
+    debug!(url = %sanitize_message(url), "registering dataset upload");
+    rate_limit_wait();
+    let response = send(
+        client
+            .post(url)
+            .basic_auth(&creds.username, Some(creds.key_str()))
+            .json(body),
+    )?;
+
+    if !response.status().is_success() {
+        return Err(GaggleError::HttpRequestError(format!(
+            "Failed to register dataset upload: HTTP {}",
+            response.status()
+        )));
+    }
+
+    let json = read_json_response(response, crate::config::max_response_bytes())?;
+    let upload_url = json
+        .get("uploadUrl")
+        .or_else(|| json.get("blobUrl"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            GaggleError::HttpRequestError("Upload response did not include a blob URL".to_string())
+        })?
+        .to_string();
+
+    debug!(url = %sanitize_message(&upload_url), bytes = archive.len(), "uploading dataset archive");
+    let response = with_retries(|| {
+        send(
+            client
+                .put(&upload_url)
+                .header(reqwest::header::CONTENT_TYPE, "application/zip")
+                .body(archive.clone()),
+        )
+    })?;
+
+    if !response.status().is_success() {
+        return Err(GaggleError::HttpRequestError(format!(
+            "Failed to upload dataset archive: HTTP {}",
+            response.status()
+        )));
+    }
+
+    Ok(json)
+}
+
+/// Packs the given files into an in-memory ZIP archive.
+///
+/// Each file is stored under its file name; duplicate names are rejected.
+pub(crate) fn zip_files(files: &[&Path]) -> Result<Vec<u8>, GaggleError> {
+    if files.is_empty() {
+        return Err(GaggleError::IoError(
+            "no files provided for upload".to_string(),
+        ));
+    }
+
+    let mut buf: Vec<u8> = Vec::new();
+    let mut seen = HashSet::new();
+    {
+        let mut zip = zip::ZipWriter::new(Cursor::new(&mut buf));
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for path in files {
+            if !path.is_file() {
+                return Err(GaggleError::IoError(format!(
+                    "Not a regular file: {}",
+                    path.display()
+                )));
+            }
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .ok_or_else(|| {
+                    GaggleError::IoError(format!("Invalid file name: {}", path.display()))
+                })?;
+            if !seen.insert(name.clone()) {
+                return Err(GaggleError::IoError(format!(
+                    "Duplicate file name in upload: {}",
+                    name
+                )));
+            }
+            let content = fs::read(path)?;
+            zip.start_file(name, options)
+                .map_err(|e| GaggleError::ZipError(e.to_string()))?;
+            zip.write_all(&content)?;
+        }
+        zip.finish()
+            .map_err(|e| GaggleError::ZipError(e.to_string()))?;
+    }
+    Ok(buf)
+}
+
+/// Derives a URL slug from a dataset title.
+fn slugify(title: &str) -> String {
+    let mut slug = String::with_capacity(title.len());
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("My Cool Dataset"), "my-cool-dataset");
+        assert_eq!(slugify("  Sales (2024) -- v2 "), "sales-2024-v2");
+        assert_eq!(slugify("日本語"), "");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_create_dataset_rejects_title_without_slug() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let data = temp.path().join("data.csv");
+        fs::write(&data, b"a\n1\n").unwrap();
+        for title in ["日本語", "!!!"] {
+            match create_dataset("owner", title, &[data.as_path()], "CC0-1.0", true) {
+                Err(GaggleError::InvalidDatasetPath(msg)) => assert!(msg.contains("slug")),
+                other => panic!(
+                    "expected InvalidDatasetPath for {:?}, got {:?}",
+                    title, other
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_zip_files_roundtrip() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let a = temp.path().join("a.csv");
+        let b = temp.path().join("b.txt");
+        fs::write(&a, b"x,y\n1,2\n").unwrap();
+        fs::write(&b, b"hello").unwrap();
+
+        let bytes = zip_files(&[a.as_path(), b.as_path()]).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("a.csv").unwrap(), &mut content)
+            .unwrap();
+        assert_eq!(content, "x,y\n1,2\n");
+    }
+
+    #[test]
+    fn test_zip_files_rejects_empty_and_duplicates() {
+        assert!(zip_files(&[]).is_err());
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let sub = temp.path().join("sub");
+        fs::create_dir_all(&sub).unwrap();
+        let a = temp.path().join("a.csv");
+        let a2 = sub.join("a.csv");
+        fs::write(&a, b"1").unwrap();
+        fs::write(&a2, b"2").unwrap();
+        let err = zip_files(&[a.as_path(), a2.as_path()]).unwrap_err();
+        assert!(err.to_string().contains("Duplicate"));
+
+        // Directories are not accepted
+        assert!(zip_files(&[sub.as_path()]).is_err());
+    }
+
+    #[test]
+    fn test_create_dataset_validates_inputs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let a = temp.path().join("a.csv");
+        fs::write(&a, b"1").unwrap();
+
+        let result = create_dataset("", "Title", &[a.as_path()], "CC0-1.0", false);
+        assert!(matches!(result, Err(GaggleError::InvalidDatasetPath(_))));
+        let result = create_dataset("owner", "  ", &[a.as_path()], "CC0-1.0", false);
+        assert!(matches!(result, Err(GaggleError::InvalidDatasetPath(_))));
+    }
+}
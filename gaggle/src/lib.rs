@@ -1,40 +1,154 @@
 mod config;
 mod error;
 mod ffi;
+mod ffi_log;
 mod kaggle;
+mod log_file;
 mod utils;
 
-pub use error::{gaggle_clear_last_error, gaggle_last_error};
+pub use error::{
+    gaggle_clear_last_error, gaggle_last_error, gaggle_last_error_code, gaggle_last_error_json,
+};
 pub use ffi::{
-    gaggle_clear_cache, gaggle_dataset_version_info, gaggle_download_dataset,
-    gaggle_enforce_cache_limit, gaggle_free, gaggle_get_cache_info, gaggle_get_dataset_info,
-    gaggle_get_file_path, gaggle_get_version, gaggle_is_dataset_current, gaggle_json_each,
-    gaggle_list_files, gaggle_prefetch_files, gaggle_search, gaggle_set_credentials,
-    gaggle_update_dataset,
+    gaggle_check_dataset, gaggle_cleanup_staging, gaggle_clear_cache, gaggle_clear_credentials,
+    gaggle_clear_dataset_cache, gaggle_clear_dataset_cache_force, gaggle_clear_http_record,
+    gaggle_clear_log_callback, gaggle_clear_search_cache, gaggle_copy_dataset_to,
+    gaggle_copy_file_to, gaggle_create_dataset, gaggle_dataset_readme, gaggle_dataset_ref,
+    gaggle_dataset_subpath, gaggle_dataset_version_info, gaggle_detect_file_type,
+    gaggle_download_dataset, gaggle_download_dataset_ex, gaggle_download_dataset_filtered,
+    gaggle_download_dataset_to, gaggle_download_if_stale, gaggle_enforce_cache_limit,
+    gaggle_evict_datasets_older_than, gaggle_export_cache, gaggle_export_dataset,
+    gaggle_flush_logs, gaggle_free, gaggle_get_build_info, gaggle_get_cache_info,
+    gaggle_get_dataset_cache_size_mb, gaggle_get_dataset_info, gaggle_get_dataset_license,
+    gaggle_get_dataset_tags, gaggle_get_file_path, gaggle_get_stats,
+    gaggle_get_total_cache_size_mb, gaggle_get_version, gaggle_glob, gaggle_guess_reader,
+    gaggle_health_check, gaggle_import_cache, gaggle_import_dataset, gaggle_infer_schema,
+    gaggle_is_cache_state_file, gaggle_is_dataset_current, gaggle_is_dry_run, gaggle_json_each,
+    gaggle_json_each_path, gaggle_json_each_recursive, gaggle_json_flatten, gaggle_json_tree,
+    gaggle_list_datasets_by_owner, gaggle_list_files, gaggle_list_files_by_format,
+    gaggle_list_http_records, gaggle_list_my_datasets, gaggle_list_new_datasets,
+    gaggle_list_popular_tags, gaggle_list_trending_datasets, gaggle_list_updated_datasets,
+    gaggle_materialize, gaggle_migrate_cache_case, gaggle_move_cache_dir, gaggle_plan_download,
+    gaggle_prefetch_files, gaggle_refresh_cache_info, gaggle_reinit_logging,
+    gaggle_reset_http_client, gaggle_reset_stats, gaggle_search, gaggle_search_all,
+    gaggle_search_by_format, gaggle_search_by_tag, gaggle_search_cached, gaggle_search_cursor,
+    gaggle_search_streaming, gaggle_set_api_base, gaggle_set_bandwidth_limit,
+    gaggle_set_credentials, gaggle_set_credentials_strict, gaggle_set_log_callback,
+    gaggle_set_log_level, gaggle_sniff_csv, gaggle_update_dataset, gaggle_update_dataset_files,
+    gaggle_validate_credentials, gaggle_verify_dataset_integrity,
 };
+pub use kaggle::normalize_dataset_ref;
 pub use kaggle::parse_dataset_path;
+pub use kaggle::parse_dataset_path_from_url;
+pub use kaggle::parse_dataset_path_full;
 pub use kaggle::parse_dataset_path_with_version;
 
-use once_cell::sync::OnceCell;
+pub use ffi::GaggleLogFn;
+
+// Native async API for Rust callers, behind the `async` feature
+#[cfg(feature = "async")]
+pub use error::GaggleError;
+#[cfg(feature = "async")]
+pub use kaggle::{
+    download_dataset_async, get_dataset_file_path_async, get_dataset_metadata_async,
+    search_datasets_async,
+};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use std::io::IsTerminal;
-use tracing_subscriber::{fmt, EnvFilter};
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
 
-static LOG_INIT: OnceCell<()> = OnceCell::new();
+/// Handle to the level filter of the installed subscriber, or `None` before logging is initialized
+static LOG_STATE: Lazy<Mutex<Option<reload::Handle<EnvFilter, Registry>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// Builds the level filter from `GAGGLE_LOG_LEVEL`, defaulting to `WARN`.
+fn env_log_filter() -> EnvFilter {
+    let level = std::env::var("GAGGLE_LOG_LEVEL").unwrap_or_else(|_| "WARN".to_string());
+    EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("WARN"))
+}
+
+/// Installs the global subscriber and returns the handle used to change its level later.
+fn install_subscriber() -> reload::Handle<EnvFilter, Registry> {
+    let (filter, handle) = reload::Layer::new(env_log_filter());
+    let console_layer = fmt::layer()
+        .with_target(false)
+        .with_level(true)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_filter(filter_fn(|_| {
+            !ffi_log::has_callback() && !log_file::is_enabled()
+        }));
+    let file_layer = fmt::layer()
+        .with_target(false)
+        .with_level(true)
+        .with_ansi(false)
+        .with_writer(log_file::LogFileWriter)
+        .with_filter(filter_fn(|_| {
+            !ffi_log::has_callback() && log_file::is_enabled()
+        }));
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(ffi_log::FfiLogLayer)
+        .with(console_layer)
+        .with(file_layer)
+        .try_init();
+    handle
+}
 
-/// Initializes global logging for Gaggle, governed by the `GAGGLE_LOG_LEVEL`
-/// environment variable.
+/// Initializes global logging for Gaggle, governed by the `GAGGLE_LOG_LEVEL`,
+/// `GAGGLE_LOG_FILE`, and `GAGGLE_LOG_MAX_SIZE_MB` environment variables.
 ///
 /// This function can be safely called multiple times; however, only the first
-/// invocation will have an effect.
+/// invocation will have an effect. Use `gaggle_reinit_logging` to pick up changed
+/// variables later.
+///
+/// Events go to the callback registered with `gaggle_set_log_callback` when there
+/// is one, and to the default formatted logger otherwise. This is checked per event, so a callback can be
+/// registered after logging was initialized. The default logger writes to the log file
+/// when `GAGGLE_LOG_FILE` is set, and to the console otherwise.
 pub fn init_logging() {
-    let _ = LOG_INIT.get_or_init(|| {
-        let level = std::env::var("GAGGLE_LOG_LEVEL").unwrap_or_else(|_| "WARN".to_string());
-        let filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("WARN"));
-        fmt()
-            .with_env_filter(filter)
-            .with_target(false)
-            .with_level(true)
-            .with_ansi(std::io::stderr().is_terminal())
-            .init();
-    });
+    let mut state = LOG_STATE.lock();
+    if state.is_some() {
+        return;
+    }
+    *state = Some(install_subscriber());
+    drop(state);
+    if let Err(e) = log_file::configure_from_env() {
+        tracing::warn!(error = %e, "cannot open log file; logging to the console instead");
+    }
+}
+
+/// Changes the log level for this process, accepting the same values as `GAGGLE_LOG_LEVEL`.
+///
+/// Logging is initialized if it was not already. The level lasts until it is changed again,
+/// or until `reinit_logging` reads `GAGGLE_LOG_LEVEL` again.
+pub(crate) fn set_log_level(level: &str) -> Result<(), error::GaggleError> {
+    let filter = EnvFilter::try_new(level.trim()).map_err(|e| {
+        error::GaggleError::IoError(format!("Invalid log level '{}': {}", level, e))
+    })?;
+    let mut state = LOG_STATE.lock();
+    let handle = state.get_or_insert_with(install_subscriber);
+    handle
+        .reload(filter)
+        .map_err(|e| error::GaggleError::IoError(format!("Cannot reload the log level: {}", e)))
+}
+
+/// Re-reads the logging environment variables and applies them to the installed subscriber.
+///
+/// Logging is initialized if it was not already. Unlike `init_logging`, a log file that
+/// cannot be opened is reported as an error.
+pub(crate) fn reinit_logging() -> Result<(), error::GaggleError> {
+    let mut state = LOG_STATE.lock();
+    match state.as_ref() {
+        Some(handle) => handle.reload(env_log_filter()).map_err(|e| {
+            error::GaggleError::IoError(format!("Cannot reload the log level: {}", e))
+        })?,
+        None => *state = Some(install_subscriber()),
+    }
+    drop(state);
+    log_file::configure_from_env()
 }
@@ -0,0 +1,216 @@
+//! Writes Gaggle's logs to the file named by `GAGGLE_LOG_FILE`.
+//!
+//! By default, the file rotates daily, and the date is appended to its name. With
+//! `GAGGLE_LOG_MAX_SIZE_MB`, it rotates once it reaches that size instead, keeping a
+//! few numbered backups. Lines are written by a background thread, so logging never
+//! blocks on disk I/O.
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::writer::OptionalWriter;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::error::GaggleError;
+
+/// Number of numbered backups kept by size-based rotation (`gaggle.log.1` is the newest)
+const LOG_FILE_BACKUPS: u32 = 3;
+
+/// The log file currently being written, together with how it was opened.
+struct ActiveLogFile {
+    path: PathBuf,
+    max_size_mb: Option<u64>,
+    writer: NonBlocking,
+    // Dropping the guard waits until the background thread has written all buffered lines
+    _guard: WorkerGuard,
+}
+
+static LOG_FILE: Lazy<RwLock<Option<ActiveLogFile>>> = Lazy::new(|| RwLock::new(None));
+
+/// Returns whether logs are currently written to a file.
+pub(crate) fn is_enabled() -> bool {
+    LOG_FILE.read().is_some()
+}
+
+/// Opens the log file named by `GAGGLE_LOG_FILE`, or stops writing to a file if it is unset.
+///
+/// Any previously opened log file is flushed and closed.
+pub(crate) fn configure_from_env() -> Result<(), GaggleError> {
+    let next = match crate::config::log_file() {
+        Some(path) => Some(open(path, crate::config::log_max_size_mb())?),
+        None => None,
+    };
+    let previous = std::mem::replace(&mut *LOG_FILE.write(), next);
+    drop(previous);
+    Ok(())
+}
+
+/// Writes out all buffered log lines.
+///
+/// The background writer is replaced with a fresh one for the same file, and the old one
+/// is dropped, which blocks until its buffered lines are on disk.
+pub(crate) fn flush() -> Result<(), GaggleError> {
+    let mut active = LOG_FILE.write();
+    let Some(current) = active.as_ref() else {
+        return Ok(());
+    };
+    let fresh = open(current.path.clone(), current.max_size_mb)?;
+    let previous = active.replace(fresh);
+    // Release the lock first, so events logged meanwhile are not blocked by the flush
+    drop(active);
+    drop(previous);
+    Ok(())
+}
+
+/// Opens `path` for appending, with daily rotation or rotation by size.
+fn open(path: PathBuf, max_size_mb: Option<u64>) -> Result<ActiveLogFile, GaggleError> {
+    let file_name = path.file_name().ok_or_else(|| {
+        GaggleError::IoError(format!("Invalid log file path: {}", path.display()))
+    })?;
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    let (writer, guard) = match max_size_mb {
+        Some(mb) => tracing_appender::non_blocking(SizeRotatingFile::open(
+            path.clone(),
+            mb.saturating_mul(1024 * 1024),
+        )?),
+        None => {
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(file_name.to_string_lossy())
+                .build(dir)
+                .map_err(|e| {
+                    GaggleError::IoError(format!("Cannot open log file {}: {}", path.display(), e))
+                })?;
+            tracing_appender::non_blocking(appender)
+        }
+    };
+
+    Ok(ActiveLogFile {
+        path,
+        max_size_mb,
+        writer,
+        _guard: guard,
+    })
+}
+
+/// A `MakeWriter` that writes to the active log file, or discards lines if there is none.
+pub(crate) struct LogFileWriter;
+
+impl<'a> MakeWriter<'a> for LogFileWriter {
+    type Writer = OptionalWriter<NonBlocking>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        match LOG_FILE.read().as_ref() {
+            Some(active) => OptionalWriter::some(active.writer.clone()),
+            None => OptionalWriter::none(),
+        }
+    }
+}
+
+/// A file that is moved to a numbered backup once it would grow past `max_bytes`.
+struct SizeRotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    file: File,
+    written: u64,
+}
+
+impl SizeRotatingFile {
+    fn open(path: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            file,
+            written,
+        })
+    }
+
+    fn backup_path(&self, n: u32) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    /// Shifts the backups up by one, dropping the oldest, and starts a new file.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for n in (1..LOG_FILE_BACKUPS).rev() {
+            let from = self.backup_path(n);
+            if from.exists() {
+                fs::rename(&from, self.backup_path(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.backup_path(1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A single line longer than the limit still goes to a file of its own
+        if self.written > 0 && self.written.saturating_add(buf.len() as u64) > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written = self.written.saturating_add(n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_rotating_file_keeps_bounded_backups() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("gaggle.log");
+        let mut file = SizeRotatingFile::open(path.clone(), 10).unwrap();
+
+        for line in ["line 1\n", "line 2\n", "line 3\n", "line 4\n", "line 5\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "line 5\n");
+        assert_eq!(read(file.backup_path(1)), "line 4\n");
+        assert_eq!(read(file.backup_path(2)), "line 3\n");
+        assert_eq!(read(file.backup_path(3)), "line 2\n");
+        assert!(!file.backup_path(4).exists());
+    }
+
+    #[test]
+    fn test_size_rotating_file_appends_to_existing_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("gaggle.log");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut file = SizeRotatingFile::open(path.clone(), 1024).unwrap();
+        file.write_all(b"new\n").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\nnew\n");
+        assert!(!file.backup_path(1).exists());
+    }
+}
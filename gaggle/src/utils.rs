@@ -1,17 +1,23 @@
+use crate::error::GaggleError;
+use serde::Serialize;
 use std::fs;
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 
 /// Recursively calculates the size of a directory in bytes.
 ///
 /// This function traverses the directory tree from the given path and sums the
-/// sizes of all files. It follows the same semantics as the previous inline
-/// helpers in `ffi.rs` and `download.rs`.
+/// sizes of all files. Symbolic links are followed, so a linked file counts with the
+/// size of its target and a linked directory is traversed. A link that points back
+/// into the tree makes the traversal loop, so use [`calculate_dir_size_no_follow`]
+/// for directories that may contain links.
+#[allow(dead_code)]
 pub fn calculate_dir_size(path: &Path) -> Result<u64, std::io::Error> {
     let mut total = 0u64;
     if path.is_dir() {
         for entry in fs::read_dir(path)? {
             let entry = entry?;
-            let metadata = entry.metadata()?;
+            let metadata = fs::metadata(entry.path())?;
             if metadata.is_dir() {
                 total = total.saturating_add(calculate_dir_size(&entry.path())?);
             } else {
@@ -22,27 +28,800 @@ pub fn calculate_dir_size(path: &Path) -> Result<u64, std::io::Error> {
     Ok(total)
 }
 
-/// Selects the appropriate DuckDB reader function based on the file extension.
+/// Recursively calculates the size of a directory in bytes without following symbolic links.
 ///
-/// The selection is case-insensitive.
-#[allow(dead_code)]
+/// A link inside the tree counts with the size of the link itself rather than its target,
+/// and linked directories are not traversed, so nothing is counted twice and links cannot
+/// make the traversal loop.
+pub fn calculate_dir_size_no_follow(path: &Path) -> Result<u64, std::io::Error> {
+    let mut total = 0u64;
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = fs::symlink_metadata(entry.path())?;
+            if metadata.is_dir() {
+                total = total.saturating_add(calculate_dir_size_no_follow(&entry.path())?);
+            } else {
+                total = total.saturating_add(metadata.len());
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Writes `contents` to `path` atomically.
+///
+/// The data is written to a temporary file next to `path`, synced to disk, and then renamed
+/// over it, so readers never see a partially written file, even if the process is killed
+/// or the machine loses power during the write.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), std::io::Error> {
+    let tmp_path = atomic_tmp_path(path);
+    let result = write_synced(&tmp_path, contents).and_then(|()| replace_file(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Writes a cache marker, such as `.downloaded`, atomically (see [`write_atomic`]).
+pub(crate) fn write_marker_atomic(path: &Path, content: &str) -> Result<(), GaggleError> {
+    write_atomic(path, content.as_bytes()).map_err(GaggleError::from)
+}
+
+/// The temporary file [`write_atomic`] writes before renaming it to `path`. The process ID
+/// keeps concurrent writers in different processes apart.
+fn atomic_tmp_path(path: &Path) -> PathBuf {
+    let mut tmp_name = path.as_os_str().to_os_string();
+    tmp_name.push(format!("{}{}", ATOMIC_TMP_INFIX, std::process::id()));
+    PathBuf::from(tmp_name)
+}
+
+/// Marks the temporary files of [`write_atomic`], which a killed process may leave behind
+pub(crate) const ATOMIC_TMP_INFIX: &str = ".tmp.";
+
+fn write_synced(path: &Path, contents: &[u8]) -> Result<(), std::io::Error> {
+    use std::io::Write;
+
+    let mut writer = std::io::BufWriter::new(fs::File::create(path)?);
+    writer.write_all(contents)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()
+}
+
+/// Renames `from` over `to`. Where renaming over an existing file fails (on some Windows
+/// file systems), the destination is removed first.
+fn replace_file(from: &Path, to: &Path) -> Result<(), std::io::Error> {
+    match fs::rename(from, to) {
+        #[cfg(windows)]
+        Err(_) if to.exists() => {
+            fs::remove_file(to)?;
+            fs::rename(from, to)
+        }
+        result => result,
+    }
+}
+
+/// Moves the directory `from` to `to`, which must not exist. Where renaming fails, as it
+/// does across file systems, the directory is copied and then removed. A failed copy
+/// removes what it copied to `to`.
+pub(crate) fn move_dir(from: &Path, to: &Path) -> Result<(), std::io::Error> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = copy_dir(from, to) {
+        let _ = fs::remove_dir_all(to);
+        return Err(e);
+    }
+    fs::remove_dir_all(from)
+}
+
+/// Recursively copies the directory `from` to `to`. Symlinks are skipped.
+fn copy_dir(from: &Path, to: &Path) -> Result<(), std::io::Error> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 timestamp in UTC, such as
+/// `2024-01-01T00:00:00Z`.
+pub(crate) fn format_rfc3339(secs: u64) -> String {
+    let days = secs / 86400;
+    let rem = secs % 86400;
+
+    // Proleptic Gregorian date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Runs blocking work, such as waiting on a download lock or extracting an archive, on
+/// Tokio's blocking thread pool.
+#[cfg(feature = "async")]
+pub(crate) async fn run_blocking<T, F>(f: F) -> Result<T, GaggleError>
+where
+    F: FnOnce() -> Result<T, GaggleError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| GaggleError::IoError(format!("Blocking task failed: {}", e)))?
+}
+
+/// A DuckDB reader function for a file, with named parameters to pass to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReaderGuess {
+    /// The name of the reader function, such as `read_parquet`.
+    pub reader: &'static str,
+    /// Named parameters for the reader, such as `{"delim": "\t"}` for TSV files.
+    pub options: std::collections::BTreeMap<&'static str, serde_json::Value>,
+}
+
+/// Compression suffixes DuckDB decompresses on the fly, so the reader is chosen by the
+/// extension in front of them
+const TRANSPARENT_COMPRESSION_SUFFIXES: [&str; 2] = [".gz", ".zst"];
+
+/// Selects the DuckDB reader function and its options based on the file extension.
+///
+/// The selection is case-insensitive, and a `.gz` or `.zst` suffix is looked through
+/// (`data.tsv.gz` is read like `data.tsv`). TSV files get a tab delimiter, while `.txt` files
+/// are left to the CSV sniffer. `.arrow`, `.feather`, and `.ipc` map to `read_arrow`, and
+/// `.db`, `.sqlite`, and `.sqlite3` map to `sqlite_scan`, which also needs a table name
+/// (flagged by the `table_required` option). Both need the matching DuckDB extensions.
+/// Anything else is read as CSV.
+pub fn guess_reader(path: &str) -> ReaderGuess {
+    let mut lower = path.to_ascii_lowercase();
+    if let Some(stem) = TRANSPARENT_COMPRESSION_SUFFIXES
+        .iter()
+        .find_map(|suffix| lower.strip_suffix(suffix))
+    {
+        lower = stem.to_string();
+    }
+    let ext = Path::new(&lower)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let mut options = std::collections::BTreeMap::new();
+    let reader = match ext {
+        "parquet" | "parq" => "read_parquet",
+        "json" | "jsonl" | "ndjson" => "read_json_auto",
+        "xlsx" => "read_excel",
+        "arrow" | "feather" | "ipc" => "read_arrow",
+        "db" | "sqlite" | "sqlite3" => {
+            options.insert("table_required", serde_json::Value::Bool(true));
+            "sqlite_scan"
+        }
+        "tsv" | "tab" => {
+            options.insert("delim", serde_json::Value::from("\t"));
+            "read_csv_auto"
+        }
+        _ => "read_csv_auto",
+    };
+    ReaderGuess { reader, options }
+}
+
+/// Selects the appropriate DuckDB reader function based on the file extension, as
+/// [`guess_reader`] does, without the options.
 pub fn guess_reader_for_path(path: &str) -> &'static str {
-    let lower = path.to_ascii_lowercase();
-    if lower.ends_with(".parquet") || lower.ends_with(".parq") {
-        "read_parquet"
-    } else if lower.ends_with(".json") || lower.ends_with(".jsonl") || lower.ends_with(".ndjson") {
-        "read_json_auto"
-    } else if lower.ends_with(".xlsx") {
-        "read_excel"
+    guess_reader(path).reader
+}
+
+/// Number of leading bytes inspected by [`detect_file_type_from_bytes`]
+const MAGIC_PREFIX_LEN: usize = 512;
+
+/// Selects the appropriate DuckDB reader function based on the first bytes of a file.
+///
+/// Recognizes Parquet (`PAR1` at the start or end), Excel workbooks (ZIP archives with
+/// `xl/workbook.xml`), gzip (by the original file name in the header, or the path without
+/// `.gz`), ORC, Avro, Arrow IPC files and streams, JSON, and CSV. ORC, Avro, and Arrow map to
+/// `read_orc`, `read_avro`, and `read_arrow`, which need the matching DuckDB extensions.
+///
+/// Returns an error if the file cannot be read or its type is not recognized.
+pub fn detect_file_type_from_bytes(path: &Path) -> Result<&'static str, GaggleError> {
+    let mut file = fs::File::open(path)?;
+    let mut head = Vec::with_capacity(MAGIC_PREFIX_LEN);
+    (&mut file)
+        .take(MAGIC_PREFIX_LEN as u64)
+        .read_to_end(&mut head)?;
+
+    if head.starts_with(b"PAR1") {
+        return Ok("read_parquet");
+    }
+    if head.starts_with(b"PK\x03\x04") {
+        let is_workbook = zip::ZipArchive::new(fs::File::open(path)?)
+            .map(|mut archive| archive.by_name("xl/workbook.xml").is_ok())
+            .unwrap_or(false);
+        return if is_workbook {
+            Ok("read_excel")
+        } else {
+            Err(GaggleError::InvalidDatasetPath(format!(
+                "{} is a ZIP archive, not a data file",
+                path.display()
+            )))
+        };
+    }
+    if head.starts_with(&[0x1f, 0x8b]) {
+        let inner = gzip_original_name(&head).unwrap_or_else(|| {
+            let outer = path.to_string_lossy();
+            let lower = outer.to_ascii_lowercase();
+            match lower.strip_suffix(".gz") {
+                Some(stem) => stem.to_string(),
+                None => lower,
+            }
+        });
+        return Ok(guess_reader_for_path(&inner));
+    }
+    if head.starts_with(b"ORC") {
+        return Ok("read_orc");
+    }
+    if head.starts_with(b"Obj\x01") {
+        return Ok("read_avro");
+    }
+    // Arrow IPC files start with `ARROW1`; streams start with a 0xFFFFFFFF continuation marker
+    if head.starts_with(b"ARROW1") || head.starts_with(&[0xff, 0xff, 0xff, 0xff]) {
+        return Ok("read_arrow");
+    }
+
+    // Parquet files always end with the magic bytes as well
+    let len = file.metadata()?.len();
+    if len >= 8 {
+        let mut tail = [0u8; 4];
+        file.seek(SeekFrom::End(-4))?;
+        file.read_exact(&mut tail)?;
+        if &tail == b"PAR1" {
+            return Ok("read_parquet");
+        }
+    }
+
+    if let Some(reader) = guess_reader_for_text(&head) {
+        return Ok(reader);
+    }
+    Err(GaggleError::InvalidDatasetPath(format!(
+        "Cannot detect the file type of {}",
+        path.display()
+    )))
+}
+
+/// Returns the original file name stored in a gzip header, if there is one.
+fn gzip_original_name(head: &[u8]) -> Option<String> {
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    let flags = *head.get(3)?;
+    if flags & FNAME == 0 {
+        return None;
+    }
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        let xlen = u16::from_le_bytes([*head.get(10)?, *head.get(11)?]) as usize;
+        pos = 12 + xlen;
+    }
+    let name = head.get(pos..)?;
+    let end = name.iter().position(|b| *b == 0)?;
+    String::from_utf8(name[..end].to_vec())
+        .ok()
+        .filter(|n| !n.is_empty())
+}
+
+/// Guesses the reader for text data: JSON if it starts with `{` or `[`, and CSV if the
+/// first line contains a comma, tab, semicolon, or pipe.
+fn guess_reader_for_text(head: &[u8]) -> Option<&'static str> {
+    if head.contains(&0) {
+        return None;
+    }
+    // The prefix may end in the middle of a multi-byte character
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with('{') || text.starts_with('[') {
+        return Some("read_json_auto");
+    }
+    let first_line = text.lines().next()?;
+    if first_line.contains([',', '\t', ';', '|']) {
+        return Some("read_csv_auto");
+    }
+    None
+}
+
+/// Selects the appropriate DuckDB reader function for a file, based on its contents if
+/// they are recognized and on its extension otherwise.
+#[allow(dead_code)]
+pub fn guess_reader_for_file(path: &Path) -> &'static str {
+    detect_file_type_from_bytes(path)
+        .unwrap_or_else(|_| guess_reader_for_path(&path.to_string_lossy()))
+}
+
+/// Number of rows [`sniff_csv`] samples when no count is given
+pub const DEFAULT_SNIFF_ROWS: usize = 1000;
+
+/// Number of bytes from the start of a file used to choose the delimiter
+const DELIMITER_SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// Number of malformed rows whose errors are reported individually
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// The type of a CSV column, as inferred by [`sniff_csv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvType {
+    Int,
+    Float,
+    Bool,
+    Date,
+    String,
+}
+
+impl CsvType {
+    /// Infers the type of a single non-empty value.
+    fn of(value: &str) -> Self {
+        if value.parse::<i64>().is_ok() {
+            CsvType::Int
+        } else if value.parse::<f64>().is_ok() {
+            CsvType::Float
+        } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+            CsvType::Bool
+        } else if is_iso_date(value) {
+            CsvType::Date
+        } else {
+            CsvType::String
+        }
+    }
+
+    /// The type of a column holding values of both types.
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (CsvType::Int, CsvType::Float) | (CsvType::Float, CsvType::Int) => CsvType::Float,
+            _ => CsvType::String,
+        }
+    }
+}
+
+/// Whether `value` is a date in the `YYYY-MM-DD` format
+fn is_iso_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return false;
+    }
+    let digits = |range: std::ops::Range<usize>| -> Option<u32> {
+        let part = &value[range];
+        part.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| part.parse().ok())
+            .flatten()
+    };
+    matches!(
+        (digits(0..4), digits(5..7), digits(8..10)),
+        (Some(_), Some(1..=12), Some(1..=31))
+    )
+}
+
+/// A column of a CSV file, as inferred by [`sniff_csv`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CsvColumn {
+    /// The name from the header, or `column0`, `column1`, and so on without one.
+    pub name: String,
+    /// The type inferred from the sampled values. Empty values are ignored, and a column
+    /// without any values is a string column.
+    #[serde(rename = "type")]
+    pub ty: CsvType,
+}
+
+/// A sampled row that could not be used, as reported by [`sniff_csv`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CsvRowError {
+    /// The line the row starts on, counting from 1.
+    pub line: u64,
+    /// Why the row could not be used.
+    pub error: String,
+}
+
+/// The schema of a CSV file, as sniffed by [`sniff_csv`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CsvSchema {
+    /// The field delimiter.
+    pub delimiter: String,
+    /// Whether the first row is a header.
+    pub has_header: bool,
+    /// The columns, in order.
+    pub columns: Vec<CsvColumn>,
+    /// The number of data rows sampled, not counting the header.
+    pub sampled_rows: usize,
+    /// The number of data rows in the file. It is exact if the whole file was sampled, and
+    /// estimated from the average size of the sampled rows otherwise.
+    pub estimated_rows: u64,
+    /// Whether `estimated_rows` is exact.
+    pub exact_row_count: bool,
+    /// The number of sampled rows that were skipped because they could not be decoded or
+    /// had a different number of fields than the first row.
+    pub malformed_rows: usize,
+    /// The first few malformed rows.
+    pub errors: Vec<CsvRowError>,
+}
+
+/// Picks the delimiter among `,`, tab, `;`, and `|` that splits the start of a file into
+/// the most rows with the same number of fields. Ties go to the earlier delimiter.
+pub(crate) fn detect_delimiter(path: &Path) -> Result<u8, GaggleError> {
+    let mut head = Vec::new();
+    fs::File::open(path)?
+        .take(DELIMITER_SAMPLE_BYTES)
+        .read_to_end(&mut head)?;
+
+    let mut best = (b',', 0usize);
+    for delimiter in [b',', b'\t', b';', b'|'] {
+        let mut counts: std::collections::HashMap<usize, usize> = Default::default();
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(head.as_slice());
+        for record in reader.byte_records().take(50).flatten() {
+            if record.len() > 1 {
+                *counts.entry(record.len()).or_default() += 1;
+            }
+        }
+        let score = counts.values().copied().max().unwrap_or(0);
+        if score > best.1 {
+            best = (delimiter, score);
+        }
+    }
+    Ok(best.0)
+}
+
+/// Sniffs the delimiter, header, column names, and column types of a CSV file from its
+/// first `sample_rows` data rows.
+///
+/// The first row is taken as a header when none of its fields are empty and all of them
+/// are strings. Rows that cannot be decoded as UTF-8 or have a different number of fields
+/// than the first row are counted and reported instead of failing the sniff, unless more
+/// than half of the sampled rows are malformed.
+pub fn sniff_csv(path: &Path, sample_rows: usize) -> Result<CsvSchema, GaggleError> {
+    let delimiter = detect_delimiter(path)?;
+    let file_len = fs::metadata(path)?.len();
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| GaggleError::CsvError(format!("{}: {}", path.display(), e)))?;
+
+    let mut first: Option<Vec<String>> = None;
+    let mut header_end = 0u64;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut malformed = 0usize;
+    let mut errors = Vec::new();
+    let mut exhausted = true;
+    let mut record = csv::StringRecord::new();
+    loop {
+        if first.is_some() && rows.len() + malformed >= sample_rows {
+            exhausted = false;
+            break;
+        }
+        let line = reader.position().line();
+        match reader.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => {}
+            Err(e) if matches!(e.kind(), csv::ErrorKind::Io(_)) => {
+                return Err(GaggleError::CsvError(format!("{}: {}", path.display(), e)));
+            }
+            Err(e) => {
+                malformed += 1;
+                if errors.len() < MAX_REPORTED_ERRORS {
+                    errors.push(CsvRowError {
+                        line: e.position().map_or(line, |p| p.line()),
+                        error: e.to_string(),
+                    });
+                }
+                continue;
+            }
+        }
+        let fields: Vec<String> = record.iter().map(str::to_string).collect();
+        match &first {
+            None => {
+                first = Some(fields);
+                header_end = reader.position().byte();
+            }
+            Some(expected) if expected.len() != fields.len() => {
+                malformed += 1;
+                if errors.len() < MAX_REPORTED_ERRORS {
+                    errors.push(CsvRowError {
+                        line,
+                        error: format!(
+                            "expected {} fields, found {}",
+                            expected.len(),
+                            fields.len()
+                        ),
+                    });
+                }
+            }
+            Some(_) => rows.push(fields),
+        }
+    }
+
+    let Some(mut first) = first else {
+        return Err(GaggleError::CsvError(format!(
+            "{} has no rows",
+            path.display()
+        )));
+    };
+    if malformed > 0 && malformed * 2 > rows.len() + malformed {
+        return Err(GaggleError::CsvError(format!(
+            "{}: {} of {} sampled rows are malformed",
+            path.display(),
+            malformed,
+            rows.len() + malformed
+        )));
+    }
+    if let Some(name) = first.first_mut() {
+        *name = name.trim_start_matches('\u{feff}').to_string();
+    }
+
+    let has_header = first
+        .iter()
+        .all(|v| !v.trim().is_empty() && CsvType::of(v.trim()) == CsvType::String);
+    let data_start = if has_header {
+        header_end
+    } else {
+        rows.insert(0, first.clone());
+        0
+    };
+    let columns = (0..first.len())
+        .map(|i| {
+            let ty = rows
+                .iter()
+                .map(|row| row[i].trim())
+                .filter(|v| !v.is_empty())
+                .map(CsvType::of)
+                .reduce(CsvType::merge)
+                .unwrap_or(CsvType::String);
+            let name = if has_header {
+                first[i].trim().to_string()
+            } else {
+                format!("column{}", i)
+            };
+            CsvColumn { name, ty }
+        })
+        .collect();
+
+    let sampled = rows.len() + malformed;
+    let estimated_rows = if exhausted || sampled == 0 {
+        sampled as u64
+    } else {
+        let sampled_bytes = reader.position().byte().saturating_sub(data_start).max(1);
+        let remaining = file_len.saturating_sub(data_start);
+        (remaining as f64 * sampled as f64 / sampled_bytes as f64).round() as u64
+    };
+
+    Ok(CsvSchema {
+        delimiter: (delimiter as char).to_string(),
+        has_header,
+        columns,
+        sampled_rows: rows.len(),
+        estimated_rows,
+        exact_row_count: exhausted,
+        malformed_rows: malformed,
+        errors,
+    })
+}
+
+/// Suffix of the marker written next to a compressed file once it has been decompressed.
+/// The marker holds the name of the decompressed file.
+pub(crate) const DECOMPRESSED_MARKER_SUFFIX: &str = ".decompressed";
+
+/// Serializes decompressions within the process, so two callers never write the same
+/// temporary file
+static MATERIALIZE_LOCK: once_cell::sync::Lazy<parking_lot::Mutex<()>> =
+    once_cell::sync::Lazy::new(|| parking_lot::Mutex::new(()));
+
+/// The compressed formats [`materialize_readable`] can unpack, by file extension
+const COMPRESSED_EXTENSIONS: [&str; 5] = [".gz", ".bz2", ".xz", ".zst", ".zip"];
+
+/// Returns a path DuckDB can read directly for `path`, decompressing it if needed.
+///
+/// A `.gz`, `.bz2`, `.xz`, or `.zst` file is decompressed next to the original, with the
+/// extension removed (`data.csv.gz` becomes `data.csv`). A `.zip` file containing exactly one
+/// data file has that file extracted next to it. Any other file is returned unchanged, as is
+/// a ZIP archive with several files. Decompression happens once: a marker next to the
+/// original records the result for later calls. The same size and compression ratio limits
+/// as archive extraction apply (`GAGGLE_MAX_EXTRACT_SIZE_MB` and
+/// `GAGGLE_MAX_COMPRESSION_RATIO`), and an existing file is never overwritten.
+pub fn materialize_readable(path: &Path) -> Result<PathBuf, GaggleError> {
+    let (Some(name), Some(parent)) = (path.file_name().and_then(|n| n.to_str()), path.parent())
+    else {
+        return Ok(path.to_path_buf());
+    };
+    let lower = name.to_ascii_lowercase();
+    let Some(ext) = COMPRESSED_EXTENSIONS
+        .iter()
+        .find(|ext| lower.len() > ext.len() && lower.ends_with(*ext))
+    else {
+        return Ok(path.to_path_buf());
+    };
+
+    let _lock = MATERIALIZE_LOCK.lock();
+    let marker = parent.join(format!("{}{}", name, DECOMPRESSED_MARKER_SUFFIX));
+    if let Ok(done) = fs::read_to_string(&marker) {
+        let output = parent.join(done.trim());
+        if !done.trim().is_empty() && output.is_file() {
+            return Ok(output);
+        }
+    }
+
+    let limits = crate::kaggle::download::ExtractLimits::from_config();
+    let compressed = fs::metadata(path)?.len();
+    let file = std::io::BufReader::new(fs::File::open(path)?);
+    let output_name = if *ext == ".zip" {
+        let mut archive = zip::ZipArchive::new(file)
+            .map_err(|e| GaggleError::ZipError(format!("{}: {}", path.display(), e)))?;
+        let mut data_files =
+            (0..archive.len()).filter(|&i| archive.by_index_raw(i).is_ok_and(is_data_entry));
+        let (Some(index), None) = (data_files.next(), data_files.next()) else {
+            return Ok(path.to_path_buf());
+        };
+        let entry = archive
+            .by_index(index)
+            .map_err(|e| GaggleError::ZipError(format!("{}: {}", path.display(), e)))?;
+        let output_name = entry
+            .enclosed_name()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .ok_or_else(|| GaggleError::ZipError(format!("Invalid entry in {}", path.display())))?;
+        write_decompressed(entry, compressed, path, &parent.join(&output_name), limits)?;
+        output_name
     } else {
-        "read_csv_auto"
+        let output_name = name[..name.len() - ext.len()].to_string();
+        let reader: Box<dyn Read> = match *ext {
+            ".gz" => Box::new(flate2::read::MultiGzDecoder::new(file)),
+            ".bz2" => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+            ".xz" => Box::new(lzma_rust2::XzReader::new(file, true)),
+            _ => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        };
+        write_decompressed(reader, compressed, path, &parent.join(&output_name), limits)?;
+        output_name
+    };
+    write_atomic(&marker, output_name.as_bytes())?;
+    Ok(parent.join(output_name))
+}
+
+/// Whether a ZIP entry is a data file, rather than a directory or a hidden or macOS
+/// metadata file.
+fn is_data_entry(entry: zip::read::ZipFile<'_, impl Read>) -> bool {
+    !entry.is_dir()
+        && !entry.name().starts_with("__MACOSX/")
+        && entry
+            .enclosed_name()
+            .and_then(|p| p.file_name().map(|n| !n.to_string_lossy().starts_with('.')))
+            .unwrap_or(false)
+}
+
+/// Extracts the entry of a ZIP archive that holds `filename` to `output`.
+///
+/// The entry is the one whose path is `filename`, or else the only one with the same file
+/// name, or else the only data file in the archive. Returns `false` without writing anything
+/// if there is no such entry. The same limits as [`materialize_readable`] apply.
+pub(crate) fn extract_zip_entry(
+    zip_path: &Path,
+    filename: &str,
+    output: &Path,
+) -> Result<bool, GaggleError> {
+    let compressed = fs::metadata(zip_path)?.len();
+    let file = std::io::BufReader::new(fs::File::open(zip_path)?);
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| GaggleError::ZipError(format!("{}: {}", zip_path.display(), e)))?;
+    let wanted = Path::new(filename);
+    let mut exact = None;
+    let mut same_name = Vec::new();
+    let mut data_files = Vec::new();
+    for i in 0..archive.len() {
+        let Ok(entry) = archive.by_index_raw(i) else {
+            continue;
+        };
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        if entry.is_dir() {
+            continue;
+        }
+        if name == wanted {
+            exact = Some(i);
+        } else if name.file_name() == wanted.file_name() {
+            same_name.push(i);
+        }
+        if is_data_entry(entry) {
+            data_files.push(i);
+        }
+    }
+    let index = match (exact, same_name.as_slice(), data_files.as_slice()) {
+        (Some(i), _, _) | (None, &[i], _) | (None, [], &[i]) => i,
+        _ => return Ok(false),
+    };
+    let entry = archive
+        .by_index(index)
+        .map_err(|e| GaggleError::ZipError(format!("{}: {}", zip_path.display(), e)))?;
+    let limits = crate::kaggle::download::ExtractLimits::from_config();
+    write_decompressed(entry, compressed, zip_path, output, limits)?;
+    Ok(true)
+}
+
+/// Streams `reader` into `output`, failing if it expands past the extraction limits.
+///
+/// The data is written to a temporary file first and renamed into place once complete.
+fn write_decompressed<R: Read>(
+    reader: R,
+    compressed: u64,
+    source: &Path,
+    output: &Path,
+    limits: crate::kaggle::download::ExtractLimits,
+) -> Result<(), GaggleError> {
+    if output.exists() {
+        return Err(GaggleError::IoError(format!(
+            "Cannot decompress {}: {} already exists",
+            source.display(),
+            output.display()
+        )));
+    }
+    let cap = limits.max_output_bytes(compressed);
+    let mut tmp_name = output.as_os_str().to_os_string();
+    tmp_name.push(format!(".tmp.{}", std::process::id()));
+    let tmp_path = PathBuf::from(tmp_name);
+    let result = (|| {
+        let mut out = std::io::BufWriter::new(fs::File::create(&tmp_path)?);
+        let written = std::io::copy(
+            &mut reader.take(cap.map_or(u64::MAX, |c| c.saturating_add(1))),
+            &mut out,
+        )
+        .map_err(|e| {
+            GaggleError::ZipError(format!("Failed to decompress {}: {}", source.display(), e))
+        })?;
+        std::io::Write::flush(&mut out)?;
+        let what = source.display().to_string();
+        limits.check_total_size(written, &what)?;
+        limits.check_ratio(written, compressed, source)?;
+        fs::rename(&tmp_path, output)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
     }
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_format_rfc3339() {
+        assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_rfc3339(1_704_067_199), "2023-12-31T23:59:59Z");
+    }
+
     #[test]
     fn test_calculate_dir_size_empty() {
         let temp = tempfile::TempDir::new().unwrap();
@@ -63,6 +842,248 @@ mod tests {
         assert!(size >= 10);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_calculate_dir_size_no_follow_counts_links_themselves() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let target = temp.path().join("target.bin");
+        fs::write(&target, vec![0u8; 4096]).unwrap();
+        let dir = temp.path().join("dir");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("own.bin"), vec![0u8; 100]).unwrap();
+        std::os::unix::fs::symlink(&target, dir.join("link.bin")).unwrap();
+        std::os::unix::fs::symlink(temp.path(), dir.join("loop")).unwrap();
+
+        let link_len = fs::symlink_metadata(dir.join("link.bin")).unwrap().len();
+        let loop_len = fs::symlink_metadata(dir.join("loop")).unwrap().len();
+        assert_eq!(
+            calculate_dir_size_no_follow(&dir).unwrap(),
+            100 + link_len + loop_len
+        );
+
+        fs::remove_file(dir.join("loop")).unwrap();
+        assert_eq!(calculate_dir_size(&dir).unwrap(), 100 + 4096);
+        assert_ne!(
+            calculate_dir_size(&dir).unwrap(),
+            calculate_dir_size_no_follow(&dir).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sniff_csv_with_header() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("data.csv");
+        fs::write(
+            &path,
+            "\u{feff}id,score,active,joined,name\n1,2.5,true,2024-01-31,\"Smith, A\"\n2,3,FALSE,2024-02-01,B\n3,,true,,C\n",
+        )
+        .unwrap();
+
+        let schema = sniff_csv(&path, DEFAULT_SNIFF_ROWS).unwrap();
+        assert_eq!(schema.delimiter, ",");
+        assert!(schema.has_header);
+        let columns: Vec<(&str, CsvType)> = schema
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.ty))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("id", CsvType::Int),
+                ("score", CsvType::Float),
+                ("active", CsvType::Bool),
+                ("joined", CsvType::Date),
+                ("name", CsvType::String),
+            ]
+        );
+        assert_eq!(schema.sampled_rows, 3);
+        assert_eq!(schema.estimated_rows, 3);
+        assert!(schema.exact_row_count);
+        assert_eq!(schema.malformed_rows, 0);
+    }
+
+    #[test]
+    fn test_sniff_csv_without_header_and_other_delimiter() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("data.txt");
+        let rows: String = (0..100)
+            .map(|i| format!("{:02};{:02}.5;x\n", i, i))
+            .collect();
+        fs::write(&path, rows).unwrap();
+
+        let schema = sniff_csv(&path, 10).unwrap();
+        assert_eq!(schema.delimiter, ";");
+        assert!(!schema.has_header);
+        assert_eq!(schema.columns[0].name, "column0");
+        assert_eq!(schema.columns[1].ty, CsvType::Float);
+        assert_eq!(schema.columns[2].ty, CsvType::String);
+        // The row count is estimated from the size of the sampled rows
+        assert!(!schema.exact_row_count);
+        assert_eq!(schema.estimated_rows, 100);
+    }
+
+    #[test]
+    fn test_sniff_csv_reports_malformed_rows() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("data.csv");
+        fs::write(&path, b"a,b\n1,2\n3\n4,5\n6,\xff\n7,8\n").unwrap();
+
+        let schema = sniff_csv(&path, DEFAULT_SNIFF_ROWS).unwrap();
+        assert_eq!(schema.sampled_rows, 3);
+        assert_eq!(schema.malformed_rows, 2);
+        assert_eq!(schema.errors.len(), 2);
+        assert_eq!(schema.errors[0].line, 3);
+        assert!(schema.errors[0].error.contains("expected 2 fields"));
+        assert_eq!(schema.columns[1].ty, CsvType::Int);
+
+        // Mostly malformed files are rejected
+        fs::write(&path, "a,b\n1\n2\n3,4\n").unwrap();
+        assert!(matches!(
+            sniff_csv(&path, DEFAULT_SNIFF_ROWS),
+            Err(GaggleError::CsvError(_))
+        ));
+        fs::write(&path, "").unwrap();
+        assert!(matches!(
+            sniff_csv(&path, DEFAULT_SNIFF_ROWS),
+            Err(GaggleError::CsvError(_))
+        ));
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    fn zip_with(files: &[(&str, &[u8])]) -> Vec<u8> {
+        use std::io::Write;
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_materialize_readable_decompresses_once() {
+        use std::io::Write;
+        let temp = tempfile::TempDir::new().unwrap();
+        let data = b"a,b\n1,2\n";
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz.write_all(data).unwrap();
+        let mut xz =
+            lzma_rust2::XzWriter::new(Vec::new(), lzma_rust2::XzOptions::with_preset(6)).unwrap();
+        xz.write_all(data).unwrap();
+        let files = [
+            ("g.csv.gz", gzip(data)),
+            ("b.csv.bz2", bz.finish().unwrap()),
+            ("x.csv.xz", xz.finish().unwrap()),
+            ("z.csv.zst", zstd::stream::encode_all(&data[..], 0).unwrap()),
+            ("archive.zip", zip_with(&[("inner/one.csv", data)])),
+        ];
+        for (name, bytes) in files {
+            let path = temp.path().join(name);
+            fs::write(&path, bytes).unwrap();
+            let output = materialize_readable(&path).unwrap();
+            assert_eq!(output.parent(), Some(temp.path()));
+            assert_eq!(fs::read(&output).unwrap(), data, "{}", name);
+            assert!(temp
+                .path()
+                .join(format!("{}{}", name, DECOMPRESSED_MARKER_SUFFIX))
+                .exists());
+        }
+        assert!(temp.path().join("one.csv").exists());
+
+        // A second call reuses the decompressed file
+        fs::write(temp.path().join("g.csv"), b"changed").unwrap();
+        let again = materialize_readable(&temp.path().join("g.csv.gz")).unwrap();
+        assert_eq!(fs::read(again).unwrap(), b"changed");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_materialize_readable_leaves_other_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let plain = temp.path().join("data.csv");
+        fs::write(&plain, b"a\n").unwrap();
+        assert_eq!(materialize_readable(&plain).unwrap(), plain);
+
+        let several = temp.path().join("several.zip");
+        fs::write(&several, zip_with(&[("a.csv", b"a"), ("b.csv", b"b")])).unwrap();
+        assert_eq!(materialize_readable(&several).unwrap(), several);
+
+        // An existing file with the decompressed name is not overwritten
+        fs::write(temp.path().join("data.csv.gz"), gzip(b"other\n")).unwrap();
+        let err = materialize_readable(&temp.path().join("data.csv.gz")).unwrap_err();
+        assert!(matches!(err, GaggleError::IoError(_)));
+        assert_eq!(fs::read(&plain).unwrap(), b"a\n");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_materialize_readable_enforces_extract_limits() {
+        std::env::remove_var("GAGGLE_MAX_EXTRACT_SIZE_MB");
+        std::env::remove_var("GAGGLE_MAX_COMPRESSION_RATIO");
+        let temp = tempfile::TempDir::new().unwrap();
+        let bomb = temp.path().join("bomb.csv.gz");
+        fs::write(&bomb, gzip(&vec![0u8; 8 * 1024 * 1024])).unwrap();
+
+        let err = materialize_readable(&bomb).unwrap_err();
+        assert!(err.to_string().contains("compression ratio"), "{}", err);
+        let leftovers: Vec<_> = fs::read_dir(temp.path()).unwrap().collect();
+        assert_eq!(leftovers.len(), 1);
+
+        std::env::set_var("GAGGLE_MAX_COMPRESSION_RATIO", "unlimited");
+        std::env::set_var("GAGGLE_MAX_EXTRACT_SIZE_MB", "1");
+        let err = materialize_readable(&bomb).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{}", err);
+
+        std::env::set_var("GAGGLE_MAX_EXTRACT_SIZE_MB", "16");
+        let output = materialize_readable(&bomb).unwrap();
+        assert_eq!(fs::metadata(output).unwrap().len(), 8 * 1024 * 1024);
+        std::env::remove_var("GAGGLE_MAX_EXTRACT_SIZE_MB");
+        std::env::remove_var("GAGGLE_MAX_COMPRESSION_RATIO");
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("notes.md");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+
+        let missing_dir = temp.path().join("missing/notes.md");
+        assert!(write_atomic(&missing_dir, b"x").is_err());
+    }
+
+    #[test]
+    fn test_write_marker_atomic_survives_interrupted_write() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let marker = temp.path().join(".downloaded");
+        write_marker_atomic(&marker, r#"{"dataset_path":"owner/ds","size_mb":1}"#).unwrap();
+
+        // A write killed halfway leaves a truncated temporary file, but not a truncated marker
+        let tmp = atomic_tmp_path(&marker);
+        fs::write(&tmp, r#"{"dataset_path":"own"#).unwrap();
+        let content = fs::read_to_string(&marker).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&content).is_ok());
+
+        // The next write replaces both the stale temporary file and the marker
+        write_marker_atomic(&marker, r#"{"dataset_path":"owner/ds","size_mb":2}"#).unwrap();
+        let value: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&marker).unwrap()).unwrap();
+        assert_eq!(value["size_mb"], 2);
+        assert!(!tmp.exists());
+    }
+
     #[test]
     fn test_guess_reader_for_path_mapping() {
         assert_eq!(guess_reader_for_path("file.parquet"), "read_parquet");
@@ -74,4 +1095,129 @@ mod tests {
         assert_eq!(guess_reader_for_path("file.csv"), "read_csv_auto");
         assert_eq!(guess_reader_for_path("file.txt"), "read_csv_auto");
     }
+
+    #[test]
+    fn test_guess_reader_extended_mapping() {
+        let tsv = guess_reader("dir/File.TSV");
+        assert_eq!(tsv.reader, "read_csv_auto");
+        assert_eq!(tsv.options["delim"], "\t");
+        assert_eq!(guess_reader("file.tab").options["delim"], "\t");
+        assert!(guess_reader("file.txt").options.is_empty());
+
+        for name in ["file.arrow", "file.Feather", "file.ipc"] {
+            assert_eq!(guess_reader(name).reader, "read_arrow", "{}", name);
+        }
+        for name in ["file.db", "file.SQLITE", "file.sqlite3"] {
+            let guess = guess_reader(name);
+            assert_eq!(guess.reader, "sqlite_scan", "{}", name);
+            assert_eq!(guess.options["table_required"], true);
+        }
+
+        // Compression suffixes are looked through, once
+        assert_eq!(guess_reader("file.parquet.gz").reader, "read_parquet");
+        assert_eq!(guess_reader("file.ndjson.zst").reader, "read_json_auto");
+        let tsv_gz = guess_reader("file.tsv.GZ");
+        assert_eq!(tsv_gz.reader, "read_csv_auto");
+        assert_eq!(tsv_gz.options["delim"], "\t");
+        assert_eq!(guess_reader("file.gz").reader, "read_csv_auto");
+        assert_eq!(guess_reader("file.json.gz.gz").reader, "read_csv_auto");
+        assert_eq!(guess_reader("no_extension").reader, "read_csv_auto");
+        assert_eq!(guess_reader("v1.parquet/part").reader, "read_csv_auto");
+
+        assert_eq!(
+            serde_json::to_value(guess_reader("a.tsv")).unwrap(),
+            serde_json::json!({"reader": "read_csv_auto", "options": {"delim": "\t"}})
+        );
+    }
+
+    #[test]
+    fn test_detect_file_type_from_bytes() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let write = |name: &str, bytes: &[u8]| {
+            let path = temp.path().join(name);
+            fs::write(&path, bytes).unwrap();
+            path
+        };
+        let detect = |path: &Path| detect_file_type_from_bytes(path).unwrap();
+
+        // Misleading extensions, so only the contents can give the type away
+        assert_eq!(detect(&write("a.txt", b"PAR1\x00\x00PAR1")), "read_parquet");
+        assert_eq!(
+            detect(&write("b.txt", b"\x15\x04\x15\x00PAR1")),
+            "read_parquet"
+        );
+        assert_eq!(detect(&write("c.txt", b"ORC\x0a\x0b")), "read_orc");
+        assert_eq!(detect(&write("d.txt", b"Obj\x01\x04\x14")), "read_avro");
+        assert_eq!(detect(&write("e.txt", b"ARROW1\x00\x00")), "read_arrow");
+        assert_eq!(
+            detect(&write("f.txt", b"\xff\xff\xff\xff\x78\x00\x00\x00")),
+            "read_arrow"
+        );
+        assert_eq!(detect(&write("g.dat", b"[{\"a\": 1}]")), "read_json_auto");
+        assert_eq!(detect(&write("h.dat", b"a;b\n1;2\n")), "read_csv_auto");
+
+        let err = detect_file_type_from_bytes(&write("i.csv", b"just some words\n")).unwrap_err();
+        assert!(err.to_string().contains("Cannot detect the file type"));
+    }
+
+    #[test]
+    fn test_detect_file_type_from_bytes_zip_and_gzip() {
+        use std::io::Write;
+        let temp = tempfile::TempDir::new().unwrap();
+        let write_zip = |name: &str, entry: &str| {
+            let path = temp.path().join(name);
+            let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+            zip.start_file(entry, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(b"x").unwrap();
+            zip.finish().unwrap();
+            path
+        };
+        let workbook = write_zip("book.bin", "xl/workbook.xml");
+        assert_eq!(
+            detect_file_type_from_bytes(&workbook).unwrap(),
+            "read_excel"
+        );
+        let archive = write_zip("archive.xlsx", "data.csv");
+        assert!(detect_file_type_from_bytes(&archive).is_err());
+        // Falls back to the extension when the contents are not recognized
+        assert_eq!(guess_reader_for_file(&archive), "read_excel");
+
+        let write_gzip = |name: &str, inner: Option<&str>| {
+            let path = temp.path().join(name);
+            let builder = match inner {
+                Some(inner) => flate2::GzBuilder::new().filename(inner),
+                None => flate2::GzBuilder::new(),
+            };
+            let mut encoder = builder.write(
+                fs::File::create(&path).unwrap(),
+                flate2::Compression::fast(),
+            );
+            encoder.write_all(b"{}").unwrap();
+            encoder.finish().unwrap();
+            path
+        };
+        let named = write_gzip("download.gz", Some("records.ndjson"));
+        assert_eq!(
+            detect_file_type_from_bytes(&named).unwrap(),
+            "read_json_auto"
+        );
+        let unnamed = write_gzip("rows.csv.gz", None);
+        assert_eq!(
+            detect_file_type_from_bytes(&unnamed).unwrap(),
+            "read_csv_auto"
+        );
+    }
+
+    #[test]
+    fn test_guess_reader_for_file_falls_back_to_extension() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("data.json");
+        fs::write(&path, b"plain words").unwrap();
+        assert_eq!(guess_reader_for_file(&path), "read_json_auto");
+        assert_eq!(
+            guess_reader_for_file(&temp.path().join("missing.parquet")),
+            "read_parquet"
+        );
+    }
 }
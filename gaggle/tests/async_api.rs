@@ -0,0 +1,216 @@
+// async_api.rs
+//
+// This file contains integration tests for the async API, which is only built with the
+// `async` feature. Like the tests in mock_http.rs, they run against a mock HTTP server.
+// The mock server is set up outside of the Tokio runtime, and each test drives the async
+// functions on a current-thread runtime.
+
+#![cfg(feature = "async")]
+
+use mockito::{Matcher, Server};
+use std::env;
+use std::future::Future;
+use std::io::Write;
+
+fn make_zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    {
+        let cursor = std::io::Cursor::new(&mut buf);
+        let mut zip = zip::ZipWriter::new(cursor);
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in files.iter() {
+            zip.start_file(name.to_string(), options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+    buf
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// Points the library at a mock server and a fresh cache directory.
+fn setup(server: &Server) -> tempfile::TempDir {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    env::set_var("GAGGLE_API_BASE", server.url());
+    env::set_var("KAGGLE_USERNAME", "user");
+    env::set_var("KAGGLE_KEY", "key");
+    temp
+}
+
+fn cleanup() {
+    for var in [
+        "GAGGLE_CACHE_DIR",
+        "GAGGLE_API_BASE",
+        "KAGGLE_USERNAME",
+        "KAGGLE_KEY",
+    ] {
+        env::remove_var(var);
+    }
+}
+
+fn assert_send<T: Send>(_: &T) {}
+
+#[test]
+#[serial_test::serial]
+fn test_async_futures_are_send() {
+    assert_send(&gaggle::search_datasets_async("q", 1, 10));
+    assert_send(&gaggle::get_dataset_metadata_async("owner/dataset"));
+    assert_send(&gaggle::download_dataset_async("owner/dataset"));
+    assert_send(&gaggle::get_dataset_file_path_async(
+        "owner/dataset",
+        "data.csv",
+    ));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_and_metadata_async() {
+    let mut server = Server::new();
+    let _temp = setup(&server);
+    let search = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::UrlEncoded("search".into(), "async search".into()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("[{\"ref\":\"owner/async-search\",\"title\":\"T\"}]")
+        .expect(1)
+        .create();
+    let meta = server
+        .mock("GET", "/datasets/view/owner/async-meta")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"ref\":\"owner/async-meta\",\"currentVersionNumber\":3}")
+        .expect(1)
+        .create();
+
+    block_on(async {
+        let results = gaggle::search_datasets_async("async search", 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(results[0]["ref"], "owner/async-search");
+        // The second search is served from the search cache
+        let cached = gaggle::search_datasets_async("async search", 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(cached, results);
+
+        let metadata = gaggle::get_dataset_metadata_async("owner/async-meta")
+            .await
+            .unwrap();
+        assert_eq!(metadata["currentVersionNumber"], 3);
+        let cached = gaggle::get_dataset_metadata_async("owner/async-meta")
+            .await
+            .unwrap();
+        assert_eq!(cached, metadata);
+    });
+
+    search.assert();
+    meta.assert();
+    cleanup();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_async_validates_input() {
+    block_on(async {
+        let err = gaggle::search_datasets_async("q", 0, 10).await.unwrap_err();
+        assert!(matches!(err, gaggle::GaggleError::InvalidDatasetPath(_)));
+    });
+}
+
+#[test]
+#[serial_test::serial]
+fn test_metadata_async_of_missing_dataset() {
+    let mut server = Server::new();
+    let _temp = setup(&server);
+    let _meta = server
+        .mock("GET", "/datasets/view/owner/async-missing")
+        .with_status(404)
+        .create();
+
+    block_on(async {
+        let err = gaggle::get_dataset_metadata_async("owner/async-missing")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, gaggle::GaggleError::DatasetNotFound(_)));
+    });
+    cleanup();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_download_dataset_async_shares_cache_and_lock() {
+    let mut server = Server::new();
+    let temp = setup(&server);
+    let _meta = server
+        .mock("GET", "/datasets/view/owner/async-download")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":5}")
+        .create();
+    let download = server
+        .mock("GET", "/datasets/download/owner/async-download")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(make_zip_bytes(&[("data.csv", b"a,b\n1,2\n")]))
+        .expect(1)
+        .create();
+
+    block_on(async {
+        // Concurrent downloads of the same dataset share one request
+        let first = tokio::spawn(gaggle::download_dataset_async("owner/async-download"));
+        let second = tokio::spawn(gaggle::download_dataset_async("owner/async-download"));
+        let dir = first.await.unwrap().unwrap();
+        assert_eq!(second.await.unwrap().unwrap(), dir);
+        assert!(dir.starts_with(temp.path()));
+        assert!(dir.join("data.csv").exists());
+        assert!(dir.join(".downloaded").exists());
+
+        // The cached file is found without another request
+        let file = gaggle::get_dataset_file_path_async("owner/async-download", "data.csv")
+            .await
+            .unwrap();
+        assert_eq!(file, dir.join("data.csv"));
+    });
+
+    download.assert();
+    cleanup();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_get_dataset_file_path_async_fetches_single_file() {
+    let mut server = Server::new();
+    let _temp = setup(&server);
+    let file = server
+        .mock("GET", "/datasets/download/owner/async-file")
+        .match_query(Matcher::UrlEncoded("fileName".into(), "part.csv".into()))
+        .with_status(200)
+        .with_body("x\n1\n")
+        .expect(1)
+        .create();
+
+    block_on(async {
+        let path = gaggle::get_dataset_file_path_async("owner/async-file", "part.csv")
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x\n1\n");
+
+        let err = gaggle::get_dataset_file_path_async("owner/async-file", "../escape.csv")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, gaggle::GaggleError::InvalidDatasetPath(_)));
+    });
+
+    file.assert();
+    cleanup();
+}
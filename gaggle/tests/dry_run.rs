@@ -0,0 +1,111 @@
+// dry_run.rs
+//
+// This integration test verifies the behavior of the Gaggle library in dry-run mode. With
+// `GAGGLE_DRY_RUN` set, downloads should return the paths they would use without making
+// any HTTP requests, and a later download without dry-run mode should still fetch the
+// dataset. A mock HTTP server is used to check which requests were made.
+
+use mockito::{Matcher, Server};
+use std::ffi::{CStr, CString};
+use std::io::Write;
+
+fn make_zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    {
+        let cursor = std::io::Cursor::new(&mut buf);
+        let mut zip = zip::ZipWriter::new(cursor);
+        let options: zip::write::FileOptions<()> =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in files.iter() {
+            zip.start_file(name.to_string(), options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+    buf
+}
+
+unsafe fn take_string(ptr: *mut std::ffi::c_char) -> String {
+    assert!(!ptr.is_null(), "FFI call failed");
+    let s = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+    gaggle::gaggle_free(ptr);
+    s
+}
+
+#[test]
+#[serial_test::serial]
+fn test_dry_run_download_makes_no_requests() {
+    let temp = tempfile::TempDir::new().unwrap();
+    std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    std::env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let any_request = server
+        .mock("GET", Matcher::Any)
+        .match_query(Matcher::Any)
+        .expect(0)
+        .create();
+
+    std::env::set_var("GAGGLE_DRY_RUN", "1");
+    assert_eq!(gaggle::gaggle_is_dry_run(), 1);
+
+    let ds = CString::new("owner/dry-dataset").unwrap();
+    let local = unsafe { take_string(gaggle::gaggle_download_dataset(ds.as_ptr())) };
+    let local = std::path::PathBuf::from(local);
+    assert!(local.is_dir());
+    assert!(local.starts_with(temp.path()));
+    assert!(!local.join(".downloaded").exists());
+
+    let marker: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(local.join(".dry_run")).unwrap()).unwrap();
+    assert_eq!(marker["dry_run"], true);
+    assert_eq!(
+        marker["would_download_url"],
+        format!("{}/datasets/download/owner/dry-dataset", server.url())
+    );
+
+    // File paths are computed without checking that the file exists
+    let file = CString::new("data.csv").unwrap();
+    let path = unsafe { take_string(gaggle::gaggle_get_file_path(ds.as_ptr(), file.as_ptr())) };
+    assert_eq!(std::path::PathBuf::from(path), local.join("data.csv"));
+
+    // Listing files returns an empty list
+    let files = unsafe { take_string(gaggle::gaggle_list_files(ds.as_ptr())) };
+    assert_eq!(files, "[]");
+
+    any_request.assert();
+    any_request.remove();
+
+    // Without dry-run mode, the dataset is downloaded for real
+    std::env::remove_var("GAGGLE_DRY_RUN");
+    assert_eq!(gaggle::gaggle_is_dry_run(), 0);
+    let _meta = server
+        .mock("GET", "/datasets/view/owner/dry-dataset")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":1}")
+        .create();
+    let download = server
+        .mock("GET", "/datasets/download/owner/dry-dataset")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(make_zip_bytes(&[("data.csv", b"a,b\n1,2\n")]))
+        .expect(1)
+        .create();
+
+    let downloaded = unsafe { take_string(gaggle::gaggle_download_dataset(ds.as_ptr())) };
+    let downloaded = std::path::PathBuf::from(downloaded);
+    assert_eq!(downloaded, local);
+    assert!(downloaded.join("data.csv").exists());
+    assert!(downloaded.join(".downloaded").exists());
+    assert!(!downloaded.join(".dry_run").exists());
+    download.assert();
+
+    std::env::remove_var("GAGGLE_CACHE_DIR");
+    std::env::remove_var("GAGGLE_API_BASE");
+}
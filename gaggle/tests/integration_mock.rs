@@ -47,7 +47,12 @@ fn integration_search_and_info_with_mock_server() {
         let ds = CString::new("owner/dataset").unwrap();
         let res = gaggle_get_dataset_info(ds.as_ptr());
         assert!(!res.is_null());
+        let info: serde_json::Value =
+            serde_json::from_str(std::ffi::CStr::from_ptr(res).to_str().unwrap()).unwrap();
         gaggle_free(res);
+        assert_eq!(info["ref"], "owner/dataset");
+        assert!(info.get("current_version").is_some());
+        assert_eq!(info["file_count"], 0);
     }
 
     unsafe {
@@ -69,6 +69,57 @@ fn test_search_datasets_with_mock() {
     env::remove_var("GAGGLE_API_BASE");
 }
 
+unsafe extern "C" fn count_item(item: *const std::ffi::c_char, user_data: *mut std::ffi::c_void) {
+    let item = CStr::from_ptr(item).to_str().unwrap();
+    let value: serde_json::Value = serde_json::from_str(item).unwrap();
+    assert!(value["ref"].as_str().unwrap().starts_with("owner/"));
+    *(user_data as *mut usize) += 1;
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_streaming_with_mock() {
+    gaggle::init_logging();
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _m = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::UrlEncoded("search".into(), "streamed".into()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"ref":"owner/a"},{"ref":"owner/b"},{"ref":"owner/c"}]"#)
+        .create();
+
+    let query = CString::new("streamed").unwrap();
+    let mut count: usize = 0;
+    let emitted = unsafe {
+        gaggle::gaggle_search_streaming(
+            query.as_ptr(),
+            1,
+            10,
+            Some(count_item),
+            &mut count as *mut usize as *mut std::ffi::c_void,
+        )
+    };
+    assert_eq!(emitted, 3);
+    assert_eq!(count, 3);
+
+    // A missing callback is an error
+    let emitted = unsafe {
+        gaggle::gaggle_search_streaming(query.as_ptr(), 1, 10, None, std::ptr::null_mut())
+    };
+    assert_eq!(emitted, -1);
+    assert_eq!(gaggle::gaggle_last_error_code(), 10);
+
+    env::remove_var("GAGGLE_API_BASE");
+}
+
 #[test]
 #[serial_test::serial]
 fn test_download_and_version_with_mock() {
@@ -312,3 +363,1891 @@ fn test_prefetch_files_mixed_results() {
     env::remove_var("GAGGLE_STRICT_ONDEMAND");
     env::remove_var("GAGGLE_API_BASE");
 }
+
+#[test]
+#[serial_test::serial]
+fn test_prefetch_files_in_parallel_preserves_order() {
+    gaggle::init_logging();
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    env::set_var("GAGGLE_STRICT_ONDEMAND", "1");
+    env::set_var("GAGGLE_PREFETCH_CONCURRENCY", "4");
+    env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    // Every third file is missing; the others take a while to arrive
+    let names: Vec<String> = (0..8).map(|i| format!("part{}.csv", i)).collect();
+    let mut mocks = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let mock = server
+            .mock("GET", "/datasets/download/owner/parts")
+            .match_query(Matcher::UrlEncoded("fileName".into(), name.clone()));
+        let mock = if i % 3 == 2 {
+            mock.with_status(404).with_body("not found")
+        } else {
+            let body = format!("id\n{}\n", i);
+            mock.with_status(200).with_chunked_body(move |w| {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                w.write_all(body.as_bytes())
+            })
+        };
+        mocks.push(mock.expect(1).create());
+    }
+
+    let ds = CString::new("owner/parts").unwrap();
+    let list = CString::new(names.join("\n")).unwrap();
+    let started = std::time::Instant::now();
+    let ptr = unsafe { gaggle::gaggle_prefetch_files(ds.as_ptr(), list.as_ptr()) };
+    let elapsed = started.elapsed();
+    assert!(!ptr.is_null());
+    let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { gaggle::gaggle_free(ptr) };
+
+    let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+    let files = v["files"].as_array().unwrap();
+    assert_eq!(files.len(), names.len());
+    for (i, (f, name)) in files.iter().zip(&names).enumerate() {
+        assert_eq!(f["name"].as_str().unwrap(), name);
+        if i % 3 == 2 {
+            assert_eq!(f["status"], "error");
+        } else {
+            assert_eq!(f["status"], "ok");
+            let content = std::fs::read_to_string(f["path"].as_str().unwrap()).unwrap();
+            assert_eq!(content, format!("id\n{}\n", i));
+        }
+    }
+    for mock in mocks {
+        mock.assert();
+    }
+    // Five slow files take at least a second one after another
+    assert!(
+        elapsed < std::time::Duration::from_millis(1000),
+        "prefetch took {:?}",
+        elapsed
+    );
+
+    env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+    env::remove_var("GAGGLE_PREFETCH_CONCURRENCY");
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_STRICT_ONDEMAND");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_results_are_cached() {
+    gaggle::init_logging();
+    let mut server = Server::new();
+    let server_url = server.url();
+    env::set_var("GAGGLE_API_BASE", &server_url);
+    gaggle::gaggle_clear_search_cache();
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let m = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::UrlEncoded("search".into(), "cached query".into()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("[{\"ref\":\"owner/cached\",\"title\":\"C\"}]")
+        .expect(1)
+        .create();
+
+    let query = CString::new("cached query").unwrap();
+    for _ in 0..2 {
+        let ptr = unsafe { gaggle::gaggle_search(query.as_ptr(), 1, 10) };
+        assert!(!ptr.is_null());
+        unsafe {
+            let s = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+            gaggle::gaggle_free(ptr);
+            assert!(s.contains("owner/cached"));
+        }
+    }
+    m.assert();
+
+    gaggle::gaggle_clear_search_cache();
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+/// Starts a mock server that answers the sorted `datasets/list` variants and
+/// points Gaggle at it with dummy credentials.
+fn sorted_listing_server() -> (mockito::ServerGuard, Vec<mockito::Mock>) {
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    gaggle::gaggle_clear_search_cache();
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let mocks = ["hottest", "published", "updated"]
+        .iter()
+        .map(|sort| {
+            server
+                .mock("GET", "/datasets/list")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("sortBy".into(), sort.to_string()),
+                    Matcher::UrlEncoded("page".into(), "2".into()),
+                    Matcher::UrlEncoded("pageSize".into(), "5".into()),
+                ]))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(format!("[{{\"ref\":\"owner/{}\"}}]", sort))
+                .expect(1)
+                .create()
+        })
+        .collect();
+    (server, mocks)
+}
+
+#[test]
+#[serial_test::serial]
+fn test_sorted_dataset_listings_with_mock() {
+    gaggle::init_logging();
+    let (_server, mocks) = sorted_listing_server();
+
+    let cases: [(extern "C" fn(i32, i32) -> *mut std::os::raw::c_char, &str); 3] = [
+        (gaggle::gaggle_list_trending_datasets, "owner/hottest"),
+        (gaggle::gaggle_list_new_datasets, "owner/published"),
+        (gaggle::gaggle_list_updated_datasets, "owner/updated"),
+    ];
+    for (f, expected) in cases {
+        let ptr = f(2, 5);
+        assert!(!ptr.is_null());
+        let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { gaggle::gaggle_free(ptr) };
+        assert!(s.contains(expected), "unexpected body: {}", s);
+    }
+    for m in mocks {
+        m.assert();
+    }
+
+    gaggle::gaggle_clear_search_cache();
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_list_datasets_by_owner_and_mine_with_mock() {
+    gaggle::init_logging();
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    gaggle::gaggle_clear_search_cache();
+
+    let user = CString::new("me").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let mocks: Vec<mockito::Mock> = ["someone", "me"]
+        .iter()
+        .map(|owner| {
+            server
+                .mock("GET", "/datasets/list")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("ownerSlug".into(), owner.to_string()),
+                    Matcher::UrlEncoded("page".into(), "1".into()),
+                    Matcher::UrlEncoded("pageSize".into(), "10".into()),
+                ]))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(format!("[{{\"ref\":\"{}/data\"}}]", owner))
+                .expect(1)
+                .create()
+        })
+        .collect();
+
+    let owner = CString::new("someone").unwrap();
+    let ptr = unsafe { gaggle::gaggle_list_datasets_by_owner(owner.as_ptr(), 1, 10) };
+    assert!(!ptr.is_null());
+    let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { gaggle::gaggle_free(ptr) };
+    assert!(s.contains("someone/data"), "unexpected body: {}", s);
+
+    let ptr = gaggle::gaggle_list_my_datasets(1, 10);
+    assert!(!ptr.is_null());
+    let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { gaggle::gaggle_free(ptr) };
+    assert!(s.contains("me/data"), "unexpected body: {}", s);
+
+    for m in mocks {
+        m.assert();
+    }
+
+    // Owner slugs containing a slash are rejected before any request is made
+    let bad = CString::new("a/b").unwrap();
+    let ptr = unsafe { gaggle::gaggle_list_datasets_by_owner(bad.as_ptr(), 1, 10) };
+    assert!(ptr.is_null());
+
+    gaggle::gaggle_clear_search_cache();
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_by_tag_falls_back_to_query_with_mock() {
+    gaggle::init_logging();
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    gaggle::gaggle_clear_search_cache();
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let tagged = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::UrlEncoded("tagIds".into(), "finance".into()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"ref":"owner/tagged"}]"#)
+        .expect(1)
+        .create();
+    let empty = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::UrlEncoded("tagIds".into(), "rare tag".into()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("[]")
+        .expect(1)
+        .create();
+    let fallback = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::UrlEncoded("search".into(), "rare tag".into()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"ref":"owner/fallback"}]"#)
+        .expect(1)
+        .create();
+
+    for (tag, expected) in [("finance", "owner/tagged"), ("rare tag", "owner/fallback")] {
+        let tag = CString::new(tag).unwrap();
+        let ptr = unsafe { gaggle::gaggle_search_by_tag(tag.as_ptr(), 1, 10) };
+        assert!(!ptr.is_null());
+        let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { gaggle::gaggle_free(ptr) };
+        assert!(s.contains(expected), "unexpected body: {}", s);
+    }
+    tagged.assert();
+    empty.assert();
+    fallback.assert();
+
+    gaggle::gaggle_clear_search_cache();
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_by_format_sends_file_type_with_mock() {
+    gaggle::init_logging();
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    gaggle::gaggle_clear_search_cache();
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let list = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("fileType".into(), "parquet".into()),
+            Matcher::UrlEncoded("page".into(), "2".into()),
+            Matcher::UrlEncoded("pageSize".into(), "5".into()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"ref":"owner/columnar"}]"#)
+        .expect(1)
+        .create();
+    let rejected = server
+        .mock("GET", Matcher::Any)
+        .match_query(Matcher::Regex("fileType=tsv".into()))
+        .expect(0)
+        .create();
+
+    // The type is matched case-insensitively
+    let file_type = CString::new("Parquet").unwrap();
+    let ptr = unsafe { gaggle::gaggle_search_by_format(file_type.as_ptr(), 2, 5) };
+    assert!(!ptr.is_null());
+    let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { gaggle::gaggle_free(ptr) };
+    assert!(s.contains("owner/columnar"), "unexpected body: {}", s);
+
+    let file_type = CString::new("tsv").unwrap();
+    let ptr = unsafe { gaggle::gaggle_search_by_format(file_type.as_ptr(), 1, 10) };
+    assert!(ptr.is_null());
+    let err = unsafe { CStr::from_ptr(gaggle::gaggle_last_error()) }
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(
+        err.contains("Unknown file type: tsv"),
+        "unexpected error: {}",
+        err
+    );
+
+    list.assert();
+    rejected.assert();
+    gaggle::gaggle_clear_search_cache();
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+fn search_page_body(prefix: &str, count: usize) -> String {
+    let items: Vec<serde_json::Value> = (0..count)
+        .map(|i| serde_json::json!({"ref": format!("owner/{}-{}", prefix, i)}))
+        .collect();
+    serde_json::Value::Array(items).to_string()
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_all_aggregates_pages() {
+    gaggle::init_logging();
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    gaggle::gaggle_clear_search_cache();
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _p1 = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("search".into(), "all pages".into()),
+            Matcher::UrlEncoded("page".into(), "1".into()),
+        ]))
+        .with_status(200)
+        .with_body(search_page_body("p1", 100))
+        .create();
+    let _p2 = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("search".into(), "all pages".into()),
+            Matcher::UrlEncoded("page".into(), "2".into()),
+        ]))
+        .with_status(200)
+        .with_body(search_page_body("p2", 30))
+        .create();
+
+    let query = CString::new("all pages").unwrap();
+    let ptr = unsafe { gaggle::gaggle_search_all(query.as_ptr(), 1000) };
+    assert!(!ptr.is_null());
+    let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { gaggle::gaggle_free(ptr) };
+    let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+    assert!(v["complete"].as_bool().unwrap());
+    assert_eq!(v["results"].as_array().unwrap().len(), 130);
+
+    gaggle::gaggle_clear_search_cache();
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_all_returns_partial_results_on_failure() {
+    gaggle::init_logging();
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+    gaggle::gaggle_clear_search_cache();
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _p1 = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("search".into(), "flaky".into()),
+            Matcher::UrlEncoded("page".into(), "1".into()),
+        ]))
+        .with_status(200)
+        .with_body(search_page_body("ok", 100))
+        .create();
+    let _p2 = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("search".into(), "flaky".into()),
+            Matcher::UrlEncoded("page".into(), "2".into()),
+        ]))
+        .with_status(500)
+        .create();
+
+    let query = CString::new("flaky").unwrap();
+    let ptr = unsafe { gaggle::gaggle_search_all(query.as_ptr(), 250) };
+    assert!(!ptr.is_null());
+    let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { gaggle::gaggle_free(ptr) };
+    let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+    assert!(!v["complete"].as_bool().unwrap());
+    assert_eq!(v["results"].as_array().unwrap().len(), 100);
+    assert!(v["error"].as_str().unwrap().contains("500"));
+
+    gaggle::gaggle_clear_search_cache();
+    env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_create_dataset_and_update_files_with_mock() {
+    let mut server = Server::new();
+    let server_url = server.url();
+    env::set_var("GAGGLE_API_BASE", &server_url);
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let data = temp.path().join("data.csv");
+    std::fs::write(&data, b"a,b\n1,2\n").unwrap();
+    let files_json = CString::new(serde_json::json!([data]).to_string()).unwrap();
+
+    let create = server
+        .mock("POST", "/datasets")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "ownerSlug": "owner",
+            "slug": "my-dataset",
+            "isPrivate": true,
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(
+            "{{\"ref\":\"owner/my-dataset\",\"uploadUrl\":\"{}/blob/create\"}}",
+            server_url
+        ))
+        .create();
+    let version = server
+        .mock("POST", "/datasets/owner/my-dataset/versions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(
+            "{{\"ref\":\"owner/my-dataset\",\"uploadUrl\":\"{}/blob/version\"}}",
+            server_url
+        ))
+        .create();
+    let blob_create = server
+        .mock("PUT", "/blob/create")
+        .match_header("content-type", "application/zip")
+        .with_status(200)
+        .create();
+    let blob_version = server
+        .mock("PUT", "/blob/version")
+        .with_status(200)
+        .create();
+
+    let owner = CString::new("owner").unwrap();
+    let title = CString::new("My Dataset").unwrap();
+    let license = CString::new("CC0-1.0").unwrap();
+    let ptr = unsafe {
+        gaggle::gaggle_create_dataset(
+            owner.as_ptr(),
+            title.as_ptr(),
+            files_json.as_ptr(),
+            license.as_ptr(),
+            1,
+        )
+    };
+    assert!(!ptr.is_null());
+    unsafe {
+        let s = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+        gaggle::gaggle_free(ptr);
+        assert!(s.contains("owner/my-dataset"));
+    }
+
+    let dataset = CString::new("owner/my-dataset").unwrap();
+    let ptr = unsafe { gaggle::gaggle_update_dataset_files(dataset.as_ptr(), files_json.as_ptr()) };
+    assert!(!ptr.is_null());
+    unsafe { gaggle::gaggle_free(ptr) };
+
+    create.assert();
+    version.assert();
+    blob_create.assert();
+    blob_version.assert();
+
+    // Invalid files JSON is rejected before any request is made
+    let bad = CString::new("not json").unwrap();
+    let ptr = unsafe { gaggle::gaggle_update_dataset_files(dataset.as_ptr(), bad.as_ptr()) };
+    assert!(ptr.is_null());
+
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_dataset_registration_is_not_retried_with_mock() {
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "3");
+    env::set_var("GAGGLE_HTTP_RETRY_DELAY", "0");
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let data = temp.path().join("data.csv");
+    std::fs::write(&data, b"a,b\n1,2\n").unwrap();
+    let files_json = CString::new(serde_json::json!([data]).to_string()).unwrap();
+
+    // A failed registration may have been handled by the server, so it is sent once
+    let version = server
+        .mock("POST", "/datasets/owner/once/versions")
+        .with_status(500)
+        .expect(1)
+        .create();
+
+    let dataset = CString::new("owner/once").unwrap();
+    let ptr = unsafe { gaggle::gaggle_update_dataset_files(dataset.as_ptr(), files_json.as_ptr()) };
+    assert!(ptr.is_null());
+    version.assert();
+
+    env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+    env::remove_var("GAGGLE_HTTP_RETRY_DELAY");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_http_record_and_playback() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    gaggle::gaggle_clear_search_cache();
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let m = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("[{\"ref\":\"owner/recorded\",\"title\":\"R\"}]")
+        .expect(1)
+        .create();
+
+    // Both flags set: recording takes precedence
+    env::set_var("GAGGLE_HTTP_RECORD", "1");
+    env::set_var("GAGGLE_HTTP_PLAYBACK", "1");
+    let query = CString::new("recorded").unwrap();
+    let search = || unsafe {
+        let ptr = gaggle::gaggle_search(query.as_ptr(), 1, 10);
+        if ptr.is_null() {
+            return None;
+        }
+        let s = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+        gaggle::gaggle_free(ptr);
+        Some(s)
+    };
+    let recorded = search().expect("recorded search");
+    assert!(recorded.contains("owner/recorded"));
+    m.assert();
+
+    let ptr = gaggle::gaggle_list_http_records();
+    assert!(!ptr.is_null());
+    let records: serde_json::Value =
+        serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+    unsafe { gaggle::gaggle_free(ptr) };
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["method"], "GET");
+    assert_eq!(records[0]["status"], 200);
+
+    // Stop the mock server and replay in offline mode
+    drop(server);
+    env::remove_var("GAGGLE_HTTP_RECORD");
+    env::set_var("GAGGLE_OFFLINE", "1");
+    gaggle::gaggle_clear_search_cache();
+    let replayed = search().expect("replayed search");
+    assert_eq!(replayed, recorded);
+
+    // Without recordings, offline playback fails
+    env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+    assert_eq!(gaggle::gaggle_clear_http_record(), 0);
+    gaggle::gaggle_clear_search_cache();
+    assert!(search().is_none());
+
+    env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+    env::remove_var("GAGGLE_OFFLINE");
+    env::remove_var("GAGGLE_HTTP_PLAYBACK");
+    env::remove_var("GAGGLE_API_BASE");
+    env::remove_var("GAGGLE_CACHE_DIR");
+}
+
+/// A log sink that collects formatted tracing output in memory.
+#[derive(Clone, Default)]
+struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+#[serial_test::serial]
+fn test_api_key_never_leaks_into_errors_or_logs() {
+    const FAKE_KEY: &str = "fake-key-0123456789abcdef";
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+
+    let user = CString::new("gaggle").unwrap();
+    let key = CString::new(FAKE_KEY).unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    let mut errors = Vec::new();
+    tracing::subscriber::with_default(subscriber, || {
+        // The API rejects the key, and the API base carries the key as URL userinfo
+        let mut server = Server::new();
+        let _unauthorized = server
+            .mock("GET", Matcher::Any)
+            .with_status(401)
+            .with_body("Unauthorized")
+            .create();
+        let authority = server.host_with_port();
+        let bases = [
+            format!("http://gaggle:{}@{}", FAKE_KEY, authority),
+            // Nothing listens on port 1, so reqwest reports the full URL in its error
+            format!("http://gaggle:{}@127.0.0.1:1", FAKE_KEY),
+        ];
+        for base in bases {
+            env::set_var("GAGGLE_API_BASE", base);
+            let ds = CString::new("owner/secret-dataset").unwrap();
+            let ptr = unsafe { gaggle::gaggle_download_dataset(ds.as_ptr()) };
+            assert!(ptr.is_null());
+            let err = unsafe { CStr::from_ptr(gaggle::gaggle_last_error()) };
+            errors.push(err.to_str().unwrap().to_string());
+        }
+    });
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("downloading dataset"), "no logs captured");
+    assert!(!logs.contains(FAKE_KEY), "key leaked into logs: {}", logs);
+    for err in &errors {
+        assert!(!err.contains(FAKE_KEY), "key leaked into error: {}", err);
+    }
+    assert!(
+        errors[1].contains("127.0.0.1:1"),
+        "unexpected error: {}",
+        errors[1]
+    );
+
+    gaggle::gaggle_clear_credentials();
+    env::remove_var("GAGGLE_API_BASE");
+    env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+    env::remove_var("GAGGLE_CACHE_DIR");
+}
+
+/// Downloads `owner/{dataset}` from 16 threads at once and returns each thread's result.
+fn download_from_16_threads(dataset: &str) -> Vec<Result<String, String>> {
+    let barrier = std::sync::Barrier::new(16);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..16)
+            .map(|_| {
+                scope.spawn(|| {
+                    barrier.wait();
+                    let ds = CString::new(format!("owner/{}", dataset)).unwrap();
+                    let ptr = unsafe { gaggle::gaggle_download_dataset(ds.as_ptr()) };
+                    if ptr.is_null() {
+                        let err = unsafe { CStr::from_ptr(gaggle::gaggle_last_error()) };
+                        return Err(err.to_str().unwrap().to_string());
+                    }
+                    let path = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+                    unsafe { gaggle::gaggle_free(ptr) };
+                    Ok(path)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    })
+}
+
+#[test]
+#[serial_test::serial]
+fn test_concurrent_downloads_of_one_dataset_share_one_request() {
+    gaggle::init_logging();
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "0");
+
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _meta = server
+        .mock("GET", Matcher::Regex(r"^/datasets/view/owner/".into()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":1}")
+        .create();
+    // The slow bodies keep the downloader busy until every thread is waiting on it
+    let zip_bytes = make_zip_bytes(&[("data.csv", b"a,b\n1,2\n")]);
+    let ok = server
+        .mock("GET", "/datasets/download/owner/shared")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_chunked_body(move |w| {
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            w.write_all(&zip_bytes)
+        })
+        .expect(1)
+        .create();
+    let corrupt = server
+        .mock("GET", "/datasets/download/owner/corrupt")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_chunked_body(|w| {
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            w.write_all(b"not a zip archive")
+        })
+        .expect(1)
+        .create();
+
+    let results = download_from_16_threads("shared");
+    ok.assert();
+    let first = results[0].clone().unwrap();
+    assert!(results.iter().all(|r| r.as_ref() == Ok(&first)));
+    assert!(std::path::Path::new(&first).join("data.csv").exists());
+
+    // Waiters get the downloader's error instead of downloading again
+    let results = download_from_16_threads("corrupt");
+    corrupt.assert();
+    for result in &results {
+        let err = result.as_ref().unwrap_err();
+        assert!(err.starts_with("[E007]"), "unexpected error: {}", err);
+    }
+
+    env::remove_var("GAGGLE_API_BASE");
+    env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+    env::remove_var("GAGGLE_CACHE_DIR");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_download_tar_gz_dataset_with_mock() {
+    gaggle::init_logging();
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _meta = server
+        .mock("GET", "/datasets/view/owner/tarball")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":2}")
+        .create();
+
+    let mut builder = tar::Builder::new(Vec::new());
+    let content = b"a,b\n1,2\n";
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "data/values.csv", &content[..])
+        .unwrap();
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(&builder.into_inner().unwrap()).unwrap();
+    let _dl = server
+        .mock("GET", "/datasets/download/owner/tarball")
+        .with_status(200)
+        .with_header("content-type", "application/gzip")
+        .with_body(gz.finish().unwrap())
+        .create();
+
+    let ds = CString::new("owner/tarball").unwrap();
+    let local_ptr = unsafe { gaggle::gaggle_download_dataset(ds.as_ptr()) };
+    assert!(!local_ptr.is_null(), "download failed");
+    let local = unsafe {
+        let s = CStr::from_ptr(local_ptr).to_str().unwrap().to_string();
+        gaggle::gaggle_free(local_ptr);
+        std::path::PathBuf::from(s)
+    };
+
+    assert_eq!(
+        std::fs::read_to_string(local.join("data").join("values.csv")).unwrap(),
+        "a,b\n1,2\n"
+    );
+    // The archive itself is removed, and its format is recorded in the marker
+    assert!(!local.join("dataset.tar.gz").exists());
+    assert!(!local.join("dataset.zip").exists());
+    let marker: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(local.join(".downloaded")).unwrap()).unwrap();
+    assert_eq!(marker["archive_format"], "tar.gz");
+
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_download_dataset_from_kaggle_url_with_mock() {
+    gaggle::init_logging();
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _dl = server
+        .mock("GET", "/datasets/download/owner/from-url")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(make_zip_bytes(&[("data.csv", b"a,b\n1,2\n")]))
+        .expect(1)
+        .create();
+
+    let ds = CString::new("https://www.kaggle.com/datasets/owner/from-url/data?select=data.csv")
+        .unwrap();
+    let local_ptr = unsafe { gaggle::gaggle_download_dataset(ds.as_ptr()) };
+    assert!(!local_ptr.is_null(), "download failed");
+    let local = unsafe {
+        let s = CStr::from_ptr(local_ptr).to_str().unwrap().to_string();
+        gaggle::gaggle_free(local_ptr);
+        std::path::PathBuf::from(s)
+    };
+    assert_eq!(local, temp.path().join("datasets/owner/from-url"));
+    assert!(local.join("data.csv").exists());
+    _dl.assert();
+
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_download_dataset_filtered_with_mock() {
+    gaggle::init_logging();
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    env::set_var("GAGGLE_KEEP_ARCHIVE", "1");
+
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    // Downloaded once; the second call extracts from the retained archive
+    let _dl = server
+        .mock("GET", "/datasets/download/owner/filtered")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(make_zip_bytes(&[
+            ("big.csv", b"a,b\n1,2\n"),
+            ("parts/", b""),
+            ("parts/p0.parquet", b"PAR1"),
+            ("notes.txt", b"notes"),
+        ]))
+        .expect(1)
+        .create();
+
+    let download = |patterns: &str| -> Option<serde_json::Value> {
+        let ds = CString::new("owner/filtered").unwrap();
+        let patterns = CString::new(patterns).unwrap();
+        let ptr =
+            unsafe { gaggle::gaggle_download_dataset_filtered(ds.as_ptr(), patterns.as_ptr()) };
+        if ptr.is_null() {
+            return None;
+        }
+        let s = unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() };
+        unsafe { gaggle::gaggle_free(ptr) };
+        Some(serde_json::from_str(&s).unwrap())
+    };
+
+    let local = temp.path().join("datasets/owner/filtered");
+    let result = download(r#"["*.parquet"]"#).expect("filtered download failed");
+    assert_eq!(
+        result["files"],
+        serde_json::json!([local.join("parts/p0.parquet").to_string_lossy()])
+    );
+    assert!(!local.join("big.csv").exists());
+    assert!(!local.join(".downloaded").exists());
+    assert!(local.join(".archive.zip").exists());
+    let marker: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(local.join(".partial")).unwrap()).unwrap();
+    assert_eq!(marker["entries"], serde_json::json!(["parts/p0.parquet"]));
+
+    let result = download(r#"["notes.txt"]"#).expect("second filtered download failed");
+    assert_eq!(result["files"].as_array().unwrap().len(), 1);
+    assert!(local.join("notes.txt").exists());
+    assert!(!local.join("big.csv").exists());
+    _dl.assert();
+
+    // Malformed pattern lists and traversal attempts are rejected
+    assert!(download(r#""*.csv""#).is_none());
+    assert!(download(r#"["../escape"]"#).is_none());
+
+    env::remove_var("GAGGLE_KEEP_ARCHIVE");
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+/// Parses a `Range: bytes={start}-{end}` header and clamps the range to `len` bytes.
+fn requested_range(req: &mockito::Request, len: usize) -> (usize, usize) {
+    let header = req.header("range")[0].to_str().unwrap();
+    let (start, end) = header
+        .strip_prefix("bytes=")
+        .unwrap()
+        .split_once('-')
+        .unwrap();
+    let start: usize = start.parse().unwrap();
+    let end: usize = end.parse().unwrap();
+    (start, end.min(len - 1))
+}
+
+fn mock_download(server: &mut Server, dataset: &str, connections: &str) -> Vec<u8> {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    env::set_var("GAGGLE_API_BASE", server.url());
+    env::set_var("GAGGLE_DOWNLOAD_CONNECTIONS", connections);
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+    let _meta = server
+        .mock("GET", format!("/datasets/view/owner/{}", dataset).as_str())
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":1}")
+        .create();
+
+    let ds = CString::new(format!("owner/{}", dataset)).unwrap();
+    let local_ptr = unsafe { gaggle::gaggle_download_dataset(ds.as_ptr()) };
+    if local_ptr.is_null() {
+        let err = unsafe { CStr::from_ptr(gaggle::gaggle_last_error()) };
+        panic!("download failed: {}", err.to_str().unwrap());
+    }
+    let local = unsafe {
+        let s = CStr::from_ptr(local_ptr).to_str().unwrap().to_string();
+        gaggle::gaggle_free(local_ptr);
+        std::path::PathBuf::from(s)
+    };
+    let data = std::fs::read(local.join("data.bin")).unwrap();
+
+    env::remove_var("GAGGLE_DOWNLOAD_CONNECTIONS");
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+    data
+}
+
+#[test]
+#[serial_test::serial]
+fn test_parallel_download_reassembles_ranges_with_mock() {
+    let data: Vec<u8> = (0..4 * 1024 * 1024 + 4321)
+        .map(|i: usize| (i * 31 % 251) as u8)
+        .collect();
+    let zip_bytes = std::sync::Arc::new(make_zip_bytes(&[("data.bin", &data)]));
+    let len = zip_bytes.len();
+
+    let mut server = Server::new();
+    let (body, headers) = (zip_bytes.clone(), zip_bytes.clone());
+    let ranged = server
+        .mock("GET", "/datasets/download/owner/ranged")
+        .match_header("range", Matcher::Regex(r"^bytes=\d+-\d+$".to_string()))
+        .with_status(206)
+        .with_header_from_request("content-range", move |req| {
+            let (start, end) = requested_range(req, headers.len());
+            format!("bytes {}-{}/{}", start, end, headers.len())
+        })
+        .with_body_from_request(move |req| {
+            let (start, end) = requested_range(req, body.len());
+            body[start..=end].to_vec()
+        })
+        // The probe, then one request per connection
+        .expect(5)
+        .create();
+    let full = server
+        .mock("GET", "/datasets/download/owner/ranged")
+        .match_header("range", Matcher::Missing)
+        .with_status(200)
+        .with_body(zip_bytes.as_slice())
+        .expect(0)
+        .create();
+
+    assert!(len > 4 * 1024 * 1024);
+    assert_eq!(mock_download(&mut server, "ranged", "4"), data);
+    ranged.assert();
+    full.assert();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_parallel_download_falls_back_when_ranges_are_ignored_with_mock() {
+    let data: Vec<u8> = (0..2 * 1024 * 1024)
+        .map(|i: usize| (i % 241) as u8)
+        .collect();
+    let zip_bytes = make_zip_bytes(&[("data.bin", &data)]);
+
+    let mut server = Server::new();
+    // The server answers the probe with the whole archive, which is then used as is
+    let full = server
+        .mock("GET", "/datasets/download/owner/no-ranges")
+        .with_status(200)
+        .with_body(zip_bytes)
+        .expect(1)
+        .create();
+
+    assert_eq!(mock_download(&mut server, "no-ranges", "4"), data);
+    full.assert();
+}
+
+/// Fetches a single file from a mock server under a bandwidth limit and returns how long it took.
+fn timed_single_file_fetch(server: &mut Server, file: &str, body: &[u8], mbps: &str) -> f64 {
+    env::set_var("GAGGLE_MAX_BANDWIDTH_MBPS", mbps);
+    let mock = server
+        .mock("GET", "/datasets/download/owner/throttled")
+        .match_query(Matcher::UrlEncoded("fileName".into(), file.into()))
+        .with_status(200)
+        .with_body(body)
+        .expect(1)
+        .create();
+
+    let ds = CString::new("owner/throttled").unwrap();
+    let fnm = CString::new(file).unwrap();
+    let started = std::time::Instant::now();
+    let ptr = unsafe { gaggle::gaggle_get_file_path(ds.as_ptr(), fnm.as_ptr()) };
+    let elapsed = started.elapsed().as_secs_f64();
+    assert!(!ptr.is_null());
+    let path = unsafe {
+        let s = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+        gaggle::gaggle_free(ptr);
+        std::path::PathBuf::from(s)
+    };
+    assert_eq!(std::fs::metadata(path).unwrap().len(), body.len() as u64);
+    mock.assert();
+    env::remove_var("GAGGLE_MAX_BANDWIDTH_MBPS");
+    elapsed
+}
+
+#[test]
+#[serial_test::serial]
+fn test_bandwidth_limit_slows_downloads_with_mock() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    // 5 MB takes about 0.5 seconds at 80 Mbps and about 2 seconds at 20 Mbps
+    let body = vec![7u8; 5_000_000];
+    let fast = timed_single_file_fetch(&mut server, "fast.bin", &body, "80");
+    let slow = timed_single_file_fetch(&mut server, "slow.bin", &body, "20");
+    assert!(fast >= 0.3, "80 Mbps download took {fast:.2}s");
+    assert!(slow >= 1.5, "20 Mbps download took {slow:.2}s");
+    assert!(
+        slow > fast * 2.0,
+        "took {fast:.2}s at 80 Mbps and {slow:.2}s at 20 Mbps"
+    );
+
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_glob_resolves_and_fetches_matching_files_with_mock() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _meta = server
+        .mock("GET", "/datasets/view/owner/globbed")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"currentVersionNumber":1,"files":[
+                {"name":"instagram/a.csv","totalBytes":4},
+                {"name":"instagram/b.csv","totalBytes":4},
+                {"name":"other/c.parquet","totalBytes":4},
+                {"name":"top.csv","totalBytes":4}]}"#,
+        )
+        .create();
+    let mut file_mocks = Vec::new();
+    for name in ["instagram/a.csv", "instagram/b.csv", "other/c.parquet"] {
+        file_mocks.push(
+            server
+                .mock("GET", "/datasets/download/owner/globbed")
+                .match_query(Matcher::UrlEncoded("fileName".into(), name.into()))
+                .with_status(200)
+                .with_body("x\n1\n")
+                .expect(1)
+                .create(),
+        );
+    }
+
+    let glob = |pattern: &str| -> Option<Vec<String>> {
+        let ds = CString::new("owner/globbed").unwrap();
+        let pattern = CString::new(pattern).unwrap();
+        let ptr = unsafe { gaggle::gaggle_glob(ds.as_ptr(), pattern.as_ptr()) };
+        if ptr.is_null() {
+            return None;
+        }
+        let s = unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() };
+        unsafe { gaggle::gaggle_free(ptr) };
+        Some(serde_json::from_str(&s).unwrap())
+    };
+
+    let dataset_dir = temp.path().join("datasets/owner/globbed");
+    let csvs = glob("instagram/*.csv").unwrap();
+    assert_eq!(
+        csvs,
+        vec![
+            dataset_dir.join("instagram/a.csv").to_string_lossy(),
+            dataset_dir.join("instagram/b.csv").to_string_lossy(),
+        ]
+    );
+    assert!(csvs.iter().all(|p| std::path::Path::new(p).is_file()));
+
+    let parquet = glob("**/*.parquet").unwrap();
+    assert_eq!(
+        parquet,
+        vec![dataset_dir.join("other/c.parquet").to_string_lossy()]
+    );
+
+    // Files fetched before are not requested again
+    assert_eq!(glob("instagram/a.csv").unwrap().len(), 1);
+
+    assert_eq!(glob("missing/*.json").unwrap(), Vec::<String>::new());
+    assert!(glob("../*.csv").is_none());
+    assert!(glob("/etc/*").is_none());
+
+    for mock in file_mocks {
+        mock.assert();
+    }
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_search_cursor_forwards_next_page_token_with_mock() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let first = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::Exact(
+            "search=cursor%20query&page=1&pageSize=2".into(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"datasets":[{"ref":"o/one"},{"ref":"o/two"}],"nextPageToken":"tok-2"}"#)
+        .expect(1)
+        .create();
+    let second = server
+        .mock("GET", "/datasets/list")
+        .match_query(Matcher::Exact(
+            "search=cursor%20query&cursor=tok-2&page=1&pageSize=2".into(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"datasets":[{"ref":"o/three"}],"nextPageToken":""}"#)
+        .expect(1)
+        .create();
+
+    let search = |cursor: Option<&str>| -> serde_json::Value {
+        let q = CString::new("cursor query").unwrap();
+        let cursor = cursor.map(|c| CString::new(c).unwrap());
+        let ptr = unsafe {
+            gaggle::gaggle_search_cursor(
+                q.as_ptr(),
+                cursor.as_ref().map_or(std::ptr::null(), |c| c.as_ptr()),
+                2,
+            )
+        };
+        assert!(!ptr.is_null());
+        let s = unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() };
+        unsafe { gaggle::gaggle_free(ptr) };
+        serde_json::from_str(&s).unwrap()
+    };
+
+    let page1 = search(None);
+    assert_eq!(page1["results"].as_array().unwrap().len(), 2);
+    assert_eq!(page1["next_cursor"], "tok-2");
+
+    // The cursor is passed back as a JSON string
+    let cursor_json = serde_json::to_string(&page1["next_cursor"]).unwrap();
+    let page2 = search(Some(&cursor_json));
+    assert_eq!(page2["results"][0]["ref"], "o/three");
+    assert!(page2["next_cursor"].is_null());
+
+    first.assert();
+    second.assert();
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+/// Mocks version `version` of `owner/stale`, whose archive is expected to be downloaded once.
+fn mock_versioned_dataset(server: &mut Server, version: u32) -> (mockito::Mock, mockito::Mock) {
+    let meta = server
+        .mock("GET", "/datasets/view/owner/stale")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!("{{\"currentVersionNumber\":{}}}", version))
+        .create();
+    let download = server
+        .mock("GET", "/datasets/download/owner/stale")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(make_zip_bytes(&[(
+            "data.csv",
+            format!("version\n{}\n", version).as_bytes(),
+        )]))
+        .expect(1)
+        .create();
+    (meta, download)
+}
+
+#[test]
+#[serial_test::serial]
+fn test_download_if_stale_with_mock() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+    let ds = CString::new("owner/stale").unwrap();
+    let if_stale = || -> serde_json::Value {
+        let ptr = unsafe { gaggle::gaggle_download_if_stale(ds.as_ptr()) };
+        assert!(!ptr.is_null());
+        let s = unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() };
+        unsafe { gaggle::gaggle_free(ptr) };
+        serde_json::from_str(&s).unwrap()
+    };
+    let read_data = |result: &serde_json::Value| {
+        let dir = std::path::PathBuf::from(result["path"].as_str().unwrap());
+        std::fs::read_to_string(dir.join("data.csv")).unwrap()
+    };
+
+    // Not cached yet, so the dataset is downloaded
+    let (meta, download) = mock_versioned_dataset(&mut server, 1);
+    let first = if_stale();
+    assert_eq!(first["was_updated"], true);
+    assert!(first["previous_version"].is_null());
+    assert_eq!(first["current_version"], "1");
+
+    // Still version 1, so nothing is downloaded
+    let second = if_stale();
+    assert_eq!(second["was_updated"], false);
+    assert_eq!(second["current_version"], "1");
+    assert_eq!(read_data(&second), "version\n1\n");
+    download.assert();
+    meta.remove();
+    download.remove();
+
+    // Version 2 is published
+    let (meta, download) = mock_versioned_dataset(&mut server, 2);
+    let third = if_stale();
+    assert_eq!(third["was_updated"], true);
+    assert_eq!(third["previous_version"], "1");
+    assert_eq!(third["current_version"], "2");
+    assert_eq!(read_data(&third), "version\n2\n");
+    download.assert();
+    meta.remove();
+    download.remove();
+
+    // With GAGGLE_ALWAYS_CHECK_UPDATES, a plain download picks up version 3
+    let (_meta, download) = mock_versioned_dataset(&mut server, 3);
+    env::set_var("GAGGLE_ALWAYS_CHECK_UPDATES", "1");
+    let ptr = unsafe { gaggle::gaggle_download_dataset(ds.as_ptr()) };
+    assert!(!ptr.is_null());
+    let dir = unsafe {
+        let s = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+        gaggle::gaggle_free(ptr);
+        std::path::PathBuf::from(s)
+    };
+    assert_eq!(
+        std::fs::read_to_string(dir.join("data.csv")).unwrap(),
+        "version\n3\n"
+    );
+    download.assert();
+
+    env::remove_var("GAGGLE_ALWAYS_CHECK_UPDATES");
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_download_dataset_to_bypasses_cache() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let cache = temp.path().join("cache");
+    env::set_var("GAGGLE_CACHE_DIR", &cache);
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+    let _meta = server
+        .mock("GET", "/datasets/view/owner/direct")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":1}")
+        .create();
+    let download = server
+        .mock("GET", "/datasets/download/owner/direct")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(make_zip_bytes(&[
+            ("train.csv", b"a,b\n1,2\n"),
+            ("nested/test.csv", b"a,b\n3,4\n"),
+        ]))
+        .expect(2)
+        .create();
+
+    let ds = CString::new("owner/direct").unwrap();
+    let target = temp.path().join("project/data");
+    let target_c = CString::new(target.to_str().unwrap()).unwrap();
+    let ptr = unsafe { gaggle::gaggle_download_dataset_to(ds.as_ptr(), target_c.as_ptr(), 0) };
+    assert!(!ptr.is_null());
+    let local = unsafe {
+        let s = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+        gaggle::gaggle_free(ptr);
+        std::path::PathBuf::from(s)
+    };
+    assert_eq!(local, target);
+    assert_eq!(
+        std::fs::read_to_string(target.join("nested/test.csv")).unwrap(),
+        "a,b\n3,4\n"
+    );
+    // Only the dataset's files are left in the target
+    let mut names: Vec<String> = std::fs::read_dir(&target)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["nested", "train.csv"]);
+    // Nothing is downloaded into the cache
+    assert!(!cache.join("datasets").exists());
+
+    // A non-empty target is refused without overwrite
+    let ptr = unsafe { gaggle::gaggle_download_dataset_to(ds.as_ptr(), target_c.as_ptr(), 0) };
+    assert!(ptr.is_null());
+    let err = unsafe { CStr::from_ptr(gaggle::gaggle_last_error()) }
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("not empty"), "unexpected error: {}", err);
+
+    std::fs::write(target.join("train.csv"), b"stale").unwrap();
+    let ptr = unsafe { gaggle::gaggle_download_dataset_to(ds.as_ptr(), target_c.as_ptr(), 1) };
+    assert!(!ptr.is_null());
+    unsafe { gaggle::gaggle_free(ptr) };
+    assert_eq!(
+        std::fs::read_to_string(target.join("train.csv")).unwrap(),
+        "a,b\n1,2\n"
+    );
+    assert!(!cache.join("datasets").exists());
+    download.assert();
+
+    // A target inside the cache is refused
+    let inside = CString::new(cache.join("direct").to_str().unwrap()).unwrap();
+    let ptr = unsafe { gaggle::gaggle_download_dataset_to(ds.as_ptr(), inside.as_ptr(), 0) };
+    assert!(ptr.is_null());
+
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_check_dataset_against_remote_listing() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+    let meta = server
+        .mock("GET", "/datasets/view/owner/checked")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"files":[{"name":"a.csv","totalBytes":8},{"name":"sub/b.csv","totalBytes":100},{"name":"c.csv","totalBytes":3}]}"#,
+        )
+        .expect(1)
+        .create();
+
+    // A partial copy: one complete file, one truncated file, and a file Kaggle does not list
+    let dir = temp.path().join("datasets/owner/checked");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("a.csv"), b"x,y\n1,2\n").unwrap();
+    std::fs::write(dir.join("sub/b.csv"), b"x,y\n").unwrap();
+    std::fs::write(dir.join("notes.txt"), b"mine").unwrap();
+    std::fs::write(dir.join(".partial"), b"{}").unwrap();
+
+    let check = |path: &str| -> Option<serde_json::Value> {
+        let ds = CString::new(path).unwrap();
+        let ptr = unsafe { gaggle::gaggle_check_dataset(ds.as_ptr()) };
+        if ptr.is_null() {
+            return None;
+        }
+        let s = unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() };
+        unsafe { gaggle::gaggle_free(ptr) };
+        Some(serde_json::from_str(&s).unwrap())
+    };
+
+    let expected = serde_json::json!({
+        "complete": false,
+        "remote_files": 3,
+        "local_files": 3,
+        "missing": ["c.csv"],
+        "size_mismatches": [{"file": "sub/b.csv", "expected_bytes": 100, "actual_bytes": 4}],
+        "extra": ["notes.txt"],
+    });
+    assert_eq!(check("owner/checked").unwrap(), expected);
+
+    // Offline, the metadata saved by the first check is used without a request
+    env::set_var("GAGGLE_OFFLINE", "1");
+    assert_eq!(check("owner/checked").unwrap(), expected);
+    assert!(check("owner/never-fetched").is_none());
+    let err = unsafe { CStr::from_ptr(gaggle::gaggle_last_error()) }
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("Offline mode"), "unexpected error: {}", err);
+    env::remove_var("GAGGLE_OFFLINE");
+    meta.assert();
+
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+fn get_stats() -> serde_json::Value {
+    let ptr = gaggle::gaggle_get_stats();
+    assert!(!ptr.is_null());
+    let s = unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() };
+    unsafe { gaggle::gaggle_free(ptr) };
+    serde_json::from_str(&s).unwrap()
+}
+
+#[test]
+#[serial_test::serial]
+fn test_stats_count_downloads_and_cache_hits_with_mock() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    env::set_var("GAGGLE_STRICT_ONDEMAND", "1");
+    env::set_var("GAGGLE_HTTP_RETRY_ATTEMPTS", "1");
+    env::set_var("GAGGLE_HTTP_RETRY_DELAY", "0");
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _meta = server
+        .mock("GET", "/datasets/view/owner/counted")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":1}")
+        .create();
+    let zip_bytes = make_zip_bytes(&[("data.csv", b"a,b\n1,2\n")]);
+    let archive_len = zip_bytes.len() as u64;
+    let _download = server
+        .mock("GET", "/datasets/download/owner/counted")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(zip_bytes)
+        .expect(1)
+        .create();
+    let _file = server
+        .mock("GET", "/datasets/download/owner/files")
+        .match_query(Matcher::UrlEncoded("fileName".into(), "part.csv".into()))
+        .with_status(200)
+        .with_body("x\n1\n")
+        .expect(1)
+        .create();
+    let _limited = server
+        .mock("GET", "/datasets/download/owner/files")
+        .match_query(Matcher::UrlEncoded("fileName".into(), "limited.csv".into()))
+        .with_status(429)
+        .with_header("retry-after", "0")
+        .expect(2)
+        .create();
+
+    assert_eq!(gaggle::gaggle_reset_stats(), 0);
+    let stats = get_stats();
+    assert_eq!(stats["api_calls"], 0);
+    assert_eq!(stats["bytes_downloaded"], 0);
+
+    // The first download is a miss, and the second is served from the cache
+    let ds = CString::new("owner/counted").unwrap();
+    for _ in 0..2 {
+        let ptr = unsafe { gaggle::gaggle_download_dataset(ds.as_ptr()) };
+        assert!(!ptr.is_null());
+        unsafe { gaggle::gaggle_free(ptr) };
+    }
+    let stats = get_stats();
+    assert_eq!(stats["cache_misses"], 1);
+    assert_eq!(stats["cache_hits"], 1);
+    assert_eq!(stats["downloads_started"], 1);
+    assert_eq!(stats["downloads_completed"], 1);
+    assert_eq!(stats["downloads_failed"], 0);
+    assert_eq!(stats["bytes_downloaded"], archive_len);
+    let api_calls = stats["api_calls"].as_u64().unwrap();
+    assert!(api_calls >= 2, "unexpected api_calls: {}", api_calls);
+
+    // A single file is fetched once, then found in the cache
+    let files = CString::new("owner/files").unwrap();
+    let part = CString::new("part.csv").unwrap();
+    for _ in 0..2 {
+        let ptr = unsafe { gaggle::gaggle_get_file_path(files.as_ptr(), part.as_ptr()) };
+        assert!(!ptr.is_null());
+        unsafe { gaggle::gaggle_free(ptr) };
+    }
+    let stats = get_stats();
+    assert_eq!(stats["single_file_fetches"], 1);
+    assert_eq!(stats["cache_misses"], 2);
+    assert_eq!(stats["cache_hits"], 2);
+    assert_eq!(stats["bytes_downloaded"], archive_len + 4);
+    assert_eq!(stats["api_calls"], api_calls + 1);
+    assert_eq!(stats["retries"], 0);
+
+    // A rate limited request is retried once before giving up
+    let limited = CString::new("limited.csv").unwrap();
+    let ptr = unsafe { gaggle::gaggle_get_file_path(files.as_ptr(), limited.as_ptr()) };
+    assert!(ptr.is_null());
+    let stats = get_stats();
+    assert_eq!(stats["retries"], 1);
+    assert_eq!(stats["api_calls"], api_calls + 3);
+
+    assert_eq!(gaggle::gaggle_reset_stats(), 0);
+    assert_eq!(get_stats()["cache_hits"], 0);
+
+    env::remove_var("GAGGLE_HTTP_RETRY_DELAY");
+    env::remove_var("GAGGLE_HTTP_RETRY_ATTEMPTS");
+    env::remove_var("GAGGLE_STRICT_ONDEMAND");
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_cache_size_functions_after_mock_download() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    let _meta = server
+        .mock("GET", "/datasets/view/owner/sized")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":1}")
+        .create();
+    let big = vec![b'x'; 3 * 1024 * 1024];
+    let _download = server
+        .mock("GET", "/datasets/download/owner/sized")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(make_zip_bytes(&[
+            ("big.csv", &big),
+            ("small.csv", b"a\n1\n"),
+        ]))
+        .create();
+
+    let ds = CString::new("owner/sized").unwrap();
+    let ptr = unsafe { gaggle::gaggle_download_dataset(ds.as_ptr()) };
+    assert!(!ptr.is_null());
+    let dir = std::path::PathBuf::from(unsafe { CStr::from_ptr(ptr).to_str().unwrap() });
+    unsafe { gaggle::gaggle_free(ptr) };
+
+    // The sizes match the files on disk
+    let bytes: u64 = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().metadata().unwrap().len())
+        .sum();
+    let expected_mb = bytes / (1024 * 1024);
+    assert_eq!(expected_mb, 3);
+    assert_eq!(
+        unsafe { gaggle::gaggle_get_dataset_cache_size_mb(ds.as_ptr()) },
+        expected_mb
+    );
+    assert_eq!(gaggle::gaggle_get_total_cache_size_mb(), expected_mb);
+
+    let ptr = gaggle::gaggle_get_cache_info();
+    let info: serde_json::Value =
+        serde_json::from_str(unsafe { CStr::from_ptr(ptr).to_str().unwrap() }).unwrap();
+    unsafe { gaggle::gaggle_free(ptr) };
+    assert_eq!(info["total_size_mb"], expected_mb);
+
+    // A dataset that is not cached has no size, and a null pointer is an error
+    let other = CString::new("owner/not-cached").unwrap();
+    assert_eq!(
+        unsafe { gaggle::gaggle_get_dataset_cache_size_mb(other.as_ptr()) },
+        0
+    );
+    assert!(gaggle::gaggle_last_error().is_null());
+    assert_eq!(
+        unsafe { gaggle::gaggle_get_dataset_cache_size_mb(std::ptr::null()) },
+        0
+    );
+    assert!(!gaggle::gaggle_last_error().is_null());
+
+    env::remove_var("GAGGLE_CACHE_DIR");
+    env::remove_var("GAGGLE_API_BASE");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_dataset_segments_are_encoded_in_request_paths_with_mock() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    env::set_var("KAGGLE_USERNAME", "user");
+    env::set_var("KAGGLE_KEY", "key");
+
+    let any_request = server
+        .mock("GET", Matcher::Any)
+        .match_query(Matcher::Any)
+        .expect(0)
+        .create();
+
+    // A dataset name with a space is rejected before any request is sent
+    let spaced = CString::new("owner.name/data set").unwrap();
+    let file_name = CString::new("a.csv").unwrap();
+    unsafe {
+        assert!(gaggle::gaggle_download_dataset(spaced.as_ptr()).is_null());
+        let err = CStr::from_ptr(gaggle::gaggle_last_error())
+            .to_str()
+            .unwrap();
+        assert!(err.contains("whitespace"), "{}", err);
+        assert!(gaggle::gaggle_get_file_path(spaced.as_ptr(), file_name.as_ptr()).is_null());
+    }
+    any_request.assert();
+    any_request.remove();
+
+    // Unreserved characters pass through, and others are percent-encoded
+    let meta = server
+        .mock("GET", "/datasets/view/Owner-Name/data.set-v1.0")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"currentVersionNumber\":1}")
+        .expect(1)
+        .create();
+    let file = server
+        .mock("GET", "/datasets/download/user%40domain.com/a%2Bb")
+        .match_query(Matcher::UrlEncoded("fileName".into(), "x.csv".into()))
+        .with_status(200)
+        .with_body("x\n1\n")
+        .expect(1)
+        .create();
+
+    let ds = CString::new("Owner-Name/data.set-v1.0").unwrap();
+    let ptr = unsafe { gaggle::gaggle_get_dataset_info(ds.as_ptr()) };
+    assert!(!ptr.is_null(), "metadata request failed");
+    unsafe { gaggle::gaggle_free(ptr) };
+    let ds = CString::new("user@domain.com/a+b").unwrap();
+    let file_name = CString::new("x.csv").unwrap();
+    let ptr = unsafe { gaggle::gaggle_get_file_path(ds.as_ptr(), file_name.as_ptr()) };
+    assert!(!ptr.is_null(), "single-file fetch failed");
+    let path = unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() };
+    unsafe { gaggle::gaggle_free(ptr) };
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "x\n1\n");
+    meta.assert();
+    file.assert();
+
+    for var in [
+        "GAGGLE_CACHE_DIR",
+        "GAGGLE_API_BASE",
+        "KAGGLE_USERNAME",
+        "KAGGLE_KEY",
+    ] {
+        env::remove_var(var);
+    }
+}
+
+/// Serves `currentVersionNumber` from the metadata endpoint of `owner/modes`.
+fn mock_modes_metadata(server: &mut Server, version: u32) -> mockito::Mock {
+    server
+        .mock("GET", "/datasets/view/owner/modes")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!("{{\"currentVersionNumber\":{}}}", version))
+        .create()
+}
+
+#[test]
+#[serial_test::serial]
+fn test_download_dataset_ex_modes_with_mock() {
+    let temp = tempfile::TempDir::new().unwrap();
+    env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = Server::new();
+    env::set_var("GAGGLE_API_BASE", server.url());
+    env::set_var("KAGGLE_USERNAME", "user");
+    env::set_var("KAGGLE_KEY", "key");
+
+    let download = |mode: i32| -> Option<std::path::PathBuf> {
+        let ds = CString::new("owner/modes").unwrap();
+        let ptr = unsafe { gaggle::gaggle_download_dataset_ex(ds.as_ptr(), mode) };
+        if ptr.is_null() {
+            return None;
+        }
+        let path = unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() };
+        unsafe { gaggle::gaggle_free(ptr) };
+        Some(std::path::PathBuf::from(path))
+    };
+    let version_of = |dir: &std::path::Path| -> serde_json::Value {
+        let marker = std::fs::read_to_string(dir.join(".downloaded")).unwrap();
+        serde_json::from_str::<serde_json::Value>(&marker).unwrap()["version"].clone()
+    };
+
+    let meta = mock_modes_metadata(&mut server, 1);
+    let archive = server
+        .mock("GET", "/datasets/download/owner/modes")
+        .with_status(200)
+        .with_header("content-type", "application/zip")
+        .with_body(make_zip_bytes(&[("data.csv", b"a\n1\n")]))
+        .expect(3)
+        .create();
+
+    // Cache first downloads once, then serves the cache
+    let dir = download(0).unwrap();
+    assert_eq!(download(0).unwrap(), dir);
+    assert_eq!(version_of(&dir), "1");
+
+    // Refresh if outdated keeps a copy of the latest version
+    assert_eq!(download(1).unwrap(), dir);
+
+    // and downloads again once a newer version is published
+    meta.remove();
+    let meta = mock_modes_metadata(&mut server, 2);
+    assert_eq!(download(1).unwrap(), dir);
+    assert_eq!(version_of(&dir), "2");
+    assert_eq!(download(1).unwrap(), dir);
+
+    // Force always downloads again
+    assert_eq!(download(2).unwrap(), dir);
+    archive.assert();
+    meta.remove();
+
+    // An unknown latest version keeps the cached copy
+    let marker = dir.join(".downloaded");
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&marker).unwrap()).unwrap();
+    metadata["version"] = serde_json::Value::Null;
+    std::fs::write(&marker, metadata.to_string()).unwrap();
+    env::set_var("GAGGLE_OFFLINE", "1");
+    assert_eq!(download(1).unwrap(), dir);
+    assert!(dir.join("data.csv").exists());
+    env::remove_var("GAGGLE_OFFLINE");
+
+    // Other modes are rejected
+    assert!(download(3).is_none());
+    let err = unsafe { CStr::from_ptr(gaggle::gaggle_last_error()) };
+    assert!(err.to_str().unwrap().contains("Invalid download mode 3"));
+
+    for var in [
+        "GAGGLE_CACHE_DIR",
+        "GAGGLE_API_BASE",
+        "KAGGLE_USERNAME",
+        "KAGGLE_KEY",
+    ] {
+        env::remove_var(var);
+    }
+}
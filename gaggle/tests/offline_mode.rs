@@ -7,9 +7,10 @@
 // environment variable, the test simulates a scenario with no internet connectivity
 // and asserts that the library's FFI functions behave as designed in this context.
 
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 
 #[test]
+#[serial_test::serial]
 fn test_offline_download_fails_when_not_cached_and_version_unknown() {
     // Enable offline
     std::env::set_var("GAGGLE_OFFLINE", "1");
@@ -34,7 +35,7 @@ fn test_offline_download_fails_when_not_cached_and_version_unknown() {
     let version_info_ptr = unsafe { gaggle::gaggle_dataset_version_info(ds.as_ptr()) };
     assert!(!version_info_ptr.is_null());
     let info = unsafe {
-        let s = std::ffi::CStr::from_ptr(version_info_ptr)
+        let s = CStr::from_ptr(version_info_ptr)
             .to_str()
             .unwrap()
             .to_string();
@@ -50,3 +51,76 @@ fn test_offline_download_fails_when_not_cached_and_version_unknown() {
     std::env::remove_var("GAGGLE_OFFLINE");
     std::env::remove_var("GAGGLE_CACHE_DIR");
 }
+
+/// Calls an FFI function returning an owned JSON string and parses it.
+fn take_json(ptr: *mut std::os::raw::c_char) -> serde_json::Value {
+    assert!(!ptr.is_null(), "FFI call failed");
+    let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { gaggle::gaggle_free(ptr) };
+    serde_json::from_str(&s).unwrap()
+}
+
+#[test]
+#[serial_test::serial]
+fn test_offline_listing_and_search_use_persisted_metadata() {
+    let temp = tempfile::TempDir::new().unwrap();
+    std::env::set_var("GAGGLE_CACHE_DIR", temp.path());
+    let mut server = mockito::Server::new();
+    std::env::set_var("GAGGLE_API_BASE", server.url());
+    let user = CString::new("user").unwrap();
+    let key = CString::new("key").unwrap();
+    unsafe {
+        let _ = gaggle::gaggle_set_credentials(user.as_ptr(), key.as_ptr());
+    }
+
+    // Fetch metadata once while online, which persists it
+    let meta = server
+        .mock("GET", "/datasets/view/owner/titanic")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"ref":"owner/titanic","title":"Titanic Survivors","files":[{"name":"train.csv","totalBytes":61194}]}"#,
+        )
+        .expect(1)
+        .create();
+    let ds = CString::new("owner/titanic").unwrap();
+    take_json(unsafe { gaggle::gaggle_get_dataset_info(ds.as_ptr()) });
+    meta.assert();
+
+    // A downloaded dataset without persisted metadata
+    let cached = temp.path().join("datasets/other/iris");
+    std::fs::create_dir_all(&cached).unwrap();
+    std::fs::write(cached.join("iris.csv"), "a\n").unwrap();
+
+    // From here on, the server must not see a single request
+    std::env::set_var("GAGGLE_OFFLINE", "1");
+    let any_request = server.mock("GET", mockito::Matcher::Any).expect(0).create();
+
+    let files = take_json(unsafe { gaggle::gaggle_list_files(ds.as_ptr()) });
+    assert_eq!(files[0]["name"], "train.csv");
+    assert_eq!(files[0]["size"], 61194);
+    assert!(files[0]["modified"].is_null());
+    assert!(files[0]["columns"].is_null());
+    let uncached = CString::new("owner/never-seen").unwrap();
+    assert!(unsafe { gaggle::gaggle_list_files(uncached.as_ptr()) }.is_null());
+
+    let search = |q: &str| {
+        let q = CString::new(q).unwrap();
+        take_json(unsafe { gaggle::gaggle_search_cached(q.as_ptr()) })
+    };
+    assert_eq!(
+        search("SURVIVORS"),
+        serde_json::json!([{"ref": "owner/titanic", "title": "Titanic Survivors", "cached": false}])
+    );
+    assert_eq!(
+        search("iris"),
+        serde_json::json!([{"ref": "other/iris", "title": null, "cached": true}])
+    );
+    assert_eq!(search("").as_array().unwrap().len(), 2);
+    assert!(search("no-such-dataset").as_array().unwrap().is_empty());
+    any_request.assert();
+
+    std::env::remove_var("GAGGLE_OFFLINE");
+    std::env::remove_var("GAGGLE_API_BASE");
+    std::env::remove_var("GAGGLE_CACHE_DIR");
+}
@@ -0,0 +1,68 @@
+// property_json_each.rs
+//
+// This file contains property-based tests for the JSON expansion functions exposed over FFI.
+// Using the `proptest` framework, these tests generate flat JSON objects and arrays (whose values
+// are all scalars) and verify that the recursive expansion, the non-recursive expansion, and the
+// flattening agree on them, since there is nothing nested to expand.
+
+use proptest::prelude::*;
+use serde_json::Value;
+use std::ffi::{CStr, CString};
+
+fn scalar() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        "[a-zA-Z0-9 ]{0,12}".prop_map(Value::from),
+    ]
+}
+
+fn flat_object() -> impl Strategy<Value = Value> {
+    prop::collection::btree_map("[a-z_]{1,8}", scalar(), 0..8)
+        .prop_map(|m| Value::Object(m.into_iter().collect()))
+}
+
+fn flat_json() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        flat_object(),
+        prop::collection::vec(scalar(), 0..8).prop_map(Value::Array),
+    ]
+}
+
+/// Calls an FFI function returning an owned string and parses each line as JSON.
+fn take_rows(ptr: *mut std::os::raw::c_char) -> Vec<Value> {
+    assert!(!ptr.is_null());
+    let out = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { gaggle::gaggle_free(ptr) };
+    out.lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn prop_flat_json_expands_the_same_recursively(value in flat_json(), max_depth in -2i32..40) {
+        let input = CString::new(value.to_string()).unwrap();
+        let flat = take_rows(unsafe { gaggle::gaggle_json_each(input.as_ptr()) });
+        let mut recursive =
+            take_rows(unsafe { gaggle::gaggle_json_each_recursive(input.as_ptr(), max_depth) });
+
+        for row in recursive.iter_mut() {
+            let depth = row.as_object_mut().unwrap().remove("depth");
+            prop_assert_eq!(depth, Some(Value::from(1)));
+        }
+        prop_assert_eq!(recursive, flat);
+    }
+
+    #[test]
+    fn prop_flat_object_flattens_to_itself(value in flat_object()) {
+        let input = CString::new(value.to_string()).unwrap();
+        let ptr = unsafe { gaggle::gaggle_json_flatten(input.as_ptr(), std::ptr::null()) };
+        assert!(!ptr.is_null());
+        let out = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { gaggle::gaggle_free(ptr) };
+        let flattened: Value = serde_json::from_str(&out).unwrap();
+        prop_assert_eq!(flattened, value);
+    }
+}
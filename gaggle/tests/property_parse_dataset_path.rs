@@ -5,6 +5,8 @@
 // to verify that the parser correctly handles valid dataset path formats and rejects invalid
 // ones. The primary goal of these tests is to guarantee the robustness and correctness of the
 // dataset path parsing logic, which is a critical component for interacting with the Kaggle API.
+// The tests also cover `parse_dataset_path_from_url`, which parses Kaggle dataset URLs, and
+// `parse_dataset_path_full`, which parses a version and a path inside the dataset.
 
 use proptest::prelude::*;
 
@@ -22,4 +24,96 @@ proptest! {
         prop_assert_eq!(ok.0, owner);
         prop_assert_eq!(ok.1, dataset);
     }
+
+    #[test]
+    fn prop_parse_dataset_url_round_trips(
+        owner in proptest::string::string_regex(r"[A-Za-z0-9_-]{1,20}").unwrap(),
+        dataset in proptest::string::string_regex(r"[A-Za-z0-9_.%+-]{0,10}[A-Za-z0-9_-]").unwrap(),
+        form in 0usize..4
+    ) {
+        prop_assume!(dataset.trim() == dataset && dataset != "." && dataset != "..");
+        // Legacy URLs cannot have owners named like Kaggle's own top-level pages
+        prop_assume!(form != 2 || !["c", "code", "datasets", "docs", "learn", "models", "search"]
+            .contains(&owner.to_ascii_lowercase().as_str()));
+        let encoded = urlencoding::encode(&dataset);
+        let input = match form {
+            0 => format!("https://www.kaggle.com/datasets/{}/{}", owner, encoded),
+            1 => format!("https://kaggle.com/datasets/{}/{}/data", owner, encoded),
+            2 => format!("https://www.kaggle.com/{}/{}", owner, encoded),
+            _ => format!("{}/{}", owner, dataset),
+        };
+        let (parsed_owner, parsed_dataset) = gaggle::parse_dataset_path_from_url(&input)
+            .map_err(|e| TestCaseError::fail(format!("{}: {}", input, e)))?;
+        prop_assert_eq!(format!("{}/{}", parsed_owner, parsed_dataset), format!("{}/{}", owner, dataset));
+    }
+
+    #[test]
+    fn prop_parse_dataset_path_full_round_trips(
+        owner in proptest::string::string_regex(r"[A-Za-z0-9_-]{1,20}").unwrap(),
+        dataset in proptest::string::string_regex(r"[A-Za-z0-9_-]{1,20}").unwrap(),
+        version in proptest::option::of(1u32..10_000),
+        subpath in proptest::option::of(
+            proptest::string::string_regex(r"[A-Za-z0-9_@-]{1,10}(/[A-Za-z0-9_.@-]{1,10}){0,3}").unwrap()
+        )
+    ) {
+        prop_assume!(subpath
+            .as_deref()
+            .is_none_or(|s| s.split('/').all(|c| c != "." && c != "..")));
+        let mut input = format!("{}/{}", owner, dataset);
+        if let Some(v) = version {
+            input.push_str(&format!("@v{}", v));
+        }
+        if let Some(s) = &subpath {
+            input.push('/');
+            input.push_str(s);
+        }
+        let (o, d, v, s) = gaggle::parse_dataset_path_full(&input)
+            .map_err(|e| TestCaseError::fail(format!("{}: {}", input, e)))?;
+        prop_assert_eq!(o, owner);
+        prop_assert_eq!(d, dataset);
+        prop_assert_eq!(v, version.map(|v| v.to_string()));
+        prop_assert_eq!(s, subpath);
+    }
+
+    #[test]
+    fn prop_parse_dataset_path_full_rejects_traversal_in_subpath(
+        owner in proptest::string::string_regex(r"[A-Za-z0-9_-]{1,20}").unwrap(),
+        dataset in proptest::string::string_regex(r"[A-Za-z0-9_-]{1,20}").unwrap(),
+        version in proptest::option::of(1u32..10_000),
+        before in proptest::string::string_regex(r"([A-Za-z0-9_-]{1,10}/){0,2}").unwrap(),
+        after in proptest::string::string_regex(r"(/[A-Za-z0-9_.-]{1,10}){0,2}").unwrap()
+    ) {
+        let version = version.map(|v| format!("@v{}", v)).unwrap_or_default();
+        let input = format!("{}/{}{}/{}..{}", owner, dataset, version, before, after);
+        prop_assert!(gaggle::parse_dataset_path_full(&input).is_err(), "Accepted: {}", input);
+    }
+
+    #[test]
+    fn prop_normalize_dataset_ref_keeps_plain_paths(
+        owner in proptest::string::string_regex(r"[A-Za-z0-9_-]{1,20}").unwrap(),
+        dataset in proptest::string::string_regex(r"[A-Za-z0-9_-]{1,20}").unwrap(),
+        version in proptest::option::of(1u32..10_000)
+    ) {
+        let plain = format!("{}/{}", owner, dataset);
+        let input = match version {
+            Some(v) => format!("{}@v{}", plain, v),
+            None => plain.clone(),
+        };
+        // Plain paths pass through unchanged and parse as before
+        prop_assert_eq!(gaggle::normalize_dataset_ref(&input).unwrap(), input.clone());
+        let (o, d, v) = gaggle::parse_dataset_path_with_version(&input).unwrap();
+        prop_assert_eq!((o, d), gaggle::parse_dataset_path(&plain).unwrap());
+        prop_assert_eq!(v, version.map(|v| v.to_string()));
+
+        // The URL of the same dataset normalizes to the plain path
+        let url = match version {
+            Some(v) => format!("https://www.kaggle.com/datasets/{}/versions/{}?select=x", plain, v),
+            None => format!("https://www.kaggle.com/datasets/{}", plain),
+        };
+        let expected = match version {
+            Some(v) => format!("{}@{}", plain, v),
+            None => plain,
+        };
+        prop_assert_eq!(gaggle::normalize_dataset_ref(&url).unwrap(), expected);
+    }
 }
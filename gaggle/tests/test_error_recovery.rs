@@ -6,7 +6,9 @@
 // invalid operations are performed, that they can be cleared using the provided FFI functions,
 // and that subsequent operations can be executed successfully after an error has been cleared.
 
-use gaggle::{gaggle_clear_last_error, gaggle_last_error};
+use gaggle::{
+    gaggle_clear_last_error, gaggle_last_error, gaggle_last_error_code, gaggle_last_error_json,
+};
 use gaggle::{gaggle_download_dataset, gaggle_search, gaggle_set_credentials};
 use std::ffi::{CStr, CString};
 
@@ -91,14 +93,66 @@ fn test_multiple_errors_in_sequence() {
         gaggle_set_credentials(std::ptr::null(), std::ptr::null());
         let err1 = gaggle_last_error();
         assert!(!err1.is_null());
+        // The pointer is only valid until the next error is set, so copy it first
+        let err1_str = CStr::from_ptr(err1).to_str().unwrap().to_string();
 
         gaggle_search(std::ptr::null(), 1, 10);
         let err2 = gaggle_last_error();
         assert!(!err2.is_null());
 
-        let err1_str = CStr::from_ptr(err1).to_str().unwrap();
         let err2_str = CStr::from_ptr(err2).to_str().unwrap();
         assert!(!err1_str.is_empty());
         assert!(!err2_str.is_empty());
     }
 }
+
+#[test]
+fn test_structured_error_includes_dataset_context() {
+    unsafe {
+        let path = CString::new("invalid-path-without-slash").unwrap();
+        let result = gaggle_download_dataset(path.as_ptr());
+        assert!(result.is_null());
+
+        let json_ptr = gaggle_last_error_json();
+        assert!(!json_ptr.is_null());
+        let json: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(json_ptr).to_str().unwrap()).unwrap();
+        assert_eq!(json["code"], "E004");
+        assert_eq!(json["category"], "InvalidPath");
+        assert_eq!(json["dataset"], "invalid-path-without-slash");
+        assert_eq!(json["retryable"], false);
+
+        gaggle_clear_last_error();
+        assert!(gaggle_last_error_json().is_null());
+    }
+}
+
+#[test]
+fn test_error_code_across_multiple_failing_calls() {
+    unsafe {
+        gaggle_clear_last_error();
+        assert_eq!(gaggle_last_error_code(), 0);
+
+        // E010: null pointer
+        gaggle_set_credentials(std::ptr::null(), std::ptr::null());
+        assert_eq!(gaggle_last_error_code(), 10);
+
+        // E004: invalid dataset path
+        let path = CString::new("no-slash").unwrap();
+        assert!(gaggle_download_dataset(path.as_ptr()).is_null());
+        assert_eq!(gaggle_last_error_code(), 4);
+
+        // The code survives reading the message and JSON
+        assert!(!gaggle_last_error().is_null());
+        assert!(!gaggle_last_error_json().is_null());
+        assert_eq!(gaggle_last_error_code(), 4);
+
+        // E010 again from a different function
+        assert!(gaggle_search(std::ptr::null(), 1, 10).is_null());
+        assert_eq!(gaggle_last_error_code(), 10);
+
+        gaggle_clear_last_error();
+        assert_eq!(gaggle_last_error_code(), 0);
+        assert!(gaggle_last_error().is_null());
+    }
+}